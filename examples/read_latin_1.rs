@@ -6,9 +6,8 @@ extern crate ropey;
 
 use std::fs::File;
 use std::io;
-use std::io::Read;
 
-use ropey::RopeBuilder;
+use ropey::{Encoding, Rope};
 
 fn main() {
     // Get filepath from commandline
@@ -22,28 +21,8 @@ fn main() {
         panic!()
     };
 
-    // Get everything set up to begin reading and decoding.
-    let mut buf = vec![0u8; 1 << 14]; // Buffer for raw bytes.
-    let mut buf_str = String::with_capacity(1 << 14); // Buffer for decoded utf8.
-    let mut builder = RopeBuilder::new();
-    let mut file = io::BufReader::new(File::open(&filepath).unwrap());
-
-    // Read the data in chunks, decoding and appending to the rope builder
-    // as we go.
+    // Read the file, decoding it as we go.
     // (Note: in real code you should handle errors from the reader!)
-    while let Ok(n) = file.read(&mut buf) {
-        if n == 0 {
-            break;
-        }
-
-        // Decode and append the chunk to the rope builder.
-        buf_str.clear();
-        for &byte in &buf[..n] {
-            buf_str.push(byte as char);
-        }
-        builder.append(&buf_str);
-    }
-
-    // Build rope.
-    let _rope = builder.finish();
+    let file = io::BufReader::new(File::open(&filepath).unwrap());
+    let (_rope, _info) = Rope::from_reader_with_encoding(file, Encoding::Latin1).unwrap();
 }
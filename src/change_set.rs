@@ -0,0 +1,783 @@
+use std;
+
+use edit::Edit;
+use rope::Rope;
+use str_utils::{char_to_byte_idx, count_chars};
+
+/// A single step of a [`ChangeSet`](struct.ChangeSet.html).
+///
+/// All lengths are in chars.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// Keep the next `n` chars of the text unchanged.
+    Retain(usize),
+    /// Remove the next `n` chars of the text.
+    Delete(usize),
+    /// Insert the given text at the current position.
+    Insert(String),
+}
+
+/// Which side of an insertion a position sticks to when it is mapped
+/// through a [`ChangeSet`](struct.ChangeSet.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Assoc {
+    /// Stay before text inserted at the position.
+    Before,
+    /// Move after text inserted at the position.
+    After,
+}
+
+/// A reusable description of a set of changes to a text.
+///
+/// A `ChangeSet` is a sequence of [`Operation`](enum.Operation.html)s that
+/// walk over a text of a specific length from start to end, keeping,
+/// removing, and inserting text as they go.  Besides being applied to a
+/// `Rope`, a `ChangeSet` can be inverted (for undo), composed with a
+/// following `ChangeSet` (to squash history), and transformed against a
+/// concurrent `ChangeSet` (for collaborative editing).
+///
+/// `ChangeSet`s are built up from start to end with
+/// [`retain()`](#method.retain), [`delete()`](#method.delete), and
+/// [`insert()`](#method.insert).  Adjacent operations of the same kind are
+/// merged, so two `ChangeSet`s that make the same changes compare equal.
+///
+/// # Example
+///
+/// ```
+/// # use ropey::{ChangeSet, Rope};
+/// let mut rope = Rope::from_str("Hello world!");
+///
+/// let mut changes = ChangeSet::new(rope.len_chars());
+/// changes.retain(6);
+/// changes.delete(5);
+/// changes.insert("there");
+/// changes.retain(1);
+///
+/// let undo = changes.invert(&rope);
+/// changes.apply(&mut rope);
+/// assert_eq!(rope, "Hello there!");
+///
+/// undo.apply(&mut rope);
+/// assert_eq!(rope, "Hello world!");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChangeSet {
+    ops: Vec<Operation>,
+    len: usize,
+    len_after: usize,
+    // The number of chars of the input text that `ops` have covered so
+    // far.  Equal to `len` once the `ChangeSet` is complete.
+    covered: usize,
+}
+
+impl ChangeSet {
+    /// Creates an empty `ChangeSet` for a text of `len` chars.
+    ///
+    /// The returned `ChangeSet` has no operations yet.  Any part of the
+    /// text that isn't covered by operations by the time the `ChangeSet` is
+    /// used is treated as retained.
+    #[inline]
+    pub fn new(len: usize) -> ChangeSet {
+        ChangeSet {
+            ops: Vec::new(),
+            len,
+            len_after: len,
+            covered: 0,
+        }
+    }
+
+    /// Length in chars of the text the `ChangeSet` applies to.
+    #[inline]
+    pub fn len_before(&self) -> usize {
+        self.len
+    }
+
+    /// Length in chars of the text after the `ChangeSet` is applied.
+    #[inline]
+    pub fn len_after(&self) -> usize {
+        self.len_after
+    }
+
+    /// Returns whether the `ChangeSet` leaves the text unchanged.
+    #[inline]
+    pub fn is_identity(&self) -> bool {
+        self.ops.iter().all(|op| matches!(*op, Operation::Retain(_)))
+    }
+
+    /// The `ChangeSet`'s operations, from the start of the text to the end.
+    ///
+    /// A trailing retain of the rest of the text may be left implicit.
+    #[inline]
+    pub fn ops(&self) -> &[Operation] {
+        &self.ops
+    }
+
+    //-----------------------------------------------------------------------
+    // Building
+
+    /// Keeps the next `n` chars of the text unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this would go past the end of the text.
+    pub fn retain(&mut self, n: usize) {
+        self.advance(n);
+        if n == 0 {
+            return;
+        }
+
+        if let Some(&mut Operation::Retain(ref mut count)) = self.ops.last_mut() {
+            *count += n;
+            return;
+        }
+        self.ops.push(Operation::Retain(n));
+    }
+
+    /// Removes the next `n` chars of the text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this would go past the end of the text.
+    pub fn delete(&mut self, n: usize) {
+        self.advance(n);
+        if n == 0 {
+            return;
+        }
+        self.len_after -= n;
+
+        if let Some(&mut Operation::Delete(ref mut count)) = self.ops.last_mut() {
+            *count += n;
+            return;
+        }
+        self.ops.push(Operation::Delete(n));
+    }
+
+    /// Inserts `text` at the current position.
+    pub fn insert(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.len_after += count_chars(text);
+
+        // Inserts are always kept in front of an adjacent delete, so that
+        // there is only one way to represent a given change.
+        let len = self.ops.len();
+        let delete_at_end = matches!(self.ops.last(), Some(&Operation::Delete(_)));
+        let insert_idx = if delete_at_end { len - 1 } else { len };
+
+        if insert_idx > 0 {
+            if let Operation::Insert(ref mut prev) = self.ops[insert_idx - 1] {
+                prev.push_str(text);
+                return;
+            }
+        }
+        self.ops.insert(insert_idx, Operation::Insert(text.into()));
+    }
+
+    fn advance(&mut self, n: usize) {
+        assert!(
+            self.covered + n <= self.len,
+            "Attempt to build ChangeSet past end of text: {} chars past position {}, text length {}",
+            n,
+            self.covered,
+            self.len
+        );
+        self.covered += n;
+    }
+
+    //-----------------------------------------------------------------------
+    // Using
+
+    /// Applies the `ChangeSet` to `rope`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rope`'s length in chars doesn't match
+    /// [`len_before()`](#method.len_before).
+    pub fn apply(&self, rope: &mut Rope) {
+        assert_eq!(
+            rope.len_chars(),
+            self.len,
+            "Attempt to apply ChangeSet to a Rope of the wrong length"
+        );
+
+        rope.apply_edits(&self.to_edits());
+    }
+
+    /// Returns a `ChangeSet` that undoes this one.
+    ///
+    /// `original` must be the text from _before_ this `ChangeSet` is
+    /// applied, since that's where the text of the inverted deletions comes
+    /// from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `original`'s length in chars doesn't match
+    /// [`len_before()`](#method.len_before).
+    pub fn invert(&self, original: &Rope) -> ChangeSet {
+        assert_eq!(
+            original.len_chars(),
+            self.len,
+            "Attempt to invert ChangeSet against a Rope of the wrong length"
+        );
+
+        let mut inverted = ChangeSet::new(self.len_after);
+        let mut pos = 0;
+        for op in &self.ops {
+            match *op {
+                Operation::Retain(n) => {
+                    inverted.retain(n);
+                    pos += n;
+                }
+                Operation::Delete(n) => {
+                    for chunk in original.slice(pos..(pos + n)).chunks() {
+                        inverted.insert(chunk);
+                    }
+                    pos += n;
+                }
+                Operation::Insert(ref text) => {
+                    inverted.delete(count_chars(text));
+                }
+            }
+        }
+        inverted.finish()
+    }
+
+    /// Combines this `ChangeSet` with one that follows it into a single
+    /// `ChangeSet` with the same effect as applying both in sequence.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other`'s [`len_before()`](#method.len_before) doesn't match this
+    /// `ChangeSet`'s [`len_after()`](#method.len_after).
+    pub fn compose(&self, other: &ChangeSet) -> ChangeSet {
+        assert_eq!(
+            self.len_after, other.len,
+            "Attempt to compose ChangeSets with mismatched lengths"
+        );
+
+        let mut composed = ChangeSet::new(self.len);
+        let mut a_ops = self.full_ops().into_iter();
+        let mut b_ops = other.full_ops().into_iter();
+        let mut a = a_ops.next();
+        let mut b = b_ops.next();
+
+        loop {
+            match (a, b) {
+                (None, None) => break,
+
+                // Deletions from the first are unaffected by the second,
+                // and insertions from the second are unaffected by the first.
+                (Some(Operation::Delete(n)), b_op) => {
+                    composed.delete(n);
+                    a = a_ops.next();
+                    b = b_op;
+                }
+                (a_op, Some(Operation::Insert(text))) => {
+                    composed.insert(&text);
+                    a = a_op;
+                    b = b_ops.next();
+                }
+
+                (None, _) | (_, None) => unreachable!(),
+
+                (Some(Operation::Retain(i)), Some(Operation::Retain(j))) => {
+                    composed.retain(i.min(j));
+                    let (a_rest, b_rest) = split_pair(i, j);
+                    a = a_rest.map(Operation::Retain).or_else(|| a_ops.next());
+                    b = b_rest.map(Operation::Retain).or_else(|| b_ops.next());
+                }
+                (Some(Operation::Retain(i)), Some(Operation::Delete(j))) => {
+                    composed.delete(i.min(j));
+                    let (a_rest, b_rest) = split_pair(i, j);
+                    a = a_rest.map(Operation::Retain).or_else(|| a_ops.next());
+                    b = b_rest.map(Operation::Delete).or_else(|| b_ops.next());
+                }
+                (Some(Operation::Insert(text)), Some(Operation::Retain(j))) => {
+                    let (head, tail) = split_text(text, j);
+                    composed.insert(&head);
+                    let (_, b_rest) = split_pair(count_chars(&head), j);
+                    a = tail.map(Operation::Insert).or_else(|| a_ops.next());
+                    b = b_rest.map(Operation::Retain).or_else(|| b_ops.next());
+                }
+                (Some(Operation::Insert(text)), Some(Operation::Delete(j))) => {
+                    // Text inserted by the first and deleted by the second
+                    // cancels out.
+                    let (head, tail) = split_text(text, j);
+                    let (_, b_rest) = split_pair(count_chars(&head), j);
+                    a = tail.map(Operation::Insert).or_else(|| a_ops.next());
+                    b = b_rest.map(Operation::Delete).or_else(|| b_ops.next());
+                }
+            }
+        }
+
+        composed.finish()
+    }
+
+    /// Transforms this `ChangeSet` to apply after `other`, where both were
+    /// made concurrently against the same text.
+    ///
+    /// If `a` and `b` are concurrent `ChangeSet`s, then applying `b` followed
+    /// by `a.transform(&b, x)` gives the same result as applying `a` followed
+    /// by `b.transform(&a, !x)`.
+    ///
+    /// `self_first` decides the order of text that both `ChangeSet`s insert
+    /// at the same position: if true this `ChangeSet`'s text goes first,
+    /// and otherwise `other`'s does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the two `ChangeSet`s' [`len_before()`](#method.len_before)s don't match.
+    pub fn transform(&self, other: &ChangeSet, self_first: bool) -> ChangeSet {
+        assert_eq!(
+            self.len, other.len,
+            "Attempt to transform ChangeSets with mismatched lengths"
+        );
+
+        let mut transformed = ChangeSet::new(other.len_after);
+        let mut a_ops = self.full_ops().into_iter();
+        let mut b_ops = other.full_ops().into_iter();
+        let mut a = a_ops.next();
+        let mut b = b_ops.next();
+
+        loop {
+            match (a, b) {
+                (None, None) => break,
+
+                (Some(Operation::Insert(a_text)), Some(Operation::Insert(b_text))) => {
+                    if self_first {
+                        transformed.insert(&a_text);
+                        a = a_ops.next();
+                        b = Some(Operation::Insert(b_text));
+                    } else {
+                        transformed.retain(count_chars(&b_text));
+                        a = Some(Operation::Insert(a_text));
+                        b = b_ops.next();
+                    }
+                }
+                (Some(Operation::Insert(text)), b_op) => {
+                    transformed.insert(&text);
+                    a = a_ops.next();
+                    b = b_op;
+                }
+                (a_op, Some(Operation::Insert(text))) => {
+                    // Text inserted by the other is kept as-is.
+                    transformed.retain(count_chars(&text));
+                    a = a_op;
+                    b = b_ops.next();
+                }
+
+                (None, _) | (_, None) => unreachable!(),
+
+                (Some(Operation::Retain(i)), Some(Operation::Retain(j))) => {
+                    transformed.retain(i.min(j));
+                    let (a_rest, b_rest) = split_pair(i, j);
+                    a = a_rest.map(Operation::Retain).or_else(|| a_ops.next());
+                    b = b_rest.map(Operation::Retain).or_else(|| b_ops.next());
+                }
+                (Some(Operation::Delete(i)), Some(Operation::Retain(j))) => {
+                    transformed.delete(i.min(j));
+                    let (a_rest, b_rest) = split_pair(i, j);
+                    a = a_rest.map(Operation::Delete).or_else(|| a_ops.next());
+                    b = b_rest.map(Operation::Retain).or_else(|| b_ops.next());
+                }
+                (Some(Operation::Retain(i)), Some(Operation::Delete(j))) => {
+                    // Already deleted by the other.
+                    let (a_rest, b_rest) = split_pair(i, j);
+                    a = a_rest.map(Operation::Retain).or_else(|| a_ops.next());
+                    b = b_rest.map(Operation::Delete).or_else(|| b_ops.next());
+                }
+                (Some(Operation::Delete(i)), Some(Operation::Delete(j))) => {
+                    // Deleted by both.
+                    let (a_rest, b_rest) = split_pair(i, j);
+                    a = a_rest.map(Operation::Delete).or_else(|| a_ops.next());
+                    b = b_rest.map(Operation::Delete).or_else(|| b_ops.next());
+                }
+            }
+        }
+
+        transformed.finish()
+    }
+
+    /// Maps a char index in the text from before the `ChangeSet` is applied
+    /// to the corresponding char index after it is applied.
+    ///
+    /// Positions inside deleted text map to where the deletion happened.
+    /// `assoc` decides whether a position where text is inserted ends up
+    /// before or after that text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_before()`).
+    pub fn map_pos(&self, char_idx: usize, assoc: Assoc) -> usize {
+        assert!(
+            char_idx <= self.len,
+            "Attempt to map position past end of ChangeSet: char index {}, ChangeSet length {}",
+            char_idx,
+            self.len
+        );
+
+        let mut old_pos = 0;
+        let mut new_pos = 0;
+        for op in &self.ops {
+            match *op {
+                Operation::Retain(n) => {
+                    if char_idx < old_pos + n {
+                        return new_pos + (char_idx - old_pos);
+                    }
+                    old_pos += n;
+                    new_pos += n;
+                }
+                Operation::Delete(n) => {
+                    if char_idx < old_pos + n {
+                        return new_pos;
+                    }
+                    old_pos += n;
+                }
+                Operation::Insert(ref text) => {
+                    if char_idx == old_pos && assoc == Assoc::Before {
+                        return new_pos;
+                    }
+                    new_pos += count_chars(text);
+                }
+            }
+        }
+
+        // In the implicitly retained tail.
+        new_pos + (char_idx - old_pos)
+    }
+
+    //-----------------------------------------------------------------------
+    // Internal
+
+    /// Returns the `ChangeSet` with any implicit trailing retain made
+    /// explicit.
+    fn finish(mut self) -> ChangeSet {
+        let rest = self.len - self.covered;
+        self.retain(rest);
+        self
+    }
+
+    fn full_ops(&self) -> Vec<Operation> {
+        self.clone().finish().ops
+    }
+
+    /// The operations without any trailing retain, which may or may not
+    /// have been left implicit.
+    fn trimmed_ops(&self) -> &[Operation] {
+        match self.ops.last() {
+            Some(&Operation::Retain(_)) => &self.ops[..(self.ops.len() - 1)],
+            _ => &self.ops,
+        }
+    }
+
+    /// Converts the `ChangeSet` into equivalent `Edit`s, merging adjacent
+    /// deletes and inserts into single replacements.
+    fn to_edits(&self) -> Vec<Edit> {
+        let mut edits = Vec::new();
+        let mut pending: Option<Edit> = None;
+        let mut pos = 0;
+        for op in &self.ops {
+            match *op {
+                Operation::Retain(n) => {
+                    if let Some(edit) = pending.take() {
+                        edits.push(edit);
+                    }
+                    pos += n;
+                }
+                Operation::Delete(n) => {
+                    pending.get_or_insert_with(|| Edit::insert(pos, "")).range.end += n;
+                    pos += n;
+                }
+                Operation::Insert(ref text) => {
+                    pending
+                        .get_or_insert_with(|| Edit::insert(pos, ""))
+                        .text
+                        .push_str(text);
+                }
+            }
+        }
+        if let Some(edit) = pending.take() {
+            edits.push(edit);
+        }
+        edits
+    }
+}
+
+impl std::cmp::PartialEq for ChangeSet {
+    fn eq(&self, other: &ChangeSet) -> bool {
+        self.len == other.len && self.trimmed_ops() == other.trimmed_ops()
+    }
+}
+
+impl std::cmp::Eq for ChangeSet {}
+
+impl std::hash::Hash for ChangeSet {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        self.trimmed_ops().hash(state);
+    }
+}
+
+/// Given the lengths of two overlapping operations, returns what is left
+/// of each after consuming the shorter one.
+#[inline]
+fn split_pair(a: usize, b: usize) -> (Option<usize>, Option<usize>) {
+    match a.cmp(&b) {
+        std::cmp::Ordering::Less => (None, Some(b - a)),
+        std::cmp::Ordering::Equal => (None, None),
+        std::cmp::Ordering::Greater => (Some(a - b), None),
+    }
+}
+
+/// Splits `text` after its first `n` chars, returning the remainder as
+/// `None` if it would be empty.
+fn split_text(mut text: String, n: usize) -> (String, Option<String>) {
+    let byte_idx = char_to_byte_idx(&text, n);
+    if byte_idx == text.len() {
+        (text, None)
+    } else {
+        let tail = text.split_off(byte_idx);
+        (text, Some(tail))
+    }
+}
+
+//=============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 12 chars
+    const TEXT: &str = "Hello world!";
+
+    fn hello_there() -> ChangeSet {
+        let mut cs = ChangeSet::new(12);
+        cs.retain(6);
+        cs.delete(5);
+        cs.insert("there");
+        cs
+    }
+
+    #[test]
+    fn build_01() {
+        let mut cs = ChangeSet::new(10);
+        cs.retain(2);
+        cs.retain(1);
+        cs.delete(1);
+        cs.insert("a");
+        cs.delete(2);
+        cs.insert("bc");
+        cs.retain(0);
+        cs.insert("");
+
+        assert_eq!(
+            cs.ops(),
+            &[
+                Operation::Retain(3),
+                Operation::Insert("abc".into()),
+                Operation::Delete(3),
+            ]
+        );
+        assert_eq!(10, cs.len_before());
+        assert_eq!(10, cs.len_after());
+        assert!(!cs.is_identity());
+    }
+
+    #[test]
+    fn build_02() {
+        let mut cs1 = ChangeSet::new(10);
+        cs1.retain(4);
+        let mut cs2 = ChangeSet::new(10);
+        cs2.retain(10);
+
+        assert!(cs1.is_identity());
+        assert_eq!(cs1, cs2);
+        assert_eq!(cs1, ChangeSet::new(10));
+        assert!(cs1 != ChangeSet::new(11));
+    }
+
+    #[test]
+    #[should_panic]
+    fn build_03() {
+        let mut cs = ChangeSet::new(10);
+        cs.retain(4);
+        cs.delete(7);
+    }
+
+    #[test]
+    fn apply_01() {
+        let mut r = Rope::from_str(TEXT);
+        hello_there().apply(&mut r);
+
+        assert_eq!(r, "Hello there!");
+    }
+
+    #[test]
+    #[should_panic]
+    fn apply_02() {
+        let mut r = Rope::from_str("Hello");
+        hello_there().apply(&mut r);
+    }
+
+    #[test]
+    fn invert_01() {
+        let mut r = Rope::from_str(TEXT);
+        let cs = hello_there();
+        let inv = cs.invert(&r);
+
+        assert_eq!(12, inv.len_before());
+        assert_eq!(12, inv.len_after());
+
+        cs.apply(&mut r);
+        inv.apply(&mut r);
+        assert_eq!(r, TEXT);
+    }
+
+    #[test]
+    fn invert_02() {
+        let r = Rope::from_str(TEXT);
+        let cs = hello_there();
+
+        assert_eq!(cs, cs.invert(&r).invert(&Rope::from_str("Hello there!")));
+    }
+
+    #[test]
+    fn compose_01() {
+        let mut r1 = Rope::from_str(TEXT);
+        let mut r2 = r1.clone();
+
+        let a = hello_there();
+        let mut b = ChangeSet::new(12);
+        b.insert(">> ");
+        b.retain(8);
+        b.delete(3);
+        b.insert("ERE");
+
+        a.apply(&mut r1);
+        b.apply(&mut r1);
+        a.compose(&b).apply(&mut r2);
+
+        assert_eq!(r1, ">> Hello thERE!");
+        assert_eq!(r1, r2);
+        assert_eq!(12, a.compose(&b).len_before());
+        assert_eq!(15, a.compose(&b).len_after());
+    }
+
+    #[test]
+    fn compose_02() {
+        // Composing with the inverse leaves the text unchanged.
+        let mut r = Rope::from_str(TEXT);
+        let cs = hello_there();
+        let composed = cs.compose(&cs.invert(&r));
+        composed.apply(&mut r);
+
+        assert_eq!(12, composed.len_after());
+        assert_eq!(r, TEXT);
+    }
+
+    #[test]
+    fn compose_03() {
+        // Inserting and then deleting the same text cancels out.
+        let mut a = ChangeSet::new(12);
+        a.retain(5);
+        a.insert("abc");
+        let mut b = ChangeSet::new(15);
+        b.retain(4);
+        b.delete(3);
+
+        let mut expected = ChangeSet::new(12);
+        expected.retain(4);
+        expected.delete(1);
+        expected.insert("c");
+
+        assert_eq!(expected, a.compose(&b));
+    }
+
+    #[test]
+    fn transform_01() {
+        let r = Rope::from_str(TEXT);
+
+        let a = hello_there();
+        let mut b = ChangeSet::new(12);
+        b.delete(1);
+        b.insert("J");
+        b.retain(8);
+        b.delete(3);
+        b.insert("?");
+
+        let mut r1 = r.clone();
+        a.apply(&mut r1);
+        b.transform(&a, false).apply(&mut r1);
+
+        let mut r2 = r.clone();
+        b.apply(&mut r2);
+        a.transform(&b, true).apply(&mut r2);
+
+        assert_eq!(r1, "Jello there?");
+        assert_eq!(r1, r2);
+    }
+
+    #[test]
+    fn transform_02() {
+        // Insertions at the same position.
+        let r = Rope::from_str(TEXT);
+        let mut a = ChangeSet::new(12);
+        a.retain(5);
+        a.insert(",");
+        let mut b = ChangeSet::new(12);
+        b.retain(5);
+        b.insert("!");
+
+        for &a_first in &[true, false] {
+            let mut r1 = r.clone();
+            a.apply(&mut r1);
+            b.transform(&a, !a_first).apply(&mut r1);
+
+            let mut r2 = r.clone();
+            b.apply(&mut r2);
+            a.transform(&b, a_first).apply(&mut r2);
+
+            assert_eq!(r1, r2);
+            if a_first {
+                assert_eq!(r1, "Hello,! world!");
+            } else {
+                assert_eq!(r1, "Hello!, world!");
+            }
+        }
+    }
+
+    #[test]
+    fn map_pos_01() {
+        let cs = hello_there();
+
+        assert_eq!(0, cs.map_pos(0, Assoc::Before));
+        assert_eq!(5, cs.map_pos(5, Assoc::After));
+        assert_eq!(6, cs.map_pos(6, Assoc::Before));
+        assert_eq!(11, cs.map_pos(6, Assoc::After));
+        assert_eq!(11, cs.map_pos(8, Assoc::Before));
+        assert_eq!(11, cs.map_pos(11, Assoc::Before));
+        assert_eq!(12, cs.map_pos(12, Assoc::Before));
+    }
+
+    #[test]
+    fn map_pos_02() {
+        let mut cs = ChangeSet::new(12);
+        cs.insert("ab");
+        cs.retain(3);
+        cs.insert("c");
+
+        assert_eq!(0, cs.map_pos(0, Assoc::Before));
+        assert_eq!(2, cs.map_pos(0, Assoc::After));
+        assert_eq!(4, cs.map_pos(2, Assoc::After));
+        assert_eq!(5, cs.map_pos(3, Assoc::Before));
+        assert_eq!(6, cs.map_pos(3, Assoc::After));
+        assert_eq!(15, cs.map_pos(12, Assoc::After));
+    }
+
+    #[test]
+    #[should_panic]
+    fn map_pos_03() {
+        hello_there().map_pos(13, Assoc::Before);
+    }
+}
@@ -0,0 +1,55 @@
+use std::ops::Range;
+
+/// A single replacement of a char range with new text.
+///
+/// `Edit`s are applied as a batch with
+/// [`Rope::apply_edits()`](struct.Rope.html#method.apply_edits).  The char
+/// range of every `Edit` in a batch refers to the text _before_ any of the
+/// batch's edits are applied, so there is no need to adjust the indices of
+/// later edits to account for earlier ones.
+///
+/// Insertions and removals are just replacements with an empty range or
+/// empty text, respectively.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Edit {
+    /// The char range of the text to be replaced.
+    pub range: Range<usize>,
+    /// The text to replace it with.
+    pub text: String,
+}
+
+impl Edit {
+    /// Creates an `Edit` that replaces the text in `char_range` with `text`.
+    #[inline]
+    pub fn replace(char_range: Range<usize>, text: &str) -> Edit {
+        Edit {
+            range: char_range,
+            text: text.into(),
+        }
+    }
+
+    /// Creates an `Edit` that inserts `text` at `char_idx`.
+    #[inline]
+    pub fn insert(char_idx: usize, text: &str) -> Edit {
+        Edit::replace(char_idx..char_idx, text)
+    }
+
+    /// Creates an `Edit` that removes the text in `char_range`.
+    #[inline]
+    pub fn remove(char_range: Range<usize>) -> Edit {
+        Edit::replace(char_range, "")
+    }
+}
+
+/// A replacement made by
+/// [`Rope::replace_all()`](struct.Rope.html#method.replace_all) or
+/// [`Rope::replace_in()`](struct.Rope.html#method.replace_in).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Replacement {
+    /// The char range of the replaced text, in the text before the
+    /// replacements were made.
+    pub old_range: Range<usize>,
+    /// The char range of the replacement text, in the text after the
+    /// replacements were made.
+    pub new_range: Range<usize>,
+}
@@ -0,0 +1,911 @@
+use std;
+use std::borrow::Cow;
+use std::io;
+
+use LineEnding;
+
+/// A text encoding that text can be loaded from and saved in.
+///
+/// See [`Rope::from_reader_with_encoding()`](struct.Rope.html#method.from_reader_with_encoding)
+/// and [`Rope::write_to_with()`](struct.Rope.html#method.write_to_with).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// UTF-8.
+    Utf8,
+    /// UTF-16, little endian.
+    Utf16Le,
+    /// UTF-16, big endian.
+    Utf16Be,
+    /// ISO-8859-1 (Latin-1), which maps each byte to the Unicode scalar
+    /// value of the same number.
+    Latin1,
+    /// Windows-1252, the superset of ISO-8859-1 that has printable
+    /// characters in place of most of the C1 control codes.
+    ///
+    /// The five bytes that Windows-1252 leaves undefined are decoded to the
+    /// C1 control codes of the same number, like web browsers do.
+    Windows1252,
+}
+
+impl Encoding {
+    /// Returns the byte order mark of the encoding, or `None` if it doesn't
+    /// have one.
+    pub fn bom(&self) -> Option<&'static [u8]> {
+        match *self {
+            Encoding::Utf8 => Some(b"\xEF\xBB\xBF"),
+            Encoding::Utf16Le => Some(b"\xFF\xFE"),
+            Encoding::Utf16Be => Some(b"\xFE\xFF"),
+            Encoding::Latin1 | Encoding::Windows1252 => None,
+        }
+    }
+
+    /// Appends the encoded bytes of `c` to `out`, returning false if `c`
+    /// can't be represented in the encoding.
+    pub(crate) fn encode_char(&self, c: char, out: &mut Vec<u8>) -> bool {
+        match *self {
+            Encoding::Utf8 => {
+                let mut buf = [0u8; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let mut buf = [0u16; 2];
+                for &unit in c.encode_utf16(&mut buf).iter() {
+                    if *self == Encoding::Utf16Le {
+                        out.extend_from_slice(&unit.to_le_bytes());
+                    } else {
+                        out.extend_from_slice(&unit.to_be_bytes());
+                    }
+                }
+            }
+            Encoding::Latin1 => match c as u32 {
+                0..=0xFF => out.push(c as u8),
+                _ => return false,
+            },
+            Encoding::Windows1252 => match c as u32 {
+                0..=0x7F | 0xA0..=0xFF => out.push(c as u8),
+                _ => match WINDOWS_1252_HIGH.iter().position(|&high| high == c) {
+                    Some(i) => out.push(0x80 + i as u8),
+                    None => return false,
+                },
+            },
+        }
+        true
+    }
+
+    /// Guesses the encoding of text from its first bytes.
+    ///
+    /// `prefix` can be any amount of text from the start, and may end in
+    /// the middle of a character.  The more text there is, the better the
+    /// guess, but a few kilobytes is usually plenty.
+    ///
+    /// The guess is based on, in order:
+    ///
+    /// 1. A byte order mark, which settles it.
+    /// 2. The pattern of zero bytes that mostly-ASCII UTF-16 text has in
+    ///    every other byte.
+    /// 3. Whether the text is valid UTF-8, which text in other encodings
+    ///    almost never is once it has any non-ASCII characters.
+    /// 4. Otherwise, whether the text has bytes in the range 0x80 to 0x9F,
+    ///    which are printable characters in Windows-1252 but control codes
+    ///    in ISO-8859-1.
+    ///
+    /// Text with zero bytes that isn't UTF-16, or with a lot of control
+    /// codes, is also flagged as likely being binary data.
+    ///
+    /// Runs in O(N) time in the length of `prefix`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::{Encoding, Rope};
+    /// let bytes: &[u8] = b"\x93Caf\xE9\x94";
+    /// let detection = Encoding::detect(bytes);
+    /// assert_eq!(detection.encoding(), Encoding::Windows1252);
+    ///
+    /// let (rope, _) = Rope::from_reader_with_encoding(bytes, detection.encoding()).unwrap();
+    /// assert_eq!(rope, "\u{201C}Caf\u{E9}\u{201D}");
+    /// ```
+    pub fn detect(prefix: &[u8]) -> EncodingDetection {
+        let mut guesses = Vec::new();
+        let mut guess = |encoding: Encoding, confidence: f32| {
+            guesses.push(EncodingGuess {
+                encoding,
+                confidence,
+            })
+        };
+
+        let stats = ByteStats::new(prefix);
+        let is_utf8 = is_utf8_prefix(prefix);
+        let mut is_binary = stats.zeros > 0 || (stats.controls * 10) > prefix.len();
+
+        let bom = [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be]
+            .iter()
+            .cloned()
+            .find(|encoding| prefix.starts_with(encoding.bom().unwrap()));
+        let utf16 = utf16_by_zeros(prefix);
+
+        if let Some(encoding) = bom {
+            guess(encoding, 1.0);
+            is_binary = false;
+        } else if let Some((encoding, confidence)) = utf16 {
+            guess(encoding, confidence);
+            is_binary = false;
+        }
+
+        if is_utf8 && (bom.is_none() || bom == Some(Encoding::Utf8)) {
+            // Multi-byte sequences that happen to be valid UTF-8 are rare in
+            // other encodings, and ASCII reads the same either way.
+            if bom.is_none() {
+                guess(Encoding::Utf8, 1.0);
+            }
+            let confidence = if stats.high > 0 { 0.1 } else { 1.0 };
+            guess(Encoding::Windows1252, confidence);
+            guess(Encoding::Latin1, confidence);
+        } else {
+            guess(
+                Encoding::Windows1252,
+                if stats.undefined_1252 > 0 { 0.2 } else { 0.8 },
+            );
+            guess(Encoding::Latin1, if stats.c1 > 0 { 0.2 } else { 0.8 });
+        }
+
+        if bom.is_some() || utf16.is_some() {
+            // Anything else is much less likely after a byte order mark or
+            // UTF-16 pattern.
+            for guess in guesses.iter_mut().skip(1) {
+                guess.confidence = guess.confidence.min(0.1);
+            }
+        }
+        guesses.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
+
+        EncodingDetection {
+            guesses,
+            is_binary,
+        }
+    }
+
+    /// Like [`detect()`](#method.detect), but guesses from the buffered
+    /// text of a reader, without consuming it.
+    ///
+    /// The reader's buffer is filled if it's empty, but not beyond that, so
+    /// the guess is based on at most one buffer's worth of text.  The
+    /// reader can then be passed on to e.g.
+    /// [`Rope::from_reader_with_encoding()`](struct.Rope.html#method.from_reader_with_encoding)
+    /// to load the text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::BufReader;
+    /// # use ropey::{Encoding, Rope};
+    /// let bytes: &[u8] = b"\xFF\xFEh\x00i\x00";
+    /// let mut reader = BufReader::new(bytes);
+    /// let detection = Encoding::detect_reader(&mut reader).unwrap();
+    /// let (rope, info) = Rope::from_reader_with_encoding(reader, detection.encoding()).unwrap();
+    ///
+    /// assert_eq!(rope, "hi");
+    /// assert_eq!(info.encoding, Encoding::Utf16Le);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the reader returns an error, that error is returned.
+    pub fn detect_reader<R: io::BufRead>(reader: &mut R) -> io::Result<EncodingDetection> {
+        loop {
+            match reader.fill_buf() {
+                Ok(prefix) => return Ok(Encoding::detect(prefix)),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Guesses of the encoding of some text, made by
+/// [`Encoding::detect()`](enum.Encoding.html#method.detect).
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodingDetection {
+    /// The encodings the text could be in, most likely first.  Encodings
+    /// that the text isn't valid in are left out, so this only ever
+    /// includes UTF-16 when there is a byte order mark or zero byte
+    /// pattern, and only includes UTF-8 when the text is valid UTF-8.
+    /// ISO-8859-1 and Windows-1252 can decode anything, so there is always
+    /// at least one guess.
+    pub guesses: Vec<EncodingGuess>,
+    /// Whether the text looks like binary data rather than text.
+    pub is_binary: bool,
+}
+
+impl EncodingDetection {
+    /// Returns the most likely encoding.
+    #[inline]
+    pub fn encoding(&self) -> Encoding {
+        self.guesses[0].encoding
+    }
+}
+
+/// A possible encoding of some text.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EncodingGuess {
+    /// The encoding.
+    pub encoding: Encoding,
+    /// How likely the text is to be in the encoding, from 0.0 to 1.0.
+    ///
+    /// Several encodings can have a confidence of 1.0, e.g. ASCII text is
+    /// equally valid in UTF-8, ISO-8859-1 and Windows-1252.
+    pub confidence: f32,
+}
+
+/// Byte counts used by `Encoding::detect()`.
+struct ByteStats {
+    zeros: usize,
+    // Control codes other than the usual whitespace and escape.
+    controls: usize,
+    // Bytes 0x80 and up.
+    high: usize,
+    // Bytes 0x80 to 0x9F.
+    c1: usize,
+    // Bytes that are undefined in Windows-1252.
+    undefined_1252: usize,
+}
+
+impl ByteStats {
+    fn new(bytes: &[u8]) -> ByteStats {
+        let mut stats = ByteStats {
+            zeros: 0,
+            controls: 0,
+            high: 0,
+            c1: 0,
+            undefined_1252: 0,
+        };
+        for &byte in bytes {
+            match byte {
+                0x00 => stats.zeros += 1,
+                0x09 | 0x0A | 0x0C | 0x0D | 0x1B => {}
+                0x01..=0x1F | 0x7F => stats.controls += 1,
+                0x81 | 0x8D | 0x8F | 0x90 | 0x9D => {
+                    stats.high += 1;
+                    stats.c1 += 1;
+                    stats.undefined_1252 += 1;
+                }
+                0x80..=0x9F => {
+                    stats.high += 1;
+                    stats.c1 += 1;
+                }
+                0xA0..=0xFF => stats.high += 1,
+                _ => {}
+            }
+        }
+        stats
+    }
+}
+
+/// Returns whether `bytes` is valid UTF-8, except that it may end in the
+/// middle of a character.
+fn is_utf8_prefix(bytes: &[u8]) -> bool {
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+/// Recognizes UTF-16 text by the zero high bytes of its ASCII characters,
+/// returning the byte order and a confidence.
+fn utf16_by_zeros(bytes: &[u8]) -> Option<(Encoding, f32)> {
+    let units = bytes.len() / 2;
+    if units == 0 {
+        return None;
+    }
+    let even_zeros = bytes.iter().step_by(2).take(units).filter(|&&b| b == 0).count();
+    let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+
+    // In UTF-16 text with even a little ASCII, many characters have a zero
+    // high byte, and next to none have a zero low byte.
+    let (encoding, zeros, other_zeros) = if odd_zeros > even_zeros {
+        (Encoding::Utf16Le, odd_zeros, even_zeros)
+    } else {
+        (Encoding::Utf16Be, even_zeros, odd_zeros)
+    };
+    if (zeros * 10) < units || (other_zeros * 20) > units {
+        return None;
+    }
+
+    let to_unit: fn([u8; 2]) -> u16 = if encoding == Encoding::Utf16Le {
+        u16::from_le_bytes
+    } else {
+        u16::from_be_bytes
+    };
+    let mut text = String::new();
+    if decode_utf16(&bytes[..(units * 2)], &mut text, false, to_unit).is_err() {
+        return None;
+    }
+
+    let confidence = 0.5 + (0.5 * zeros as f32 / units as f32);
+    Some((encoding, confidence))
+}
+
+/// The encoding that text was loaded from, and whether it started with a
+/// byte order mark.
+///
+/// Returned by
+/// [`Rope::from_reader_with_encoding()`](struct.Rope.html#method.from_reader_with_encoding)
+/// and [`Rope::from_reader_with()`](struct.Rope.html#method.from_reader_with),
+/// so that text can be saved the same way it was loaded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EncodingInfo {
+    /// The encoding the text was decoded from.
+    ///
+    /// This differs from the requested encoding when a UTF-16 byte order
+    /// mark gave the other byte order.
+    pub encoding: Encoding,
+    /// Whether the text started with a byte order mark.  The byte order
+    /// mark isn't part of the decoded text.
+    pub bom: bool,
+}
+
+impl From<EncodingInfo> for WriteOptions {
+    /// Returns options for writing text the same way it was loaded,
+    /// including any escaped bytes.
+    #[inline]
+    fn from(info: EncodingInfo) -> WriteOptions {
+        WriteOptions {
+            encoding: info.encoding,
+            bom: info.bom,
+            line_ending: None,
+            escaped_bytes: true,
+        }
+    }
+}
+
+/// Options for loading text with
+/// [`Rope::from_reader_with()`](struct.Rope.html#method.from_reader_with).
+///
+/// The defaults load utf8 text, failing on invalid utf8.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ReadOptions {
+    /// The encoding to decode the text from.  A byte order mark at the
+    /// start of the text is removed, and for UTF-16 it also determines the
+    /// byte order.
+    pub encoding: Encoding,
+    /// How to handle invalid utf8.  Ignored for other encodings.
+    pub invalid_utf8: InvalidUtf8,
+}
+
+impl Default for ReadOptions {
+    #[inline]
+    fn default() -> ReadOptions {
+        ReadOptions {
+            encoding: Encoding::Utf8,
+            invalid_utf8: InvalidUtf8::Error,
+        }
+    }
+}
+
+/// How [`Rope::from_reader_with()`](struct.Rope.html#method.from_reader_with)
+/// handles invalid utf8.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum InvalidUtf8 {
+    /// Fail with an IO error with kind `InvalidData`.
+    Error,
+    /// Load each byte of an invalid sequence as an escaped byte: a char in
+    /// the range U+10FF00 to U+10FFFF (see
+    /// [`str_utils::escape_byte()`](str_utils/fn.escape_byte.html)) that
+    /// stands in for it.
+    ///
+    /// The `Rope` keeps track of which chars are escaped bytes separately
+    /// from its text, so valid chars in that range are never mistaken for
+    /// them.  Escaped bytes move along with edits, and are dropped when
+    /// their chars are removed or replaced.  They aren't kept by slices or
+    /// by copies of the text, e.g. with `to_string()`.
+    Escape,
+}
+
+/// Options for writing text with
+/// [`Rope::write_to_with()`](struct.Rope.html#method.write_to_with).
+///
+/// The defaults write the text as-is, as utf8 without a byte order mark.
+/// Options for writing text in the same encoding it was loaded from can be
+/// made from an [`EncodingInfo`](struct.EncodingInfo.html) with `From`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WriteOptions {
+    /// The encoding to write the text in.
+    pub encoding: Encoding,
+    /// Whether to start with the encoding's byte order mark.  Ignored for
+    /// encodings that don't have one.
+    pub bom: bool,
+    /// The line ending to write LF, CR and CRLF line endings as, or `None`
+    /// to leave them as they are.  Other line breaks are always left as
+    /// they are.
+    pub line_ending: Option<LineEnding>,
+    /// Whether to write the escaped bytes from loading invalid utf8 (see
+    /// [`InvalidUtf8::Escape`](enum.InvalidUtf8.html#variant.Escape)) as
+    /// the raw bytes they stand for, whatever the encoding.  Otherwise they
+    /// are written like any other char.
+    pub escaped_bytes: bool,
+}
+
+impl Default for WriteOptions {
+    #[inline]
+    fn default() -> WriteOptions {
+        WriteOptions {
+            encoding: Encoding::Utf8,
+            bom: false,
+            line_ending: None,
+            escaped_bytes: false,
+        }
+    }
+}
+
+/// The chars that bytes 0x80 to 0x9F map to in Windows-1252.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+/// Incrementally decodes text in an `Encoding` to utf8.
+///
+/// Bytes can be fed in pieces of any size: any bytes at the end of a piece
+/// that can't be decoded yet (e.g. part of a multi-byte character, or a
+/// byte order mark that might still turn out to be one) are held on to
+/// until the next piece.
+pub(crate) struct Decoder {
+    encoding: Encoding,
+    bom: Option<bool>, // `None` until the start of the text has been seen.
+    pending: Vec<u8>,
+}
+
+impl Decoder {
+    pub(crate) fn new(encoding: Encoding) -> Decoder {
+        Decoder {
+            encoding,
+            bom: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns the encoding and byte order mark seen so far.
+    pub(crate) fn info(&self) -> EncodingInfo {
+        EncodingInfo {
+            encoding: self.encoding,
+            bom: self.bom.unwrap_or(false),
+        }
+    }
+
+    /// Decodes `bytes`, appending the text to `out`.  `last` indicates
+    /// that there are no more bytes to come.
+    ///
+    /// Returns an error with kind `InvalidData` if the bytes aren't valid
+    /// in the encoding.
+    pub(crate) fn decode(&mut self, bytes: &[u8], out: &mut String, last: bool) -> io::Result<()> {
+        let mut data = std::mem::take(&mut self.pending);
+        data.extend_from_slice(bytes);
+
+        let mut start = 0;
+        if self.bom.is_none() {
+            match self.detect_bom(&data, last) {
+                Some(bom_len) => start = bom_len,
+                None => {
+                    self.pending = data;
+                    return Ok(());
+                }
+            }
+        }
+
+        let used = match self.encoding {
+            Encoding::Utf8 => decode_utf8(&data[start..], out, last)?,
+            Encoding::Utf16Le => decode_utf16(&data[start..], out, last, u16::from_le_bytes)?,
+            Encoding::Utf16Be => decode_utf16(&data[start..], out, last, u16::from_be_bytes)?,
+            Encoding::Latin1 => {
+                out.extend(data[start..].iter().map(|&byte| byte as char));
+                data.len() - start
+            }
+            Encoding::Windows1252 => {
+                out.extend(data[start..].iter().map(|&byte| match byte {
+                    0x80..=0x9F => WINDOWS_1252_HIGH[(byte - 0x80) as usize],
+                    _ => byte as char,
+                }));
+                data.len() - start
+            }
+        };
+
+        data.drain(..(start + used));
+        self.pending = data;
+        Ok(())
+    }
+
+    /// Removes the byte order mark from `bytes`, without decoding them.
+    /// This is for text that is handled as raw bytes instead, and returns
+    /// the bytes to handle.  `last` indicates that there are no more bytes
+    /// to come.
+    pub(crate) fn strip_bom<'a>(&mut self, bytes: &'a [u8], last: bool) -> Cow<'a, [u8]> {
+        if self.bom.is_some() {
+            return Cow::Borrowed(bytes);
+        }
+
+        let mut data = std::mem::take(&mut self.pending);
+        data.extend_from_slice(bytes);
+        match self.detect_bom(&data, last) {
+            Some(bom_len) => {
+                data.drain(..bom_len);
+                Cow::Owned(data)
+            }
+            None => {
+                self.pending = data;
+                Cow::Borrowed(&[])
+            }
+        }
+    }
+
+    /// Checks for a byte order mark at the start of the text, and returns
+    /// its length, or `None` if there isn't enough text yet to tell.
+    ///
+    /// A UTF-16 byte order mark for the other byte order switches the
+    /// decoder to that byte order.
+    fn detect_bom(&mut self, data: &[u8], last: bool) -> Option<usize> {
+        let boms: &[Encoding] = match self.encoding {
+            Encoding::Utf8 => &[Encoding::Utf8],
+            Encoding::Utf16Le | Encoding::Utf16Be => &[Encoding::Utf16Le, Encoding::Utf16Be],
+            Encoding::Latin1 | Encoding::Windows1252 => &[],
+        };
+
+        for &encoding in boms {
+            let bom = encoding.bom().unwrap();
+            if data.starts_with(bom) {
+                self.encoding = encoding;
+                self.bom = Some(true);
+                return Some(bom.len());
+            }
+            if !last && data.len() < bom.len() && bom.starts_with(data) {
+                return None;
+            }
+        }
+
+        self.bom = Some(false);
+        Some(0)
+    }
+}
+
+fn invalid_data(encoding: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("stream did not contain valid {}", encoding),
+    )
+}
+
+/// Decodes as much of `bytes` as possible, and returns how many bytes were
+/// used.
+fn decode_utf8(bytes: &[u8], out: &mut String, last: bool) -> io::Result<usize> {
+    let (valid, rest_is_incomplete) = match std::str::from_utf8(bytes) {
+        Ok(text) => (text, false),
+        Err(e) => (
+            // Safe because the bytes have just been validated.
+            unsafe { std::str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) },
+            e.error_len().is_none(),
+        ),
+    };
+
+    if valid.len() < bytes.len() && (last || !rest_is_incomplete) {
+        return Err(invalid_data("UTF-8"));
+    }
+    out.push_str(valid);
+    Ok(valid.len())
+}
+
+/// Decodes as much of `bytes` as possible, and returns how many bytes were
+/// used.
+fn decode_utf16(
+    bytes: &[u8],
+    out: &mut String,
+    last: bool,
+    to_unit: fn([u8; 2]) -> u16,
+) -> io::Result<usize> {
+    let units = bytes.chunks_exact(2).map(|pair| to_unit([pair[0], pair[1]]));
+    let mut used = 0;
+    for c in std::char::decode_utf16(units) {
+        match c {
+            Ok(c) => {
+                out.push(c);
+                used += c.len_utf16() * 2;
+            }
+            Err(e) => {
+                // A leading surrogate at the very end may just be waiting
+                // for its trailing surrogate.
+                let is_leading = (0xD800..0xDC00).contains(&e.unpaired_surrogate());
+                if !last && is_leading && (used + 2) == (bytes.len() & !1) {
+                    break;
+                }
+                return Err(invalid_data("UTF-16"));
+            }
+        }
+    }
+
+    if last && used < bytes.len() {
+        return Err(invalid_data("UTF-16"));
+    }
+    Ok(used)
+}
+
+//=============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes `bytes` in pieces of `piece_len` bytes.
+    fn decode_in_pieces(
+        encoding: Encoding,
+        bytes: &[u8],
+        piece_len: usize,
+    ) -> io::Result<(String, EncodingInfo)> {
+        let mut decoder = Decoder::new(encoding);
+        let mut text = String::new();
+        for piece in bytes.chunks(piece_len) {
+            decoder.decode(piece, &mut text, false)?;
+        }
+        decoder.decode(&[], &mut text, true)?;
+        Ok((text, decoder.info()))
+    }
+
+    fn assert_decodes(encoding: Encoding, bytes: &[u8], text: &str, info: EncodingInfo) {
+        for piece_len in 1..(bytes.len() + 2) {
+            let (decoded, decoded_info) = decode_in_pieces(encoding, bytes, piece_len).unwrap();
+            assert_eq!(decoded, text);
+            assert_eq!(decoded_info, info);
+        }
+    }
+
+    fn info(encoding: Encoding, bom: bool) -> EncodingInfo {
+        EncodingInfo {
+            encoding: encoding,
+            bom: bom,
+        }
+    }
+
+    #[test]
+    fn decode_utf8_01() {
+        let text = "a\u{E9}\u{3053}\u{1F438}";
+        assert_decodes(Encoding::Utf8, text.as_bytes(), text, info(Encoding::Utf8, false));
+
+        let mut bytes = b"\xEF\xBB\xBF".to_vec();
+        bytes.extend_from_slice(text.as_bytes());
+        assert_decodes(Encoding::Utf8, &bytes, text, info(Encoding::Utf8, true));
+
+        // Only the first byte order mark is removed.
+        assert_decodes(
+            Encoding::Utf8,
+            b"\xEF\xBB\xBF\xEF\xBB\xBF",
+            "\u{FEFF}",
+            info(Encoding::Utf8, true),
+        );
+    }
+
+    #[test]
+    fn decode_utf8_02() {
+        for &bytes in &[&b"a\xFFb"[..], b"a\xE3\x81", b"\xEF\xBB"] {
+            for piece_len in 1..4 {
+                let err = decode_in_pieces(Encoding::Utf8, bytes, piece_len).unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_utf16_01() {
+        let text = "a\u{E9}\u{3053}\u{1F438}";
+        let le: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_le_bytes().to_vec()).collect();
+        let be: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_be_bytes().to_vec()).collect();
+        assert_decodes(Encoding::Utf16Le, &le, text, info(Encoding::Utf16Le, false));
+        assert_decodes(Encoding::Utf16Be, &be, text, info(Encoding::Utf16Be, false));
+
+        // The byte order mark wins over the requested byte order.
+        let mut bytes = b"\xFE\xFF".to_vec();
+        bytes.extend_from_slice(&be);
+        assert_decodes(Encoding::Utf16Le, &bytes, text, info(Encoding::Utf16Be, true));
+        let mut bytes = b"\xFF\xFE".to_vec();
+        bytes.extend_from_slice(&le);
+        assert_decodes(Encoding::Utf16Be, &bytes, text, info(Encoding::Utf16Le, true));
+
+        assert_decodes(Encoding::Utf16Le, b"", "", info(Encoding::Utf16Le, false));
+    }
+
+    #[test]
+    fn decode_utf16_02() {
+        // Odd length, unpaired surrogates.
+        for &bytes in &[&b"a\x00b"[..], b"\x3D\xD8a\x00", b"a\x00\x3D\xD8", b"\x38\xDC"] {
+            for piece_len in 1..5 {
+                let err = decode_in_pieces(Encoding::Utf16Le, bytes, piece_len).unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_single_byte_01() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let latin_1: String = (0..=255u8).map(|byte| byte as char).collect();
+        assert_decodes(Encoding::Latin1, &bytes, &latin_1, info(Encoding::Latin1, false));
+
+        let (windows_1252, _) = decode_in_pieces(Encoding::Windows1252, &bytes, 7).unwrap();
+        assert_eq!(windows_1252.chars().count(), 256);
+        assert_eq!(&windows_1252[..0x80], &latin_1[..0x80]);
+        assert_eq!(windows_1252.chars().nth(0x80), Some('\u{20AC}'));
+        assert_eq!(windows_1252.chars().nth(0x81), Some('\u{81}'));
+        assert_eq!(windows_1252.chars().nth(0x9F), Some('\u{178}'));
+        assert!(windows_1252.ends_with(&latin_1[latin_1.len() - 192..]));
+
+        // No byte order marks in single byte encodings.
+        assert_decodes(
+            Encoding::Latin1,
+            b"\xEF\xBB\xBF",
+            "\u{EF}\u{BB}\u{BF}",
+            info(Encoding::Latin1, false),
+        );
+    }
+
+    #[test]
+    fn strip_bom_01() {
+        let bytes = b"\xEF\xBB\xBFa\xFF";
+        for piece_len in 1..(bytes.len() + 2) {
+            let mut decoder = Decoder::new(Encoding::Utf8);
+            let mut stripped = Vec::new();
+            for piece in bytes.chunks(piece_len) {
+                stripped.extend_from_slice(&decoder.strip_bom(piece, false));
+            }
+            stripped.extend_from_slice(&decoder.strip_bom(&[], true));
+            assert_eq!(stripped, b"a\xFF");
+            assert_eq!(decoder.info(), info(Encoding::Utf8, true));
+        }
+
+        // Part of a byte order mark at the very end is kept.
+        let mut decoder = Decoder::new(Encoding::Utf8);
+        assert!(decoder.strip_bom(b"\xEF\xBB", false).is_empty());
+        assert_eq!(&*decoder.strip_bom(&[], true), b"\xEF\xBB");
+        assert_eq!(decoder.info(), info(Encoding::Utf8, false));
+    }
+
+    fn encode(encoding: Encoding, text: &str) -> Option<Vec<u8>> {
+        let mut out = Vec::new();
+        for c in text.chars() {
+            if !encoding.encode_char(c, &mut out) {
+                return None;
+            }
+        }
+        Some(out)
+    }
+
+    #[test]
+    fn encode_char_01() {
+        let text = "Hi \u{E9}\u{20AC}\u{1F600}";
+        assert_eq!(encode(Encoding::Utf8, text).unwrap(), text.as_bytes());
+        assert_eq!(
+            encode(Encoding::Utf16Le, text).unwrap(),
+            b"H\x00i\x00 \x00\xE9\x00\xAC\x20\x3D\xD8\x00\xDE"
+        );
+        assert_eq!(
+            encode(Encoding::Utf16Be, text).unwrap(),
+            b"\x00H\x00i\x00 \x00\xE9\x20\xAC\xD8\x3D\xDE\x00"
+        );
+        assert_eq!(encode(Encoding::Latin1, text), None);
+        assert_eq!(encode(Encoding::Windows1252, text), None);
+        assert_eq!(encode(Encoding::Latin1, "Hi \u{E9}\u{80}").unwrap(), b"Hi \xE9\x80");
+        assert_eq!(encode(Encoding::Windows1252, "Hi \u{E9}\u{80}"), None);
+    }
+
+    #[test]
+    fn encode_char_02() {
+        // Everything that decodes round-trips.
+        let bytes: Vec<u8> = (0..=255).collect();
+        for &encoding in &[Encoding::Latin1, Encoding::Windows1252] {
+            let (text, _) = decode_in_pieces(encoding, &bytes, 256).unwrap();
+            assert_eq!(encode(encoding, &text).unwrap(), bytes);
+        }
+    }
+
+    fn encodings(detection: &EncodingDetection) -> Vec<Encoding> {
+        detection.guesses.iter().map(|guess| guess.encoding).collect()
+    }
+
+    #[test]
+    fn detect_01() {
+        // ASCII and UTF-8.
+        let detection = Encoding::detect(b"Hello\r\nworld!");
+        assert_eq!(
+            encodings(&detection),
+            vec![Encoding::Utf8, Encoding::Windows1252, Encoding::Latin1]
+        );
+        assert!(detection.guesses.iter().all(|guess| guess.confidence == 1.0));
+        assert!(!detection.is_binary);
+
+        let detection = Encoding::detect(b"");
+        assert_eq!(detection.encoding(), Encoding::Utf8);
+        assert!(!detection.is_binary);
+
+        // Ending in the middle of a char.
+        let detection = Encoding::detect(&"Hello みんな".as_bytes()[..13]);
+        assert_eq!(
+            encodings(&detection),
+            vec![Encoding::Utf8, Encoding::Windows1252, Encoding::Latin1]
+        );
+        assert_eq!(detection.guesses[0].confidence, 1.0);
+        assert!(detection.guesses[1].confidence < 0.5);
+    }
+
+    #[test]
+    fn detect_02() {
+        // Byte order marks.
+        let detection = Encoding::detect(b"\xEF\xBB\xBFHi");
+        assert_eq!(detection.encoding(), Encoding::Utf8);
+        assert!(detection.guesses[1].confidence < 0.5);
+
+        let detection = Encoding::detect(b"\xFF\xFEH\x00i\x00");
+        assert_eq!(detection.encoding(), Encoding::Utf16Le);
+        assert_eq!(detection.guesses[0].confidence, 1.0);
+        assert!(!detection.is_binary);
+
+        let detection = Encoding::detect(b"\xFE\xFF\x30\x7F\x30\x93");
+        assert_eq!(detection.encoding(), Encoding::Utf16Be);
+        assert!(!detection.is_binary);
+    }
+
+    #[test]
+    fn detect_03() {
+        // UTF-16 without a byte order mark.
+        let text = "Hello みんな! Hello world!";
+        let le: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_le_bytes().to_vec()).collect();
+        let be: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_be_bytes().to_vec()).collect();
+
+        let detection = Encoding::detect(&le[..(le.len() - 1)]);
+        assert_eq!(detection.encoding(), Encoding::Utf16Le);
+        assert!(detection.guesses[0].confidence > 0.5);
+        assert!(detection.guesses[1].confidence < 0.5);
+        assert!(!detection.is_binary);
+
+        let detection = Encoding::detect(&be);
+        assert_eq!(detection.encoding(), Encoding::Utf16Be);
+        assert!(!detection.is_binary);
+    }
+
+    #[test]
+    fn detect_04() {
+        // Single byte encodings.
+        let detection = Encoding::detect(b"Caf\xE9 cr\xE8me");
+        assert_eq!(
+            encodings(&detection),
+            vec![Encoding::Windows1252, Encoding::Latin1]
+        );
+        assert!(!detection.is_binary);
+
+        let detection = Encoding::detect(b"\x93Caf\xE9\x94");
+        assert_eq!(detection.encoding(), Encoding::Windows1252);
+        assert!(detection.guesses[0].confidence > detection.guesses[1].confidence);
+
+        let detection = Encoding::detect(b"\x81Caf\xE9\x8D");
+        assert_eq!(detection.guesses[0].confidence, detection.guesses[1].confidence);
+        assert!(detection.guesses[0].confidence < 0.5);
+    }
+
+    #[test]
+    fn detect_05() {
+        // Binary data.
+        let detection = Encoding::detect(b"\x7FELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00");
+        assert!(detection.is_binary);
+        assert!(!detection.guesses.is_empty());
+
+        let detection = Encoding::detect(b"\x01\x02\x03\x04 text \x05\x06");
+        assert!(detection.is_binary);
+    }
+
+    #[test]
+    fn detect_reader_01() {
+        let mut reader = io::BufReader::with_capacity(4, &b"\xFE\xFF\x00h\x00i"[..]);
+        let detection = Encoding::detect_reader(&mut reader).unwrap();
+        assert_eq!(detection.encoding(), Encoding::Utf16Be);
+
+        // Nothing is consumed.
+        let mut bytes = Vec::new();
+        io::Read::read_to_end(&mut reader, &mut bytes).unwrap();
+        assert_eq!(bytes, b"\xFE\xFF\x00h\x00i");
+    }
+}
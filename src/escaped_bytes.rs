@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+/// The raw bytes that a `Rope`'s chars stand in for, from loading invalid
+/// utf8 with [`InvalidUtf8::Escape`](enum.InvalidUtf8.html).
+///
+/// These are kept apart from the text, so that the chars standing in for
+/// bytes are never confused with the same chars in the text itself.  Each
+/// entry is the char index of a stand-in char and the byte it stands for,
+/// sorted by char index.  The entries are shared between clones, and only
+/// copied when one of the clones is edited.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EscapedBytes {
+    entries: Option<Arc<Vec<(usize, u8)>>>,
+}
+
+impl EscapedBytes {
+    #[inline]
+    pub(crate) fn new() -> EscapedBytes {
+        EscapedBytes { entries: None }
+    }
+
+    /// Creates the escaped bytes from entries sorted by char index.
+    pub(crate) fn from_sorted(entries: Vec<(usize, u8)>) -> EscapedBytes {
+        if entries.is_empty() {
+            EscapedBytes::new()
+        } else {
+            EscapedBytes {
+                entries: Some(Arc::new(entries)),
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_none()
+    }
+
+    #[inline]
+    pub(crate) fn entries(&self) -> &[(usize, u8)] {
+        match self.entries {
+            Some(ref entries) => entries,
+            None => &[],
+        }
+    }
+
+    /// Updates the entries for a batch of replacements, each given as
+    /// `(start, end, inserted_char_count)`, sorted by position and
+    /// non-overlapping.
+    ///
+    /// Entries for replaced chars are dropped, since their stand-in chars
+    /// are gone, and the rest move with their chars.  Runs in O(K + E) time
+    /// for K entries and E replacements.
+    pub(crate) fn map_edits(&mut self, edits: &[(usize, usize, usize)]) {
+        let entries = match self.entries {
+            Some(ref entries) => entries,
+            None => return,
+        };
+
+        let mut mapped = Vec::with_capacity(entries.len());
+        let mut edits = edits.iter().peekable();
+        let mut delta: isize = 0;
+        for &(char_idx, byte) in entries.iter() {
+            while let Some(&&(start, end, inserted)) = edits.peek() {
+                if char_idx < end {
+                    break;
+                }
+                delta += inserted as isize - (end - start) as isize;
+                edits.next();
+            }
+            let replaced = match edits.peek() {
+                Some(&&(start, end, _)) => char_idx >= start && char_idx < end,
+                None => false,
+            };
+            if !replaced {
+                mapped.push(((char_idx as isize + delta) as usize, byte));
+            }
+        }
+        *self = EscapedBytes::from_sorted(mapped);
+    }
+
+    /// Removes and returns the entries at or after `char_idx`, made
+    /// relative to it.
+    pub(crate) fn split_off(&mut self, char_idx: usize) -> EscapedBytes {
+        let split_idx = {
+            let entries = self.entries();
+            match entries.binary_search_by_key(&char_idx, |&(idx, _)| idx) {
+                Ok(i) | Err(i) => i,
+            }
+        };
+        if split_idx == self.entries().len() {
+            return EscapedBytes::new();
+        }
+
+        let right = self.entries()[split_idx..]
+            .iter()
+            .map(|&(idx, byte)| (idx - char_idx, byte))
+            .collect();
+        let left = self.entries()[..split_idx].to_vec();
+        *self = EscapedBytes::from_sorted(left);
+        EscapedBytes::from_sorted(right)
+    }
+
+    /// Adds `other`'s entries, offset by `char_offset`, which is at or after
+    /// all of this set's entries.
+    pub(crate) fn append(&mut self, other: &EscapedBytes, char_offset: usize) {
+        if other.is_empty() {
+            return;
+        }
+        let mut entries = self.entries().to_vec();
+        entries.extend(
+            other
+                .entries()
+                .iter()
+                .map(|&(idx, byte)| (idx + char_offset, byte)),
+        );
+        *self = EscapedBytes::from_sorted(entries);
+    }
+}
+
+//=============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(escaped: &EscapedBytes) -> Vec<usize> {
+        escaped.entries().iter().map(|&(idx, _)| idx).collect()
+    }
+
+    #[test]
+    fn map_edits_01() {
+        let mut escaped = EscapedBytes::from_sorted(vec![(1, 0x80), (3, 0x81), (5, 0x82)]);
+
+        // Insertion right at an entry moves it along.
+        escaped.map_edits(&[(3, 3, 2)]);
+        assert_eq!(positions(&escaped), vec![1, 5, 7]);
+
+        // Replaced entries are dropped.
+        escaped.map_edits(&[(0, 2, 1), (5, 6, 0)]);
+        assert_eq!(escaped.entries(), &[(5, 0x82)]);
+        escaped.map_edits(&[(5, 6, 3)]);
+        assert!(escaped.is_empty());
+    }
+
+    #[test]
+    fn split_off_append_01() {
+        let mut escaped = EscapedBytes::from_sorted(vec![(1, 0x80), (3, 0x81), (5, 0x82)]);
+        let right = escaped.split_off(3);
+        assert_eq!(positions(&escaped), vec![1]);
+        assert_eq!(positions(&right), vec![0, 2]);
+
+        escaped.append(&right, 4);
+        assert_eq!(escaped.entries(), &[(1, 0x80), (4, 0x81), (6, 0x82)]);
+        assert!(escaped.split_off(7).is_empty());
+    }
+}
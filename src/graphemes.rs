@@ -0,0 +1,88 @@
+//! Grapheme cluster boundary searches over the chunks of a `RopeSlice`.
+//!
+//! These work in terms of byte indices, and feed the `GraphemeCursor` from
+//! `unicode-segmentation` one chunk at a time, fetching neighboring chunks
+//! whenever a cluster (or the context needed to find its boundaries)
+//! spans a chunk seam.
+
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+
+use slice::RopeSlice;
+
+/// Returns the byte index of the grapheme boundary before `byte_idx`, or 0
+/// if there is none.
+pub(crate) fn prev_grapheme_boundary_byte(slice: &RopeSlice, byte_idx: usize) -> usize {
+    if byte_idx == 0 {
+        return 0;
+    }
+
+    let (mut chunk, mut chunk_byte_idx, _, _) = slice.chunk_at_byte(byte_idx);
+    let mut gc = GraphemeCursor::new(byte_idx, slice.len_bytes(), true);
+
+    loop {
+        match gc.prev_boundary(chunk, chunk_byte_idx) {
+            Ok(None) => return 0,
+            Ok(Some(n)) => return n,
+            Err(GraphemeIncomplete::PrevChunk) => {
+                let (c, b, _, _) = slice.chunk_at_byte(chunk_byte_idx - 1);
+                chunk = c;
+                chunk_byte_idx = b;
+            }
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let (ctx_chunk, ctx_byte_idx, _, _) = slice.chunk_at_byte(n - 1);
+                gc.provide_context(ctx_chunk, ctx_byte_idx);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Returns the byte index of the grapheme boundary after `byte_idx`, or
+/// the length of the slice if there is none.
+pub(crate) fn next_grapheme_boundary_byte(slice: &RopeSlice, byte_idx: usize) -> usize {
+    let len = slice.len_bytes();
+    if byte_idx >= len {
+        return len;
+    }
+
+    let (mut chunk, mut chunk_byte_idx, _, _) = slice.chunk_at_byte(byte_idx);
+    let mut gc = GraphemeCursor::new(byte_idx, len, true);
+
+    loop {
+        match gc.next_boundary(chunk, chunk_byte_idx) {
+            Ok(None) => return len,
+            Ok(Some(n)) => return n,
+            Err(GraphemeIncomplete::NextChunk) => {
+                chunk_byte_idx += chunk.len();
+                chunk = slice.chunk_at_byte(chunk_byte_idx).0;
+            }
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let (ctx_chunk, ctx_byte_idx, _, _) = slice.chunk_at_byte(n - 1);
+                gc.provide_context(ctx_chunk, ctx_byte_idx);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Returns whether `byte_idx` is a grapheme boundary.
+pub(crate) fn is_grapheme_boundary_byte(slice: &RopeSlice, byte_idx: usize) -> bool {
+    let len = slice.len_bytes();
+    if byte_idx == 0 || byte_idx >= len {
+        return true;
+    }
+
+    let (chunk, chunk_byte_idx, _, _) = slice.chunk_at_byte(byte_idx);
+    let mut gc = GraphemeCursor::new(byte_idx, len, true);
+
+    loop {
+        match gc.is_boundary(chunk, chunk_byte_idx) {
+            Ok(n) => return n,
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let (ctx_chunk, ctx_byte_idx, _, _) = slice.chunk_at_byte(n - 1);
+                gc.provide_context(ctx_chunk, ctx_byte_idx);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,650 @@
+use std;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rope::Rope;
+use tree::Node;
+
+/// An undo tree of `Rope` snapshots.
+///
+/// Cloning a `Rope` is cheap and the clones share all of their unedited
+/// text, so `History` simply stores a full snapshot of the text for every
+/// revision.  Revisions form a tree: committing a new revision after
+/// undoing starts a new branch rather than discarding the undone
+/// revisions, and every revision stays reachable via
+/// [`jump_to()`](#method.jump_to) and
+/// [`earlier()`](#method.earlier)/[`later()`](#method.later).
+///
+/// Revisions are identified by sequential ids, starting with 0 for the
+/// initial text.
+///
+/// Optionally, a memory budget can be set.  Whenever the memory used by
+/// all snapshots together exceeds the budget, the oldest revisions are
+/// evicted until it doesn't (or until only the current revision and newer
+/// ones are left).
+///
+/// # Example
+///
+/// ```
+/// # use ropey::{History, Rope};
+/// let mut rope = Rope::from_str("Hello");
+/// let mut history = History::new(rope.clone());
+///
+/// rope.insert(5, " world");
+/// history.commit(rope.clone());
+/// rope.insert(11, "!");
+/// history.commit(rope.clone());
+///
+/// assert_eq!(history.undo().unwrap(), "Hello world");
+/// assert_eq!(history.undo().unwrap(), "Hello");
+/// assert!(history.undo().is_none());
+/// assert_eq!(history.redo().unwrap(), "Hello world");
+/// ```
+#[derive(Debug, Clone)]
+pub struct History {
+    revisions: VecDeque<Revision>,
+    first_id: usize, // The id of `revisions[0]`.
+    current: usize,
+    memory_budget: Option<usize>,
+    // The references to each distinct node of the snapshots, by address,
+    // kept up to date while there is a memory budget.  See `tally_refs()`.
+    ref_counts: HashMap<usize, usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Revision {
+    rope: Rope,
+    parent: Option<usize>,
+    // The child that `redo()` moves to.
+    last_child: Option<usize>,
+    time: Instant,
+}
+
+/// Memory used by the snapshots in a [`History`](struct.History.html), in
+/// bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// What the snapshots would take up if none of them shared any memory.
+    pub total_bytes: usize,
+    /// What the snapshots actually take up.
+    pub unique_bytes: usize,
+}
+
+impl MemoryUsage {
+    /// Memory saved by snapshots sharing text with each other.
+    #[inline]
+    pub fn shared_bytes(&self) -> usize {
+        self.total_bytes - self.unique_bytes
+    }
+}
+
+impl History {
+    /// Creates a `History` whose initial revision (revision 0) is `rope`.
+    #[inline]
+    pub fn new(rope: Rope) -> History {
+        History::new_at(rope, Instant::now())
+    }
+
+    /// Like [`new()`](#method.new), but with an explicit time for the
+    /// initial revision.
+    pub fn new_at(rope: Rope, time: Instant) -> History {
+        let mut revisions = VecDeque::new();
+        revisions.push_back(Revision {
+            rope,
+            parent: None,
+            last_child: None,
+            time,
+        });
+        History {
+            revisions,
+            first_id: 0,
+            current: 0,
+            memory_budget: None,
+            ref_counts: HashMap::new(),
+        }
+    }
+
+    //-----------------------------------------------------------------------
+    // Informational methods
+
+    /// The text at the current revision.
+    #[inline]
+    pub fn current(&self) -> &Rope {
+        &self.rev(self.current).rope
+    }
+
+    /// The id of the current revision.
+    #[inline]
+    pub fn current_revision(&self) -> usize {
+        self.current
+    }
+
+    /// The id of the oldest revision that hasn't been evicted.
+    #[inline]
+    pub fn oldest_revision(&self) -> usize {
+        self.first_id
+    }
+
+    /// The id of the most recently committed revision.
+    #[inline]
+    pub fn newest_revision(&self) -> usize {
+        self.first_id + self.revisions.len() - 1
+    }
+
+    /// The text at the given revision, or `None` if there is no such
+    /// revision or it has been evicted.
+    #[inline]
+    pub fn revision(&self, revision: usize) -> Option<&Rope> {
+        self.get_rev(revision).map(|rev| &rev.rope)
+    }
+
+    /// The parent of the given revision, or `None` if it has no parent (or
+    /// its parent has been evicted).
+    #[inline]
+    pub fn parent(&self, revision: usize) -> Option<usize> {
+        self.get_rev(revision)
+            .and_then(|rev| rev.parent)
+            .filter(|&parent| parent >= self.first_id)
+    }
+
+    /// The time at which the given revision was committed, or `None` if
+    /// there is no such revision or it has been evicted.
+    #[inline]
+    pub fn time(&self, revision: usize) -> Option<Instant> {
+        self.get_rev(revision).map(|rev| rev.time)
+    }
+
+    /// Computes the memory used by all of the snapshots together.
+    ///
+    /// Runs in time proportional to the number of tree nodes that aren't
+    /// shared between snapshots.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut node_counts = HashMap::new();
+        let mut total_nodes = 0;
+        for rev in &self.revisions {
+            total_nodes += count_nodes(&rev.rope.root, &mut node_counts);
+        }
+
+        MemoryUsage {
+            total_bytes: total_nodes * NODE_BYTES,
+            unique_bytes: node_counts.len() * NODE_BYTES,
+        }
+    }
+
+    //-----------------------------------------------------------------------
+    // Edit methods
+
+    /// Commits `rope` as a new revision, as a child of the current
+    /// revision, and makes it the current revision.
+    ///
+    /// Returns the id of the new revision.
+    #[inline]
+    pub fn commit(&mut self, rope: Rope) -> usize {
+        self.commit_at(rope, Instant::now())
+    }
+
+    /// Like [`commit()`](#method.commit), but with an explicit time for the
+    /// revision.
+    ///
+    /// Times are expected to increase with each commit.
+    pub fn commit_at(&mut self, rope: Rope, time: Instant) -> usize {
+        let id = self.newest_revision() + 1;
+        self.revisions.push_back(Revision {
+            rope,
+            parent: Some(self.current),
+            last_child: None,
+            time,
+        });
+        self.rev_mut(self.current).last_child = Some(id);
+        self.current = id;
+
+        if self.memory_budget.is_some() {
+            let root = &self.revisions.back().unwrap().rope.root;
+            tally_refs(root, &mut self.ref_counts);
+            self.enforce_memory_budget();
+        }
+        id
+    }
+
+    /// Sets the memory budget in bytes, as measured by
+    /// [`memory_usage()`](#method.memory_usage)`.unique_bytes`, or removes
+    /// it with `None`.
+    ///
+    /// Evicts revisions right away if the new budget is already exceeded.
+    pub fn set_memory_budget(&mut self, budget: Option<usize>) {
+        if budget.is_none() {
+            self.ref_counts = HashMap::new();
+        } else if self.memory_budget.is_none() {
+            for rev in &self.revisions {
+                tally_refs(&rev.rope.root, &mut self.ref_counts);
+            }
+        }
+        self.memory_budget = budget;
+        self.enforce_memory_budget();
+    }
+
+    //-----------------------------------------------------------------------
+    // Navigation methods
+
+    /// Moves to the parent of the current revision, and returns its text.
+    ///
+    /// Returns `None`, and stays put, if the current revision has no
+    /// parent.
+    pub fn undo(&mut self) -> Option<&Rope> {
+        let parent = self.parent(self.current)?;
+        self.rev_mut(parent).last_child = Some(self.current);
+        self.current = parent;
+        Some(self.current())
+    }
+
+    /// Moves to the most recently visited child of the current revision,
+    /// and returns its text.
+    ///
+    /// Returns `None`, and stays put, if the current revision has no
+    /// children.
+    pub fn redo(&mut self) -> Option<&Rope> {
+        let child = self.rev(self.current).last_child?;
+        self.current = child;
+        Some(self.current())
+    }
+
+    /// Moves to the given revision, and returns its text.
+    ///
+    /// Afterwards, [`redo()`](#method.redo) from any of the revision's
+    /// ancestors leads back towards it.
+    ///
+    /// Returns `None`, and stays put, if there is no such revision or it
+    /// has been evicted.
+    pub fn jump_to(&mut self, revision: usize) -> Option<&Rope> {
+        self.get_rev(revision)?;
+
+        let mut child = revision;
+        while let Some(parent) = self.parent(child) {
+            self.rev_mut(parent).last_child = Some(child);
+            child = parent;
+        }
+
+        self.current = revision;
+        Some(self.current())
+    }
+
+    /// Moves to the newest revision committed at least `duration` before the
+    /// current one, and returns its text.
+    ///
+    /// Moves to the oldest revision if there is none that old.  This
+    /// navigates by time across all branches of the tree.
+    pub fn earlier(&mut self, duration: Duration) -> &Rope {
+        let current_time = self.rev(self.current).time;
+        let target = current_time.checked_sub(duration);
+        let found = target.and_then(|target| {
+            (self.first_id..=self.newest_revision())
+                .rev()
+                .find(|&id| self.rev(id).time <= target)
+        });
+
+        let revision = found.unwrap_or(self.first_id);
+        self.jump_to(revision).unwrap()
+    }
+
+    /// Moves to the oldest revision committed at least `duration` after the
+    /// current one, and returns its text.
+    ///
+    /// Moves to the newest revision if there is none that new.  This
+    /// navigates by time across all branches of the tree.
+    pub fn later(&mut self, duration: Duration) -> &Rope {
+        let current_time = self.rev(self.current).time;
+        let target = current_time.checked_add(duration);
+        let found = target.and_then(|target| {
+            (self.first_id..=self.newest_revision()).find(|&id| self.rev(id).time >= target)
+        });
+
+        let revision = found.unwrap_or_else(|| self.newest_revision());
+        self.jump_to(revision).unwrap()
+    }
+
+    //-----------------------------------------------------------------------
+    // Internal
+
+    #[inline]
+    fn get_rev(&self, revision: usize) -> Option<&Revision> {
+        if revision < self.first_id {
+            None
+        } else {
+            self.revisions.get(revision - self.first_id)
+        }
+    }
+
+    #[inline]
+    fn rev(&self, revision: usize) -> &Revision {
+        &self.revisions[revision - self.first_id]
+    }
+
+    #[inline]
+    fn rev_mut(&mut self, revision: usize) -> &mut Revision {
+        &mut self.revisions[revision - self.first_id]
+    }
+
+    /// Evicts the oldest revisions until the memory budget is met.
+    ///
+    /// The oldest revision is always the root of its part of the tree, since
+    /// parents are always older than their children, so evicting it never
+    /// disconnects a revision from its remaining ancestors.
+    ///
+    /// The references to each distinct node are kept in `ref_counts` as
+    /// revisions are committed, and each evicted revision only releases the
+    /// nodes that no remaining revision shares, so this never re-traverses
+    /// the snapshots.
+    fn enforce_memory_budget(&mut self) {
+        let budget = match self.memory_budget {
+            Some(budget) => budget,
+            None => return,
+        };
+
+        // `ref_counts` has an entry for each distinct node, so its length is
+        // the same node count that `memory_usage()` computes.
+        while self.first_id != self.current && self.ref_counts.len() * NODE_BYTES > budget {
+            let rev = self.revisions.pop_front().unwrap();
+            release_refs(&rev.rope.root, &mut self.ref_counts);
+            self.first_id += 1;
+        }
+    }
+}
+
+/// Memory taken up by a single tree node, including its `Arc`'s reference
+/// counts.
+const NODE_BYTES: usize = std::mem::size_of::<Node>() + 2 * std::mem::size_of::<usize>();
+
+/// Returns the number of nodes in the tree under `node`, including `node`.
+///
+/// Each distinct node's count is memoized in `node_counts`, so subtrees
+/// that are shared with trees that were already counted are not traversed
+/// again.
+fn count_nodes(node: &Arc<Node>, node_counts: &mut HashMap<*const Node, usize>) -> usize {
+    let ptr = &**node as *const Node;
+    if let Some(&count) = node_counts.get(&ptr) {
+        return count;
+    }
+
+    let mut count = 1;
+    if let Node::Internal(ref children) = **node {
+        for child in children.nodes() {
+            count += count_nodes(child, node_counts);
+        }
+    }
+    node_counts.insert(ptr, count);
+    count
+}
+
+/// Adds a reference to `node` to `ref_counts`, which counts the references
+/// to each distinct node from revisions and from other distinct nodes.
+///
+/// The children of a node are only tallied the first time the node is
+/// seen, since further references to it share the same children.  Nodes
+/// are keyed by address, which stays unique for as long as the node has
+/// an entry, since the revisions keep it alive until then.
+fn tally_refs(node: &Arc<Node>, ref_counts: &mut HashMap<usize, usize>) {
+    let ptr = &**node as *const Node as usize;
+    let count = ref_counts.entry(ptr).or_insert(0);
+    *count += 1;
+    if *count > 1 {
+        return;
+    }
+
+    if let Node::Internal(ref children) = **node {
+        for child in children.nodes() {
+            tally_refs(child, ref_counts);
+        }
+    }
+}
+
+/// Removes a reference to `node` from `ref_counts`, undoing
+/// [`tally_refs()`](fn.tally_refs.html).
+///
+/// When the last reference to a node is removed, its entry is removed and
+/// its children are released in turn.
+fn release_refs(node: &Arc<Node>, ref_counts: &mut HashMap<usize, usize>) {
+    let ptr = &**node as *const Node as usize;
+    {
+        let count = ref_counts.get_mut(&ptr).unwrap();
+        *count -= 1;
+        if *count > 0 {
+            return;
+        }
+    }
+    ref_counts.remove(&ptr);
+
+    if let Node::Internal(ref children) = **node {
+        for child in children.nodes() {
+            release_refs(child, ref_counts);
+        }
+    }
+}
+
+//=============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a linear history "a", "ab", "abc", ... with one revision
+    // committed every second.
+    fn linear_history(start: Instant, n: usize) -> History {
+        let mut rope = Rope::from_str("a");
+        let mut history = History::new_at(rope.clone(), start);
+        for i in 1..n {
+            rope.insert(i, "bcdefghij".get((i - 1)..i).unwrap());
+            history.commit_at(rope.clone(), start + Duration::from_secs(i as u64));
+        }
+        history
+    }
+
+    #[test]
+    fn new_01() {
+        let history = History::new(Rope::from_str("Hello"));
+        assert_eq!(history.current(), "Hello");
+        assert_eq!(history.current_revision(), 0);
+        assert_eq!(history.oldest_revision(), 0);
+        assert_eq!(history.newest_revision(), 0);
+        assert_eq!(history.parent(0), None);
+    }
+
+    #[test]
+    fn undo_redo_01() {
+        let mut history = linear_history(Instant::now(), 3);
+        assert_eq!(history.current(), "abc");
+        assert_eq!(history.undo().unwrap(), "ab");
+        assert_eq!(history.undo().unwrap(), "a");
+        assert!(history.undo().is_none());
+        assert_eq!(history.current_revision(), 0);
+        assert_eq!(history.redo().unwrap(), "ab");
+        assert_eq!(history.redo().unwrap(), "abc");
+        assert!(history.redo().is_none());
+        assert_eq!(history.current_revision(), 2);
+    }
+
+    #[test]
+    fn branch_01() {
+        let mut history = linear_history(Instant::now(), 3);
+        history.undo();
+        let id = history.commit(Rope::from_str("abX"));
+        assert_eq!(id, 3);
+        assert_eq!(history.parent(3), Some(1));
+
+        // The old branch is still there.
+        assert_eq!(history.revision(2).unwrap(), "abc");
+
+        // Redo follows the most recent branch.
+        history.undo();
+        assert_eq!(history.redo().unwrap(), "abX");
+    }
+
+    #[test]
+    fn branch_02() {
+        let mut history = linear_history(Instant::now(), 3);
+        history.undo();
+        history.commit(Rope::from_str("abX"));
+        history.undo();
+
+        // Jumping to a branch makes it the one redo follows.
+        history.jump_to(2);
+        history.undo();
+        assert_eq!(history.redo().unwrap(), "abc");
+    }
+
+    #[test]
+    fn jump_to_01() {
+        let mut history = linear_history(Instant::now(), 4);
+        history.undo();
+        history.undo();
+        history.commit(Rope::from_str("abX"));
+        assert_eq!(history.jump_to(3).unwrap(), "abcd");
+        assert_eq!(history.current_revision(), 3);
+
+        // Redo from an ancestor leads back to the revision jumped to.
+        history.jump_to(0);
+        assert_eq!(history.redo().unwrap(), "ab");
+        assert_eq!(history.redo().unwrap(), "abc");
+        assert_eq!(history.redo().unwrap(), "abcd");
+    }
+
+    #[test]
+    fn jump_to_02() {
+        let mut history = linear_history(Instant::now(), 3);
+        assert!(history.jump_to(3).is_none());
+        assert_eq!(history.current_revision(), 2);
+    }
+
+    #[test]
+    fn earlier_later_01() {
+        let mut history = linear_history(Instant::now(), 6);
+        assert_eq!(history.earlier(Duration::from_secs(2)), "abcd");
+        assert_eq!(history.earlier(Duration::from_millis(1500)), "ab");
+        assert_eq!(history.earlier(Duration::from_secs(100)), "a");
+        assert_eq!(history.later(Duration::from_secs(3)), "abcd");
+        assert_eq!(history.later(Duration::from_millis(500)), "abcde");
+        assert_eq!(history.later(Duration::from_secs(100)), "abcdef");
+    }
+
+    #[test]
+    fn earlier_later_02() {
+        // Time navigation crosses branches.
+        let start = Instant::now();
+        let mut history = linear_history(start, 3);
+        history.undo();
+        history.undo();
+        history.commit_at(Rope::from_str("aX"), start + Duration::from_secs(10));
+        assert_eq!(history.earlier(Duration::from_secs(8)), "abc");
+        assert_eq!(history.later(Duration::from_secs(1)), "aX");
+    }
+
+    #[test]
+    fn memory_usage_01() {
+        let mut rope = Rope::from_str(&"Hello world! ".repeat(100));
+        let mut history = History::new(rope.clone());
+        let usage_1 = history.memory_usage();
+        assert_eq!(usage_1.shared_bytes(), 0);
+
+        rope.insert(0, "Hi! ");
+        history.commit(rope.clone());
+        let usage_2 = history.memory_usage();
+        assert_eq!(usage_1.total_bytes, usage_1.unique_bytes);
+        assert!(usage_2.shared_bytes() > 0);
+        assert!(usage_2.unique_bytes < usage_1.unique_bytes * 2);
+    }
+
+    #[test]
+    fn memory_budget_01() {
+        let mut rope = Rope::from_str(&"Hello world! ".repeat(100));
+        let mut history = History::new(rope.clone());
+        for i in 0..10 {
+            rope.remove(i..(i + 1));
+            rope.insert(rope.len_chars() - i, "Bye! ");
+            history.commit(rope.clone());
+        }
+        let all = history.memory_usage().unique_bytes;
+        let one = History::new(rope.clone()).memory_usage().unique_bytes;
+        let budget = one + (all - one) / 2;
+
+        history.set_memory_budget(Some(budget));
+        assert!(history.oldest_revision() > 0);
+        assert!(history.memory_usage().unique_bytes <= budget);
+        assert_eq!(history.current_revision(), 10);
+        assert_eq!(history.current(), &rope);
+        assert!(history.revision(0).is_none());
+        assert_eq!(history.parent(history.oldest_revision()), None);
+    }
+
+    #[test]
+    fn memory_budget_02() {
+        // The current revision is never evicted.
+        let mut history = linear_history(Instant::now(), 4);
+        history.undo();
+        history.set_memory_budget(Some(0));
+        assert_eq!(history.oldest_revision(), 2);
+        assert_eq!(history.current(), "abc");
+        assert_eq!(history.revision(3).unwrap(), "abcd");
+        assert!(history.undo().is_none());
+
+        history.commit(Rope::from_str("abcX"));
+        assert_eq!(history.oldest_revision(), 4);
+    }
+
+    #[test]
+    fn memory_budget_03() {
+        // Evicts just enough revisions to meet the budget as each revision
+        // is committed.
+        let mut rope = Rope::from_str(&"Hello world! ".repeat(100));
+        let mut history = History::new(rope.clone());
+        let mut ropes = vec![rope.clone()];
+        let budget = history.memory_usage().unique_bytes * 3 / 2;
+        history.set_memory_budget(Some(budget));
+        for i in 0..30 {
+            let char_idx = (i * 37) % rope.len_chars();
+            rope.insert(char_idx, "Hi! ");
+            ropes.push(rope.clone());
+            history.commit(rope.clone());
+            assert!(history.memory_usage().unique_bytes <= budget);
+
+            // Keeping the most recently evicted revision as well would
+            // have exceeded the budget.
+            let oldest = history.oldest_revision();
+            if oldest > 0 {
+                let mut unevicted = History::new(ropes[oldest - 1].clone());
+                for rope in &ropes[oldest..] {
+                    unevicted.commit(rope.clone());
+                }
+                assert!(unevicted.memory_usage().unique_bytes > budget);
+            }
+        }
+        assert!(history.oldest_revision() > 0);
+        assert_eq!(history.current(), &rope);
+    }
+
+    #[test]
+    fn memory_budget_04() {
+        // Many commits under a tight budget, with the budget changed along
+        // the way.
+        let mut rope = Rope::from_str(&"Hello world! ".repeat(200));
+        let mut history = History::new(rope.clone());
+        let budget = history.memory_usage().unique_bytes * 3 / 2;
+        history.set_memory_budget(Some(budget));
+        for i in 0..2000 {
+            let char_idx = (i * 53) % (rope.len_chars() - 1);
+            rope.remove(char_idx..(char_idx + 2));
+            rope.insert(char_idx, "Hi");
+            history.commit(rope.clone());
+            if i == 1000 {
+                history.set_memory_budget(None);
+            } else if i == 1500 {
+                history.set_memory_budget(Some(budget));
+            }
+        }
+
+        let usage = history.memory_usage();
+        assert!(usage.unique_bytes <= budget);
+        assert_eq!(history.ref_counts.len() * NODE_BYTES, usage.unique_bytes);
+        assert!(history.oldest_revision() > 1500);
+        assert_eq!(history.current(), &rope);
+    }
+}
@@ -5,110 +5,429 @@
 //! `RopeSlice` refers to.  For the line and chunk, iterators, the data
 //! of the first and last yielded item will be truncated to match the
 //! `RopeSlice`.
+//!
+//! All iterators are double-ended, so they can be consumed from either
+//! end (e.g. with `rev()`).  They also have a `prev()` method, which
+//! moves the front of the iterator backwards again, yielding the same
+//! items that `next()` yielded but in reverse order.  This makes it
+//! possible to change direction in the middle of iteration without
+//! collecting anything first.
 
 use std::str;
 use std::sync::Arc;
 
+#[cfg(feature = "graphemes")]
+use graphemes::{next_grapheme_boundary_byte, prev_grapheme_boundary_byte};
 use slice::RopeSlice;
-use str_utils::{char_to_byte_idx, char_to_line_idx, line_to_byte_idx, line_to_char_idx};
-use tree::Node;
+use str_utils::{
+    char_to_byte_idx, char_to_line_idx_with_policy, count_line_breaks,
+    line_to_byte_idx_with_policy, line_to_char_idx_with_policy,
+};
+use tree::{Node, NodeChildren};
+use LineBreakPolicy;
 
 //==========================================================
 
 /// An iterator over a `Rope`'s bytes.
 pub struct Bytes<'a> {
-    chunk_iter: Chunks<'a>,
-    cur_chunk: str::Bytes<'a>,
+    cursor: ChunkCursor<'a>,
 }
 
 impl<'a> Bytes<'a> {
     pub(crate) fn new(node: &Arc<Node>) -> Bytes {
         Bytes {
-            chunk_iter: Chunks::new(node),
-            cur_chunk: "".bytes(),
+            cursor: ChunkCursor::new(node),
         }
     }
 
     pub(crate) fn new_with_range(node: &Arc<Node>, start_char: usize, end_char: usize) -> Bytes {
+        Bytes::new_with_range_at(node, 0, start_char, end_char)
+    }
+
+    /// `at_byte` is relative to `start_char`.
+    pub(crate) fn new_with_range_at(
+        node: &Arc<Node>,
+        at_byte: usize,
+        start_char: usize,
+        end_char: usize,
+    ) -> Bytes<'_> {
         Bytes {
-            chunk_iter: Chunks::new_with_range(node, start_char, end_char),
-            cur_chunk: "".bytes(),
+            cursor: ChunkCursor::new_with_range_at(node, at_byte, start_char, end_char),
         }
     }
 
     pub(crate) fn from_str(text: &str) -> Bytes {
+        Bytes::from_str_at(text, 0)
+    }
+
+    pub(crate) fn from_str_at(text: &str, at_byte: usize) -> Bytes<'_> {
         Bytes {
-            chunk_iter: Chunks::new_empty(),
-            cur_chunk: text.bytes(),
+            cursor: ChunkCursor::from_str_at(text, at_byte),
         }
     }
+
+    /// Moves the front of the iterator backwards by one byte and returns
+    /// that byte.
+    ///
+    /// This is the inverse of `next()`: calling `next()` followed by
+    /// `prev()` returns the same byte twice.  Returns `None` when the front
+    /// of the iterator is at the start of the text.
+    #[inline]
+    pub fn prev(&mut self) -> Option<u8> {
+        let c = &mut self.cursor;
+        if c.front <= c.start {
+            return None;
+        }
+        c.front -= 1;
+        let (chunk, chunk_start) = c.front_chunk_at(c.front);
+        Some(chunk.as_bytes()[c.front - chunk_start])
+    }
 }
 
 impl<'a> Iterator for Bytes<'a> {
     type Item = u8;
 
+    #[inline]
     fn next(&mut self) -> Option<u8> {
-        loop {
-            if let Some(c) = self.cur_chunk.next() {
-                return Some(c);
-            } else if let Some(chunk) = self.chunk_iter.next() {
-                self.cur_chunk = chunk.bytes();
-                continue;
-            } else {
-                return None;
-            }
+        let c = &mut self.cursor;
+        if c.front >= c.back {
+            return None;
         }
+        let (chunk, chunk_start) = c.front_chunk_at(c.front);
+        let byte = chunk.as_bytes()[c.front - chunk_start];
+        c.front += 1;
+        Some(byte)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.cursor.back - self.cursor.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a> DoubleEndedIterator for Bytes<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<u8> {
+        let c = &mut self.cursor;
+        if c.back <= c.front {
+            return None;
+        }
+        c.back -= 1;
+        let (chunk, chunk_start) = c.back_chunk_at(c.back);
+        Some(chunk.as_bytes()[c.back - chunk_start])
     }
 }
 
+impl<'a> ExactSizeIterator for Bytes<'a> {}
+
 //==========================================================
 
 /// An iterator over a `Rope`'s chars.
 pub struct Chars<'a> {
-    chunk_iter: Chunks<'a>,
-    cur_chunk: str::Chars<'a>,
+    cursor: ChunkCursor<'a>,
 }
 
 impl<'a> Chars<'a> {
     pub(crate) fn new(node: &Arc<Node>) -> Chars {
         Chars {
-            chunk_iter: Chunks::new(node),
-            cur_chunk: "".chars(),
+            cursor: ChunkCursor::new(node),
         }
     }
 
     pub(crate) fn new_with_range(node: &Arc<Node>, start_char: usize, end_char: usize) -> Chars {
+        Chars::new_with_range_at(node, 0, start_char, end_char)
+    }
+
+    /// `at_byte` is relative to `start_char`.
+    pub(crate) fn new_with_range_at(
+        node: &Arc<Node>,
+        at_byte: usize,
+        start_char: usize,
+        end_char: usize,
+    ) -> Chars<'_> {
         Chars {
-            chunk_iter: Chunks::new_with_range(node, start_char, end_char),
-            cur_chunk: "".chars(),
+            cursor: ChunkCursor::new_with_range_at(node, at_byte, start_char, end_char),
         }
     }
 
     pub(crate) fn from_str(text: &str) -> Chars {
+        Chars::from_str_at(text, 0)
+    }
+
+    pub(crate) fn from_str_at(text: &str, at_byte: usize) -> Chars<'_> {
         Chars {
-            chunk_iter: Chunks::new_empty(),
-            cur_chunk: text.chars(),
+            cursor: ChunkCursor::from_str_at(text, at_byte),
         }
     }
+
+    /// Moves the front of the iterator backwards by one char and returns
+    /// that char.
+    ///
+    /// This is the inverse of `next()`: calling `next()` followed by
+    /// `prev()` returns the same char twice.  Returns `None` when the front
+    /// of the iterator is at the start of the text.
+    #[inline]
+    pub fn prev(&mut self) -> Option<char> {
+        let c = &mut self.cursor;
+        if c.front <= c.start {
+            return None;
+        }
+        let (chunk, chunk_start) = c.front_chunk_at(c.front - 1);
+        let ch = chunk[..(c.front - chunk_start)].chars().next_back().unwrap();
+        c.front -= ch.len_utf8();
+        Some(ch)
+    }
 }
 
 impl<'a> Iterator for Chars<'a> {
     type Item = char;
 
+    #[inline]
     fn next(&mut self) -> Option<char> {
+        let c = &mut self.cursor;
+        if c.front >= c.back {
+            return None;
+        }
+        let (chunk, chunk_start) = c.front_chunk_at(c.front);
+        let ch = chunk[(c.front - chunk_start)..].chars().next().unwrap();
+        c.front += ch.len_utf8();
+        Some(ch)
+    }
+}
+
+impl<'a> DoubleEndedIterator for Chars<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<char> {
+        let c = &mut self.cursor;
+        if c.back <= c.front {
+            return None;
+        }
+        let (chunk, chunk_start) = c.back_chunk_at(c.back - 1);
+        let ch = chunk[..(c.back - chunk_start)].chars().next_back().unwrap();
+        c.back -= ch.len_utf8();
+        Some(ch)
+    }
+}
+
+//==========================================================
+
+/// Front and back byte positions within a range of text, along with the
+/// leaves those positions were last found in.
+///
+/// `Bytes`, `Chars` and `Chunks` step through their current chunk directly,
+/// and only step to a neighboring leaf of the tree when they step out of it.
+struct ChunkCursor<'a> {
+    node: Option<&'a Arc<Node>>,
+    start: usize,
+    front: usize,
+    back: usize,
+    front_leaf: LeafCursor<'a>,
+    back_leaf: LeafCursor<'a>,
+}
+
+impl<'a> ChunkCursor<'a> {
+    fn new(node: &'a Arc<Node>) -> ChunkCursor<'a> {
+        let end = node.byte_count();
+        ChunkCursor {
+            node: Some(node),
+            start: 0,
+            front: 0,
+            back: end,
+            front_leaf: LeafCursor::from_str("", 0),
+            back_leaf: LeafCursor::from_str("", 0),
+        }
+    }
+
+    fn new_with_range_at(
+        node: &'a Arc<Node>,
+        at_byte: usize,
+        start_char: usize,
+        end_char: usize,
+    ) -> ChunkCursor<'a> {
+        let (start, end) = char_range_to_byte_range(node, start_char, end_char);
+        debug_assert!(start + at_byte <= end);
+        ChunkCursor {
+            node: Some(node),
+            start,
+            front: start + at_byte,
+            back: end,
+            front_leaf: LeafCursor::from_str("", start),
+            back_leaf: LeafCursor::from_str("", start),
+        }
+    }
+
+    fn from_str_at(text: &'a str, at_byte: usize) -> ChunkCursor<'a> {
+        debug_assert!(at_byte <= text.len());
+        ChunkCursor {
+            node: None,
+            start: 0,
+            front: at_byte,
+            back: text.len(),
+            front_leaf: LeafCursor::from_str(text, 0),
+            back_leaf: LeafCursor::from_str(text, 0),
+        }
+    }
+
+    /// Returns the chunk containing `byte_idx` and its starting byte index,
+    /// updating the cached front chunk if needed.
+    #[inline]
+    fn front_chunk_at(&mut self, byte_idx: usize) -> (&'a str, usize) {
+        if !self.front_leaf.contains(byte_idx) {
+            seek_leaf(self.node.unwrap(), &mut self.front_leaf, byte_idx);
+        }
+        (self.front_leaf.text, self.front_leaf.start)
+    }
+
+    /// Returns the chunk containing `byte_idx` and its starting byte index,
+    /// updating the cached back chunk if needed.
+    #[inline]
+    fn back_chunk_at(&mut self, byte_idx: usize) -> (&'a str, usize) {
+        if !self.back_leaf.contains(byte_idx) {
+            seek_leaf(self.node.unwrap(), &mut self.back_leaf, byte_idx);
+        }
+        (self.back_leaf.text, self.back_leaf.start)
+    }
+}
+
+/// Moves `leaf` to the leaf containing `byte_idx`, starting it from the
+/// root if it isn't in the tree yet.
+#[inline(never)]
+fn seek_leaf<'a>(node: &'a Arc<Node>, leaf: &mut LeafCursor<'a>, byte_idx: usize) {
+    if leaf.stack.is_empty() {
+        *leaf = LeafCursor::at_byte(node, byte_idx);
+    } else {
+        leaf.seek(byte_idx);
+    }
+}
+
+/// A leaf of a tree, along with the path of internal nodes leading to it.
+///
+/// Stepping to a neighboring leaf only walks up and down as far as the
+/// nearest common ancestor, so visiting every leaf in order takes amortized
+/// constant time per leaf.
+struct LeafCursor<'a> {
+    /// The internal nodes on the path, and the index of the child taken in
+    /// each.  Empty for a single leaf or a plain string.
+    stack: Vec<(&'a NodeChildren, usize)>,
+    text: &'a str,
+    start: usize,
+}
+
+impl<'a> LeafCursor<'a> {
+    /// Creates a cursor over just `text`, starting at byte index `start`.
+    fn from_str(text: &'a str, start: usize) -> LeafCursor<'a> {
+        LeafCursor {
+            stack: Vec::new(),
+            text,
+            start,
+        }
+    }
+
+    #[inline(always)]
+    fn contains(&self, byte_idx: usize) -> bool {
+        byte_idx >= self.start && byte_idx < (self.start + self.text.len())
+    }
+
+    /// Creates a cursor at the leaf containing `byte_idx`.
+    fn at_byte(node: &'a Arc<Node>, byte_idx: usize) -> LeafCursor<'a> {
+        let mut cursor = LeafCursor {
+            stack: Vec::new(),
+            text: "",
+            start: 0,
+        };
+        let mut node = &**node;
+        let mut byte_idx = byte_idx;
+        loop {
+            match *node {
+                Node::Leaf(ref text) => {
+                    cursor.text = text;
+                    return cursor;
+                }
+                Node::Internal(ref children) => {
+                    let (child_i, acc_info) = children.search_byte_idx(byte_idx);
+                    cursor.stack.push((children, child_i));
+                    cursor.start += acc_info.bytes as usize;
+                    byte_idx -= acc_info.bytes as usize;
+                    node = &*children.nodes()[child_i];
+                }
+            }
+        }
+    }
+
+    /// Steps through neighboring leaves to the one containing `byte_idx`.
+    fn seek(&mut self, byte_idx: usize) {
+        while byte_idx >= self.start + self.text.len() && self.next_leaf() {}
+        while byte_idx < self.start && self.prev_leaf() {}
+    }
+
+    /// Moves to the next leaf, returning false if there isn't one.
+    fn next_leaf(&mut self) -> bool {
+        let depth = match self
+            .stack
+            .iter()
+            .rposition(|&(children, i)| (i + 1) < children.len())
+        {
+            Some(depth) => depth,
+            None => return false,
+        };
+        self.stack.truncate(depth + 1);
+        self.stack[depth].1 += 1;
+        self.start += self.text.len();
+        let (children, i) = self.stack[depth];
+        self.descend(&children.nodes()[i], false);
+        true
+    }
+
+    /// Moves to the previous leaf, returning false if there isn't one.
+    fn prev_leaf(&mut self) -> bool {
+        let depth = match self.stack.iter().rposition(|&(_, i)| i > 0) {
+            Some(depth) => depth,
+            None => return false,
+        };
+        self.stack.truncate(depth + 1);
+        self.stack[depth].1 -= 1;
+        let (children, i) = self.stack[depth];
+        self.descend(&children.nodes()[i], true);
+        self.start -= self.text.len();
+        true
+    }
+
+    /// Descends to the first or last leaf of `node`.
+    fn descend(&mut self, node: &'a Node, to_last: bool) {
+        let mut node = node;
         loop {
-            if let Some(c) = self.cur_chunk.next() {
-                return Some(c);
-            } else if let Some(chunk) = self.chunk_iter.next() {
-                self.cur_chunk = chunk.chars();
-                continue;
-            } else {
-                return None;
+            match *node {
+                Node::Leaf(ref text) => {
+                    self.text = text;
+                    return;
+                }
+                Node::Internal(ref children) => {
+                    let i = if to_last { children.len() - 1 } else { 0 };
+                    self.stack.push((children, i));
+                    node = &*children.nodes()[i];
+                }
             }
         }
     }
 }
 
+fn char_range_to_byte_range(
+    node: &Arc<Node>,
+    start_char: usize,
+    end_char: usize,
+) -> (usize, usize) {
+    let start_byte = {
+        let (chunk, b, c, _) = node.get_chunk_at_char(start_char);
+        b + char_to_byte_idx(chunk, start_char - c)
+    };
+    let end_byte = {
+        let (chunk, b, c, _) = node.get_chunk_at_char(end_char);
+        b + char_to_byte_idx(chunk, end_char - c)
+    };
+    (start_byte, end_byte)
+}
+
 //==========================================================
 
 /// An iterator over a `Rope`'s lines.
@@ -124,42 +443,131 @@ enum LinesEnum<'a> {
         node: &'a Arc<Node>,
         start_char: usize,
         end_char: usize,
-        line_idx: usize,
+        start_line: usize,
+        front_line: usize,
+        back_line: usize, // One past the last line to yield.
+        policy: LineBreakPolicy,
     },
     Light {
         text: &'a str,
-        done: bool,
+        line_count: usize,
+        front_line: usize,
+        back_line: usize, // One past the last line to yield.
+        front_byte: usize,
+        back_byte: usize,
+        policy: LineBreakPolicy,
     },
 }
 
 impl<'a> Lines<'a> {
-    pub(crate) fn new(node: &Arc<Node>) -> Lines {
+    pub(crate) fn new(node: &Arc<Node>, policy: LineBreakPolicy) -> Lines<'_> {
         Lines(LinesEnum::Full {
             node: node,
             start_char: 0,
-            end_char: node.text_info().chars as usize,
-            line_idx: 0,
+            end_char: node.char_count(),
+            start_line: 0,
+            front_line: 0,
+            back_line: node.line_break_count(policy) + 1,
+            policy,
         })
     }
 
-    pub(crate) fn new_with_range(node: &Arc<Node>, start_char: usize, end_char: usize) -> Lines {
+    pub(crate) fn new_with_range(
+        node: &Arc<Node>,
+        start_char: usize,
+        end_char: usize,
+        policy: LineBreakPolicy,
+    ) -> Lines<'_> {
+        Lines::new_with_range_at(node, 0, start_char, end_char, policy)
+    }
+
+    /// `at_line` is relative to the line that `start_char` is on.
+    pub(crate) fn new_with_range_at(
+        node: &Arc<Node>,
+        at_line: usize,
+        start_char: usize,
+        end_char: usize,
+        policy: LineBreakPolicy,
+    ) -> Lines<'_> {
+        let start_line = {
+            let (chunk, _, c, l) = node.get_chunk_at_char(start_char);
+            l + char_to_line_idx_with_policy(chunk, start_char - c, policy)
+        };
+        let end_line = {
+            let (chunk, _, c, l) = node.get_chunk_at_char(end_char);
+            l + char_to_line_idx_with_policy(chunk, end_char - c, policy)
+        };
         Lines(LinesEnum::Full {
             node: node,
             start_char: start_char,
             end_char: end_char,
-            line_idx: {
-                let (chunk, _, c, l) = node.get_chunk_at_char(start_char);
-                l + char_to_line_idx(chunk, start_char - c)
-            },
+            start_line,
+            front_line: start_line + at_line,
+            back_line: end_line + 1,
+            policy,
         })
     }
 
-    pub(crate) fn from_str(text: &str) -> Lines {
+    pub(crate) fn from_str(text: &str, policy: LineBreakPolicy) -> Lines<'_> {
+        Lines::from_str_at(text, 0, policy)
+    }
+
+    pub(crate) fn from_str_at(text: &str, at_line: usize, policy: LineBreakPolicy) -> Lines<'_> {
+        let line_count = count_line_breaks(text, policy) + 1;
+        debug_assert!(at_line <= line_count);
         Lines(LinesEnum::Light {
             text: text,
-            done: false,
+            line_count,
+            front_line: at_line,
+            back_line: line_count,
+            front_byte: line_to_byte_idx_with_policy(text, at_line, policy),
+            back_byte: text.len(),
+            policy,
         })
     }
+
+    /// Moves the front of the iterator backwards by one line and returns
+    /// that line.
+    ///
+    /// This is the inverse of `next()`: calling `next()` followed by
+    /// `prev()` returns the same line twice.  Returns `None` when the front
+    /// of the iterator is at the first line.
+    pub fn prev(&mut self) -> Option<RopeSlice<'a>> {
+        match *self {
+            Lines(LinesEnum::Full {
+                node,
+                start_char,
+                end_char,
+                start_line,
+                ref mut front_line,
+                policy,
+                ..
+            }) => {
+                if *front_line <= start_line {
+                    return None;
+                }
+                *front_line -= 1;
+                Some(full_line(node, *front_line, start_char, end_char, policy))
+            }
+            Lines(LinesEnum::Light {
+                text,
+                line_count,
+                ref mut front_line,
+                ref mut front_byte,
+                policy,
+                ..
+            }) => {
+                if *front_line == 0 {
+                    return None;
+                }
+                *front_line -= 1;
+                let end = *front_byte;
+                let is_last_line = *front_line + 1 == line_count;
+                *front_byte = light_line_start(text, end, is_last_line, policy);
+                Some(RopeSlice::from_str_with_policy(&text[*front_byte..end], policy))
+            }
+        }
+    }
 }
 
 impl<'a> Iterator for Lines<'a> {
@@ -168,59 +576,169 @@ impl<'a> Iterator for Lines<'a> {
     fn next(&mut self) -> Option<RopeSlice<'a>> {
         match *self {
             Lines(LinesEnum::Full {
-                ref mut node,
+                node,
                 start_char,
                 end_char,
-                ref mut line_idx,
+                ref mut front_line,
+                back_line,
+                policy,
+                ..
             }) => {
-                if *line_idx > node.line_break_count() {
+                if *front_line >= back_line {
                     return None;
-                } else {
-                    let a = {
-                        // Find the char that corresponds to the start of the line.
-                        let (chunk, _, c, l) = node.get_chunk_at_line_break(*line_idx);
-                        let a = (c + line_to_char_idx(chunk, *line_idx - l)).max(start_char);
-
-                        // Early out if we're past the specified end char
-                        if a > end_char {
-                            *line_idx = node.line_break_count() + 1;
-                            return None;
-                        }
-
-                        a
-                    };
-
-                    let b = if *line_idx < node.line_break_count() {
-                        // Find the char that corresponds to the start of the line.
-                        let (chunk, _, c, l) = node.get_chunk_at_line_break(*line_idx + 1);
-                        c + line_to_char_idx(chunk, *line_idx + 1 - l)
-                    } else {
-                        node.char_count()
-                    }.min(end_char);
-
-                    *line_idx += 1;
-
-                    return Some(RopeSlice::new_with_range(node, a, b));
                 }
+                let line = full_line(node, *front_line, start_char, end_char, policy);
+                *front_line += 1;
+                Some(line)
             }
             Lines(LinesEnum::Light {
-                ref mut text,
-                ref mut done,
+                text,
+                ref mut front_line,
+                back_line,
+                ref mut front_byte,
+                policy,
+                ..
             }) => {
-                if *done {
+                if *front_line >= back_line {
                     return None;
-                } else if text.len() == 0 {
-                    *done = true;
-                    return Some(RopeSlice::from_str(""));
-                } else {
-                    let split_idx = line_to_byte_idx(text, 1);
-                    let t = &text[..split_idx];
-                    *text = &text[split_idx..];
-                    return Some(RopeSlice::from_str(t));
                 }
+                let start = *front_byte;
+                *front_byte = start + line_to_byte_idx_with_policy(&text[start..], 1, policy);
+                *front_line += 1;
+                Some(RopeSlice::from_str_with_policy(&text[start..*front_byte], policy))
             }
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = match *self {
+            Lines(LinesEnum::Full {
+                front_line,
+                back_line,
+                ..
+            })
+            | Lines(LinesEnum::Light {
+                front_line,
+                back_line,
+                ..
+            }) => back_line.saturating_sub(front_line),
+        };
+        (len, Some(len))
+    }
+}
+
+impl<'a> DoubleEndedIterator for Lines<'a> {
+    fn next_back(&mut self) -> Option<RopeSlice<'a>> {
+        match *self {
+            Lines(LinesEnum::Full {
+                node,
+                start_char,
+                end_char,
+                front_line,
+                ref mut back_line,
+                policy,
+                ..
+            }) => {
+                if *back_line <= front_line {
+                    return None;
+                }
+                *back_line -= 1;
+                Some(full_line(node, *back_line, start_char, end_char, policy))
+            }
+            Lines(LinesEnum::Light {
+                text,
+                line_count,
+                front_line,
+                ref mut back_line,
+                ref mut back_byte,
+                policy,
+                ..
+            }) => {
+                if *back_line <= front_line {
+                    return None;
+                }
+                *back_line -= 1;
+                let end = *back_byte;
+                let is_last_line = *back_line + 1 == line_count;
+                *back_byte = light_line_start(text, end, is_last_line, policy);
+                Some(RopeSlice::from_str_with_policy(&text[*back_byte..end], policy))
+            }
+        }
+    }
+}
+
+impl<'a> ExactSizeIterator for Lines<'a> {}
+
+/// Returns the given line of `node`, truncated to the given char range.
+fn full_line<'a>(
+    node: &'a Arc<Node>,
+    line_idx: usize,
+    start_char: usize,
+    end_char: usize,
+    policy: LineBreakPolicy,
+) -> RopeSlice<'a> {
+    let a = {
+        // Find the char that corresponds to the start of the line.
+        let (chunk, _, c, l) = node.get_chunk_at_line_break(line_idx);
+        (c + line_to_char_idx_with_policy(chunk, line_idx - l, policy)).max(start_char)
+    };
+
+    let b = if line_idx < node.line_break_count(policy) {
+        // Find the char that corresponds to the start of the next line.
+        let (chunk, _, c, l) = node.get_chunk_at_line_break(line_idx + 1);
+        c + line_to_char_idx_with_policy(chunk, line_idx + 1 - l, policy)
+    } else {
+        node.char_count()
+    }.min(end_char);
+
+    RopeSlice::new_with_range(node, a, b, policy)
+}
+
+/// Returns the byte index of the start of the line in `text` that ends at
+/// `line_end`.
+///
+/// `is_last_line` indicates that the line has no line break of its own,
+/// i.e. that it's the last line of the text.
+fn light_line_start(
+    text: &str,
+    line_end: usize,
+    is_last_line: bool,
+    policy: LineBreakPolicy,
+) -> usize {
+    let mut end = line_end;
+    if !is_last_line {
+        // Skip the line's own line break.
+        end -= if text[..end].ends_with("\r\n") {
+            2
+        } else {
+            text[..end].chars().next_back().unwrap().len_utf8()
+        };
+    }
+
+    text[..end]
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| is_line_break_char(c, policy))
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(0)
+}
+
+#[inline]
+fn is_line_break_char(c: char, policy: LineBreakPolicy) -> bool {
+    match policy {
+        LineBreakPolicy::Lf => c == '\u{000A}',
+        LineBreakPolicy::LfCr => c == '\u{000A}' || c == '\u{000D}',
+        LineBreakPolicy::Unicode => matches!(
+            c,
+            '\u{000A}'
+                | '\u{000B}'
+                | '\u{000C}'
+                | '\u{000D}'
+                | '\u{0085}'
+                | '\u{2028}'
+                | '\u{2029}'
+        ),
+    }
 }
 
 //==========================================================
@@ -248,53 +766,68 @@ impl<'a> Iterator for Lines<'a> {
 /// CRLF pairs there are no guarantees about where the chunks are split.  For
 /// example, they may be zero-sized, they don't necessarily align with line
 /// breaks, etc.
-pub struct Chunks<'a>(ChunksEnum<'a>);
-
-enum ChunksEnum<'a> {
-    Full {
-        node_stack: Vec<&'a Arc<Node>>,
-        start: usize,
-        end: usize,
-        idx: usize,
-    },
-    Light {
-        text: &'a str,
-    },
+pub struct Chunks<'a> {
+    cursor: ChunkCursor<'a>,
+    end: usize,
 }
 
 impl<'a> Chunks<'a> {
     pub(crate) fn new(node: &Arc<Node>) -> Chunks {
-        Chunks(ChunksEnum::Full {
-            node_stack: vec![node],
-            start: 0,
-            end: node.text_info().bytes as usize,
-            idx: 0,
-        })
+        let cursor = ChunkCursor::new(node);
+        Chunks {
+            end: cursor.back,
+            cursor,
+        }
     }
 
-    pub(crate) fn new_empty() -> Chunks<'static> {
-        Chunks(ChunksEnum::Light { text: "" })
+    pub(crate) fn new_with_range(node: &Arc<Node>, start_char: usize, end_char: usize) -> Chunks {
+        Chunks::new_with_range_at(node, 0, start_char, end_char)
     }
 
-    pub(crate) fn new_with_range(node: &Arc<Node>, start_char: usize, end_char: usize) -> Chunks {
-        let start_byte = {
-            let (chunk, b, c, _) = node.get_chunk_at_char(start_char);
-            b + char_to_byte_idx(chunk, start_char - c)
-        };
-        let end_byte = {
-            let (chunk, b, c, _) = node.get_chunk_at_char(end_char);
-            b + char_to_byte_idx(chunk, end_char - c)
-        };
-        Chunks(ChunksEnum::Full {
-            node_stack: vec![node],
-            start: start_byte,
-            end: end_byte,
-            idx: 0,
-        })
+    /// `at_byte` is relative to `start_char`, and should be the start of a
+    /// chunk (clipped to the range) or the end of the range.
+    pub(crate) fn new_with_range_at(
+        node: &Arc<Node>,
+        at_byte: usize,
+        start_char: usize,
+        end_char: usize,
+    ) -> Chunks<'_> {
+        let cursor = ChunkCursor::new_with_range_at(node, at_byte, start_char, end_char);
+        Chunks {
+            end: cursor.back,
+            cursor,
+        }
     }
 
     pub(crate) fn from_str(text: &str) -> Chunks {
-        Chunks(ChunksEnum::Light { text: text })
+        Chunks::from_str_at(text, 0)
+    }
+
+    /// `at_byte` should be either zero or the length of `text`.
+    pub(crate) fn from_str_at(text: &str, at_byte: usize) -> Chunks<'_> {
+        debug_assert!(at_byte == 0 || at_byte == text.len());
+        Chunks {
+            cursor: ChunkCursor::from_str_at(text, at_byte),
+            end: text.len(),
+        }
+    }
+
+    /// Moves the front of the iterator backwards by one chunk and returns
+    /// that chunk.
+    ///
+    /// This is the inverse of `next()`: calling `next()` followed by
+    /// `prev()` returns the same chunk twice.  Returns `None` when the front
+    /// of the iterator is at the start of the text.
+    pub fn prev(&mut self) -> Option<&'a str> {
+        let c = &mut self.cursor;
+        if c.front <= c.start {
+            return None;
+        }
+        let (chunk, chunk_start) = c.front_chunk_at(c.front - 1);
+        let start = chunk_start.max(c.start);
+        let end = c.front;
+        c.front = start;
+        Some(&chunk[(start - chunk_start)..(end - chunk_start)])
     }
 }
 
@@ -302,72 +835,120 @@ impl<'a> Iterator for Chunks<'a> {
     type Item = &'a str;
 
     fn next(&mut self) -> Option<&'a str> {
-        match *self {
-            Chunks(ChunksEnum::Full {
-                ref mut node_stack,
-                start,
-                end,
-                ref mut idx,
-            }) => {
-                if *idx >= end {
-                    return None;
-                }
+        let c = &mut self.cursor;
+        if c.front >= c.back {
+            return None;
+        }
+        let (chunk, chunk_start) = c.front_chunk_at(c.front);
+        let start = c.front;
+        let end = (chunk_start + chunk.len()).min(self.end);
+        c.front = end;
+        Some(&chunk[(start - chunk_start)..(end - chunk_start)])
+    }
+}
 
-                loop {
-                    if let Some(node) = node_stack.pop() {
-                        match **node {
-                            Node::Leaf(ref text) => {
-                                let start_byte = if start <= *idx { 0 } else { start - *idx };
-                                let end_byte = if end >= (*idx + text.len()) {
-                                    text.len()
-                                } else {
-                                    end - *idx
-                                };
-                                *idx += text.len();
-                                return Some(&text[start_byte..end_byte]);
-                            }
-
-                            Node::Internal(ref children) => {
-                                // Find the first child that isn't before `start`,
-                                // updating `idx` as we go.
-                                let mut child_i = 0;
-                                for inf in children.info().iter() {
-                                    if (*idx + inf.bytes as usize) > start {
-                                        break;
-                                    } else {
-                                        *idx += inf.bytes as usize;
-                                        child_i += 1;
-                                    }
-                                }
-                                // Push relevant children to the stack.
-                                for child in (&children.nodes()[child_i..]).iter().rev() {
-                                    node_stack.push(child);
-                                }
-                            }
-                        }
-                    } else {
-                        return None;
-                    }
-                }
-            }
-            Chunks(ChunksEnum::Light { ref mut text }) => {
-                if text.is_empty() {
-                    return None;
-                } else {
-                    let t = *text;
-                    *text = "";
-                    return Some(t);
-                }
-            }
+impl<'a> DoubleEndedIterator for Chunks<'a> {
+    fn next_back(&mut self) -> Option<&'a str> {
+        let c = &mut self.cursor;
+        if c.back <= c.front {
+            return None;
+        }
+        let (chunk, chunk_start) = c.back_chunk_at(c.back - 1);
+        let start = chunk_start.max(c.start);
+        let end = c.back;
+        c.back = start;
+        Some(&chunk[(start - chunk_start)..(end - chunk_start)])
+    }
+}
+
+//===========================================================
+
+/// An iterator over a `Rope`'s extended grapheme clusters.
+///
+/// Each grapheme cluster is yielded as a `RopeSlice`, including clusters
+/// that span more than one chunk.
+///
+/// Only available with the `graphemes` cargo feature enabled.
+#[cfg(feature = "graphemes")]
+pub struct Graphemes<'a> {
+    text: RopeSlice<'a>,
+    front: usize, // Byte index
+    back: usize,  // Byte index
+}
+
+#[cfg(feature = "graphemes")]
+impl<'a> Graphemes<'a> {
+    pub(crate) fn new(text: RopeSlice<'a>) -> Graphemes<'a> {
+        Graphemes {
+            text,
+            front: 0,
+            back: text.len_bytes(),
+        }
+    }
+
+    /// Moves the front of the iterator backwards by one grapheme cluster
+    /// and returns that grapheme cluster.
+    ///
+    /// This is the inverse of `next()`: calling `next()` followed by
+    /// `prev()` returns the same grapheme cluster twice.  Returns `None`
+    /// when the front of the iterator is at the start of the text.
+    pub fn prev(&mut self) -> Option<RopeSlice<'a>> {
+        if self.front == 0 {
+            return None;
+        }
+        let end = self.front;
+        self.front = prev_grapheme_boundary_byte(&self.text, end);
+        Some(self.grapheme(self.front, end))
+    }
+
+    /// Returns the grapheme cluster spanning the given byte range.
+    ///
+    /// Clusters that fit within a single chunk are returned as a slice of
+    /// that chunk, without going through the tree again.
+    fn grapheme(&self, start: usize, end: usize) -> RopeSlice<'a> {
+        let (chunk, chunk_byte_idx, _, _) = self.text.chunk_at_byte(start);
+        if end <= chunk_byte_idx + chunk.len() {
+            let text = &chunk[(start - chunk_byte_idx)..(end - chunk_byte_idx)];
+            RopeSlice::from_str_with_policy(text, self.text.line_break_policy())
+        } else {
+            self.text.byte_slice(start..end)
         }
     }
 }
 
+#[cfg(feature = "graphemes")]
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = RopeSlice<'a>;
+
+    fn next(&mut self) -> Option<RopeSlice<'a>> {
+        if self.front >= self.back {
+            return None;
+        }
+        let start = self.front;
+        self.front = next_grapheme_boundary_byte(&self.text, start).min(self.back);
+        Some(self.grapheme(start, self.front))
+    }
+}
+
+#[cfg(feature = "graphemes")]
+impl<'a> DoubleEndedIterator for Graphemes<'a> {
+    fn next_back(&mut self) -> Option<RopeSlice<'a>> {
+        if self.back <= self.front {
+            return None;
+        }
+        let end = self.back;
+        self.back = prev_grapheme_boundary_byte(&self.text, end).max(self.front);
+        Some(self.grapheme(self.back, end))
+    }
+}
+
 //===========================================================
 
 #[cfg(test)]
 mod tests {
-    use Rope;
+    #[cfg(feature = "graphemes")]
+    use unicode_segmentation::UnicodeSegmentation;
+    use {LineBreakPolicy, Rope, RopeSlice};
 
     const TEXT: &str = "\r\n\
                         Hello there!  How're you doing?  It's a fine day, \
@@ -436,6 +1017,12 @@ mod tests {
                         私たちが生きだって嬉しいではないか？\r\n\
                         ";
 
+    #[cfg(feature = "graphemes")]
+    const TEXT_GRAPHEMES: &str = "He\u{303}\u{303}\u{303}\u{303}\u{303}\u{303}\u{303}\u{303}llo \
+                                  \u{1F1EC}\u{1F1E7}\u{1F1EF}\u{1F1F5}\u{1F1FA}\u{1F1F8} \
+                                  a\r\nb \u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467} \
+                                  o\u{301}\u{301}\u{301}\u{301}\u{301}\u{301}k!";
+
     #[test]
     fn bytes_01() {
         let r = Rope::from_str(TEXT);
@@ -572,4 +1159,393 @@ mod tests {
             idx += chunk.len();
         }
     }
+
+    #[test]
+    fn bytes_rev_01() {
+        let r = Rope::from_str(TEXT);
+        let bytes: Vec<u8> = r.bytes().rev().collect();
+        let expected: Vec<u8> = TEXT.bytes().rev().collect();
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn bytes_rev_02() {
+        let r = Rope::from_str(TEXT);
+
+        let s_start = 116;
+        let s_end = 331;
+        let s_start_byte = r.char_to_byte(s_start);
+        let s_end_byte = r.char_to_byte(s_end);
+
+        let s1 = r.slice(s_start..s_end);
+        let s2 = &TEXT[s_start_byte..s_end_byte];
+
+        assert_eq!(s1.bytes().len(), s2.len());
+        assert!(s1.bytes().rev().eq(s2.bytes().rev()));
+    }
+
+    #[test]
+    fn bytes_prev_01() {
+        let r = Rope::from_str(TEXT);
+        let mut itr = r.bytes();
+        let mut stack = Vec::new();
+        for _ in 0..100 {
+            stack.push(itr.next().unwrap());
+        }
+        while let Some(b) = itr.prev() {
+            assert_eq!(stack.pop().unwrap(), b);
+        }
+        assert!(stack.is_empty());
+        assert_eq!(Some(TEXT.as_bytes()[0]), itr.next());
+    }
+
+    #[test]
+    fn bytes_double_ended_01() {
+        let r = Rope::from_str(TEXT);
+        let mut itr = r.bytes();
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        loop {
+            match itr.next() {
+                Some(b) => front.push(b),
+                None => break,
+            }
+            match itr.next_back() {
+                Some(b) => back.push(b),
+                None => break,
+            }
+        }
+        back.reverse();
+        front.extend(back);
+        assert_eq!(front, TEXT.as_bytes());
+    }
+
+    #[test]
+    fn chars_rev_01() {
+        let r = Rope::from_str(TEXT);
+        assert!(r.chars().rev().eq(TEXT.chars().rev()));
+    }
+
+    #[test]
+    fn chars_rev_02() {
+        let r = Rope::from_str(TEXT);
+
+        let s_start = 116;
+        let s_end = 331;
+        let s_start_byte = r.char_to_byte(s_start);
+        let s_end_byte = r.char_to_byte(s_end);
+
+        let s1 = r.slice(s_start..s_end);
+        let s2 = &TEXT[s_start_byte..s_end_byte];
+
+        assert!(s1.chars().rev().eq(s2.chars().rev()));
+    }
+
+    #[test]
+    fn chars_prev_01() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(116..331);
+        let mut itr = s.chars();
+        let mut stack = Vec::new();
+        while let Some(c) = itr.next() {
+            stack.push(c);
+        }
+        while let Some(c) = itr.prev() {
+            assert_eq!(stack.pop().unwrap(), c);
+        }
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn chars_double_ended_01() {
+        let r = Rope::from_str(TEXT);
+        let mut itr = r.chars();
+        assert_eq!(Some('\r'), itr.next());
+        assert_eq!(Some('\n'), itr.next_back());
+        assert_eq!(Some('\r'), itr.next_back());
+        assert_eq!(Some('\n'), itr.next());
+        assert_eq!(Some('\n'), itr.prev());
+        assert_eq!(Some('\r'), itr.prev());
+        assert_eq!(None, itr.prev());
+    }
+
+    #[test]
+    fn lines_rev_01() {
+        let r = Rope::from_str(TEXT);
+        let lines: Vec<_> = r.lines().collect();
+        let rev_lines: Vec<_> = r.lines().rev().collect();
+
+        assert_eq!(34, rev_lines.len());
+        for (a, b) in lines.iter().zip(rev_lines.iter().rev()) {
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn lines_rev_02() {
+        let text = "Hello there!\nHow goes it?";
+        let r = Rope::from_str(text);
+
+        let mut lines = r.lines();
+        assert_eq!(2, lines.len());
+        assert_eq!("How goes it?", lines.next_back().unwrap());
+        assert_eq!("Hello there!\n", lines.next_back().unwrap());
+        assert!(lines.next_back().is_none());
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn lines_prev_01() {
+        let r = Rope::from_str(TEXT);
+        let mut lines = r.lines();
+
+        assert!(lines.prev().is_none());
+        assert_eq!("\r\n", lines.next().unwrap());
+        let line = lines.next().unwrap();
+        assert_eq!(line, lines.prev().unwrap());
+        assert_eq!("\r\n", lines.prev().unwrap());
+        assert!(lines.prev().is_none());
+        assert_eq!("\r\n", lines.next().unwrap());
+    }
+
+    #[test]
+    fn lines_sliced_rev_01() {
+        let r = Rope::from_str(TEXT);
+
+        let s_start = 116;
+        let s_end = 331;
+        let s_start_byte = r.char_to_byte(s_start);
+        let s_end_byte = r.char_to_byte(s_end);
+
+        let s1 = r.slice(s_start..s_end);
+        let s2 = &TEXT[s_start_byte..s_end_byte];
+
+        for (liner, linet) in s1.lines().rev().zip(s2.lines().rev()) {
+            assert_eq!(liner.to_string().trim_right(), linet);
+        }
+    }
+
+    #[test]
+    fn lines_light_01() {
+        let s = RopeSlice::from_str("Hello\r\nthere\u{2028}world\n");
+
+        let lines: Vec<_> = s.lines().collect();
+        assert_eq!(lines, ["Hello\r\n", "there\u{2028}", "world\n", ""]);
+
+        let mut rev_lines: Vec<_> = s.lines().rev().collect();
+        rev_lines.reverse();
+        assert_eq!(lines, rev_lines);
+
+        let mut itr = s.lines();
+        while itr.next().is_some() {}
+        let mut prev_lines = Vec::new();
+        while let Some(line) = itr.prev() {
+            prev_lines.push(line);
+        }
+        prev_lines.reverse();
+        assert_eq!(lines, prev_lines);
+    }
+
+    #[test]
+    fn lines_light_02() {
+        let s = RopeSlice::from_str("");
+        let mut itr = s.lines();
+
+        assert_eq!(1, itr.len());
+        assert_eq!("", itr.next_back().unwrap());
+        assert!(itr.next().is_none());
+        assert!(itr.prev().is_none());
+    }
+
+    #[test]
+    fn lines_light_03() {
+        let text = "a\u{000C}b\r\nc\rd\u{2028}e\nf";
+
+        let s = RopeSlice::from_str_with_policy(text, LineBreakPolicy::Lf);
+        let lines: Vec<_> = s.lines().collect();
+        assert_eq!(lines, ["a\u{000C}b\r\n", "c\rd\u{2028}e\n", "f"]);
+        let mut rev_lines: Vec<_> = s.lines().rev().collect();
+        rev_lines.reverse();
+        assert_eq!(lines, rev_lines);
+
+        let s = RopeSlice::from_str_with_policy(text, LineBreakPolicy::LfCr);
+        let lines: Vec<_> = s.lines().collect();
+        assert_eq!(lines, ["a\u{000C}b\r\n", "c\r", "d\u{2028}e\n", "f"]);
+        let mut rev_lines: Vec<_> = s.lines().rev().collect();
+        rev_lines.reverse();
+        assert_eq!(lines, rev_lines);
+    }
+
+    #[test]
+    fn chunks_rev_01() {
+        let r = Rope::from_str(TEXT);
+
+        let mut idx = TEXT.len();
+        for chunk in r.chunks().rev() {
+            assert_eq!(chunk, &TEXT[(idx - chunk.len())..idx]);
+            idx -= chunk.len();
+        }
+        assert_eq!(0, idx);
+    }
+
+    #[test]
+    fn chunks_sliced_rev_01() {
+        let r = Rope::from_str(TEXT);
+
+        let s_start = 116;
+        let s_end = 331;
+        let s_start_byte = r.char_to_byte(s_start);
+        let s_end_byte = r.char_to_byte(s_end);
+
+        let s1 = r.slice(s_start..s_end);
+        let s2 = &TEXT[s_start_byte..s_end_byte];
+
+        let mut idx = s2.len();
+        for chunk in s1.chunks().rev() {
+            assert_eq!(chunk, &s2[(idx - chunk.len())..idx]);
+            idx -= chunk.len();
+        }
+        assert_eq!(0, idx);
+    }
+
+    #[test]
+    fn chunks_prev_01() {
+        let r = Rope::from_str(TEXT);
+        let mut itr = r.chunks();
+        let mut stack = Vec::new();
+        for _ in 0..10 {
+            stack.push(itr.next().unwrap());
+        }
+        while let Some(chunk) = itr.prev() {
+            assert_eq!(stack.pop().unwrap(), chunk);
+        }
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn chunks_prev_02() {
+        // Change direction at every chunk.
+        let r = Rope::from_str(TEXT);
+        let chunks: Vec<_> = r.chunks().collect();
+        for turn in 0..chunks.len() {
+            let mut itr = r.chunks();
+            for i in 0..turn {
+                assert_eq!(itr.next(), Some(chunks[i]));
+            }
+            for i in (0..turn).rev() {
+                assert_eq!(itr.prev(), Some(chunks[i]));
+            }
+            assert_eq!(itr.prev(), None);
+            for i in 0..chunks.len() {
+                assert_eq!(itr.next(), Some(chunks[i]));
+            }
+            assert_eq!(itr.next(), None);
+        }
+    }
+
+    #[test]
+    fn chunks_light_01() {
+        let s = RopeSlice::from_str("Hello world!");
+        let mut itr = s.chunks();
+
+        assert!(itr.prev().is_none());
+        assert_eq!("Hello world!", itr.next().unwrap());
+        assert!(itr.next().is_none());
+        assert!(itr.next_back().is_none());
+        assert_eq!("Hello world!", itr.prev().unwrap());
+        assert_eq!("Hello world!", itr.next_back().unwrap());
+        assert!(itr.next().is_none());
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn graphemes_01() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let graphemes: Vec<_> = r.graphemes().collect();
+        let expected: Vec<_> = TEXT_GRAPHEMES.graphemes(true).collect();
+
+        assert_eq!(expected.len(), graphemes.len());
+        for (g1, g2) in graphemes.iter().zip(expected.iter()) {
+            assert_eq!(g1, g2);
+        }
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn graphemes_02() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let s = r.slice(3..38);
+        let text = s.to_string();
+
+        let mut itr = s.graphemes();
+        for g in text.graphemes(true) {
+            assert_eq!(itr.next().unwrap(), g);
+        }
+        assert!(itr.next().is_none());
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn graphemes_rev_01() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let s = r.slice(3..38);
+        let text = s.to_string();
+
+        let mut itr = s.graphemes().rev();
+        for g in text.graphemes(true).rev() {
+            assert_eq!(itr.next().unwrap(), g);
+        }
+        assert!(itr.next().is_none());
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn graphemes_double_ended_01() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let mut itr = r.graphemes();
+        let mut expected = TEXT_GRAPHEMES.graphemes(true);
+
+        loop {
+            match (itr.next(), expected.next()) {
+                (Some(g1), Some(g2)) => assert_eq!(g1, g2),
+                (None, None) => break,
+                _ => panic!("grapheme count mismatch"),
+            }
+            match (itr.next_back(), expected.next_back()) {
+                (Some(g1), Some(g2)) => assert_eq!(g1, g2),
+                (None, None) => break,
+                _ => panic!("grapheme count mismatch"),
+            }
+        }
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn graphemes_prev_01() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let mut itr = r.graphemes();
+        let mut stack = Vec::new();
+        for _ in 0..10 {
+            stack.push(itr.next().unwrap());
+        }
+        while let Some(g) = itr.prev() {
+            assert_eq!(stack.pop().unwrap(), g);
+        }
+        assert!(stack.is_empty());
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn graphemes_light_01() {
+        let s = RopeSlice::from_str("a\r\ne\u{301}!");
+        let mut itr = s.graphemes();
+
+        assert!(itr.prev().is_none());
+        assert_eq!("a", itr.next().unwrap());
+        assert_eq!("\r\n", itr.next().unwrap());
+        assert_eq!("!", itr.next_back().unwrap());
+        assert_eq!("e\u{301}", itr.next().unwrap());
+        assert!(itr.next().is_none());
+        assert_eq!("e\u{301}", itr.prev().unwrap());
+    }
 }
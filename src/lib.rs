@@ -14,9 +14,52 @@
 //! - [`RopeSlice`](struct.RopeSlice.html): an immutable view into part of a
 //!   `Rope`.
 //! - [`iter`](iter/index.html): iterators over `Rope`/`RopeSlice` data.
+//! - [`search`](search/index.html): types for searching `Rope`/`RopeSlice`
+//!   text.
+//! - [`regex`](regex/index.html): regular expressions for searching
+//!   `Rope`/`RopeSlice` text (with the optional `regex` cargo feature).
 //! - [`RopeBuilder`](struct.RopeBuilder.html): an efficient incremental
 //!   `Rope` builder.
 //!
+//! Edits can also be described up front and applied as a batch, either as a
+//! list of [`Edit`](struct.Edit.html)s or as a
+//! [`ChangeSet`](struct.ChangeSet.html), which additionally supports undo,
+//! composition, and operational transformation.
+//! [`History`](struct.History.html) keeps an undo tree of whole-text
+//! snapshots, which is cheap because `Rope` clones share their data.
+//! Positions that need to follow the text through edits can be tracked
+//! with marks (see [`Rope::add_mark()`](struct.Rope.html#method.add_mark)).
+//!
+//! Most of `Rope`'s and `RopeSlice`'s methods panic when given out-of-bounds
+//! indices.  Each of them also has a non-panicking `try_*` counterpart that
+//! instead returns an [`Error`](enum.Error.html) describing what was wrong.
+//!
+//! With the optional `graphemes` cargo feature enabled, `Rope` and
+//! `RopeSlice` can also find grapheme cluster boundaries and iterate over
+//! grapheme clusters (see e.g.
+//! [`RopeSlice::graphemes()`](struct.RopeSlice.html#method.graphemes)).
+//!
+//! With the optional `multi-pattern` cargo feature enabled, `RopeSlice`
+//! can also search for many patterns at once (see
+//! [`search::MultiPattern`](search/struct.MultiPattern.html)).
+//!
+//! With the optional `parallel` cargo feature enabled, large texts can be
+//! built into a `Rope` using multiple threads (see
+//! [`Rope::from_str_parallel()`](struct.Rope.html#method.from_str_parallel)).
+//! This feature needs Rust 1.63 or later.
+//!
+//! With the optional `regex` cargo feature enabled, `Rope` and `RopeSlice`
+//! can also be searched with regular expressions (see the
+//! [`regex`](regex/index.html) module).  This feature needs Rust 1.65 or
+//! later.
+//!
+//! With the optional `serde` cargo feature enabled, `Rope` and `RopeSlice`
+//! implement `Serialize`, and `Rope` implements `Deserialize`.  Both are
+//! represented as a plain string.
+//!
+//! Ropey itself needs Rust 1.61 or later, because of its `memchr`
+//! dependency.
+//!
 //! # A Basic Example
 //!
 //! Let's say we want to open up a file, replace the 516th line (the writing
@@ -128,21 +171,282 @@
 //!
 //! CRLF pairs are always treated as a single line break, and are never split
 //! across chunks.  Note, however, that slicing can still split them.
+//!
+//! That's the default, at least.  Each `Rope` can be switched to a narrower
+//! set of line breaks with
+//! [`Rope::set_line_break_policy()`](struct.Rope.html#method.set_line_break_policy),
+//! which all of its line-related methods, slices, and iterators then
+//! respect.  See [`LineBreakPolicy`](enum.LineBreakPolicy.html) for the
+//! available options.
 
 #![cfg_attr(feature = "cargo-clippy", allow(inline_always))]
 #![cfg_attr(feature = "cargo-clippy", allow(needless_return))]
 
+#[cfg(feature = "multi-pattern")]
+extern crate aho_corasick;
+extern crate memchr;
+#[cfg(feature = "regex")]
+extern crate regex_syntax;
 extern crate smallvec;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "graphemes")]
+extern crate unicode_segmentation;
 
+mod change_set;
 mod crlf;
+mod edit;
+mod encoding;
+mod escaped_bytes;
+#[cfg(feature = "graphemes")]
+mod graphemes;
+mod history;
+mod loader;
+mod mark;
 mod rope;
 mod rope_builder;
+#[cfg(feature = "serde")]
+mod serde_impls;
 mod slice;
 mod tree;
 
 pub mod iter;
+#[cfg(feature = "regex")]
+pub mod regex;
+pub mod search;
 pub mod str_utils;
 
+pub use change_set::{Assoc, ChangeSet, Operation};
+pub use edit::{Edit, Replacement};
+pub use encoding::{
+    Encoding, EncodingDetection, EncodingGuess, EncodingInfo, InvalidUtf8, ReadOptions,
+    WriteOptions,
+};
+pub use history::{History, MemoryUsage};
+pub use loader::{LoadOutcome, LoadProgress, LoadStatus, RopeLoader};
+pub use mark::{MarkId, Marks};
 pub use rope::Rope;
 pub use rope_builder::RopeBuilder;
 pub use slice::RopeSlice;
+
+//==============================================================
+// Line breaks
+
+/// The set of character sequences that are treated as line breaks.
+///
+/// A `Rope` only keeps track of its line breaks under its current policy,
+/// so switching to another policy means counting them again.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LineBreakPolicy {
+    /// Only `U+000A` (LF).  A CRLF pair still counts as a single line break,
+    /// via its LF.
+    Lf,
+    /// `U+000A` (LF), `U+000D` (CR), and CRLF pairs.
+    LfCr,
+    /// All of the line breaks listed in the [crate docs](index.html#a-note-about-line-endings).
+    Unicode,
+}
+
+impl Default for LineBreakPolicy {
+    #[inline]
+    fn default() -> LineBreakPolicy {
+        LineBreakPolicy::Unicode
+    }
+}
+
+/// A line ending to convert line endings to.
+///
+/// See [`WriteOptions`](struct.WriteOptions.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// `U+000A` (LF), as on Unix.
+    Lf,
+    /// `U+000D U+000A` (CRLF), as on Windows.
+    Crlf,
+    /// `U+000D` (CR), as on classic Mac OS.
+    Cr,
+}
+
+impl LineEnding {
+    /// Returns the text of the line ending.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+}
+
+/// Counts of each kind of line break in a text.
+///
+/// Returned by
+/// [`Rope::line_ending_stats()`](struct.Rope.html#method.line_ending_stats).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct LineEndingStats {
+    /// `U+000A` (LF), not counting the LFs of CRLF pairs.
+    pub lf: usize,
+    /// `U+000D U+000A` (CRLF).
+    pub crlf: usize,
+    /// `U+000D` (CR), not counting the CRs of CRLF pairs.
+    pub cr: usize,
+    /// `U+0085` (NEL).
+    pub nel: usize,
+    /// `U+2028` (Line Separator).
+    pub ls: usize,
+    /// `U+2029` (Paragraph Separator).
+    pub ps: usize,
+    /// `U+000B` (Vertical Tab).
+    pub vt: usize,
+    /// `U+000C` (Form Feed).
+    pub ff: usize,
+}
+
+impl LineEndingStats {
+    /// Returns the most common of the LF, CRLF and CR line endings, or
+    /// `None` if there are none of them.  Ties go to LF, then CRLF.
+    pub fn dominant(&self) -> Option<LineEnding> {
+        let counts = [
+            (self.lf, LineEnding::Lf),
+            (self.crlf, LineEnding::Crlf),
+            (self.cr, LineEnding::Cr),
+        ];
+        let mut dominant = None;
+        let mut max_count = 0;
+        for &(count, line_ending) in &counts {
+            if count > max_count {
+                dominant = Some(line_ending);
+                max_count = count;
+            }
+        }
+        dominant
+    }
+}
+
+//==============================================================
+// Error handling
+
+use std::fmt;
+
+/// Ropey's result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Ropey's error type.
+///
+/// Returned by the non-panicking `try_*` methods of `Rope` and `RopeSlice`
+/// in the situations where their panicking counterparts would panic.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Indicates that the passed byte index was out of bounds.
+    ///
+    /// Contains the index attempted and the actual length of the
+    /// `Rope`/`RopeSlice` in bytes, in that order.
+    ByteIndexOutOfBounds(usize, usize),
+
+    /// Indicates that the passed char index was out of bounds.
+    ///
+    /// Contains the index attempted and the actual length of the
+    /// `Rope`/`RopeSlice` in chars, in that order.
+    CharIndexOutOfBounds(usize, usize),
+
+    /// Indicates that the passed line index was out of bounds.
+    ///
+    /// Contains the index attempted and the actual length of the
+    /// `Rope`/`RopeSlice` in lines, in that order.
+    LineIndexOutOfBounds(usize, usize),
+
+    /// Indicates that the passed utf16 code-unit index was out of
+    /// bounds.
+    ///
+    /// Contains the index attempted and the actual length of the
+    /// `Rope`/`RopeSlice` in utf16 code units, in that order.
+    Utf16IndexOutOfBounds(usize, usize),
+
+    /// Indicates that the passed byte index was not a char boundary.
+    ///
+    /// Contains the passed byte index.
+    ByteIndexNotCharBoundary(usize),
+
+    /// Indicates that a reversed byte-index range (end < start) was
+    /// encountered.
+    ///
+    /// Contains the [start, end) byte indices of the range, in that order.
+    ByteRangeInvalid(usize, usize),
+
+    /// Indicates that a reversed char-index range (end < start) was
+    /// encountered.
+    ///
+    /// Contains the [start, end) char indices of the range, in that order.
+    CharRangeInvalid(usize, usize),
+
+    /// Indicates that two edits in a batch of edits overlap.
+    ///
+    /// Contains the indices of the two edits within the batch, in
+    /// ascending order.
+    EditsOverlap(usize, usize),
+
+    /// Indicates that a char can't be represented in the encoding that
+    /// text is being written in.
+    ///
+    /// Contains the char index of the char, and the char itself.
+    UnencodableChar(usize, char),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::ByteIndexOutOfBounds(index, len) => write!(
+                f,
+                "Byte index out of bounds: byte index {}, Rope/RopeSlice byte length {}",
+                index, len
+            ),
+            Error::CharIndexOutOfBounds(index, len) => write!(
+                f,
+                "Char index out of bounds: char index {}, Rope/RopeSlice char length {}",
+                index, len
+            ),
+            Error::LineIndexOutOfBounds(index, len) => write!(
+                f,
+                "Line index out of bounds: line index {}, Rope/RopeSlice line count {}",
+                index, len
+            ),
+            Error::Utf16IndexOutOfBounds(index, len) => write!(
+                f,
+                "Utf16 code-unit index out of bounds: utf16 index {}, Rope/RopeSlice utf16 length {}",
+                index, len
+            ),
+            Error::ByteIndexNotCharBoundary(index) => write!(
+                f,
+                "Byte index is not a valid char boundary: byte index {}",
+                index
+            ),
+            Error::ByteRangeInvalid(start, end) => write!(
+                f,
+                "Invalid byte range {}..{}: start must be <= end",
+                start, end
+            ),
+            Error::CharRangeInvalid(start, end) => write!(
+                f,
+                "Invalid char range {}..{}: start must be <= end",
+                start, end
+            ),
+            Error::EditsOverlap(a, b) => write!(f, "Edits overlap: edit {} and edit {}", a, b),
+            Error::UnencodableChar(index, c) => write!(
+                f,
+                "Char can't be represented in the encoding: char index {}, char {:?}",
+                index, c
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Just re-use the debug impl.
+        fmt::Debug::fmt(self, f)
+    }
+}
@@ -0,0 +1,363 @@
+use std;
+use std::io;
+
+use rope::Rope;
+use rope_builder::RopeBuilder;
+use str_utils::byte_to_line_idx;
+
+/// An incremental `Rope` loader, for loading large texts without blocking
+/// until they're done.
+///
+/// Like [`Rope::from_reader()`](struct.Rope.html#method.from_reader), this
+/// loads utf8 text from a reader, but it also:
+///
+/// - ...reports its progress after every read, to a callback set with
+///   [`on_progress()`](#method.on_progress).
+/// - ...can be cancelled between reads, by returning `false` from that
+///   callback.
+/// - ...can stop at a maximum size, set with
+///   [`max_bytes()`](#method.max_bytes).
+///
+/// When loading stops early, the text loaded so far is still returned,
+/// along with how far into the reader's output it goes.
+///
+/// # Example
+/// ```
+/// # use ropey::{LoadStatus, RopeLoader};
+/// let text = "Hello world!\n".repeat(10000);
+/// let mut updates = 0;
+/// let outcome = RopeLoader::new()
+///     .max_bytes(100000)
+///     .on_progress(|progress| {
+///         updates += 1;
+///         progress.lines < 5000
+///     })
+///     .load(text.as_bytes())
+///     .unwrap();
+///
+/// assert_eq!(outcome.status, LoadStatus::Cancelled);
+/// assert_eq!(outcome.rope.len_bytes(), outcome.byte_offset);
+/// assert!(updates > 0);
+/// ```
+pub struct RopeLoader<'a> {
+    max_bytes: Option<usize>,
+    on_progress: Option<Box<dyn FnMut(LoadProgress) -> bool + 'a>>,
+}
+
+impl<'a> RopeLoader<'a> {
+    /// Creates a loader with no size limit and no progress callback.
+    #[inline]
+    pub fn new() -> RopeLoader<'a> {
+        RopeLoader {
+            max_bytes: None,
+            on_progress: None,
+        }
+    }
+
+    /// Sets the most bytes of text to load.
+    ///
+    /// Loading stops with `LoadStatus::TooLarge` if the reader has more
+    /// than this, and the returned `Rope` has the text that fits.
+    #[inline]
+    pub fn max_bytes(mut self, max_bytes: usize) -> RopeLoader<'a> {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Sets a callback to report progress to.
+    ///
+    /// The callback is called after each read from the reader, except the
+    /// last, with the amount of text loaded so far.  It returns whether to
+    /// keep loading: returning `false` cancels loading, which then stops
+    /// with `LoadStatus::Cancelled`.
+    #[inline]
+    pub fn on_progress<F>(mut self, on_progress: F) -> RopeLoader<'a>
+    where
+        F: FnMut(LoadProgress) -> bool + 'a,
+    {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// Loads a `Rope` from the output of a reader.
+    ///
+    /// Runs in O(N) time.
+    ///
+    /// # Errors
+    ///
+    /// - If the reader returns an error, `load` stops and returns that
+    ///   error.
+    /// - If non-utf8 data is encountered, an IO error with kind
+    ///   `InvalidData` is returned.
+    ///
+    /// Note: some data from the reader is likely consumed even if there is
+    /// an error.
+    pub fn load<T: io::Read>(mut self, mut reader: T) -> io::Result<LoadOutcome> {
+        const BUFFER_SIZE: usize = 1 << 16;
+        let mut builder = RopeBuilder::new();
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let mut fill_idx = 0; // How much of `buffer` is filled with unused data
+        let mut read_total = 0;
+        let mut progress = LoadProgress { bytes: 0, lines: 1 };
+        let mut after_cr = false;
+        loop {
+            // Read at most one byte past the size limit, to tell whether
+            // there is more.
+            let read_end = match self.max_bytes {
+                Some(max_bytes) => BUFFER_SIZE.min(fill_idx + max_bytes + 1 - read_total),
+                None => BUFFER_SIZE,
+            };
+            let read_count = match reader.read(&mut buffer[fill_idx..read_end]) {
+                Ok(read_count) => read_count,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            fill_idx += read_count;
+            read_total += read_count;
+
+            let too_large = match self.max_bytes {
+                Some(max_bytes) if read_total > max_bytes => {
+                    fill_idx -= read_total - max_bytes;
+                    true
+                }
+                _ => false,
+            };
+
+            // Append the valid part of the buffer to the rope.
+            let valid_count = match std::str::from_utf8(&buffer[..fill_idx]) {
+                Ok(_) => fill_idx,
+                Err(e) => {
+                    // A char cut off by the end of the read, or by the size
+                    // limit, is finished by the next read or dropped.
+                    let is_incomplete = e.error_len().is_none() && (read_count > 0 || too_large);
+                    if !is_incomplete {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "stream did not contain valid UTF-8",
+                        ));
+                    }
+                    e.valid_up_to()
+                }
+            };
+            if valid_count > 0 {
+                let text = unsafe { std::str::from_utf8_unchecked(&buffer[..valid_count]) };
+                builder.append(text);
+
+                progress.bytes += valid_count;
+                progress.lines += byte_to_line_idx(text, valid_count);
+                if after_cr && text.as_bytes()[0] == 0x0A {
+                    // A CRLF pair split between reads.
+                    progress.lines -= 1;
+                }
+                after_cr = text.as_bytes()[valid_count - 1] == 0x0D;
+            }
+
+            // Shift the unused part of the buffer to the beginning.
+            buffer.copy_within(valid_count..fill_idx, 0);
+            fill_idx -= valid_count;
+
+            let status = if too_large {
+                LoadStatus::TooLarge
+            } else if read_count == 0 {
+                LoadStatus::Complete
+            } else {
+                let keep_going = match self.on_progress {
+                    Some(ref mut on_progress) => on_progress(progress),
+                    None => true,
+                };
+                if keep_going {
+                    continue;
+                }
+                LoadStatus::Cancelled
+            };
+            return Ok(LoadOutcome {
+                rope: builder.finish(),
+                byte_offset: progress.bytes,
+                status,
+            });
+        }
+    }
+}
+
+impl<'a> Default for RopeLoader<'a> {
+    #[inline]
+    fn default() -> RopeLoader<'a> {
+        RopeLoader::new()
+    }
+}
+
+/// How much text a [`RopeLoader`](struct.RopeLoader.html) has loaded so
+/// far.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LoadProgress {
+    /// The number of bytes loaded.
+    pub bytes: usize,
+    /// The number of lines loaded, counted the same way as by
+    /// [`Rope::len_lines()`](struct.Rope.html#method.len_lines) with the
+    /// default line break policy.
+    pub lines: usize,
+}
+
+/// How loading with a [`RopeLoader`](struct.RopeLoader.html) ended.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LoadStatus {
+    /// All of the text was loaded.
+    Complete,
+    /// Loading was cancelled by the progress callback.
+    Cancelled,
+    /// The text was larger than the size limit.
+    TooLarge,
+}
+
+/// The result of loading with a [`RopeLoader`](struct.RopeLoader.html).
+#[derive(Debug, Clone)]
+pub struct LoadOutcome {
+    /// The loaded text.  If loading stopped early, this is the text loaded
+    /// up to that point.
+    pub rope: Rope,
+    /// How many bytes of the reader's output are in `rope`.  If loading
+    /// stopped early, this is where to pick up from to load the rest.
+    pub byte_offset: usize,
+    /// How loading ended.
+    pub status: LoadStatus,
+}
+
+//=============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 127 bytes, 103 chars, 4 lines
+    const TEXT: &str = "Hello there!  How're you doing?\r\nIt's \
+                        a fine day, isn't it?\r\nAren't you glad \
+                        we're alive?\r\nこんにちは、みんなさん！";
+
+    /// A reader that returns a few bytes at a time.
+    struct TrickleReader<'a> {
+        bytes: &'a [u8],
+        read_len: usize,
+    }
+
+    impl<'a> io::Read for TrickleReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.read_len.min(buf.len()).min(self.bytes.len());
+            buf[..len].copy_from_slice(&self.bytes[..len]);
+            self.bytes = &self.bytes[len..];
+            Ok(len)
+        }
+    }
+
+    fn trickle(read_len: usize) -> TrickleReader<'static> {
+        TrickleReader {
+            bytes: TEXT.as_bytes(),
+            read_len: read_len,
+        }
+    }
+
+    #[test]
+    fn load_01() {
+        for read_len in 1..10 {
+            let mut reports = Vec::new();
+            let outcome = RopeLoader::new()
+                .on_progress(|progress| {
+                    reports.push(progress);
+                    true
+                })
+                .load(trickle(read_len))
+                .unwrap();
+
+            assert_eq!(outcome.rope, TEXT);
+            assert_eq!(outcome.byte_offset, TEXT.len());
+            assert_eq!(outcome.status, LoadStatus::Complete);
+            outcome.rope.assert_integrity();
+            outcome.rope.assert_invariants();
+
+            // Progress only ever goes up, and CRLF pairs split between
+            // reads are only counted once.
+            assert!(reports.windows(2).all(|w| w[0].bytes <= w[1].bytes));
+            let last = *reports.last().unwrap();
+            assert_eq!(
+                last,
+                LoadProgress {
+                    bytes: 127,
+                    lines: 4
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn load_02() {
+        // Cancelling.
+        let outcome = RopeLoader::new()
+            .on_progress(|progress| progress.lines < 3)
+            .load(trickle(4))
+            .unwrap();
+
+        assert_eq!(outcome.status, LoadStatus::Cancelled);
+        assert_eq!(outcome.rope.len_lines(), 3);
+        assert_eq!(outcome.rope, &TEXT[..outcome.byte_offset]);
+        assert!(outcome.byte_offset < 68);
+    }
+
+    #[test]
+    fn load_03() {
+        // Size limits, including ones in the middle of a char.
+        for max_bytes in 0..(TEXT.len() + 2) {
+            let outcome = RopeLoader::new()
+                .max_bytes(max_bytes)
+                .load(trickle(5))
+                .unwrap();
+
+            let mut end = max_bytes.min(TEXT.len());
+            while !TEXT.is_char_boundary(end) {
+                end -= 1;
+            }
+            assert_eq!(outcome.rope, &TEXT[..end]);
+            assert_eq!(outcome.byte_offset, end);
+            if max_bytes < TEXT.len() {
+                assert_eq!(outcome.status, LoadStatus::TooLarge);
+            } else {
+                assert_eq!(outcome.status, LoadStatus::Complete);
+            }
+        }
+    }
+
+    #[test]
+    fn load_04() {
+        // Invalid utf8, including an incomplete char at the end.
+        let bytes = b"Hello\xFFworld";
+        let err = RopeLoader::new().load(&bytes[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let bytes = "Hello みんな".as_bytes();
+        let err = RopeLoader::new()
+            .load(&bytes[..(bytes.len() - 1)])
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Invalid utf8 within the size limit, even when stopping there.
+        let err = RopeLoader::new()
+            .max_bytes(4)
+            .load(&b"ab\xFFcdefgh"[..])
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = RopeLoader::new()
+            .max_bytes(4)
+            .load(TrickleReader {
+                bytes: b"ab\xFFcdefgh",
+                read_len: 1,
+            })
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Invalid utf8 past the limit isn't read.
+        let outcome = RopeLoader::new()
+            .max_bytes(4)
+            .load(&b"abcd\xFFgh"[..])
+            .unwrap();
+        assert_eq!(outcome.rope, "abcd");
+        assert_eq!(outcome.status, LoadStatus::TooLarge);
+    }
+}
@@ -0,0 +1,377 @@
+use std;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use change_set::Assoc;
+
+/// Source of mark ids.  Ids are unique across all `Rope`s, so that marks
+/// from different `Rope`s never collide when the `Rope`s are appended.
+static NEXT_MARK_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Identifies a mark added with
+/// [`Rope::add_mark()`](struct.Rope.html#method.add_mark).
+///
+/// Mark ids are unique across all `Rope`s, except that a cloned `Rope` has
+/// the same marks (with the same ids) as the `Rope` it was cloned from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MarkId(usize);
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Mark {
+    pub(crate) id: MarkId,
+    pub(crate) char_idx: usize,
+    pub(crate) gravity: Assoc,
+}
+
+/// The marks of a `Rope`, kept sorted by id.
+///
+/// The marks are shared between clones, so that cloning a `Rope` stays
+/// O(1), and only copied when one of the clones' marks change.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MarkSet {
+    marks: Option<Arc<Vec<Mark>>>,
+}
+
+impl MarkSet {
+    #[inline]
+    pub(crate) fn new() -> MarkSet {
+        MarkSet { marks: None }
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.marks().is_empty()
+    }
+
+    #[inline]
+    pub(crate) fn iter<'a>(&'a self) -> slice::Iter<'a, Mark> {
+        self.marks().iter()
+    }
+
+    pub(crate) fn get(&self, id: MarkId) -> Option<&Mark> {
+        let marks = self.marks();
+        marks
+            .binary_search_by_key(&id, |mark| mark.id)
+            .ok()
+            .map(|i| &marks[i])
+    }
+
+    pub(crate) fn add(&mut self, char_idx: usize, gravity: Assoc) -> MarkId {
+        let id = MarkId(NEXT_MARK_ID.fetch_add(1, Ordering::Relaxed));
+        let mark = Mark {
+            id,
+            char_idx,
+            gravity,
+        };
+
+        // Ids only ever increase, so this is almost always a push.
+        let marks = self.marks_mut();
+        match marks.binary_search_by_key(&id, |mark| mark.id) {
+            Ok(_) => unreachable!(),
+            Err(i) => marks.insert(i, mark),
+        }
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: MarkId) -> Option<Mark> {
+        let i = self.marks().binary_search_by_key(&id, |mark| mark.id).ok()?;
+        let mark = self.marks_mut().remove(i);
+        if self.is_empty() {
+            self.clear();
+        }
+        Some(mark)
+    }
+
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.marks = None;
+    }
+
+    /// Updates the marks for a batch of replacements, each given as
+    /// `(start, end, inserted_char_count)`, sorted by position and
+    /// non-overlapping.
+    ///
+    /// Marks move the same way as positions mapped through the equivalent
+    /// `ChangeSet` with `ChangeSet::map_pos()`: a mark at the start of a
+    /// replacement stays before the inserted text only if it has
+    /// `Assoc::Before` gravity, and a mark inside the replaced text (or at
+    /// its end) ends up after the inserted text.
+    ///
+    /// Runs in O(K log E + E) time for K marks and E replacements.
+    pub(crate) fn map_edits(&mut self, edits: &[(usize, usize, usize)]) {
+        if self.is_empty() || edits.is_empty() {
+            return;
+        }
+
+        // The total change in length from the replacements before each one.
+        let mut deltas = Vec::with_capacity(edits.len());
+        let mut delta: isize = 0;
+        for &(start, end, inserted) in edits {
+            deltas.push(delta);
+            delta += inserted as isize - (end - start) as isize;
+        }
+
+        for mark in self.marks_mut().iter_mut() {
+            mark.char_idx = map_through_edits(mark.char_idx, mark.gravity, edits, &deltas);
+        }
+    }
+
+    /// Removes and returns the marks that end up in the right half when
+    /// splitting at `char_idx`, made relative to the split point.
+    ///
+    /// Marks exactly at the split point stay on the left with
+    /// `Assoc::Before` gravity, and go right with `Assoc::After`.
+    pub(crate) fn split_off(&mut self, char_idx: usize) -> MarkSet {
+        let goes_right = |mark: &Mark| {
+            mark.char_idx > char_idx || (mark.char_idx == char_idx && mark.gravity == Assoc::After)
+        };
+        if !self.iter().any(goes_right) {
+            return MarkSet::new();
+        }
+
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        for mark in self.iter() {
+            if goes_right(mark) {
+                right.push(Mark {
+                    char_idx: mark.char_idx - char_idx,
+                    ..*mark
+                });
+            } else {
+                left.push(*mark);
+            }
+        }
+        *self = MarkSet::from_vec(left);
+        MarkSet::from_vec(right)
+    }
+
+    /// Adds `other`'s marks, offset by `char_offset`.  Where both sets have
+    /// a mark with the same id, this set's mark is kept.
+    pub(crate) fn append(&mut self, other: &MarkSet, char_offset: usize) {
+        if other.is_empty() {
+            return;
+        }
+
+        let marks = self.marks_mut();
+        marks.extend(other.iter().map(|mark| Mark {
+            char_idx: mark.char_idx + char_offset,
+            ..*mark
+        }));
+
+        // Stable, so this set's marks come first among equal ids.
+        marks.sort_by_key(|mark| mark.id);
+        marks.dedup_by_key(|mark| mark.id);
+    }
+
+    #[inline]
+    fn marks(&self) -> &[Mark] {
+        match self.marks {
+            Some(ref marks) => marks,
+            None => &[],
+        }
+    }
+
+    /// Returns the marks for changing, copying them first if they're shared
+    /// with a clone.
+    #[inline]
+    fn marks_mut(&mut self) -> &mut Vec<Mark> {
+        Arc::make_mut(self.marks.get_or_insert_with(Default::default))
+    }
+
+    fn from_vec(marks: Vec<Mark>) -> MarkSet {
+        if marks.is_empty() {
+            MarkSet::new()
+        } else {
+            MarkSet {
+                marks: Some(Arc::new(marks)),
+            }
+        }
+    }
+}
+
+/// Maps `char_idx` through `edits`, where `deltas` is the total change in
+/// length from the edits before each one.
+fn map_through_edits(
+    char_idx: usize,
+    gravity: Assoc,
+    edits: &[(usize, usize, usize)],
+    deltas: &[isize],
+) -> usize {
+    // Find the last edit that starts before the mark.
+    let passed = |start: usize| char_idx > start || (char_idx == start && gravity == Assoc::After);
+    let i = match edits.binary_search_by(|&(start, _, _)| {
+        if passed(start) {
+            std::cmp::Ordering::Less
+        } else {
+            std::cmp::Ordering::Greater
+        }
+    }) {
+        Ok(i) | Err(i) => i,
+    };
+    if i == 0 {
+        return char_idx;
+    }
+
+    let (start, end, inserted) = edits[i - 1];
+    let delta = deltas[i - 1];
+    if char_idx < end {
+        (start as isize + delta) as usize + inserted
+    } else {
+        (char_idx as isize + delta + inserted as isize - (end - start) as isize) as usize
+    }
+}
+
+//==========================================================
+
+/// An iterator over a `Rope`'s marks.
+///
+/// Yields the id and char index of each mark, in the order the marks were
+/// added.
+pub struct Marks<'a> {
+    iter: slice::Iter<'a, Mark>,
+}
+
+impl<'a> Marks<'a> {
+    pub(crate) fn new(marks: &'a MarkSet) -> Marks<'a> {
+        Marks { iter: marks.iter() }
+    }
+}
+
+impl<'a> Iterator for Marks<'a> {
+    type Item = (MarkId, usize);
+
+    #[inline]
+    fn next(&mut self) -> Option<(MarkId, usize)> {
+        self.iter.next().map(|mark| (mark.id, mark.char_idx))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for Marks<'a> {}
+
+//=============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marks_at(positions: &[(usize, Assoc)]) -> MarkSet {
+        let mut marks = MarkSet::new();
+        for &(char_idx, gravity) in positions {
+            marks.add(char_idx, gravity);
+        }
+        marks
+    }
+
+    fn positions(marks: &MarkSet) -> Vec<usize> {
+        marks.iter().map(|mark| mark.char_idx).collect()
+    }
+
+    #[test]
+    fn map_edits_01() {
+        // Insertion.
+        let mut marks = marks_at(&[
+            (2, Assoc::Before),
+            (3, Assoc::Before),
+            (3, Assoc::After),
+            (4, Assoc::Before),
+        ]);
+        marks.map_edits(&[(3, 3, 5)]);
+        assert_eq!(positions(&marks), vec![2, 3, 8, 9]);
+    }
+
+    #[test]
+    fn map_edits_02() {
+        // Removal.
+        let mut marks = marks_at(&[
+            (2, Assoc::After),
+            (3, Assoc::After),
+            (4, Assoc::Before),
+            (6, Assoc::Before),
+            (7, Assoc::After),
+        ]);
+        marks.map_edits(&[(3, 6, 0)]);
+        assert_eq!(positions(&marks), vec![2, 3, 3, 3, 4]);
+    }
+
+    #[test]
+    fn map_edits_03() {
+        // Replacement, plus an insertion right after it.
+        let mut marks = marks_at(&[
+            (3, Assoc::Before),
+            (3, Assoc::After),
+            (4, Assoc::Before),
+            (6, Assoc::Before),
+            (6, Assoc::After),
+            (10, Assoc::Before),
+        ]);
+        marks.map_edits(&[(3, 6, 2), (6, 6, 4)]);
+        assert_eq!(positions(&marks), vec![3, 5, 5, 5, 9, 13]);
+    }
+
+    #[test]
+    fn map_edits_04() {
+        // Many replacements, each mark landing between or inside them.
+        let mut marks = marks_at(&[
+            (0, Assoc::Before),
+            (5, Assoc::After),
+            (12, Assoc::Before),
+            (21, Assoc::After),
+            (40, Assoc::Before),
+        ]);
+        let edits: Vec<_> = (0..10).map(|i| (i * 4 + 1, i * 4 + 3, 3)).collect();
+        marks.map_edits(&edits);
+        assert_eq!(positions(&marks), vec![0, 9, 15, 29, 50]);
+    }
+
+    #[test]
+    fn clone_01() {
+        // Clones share their marks until one of them changes.
+        let mut marks = marks_at(&[(1, Assoc::Before), (4, Assoc::After)]);
+        let copy = marks.clone();
+        marks.map_edits(&[(0, 0, 2)]);
+        assert_eq!(positions(&marks), vec![3, 6]);
+        assert_eq!(positions(&copy), vec![1, 4]);
+    }
+
+    #[test]
+    fn split_off_01() {
+        let mut marks = marks_at(&[
+            (1, Assoc::After),
+            (5, Assoc::Before),
+            (5, Assoc::After),
+            (7, Assoc::Before),
+        ]);
+        let right = marks.split_off(5);
+        assert_eq!(positions(&marks), vec![1, 5]);
+        assert_eq!(positions(&right), vec![0, 2]);
+    }
+
+    #[test]
+    fn append_01() {
+        let mut left = marks_at(&[(1, Assoc::Before)]);
+        let right = marks_at(&[(0, Assoc::Before), (2, Assoc::After)]);
+        left.append(&right, 4);
+        assert_eq!(positions(&left), vec![1, 4, 6]);
+
+        // Appending marks that are already there keeps the existing ones.
+        let copy = left.clone();
+        left.append(&copy, 10);
+        assert_eq!(positions(&left), vec![1, 4, 6]);
+    }
+
+    #[test]
+    fn remove_01() {
+        let mut marks = marks_at(&[(1, Assoc::Before), (2, Assoc::Before)]);
+        let id = marks.iter().next().unwrap().id;
+        assert_eq!(marks.remove(id).unwrap().char_idx, 1);
+        assert!(marks.remove(id).is_none());
+        assert!(marks.get(id).is_none());
+        assert_eq!(positions(&marks), vec![2]);
+    }
+}
@@ -0,0 +1,907 @@
+//! Regular expression search in a `Rope`/`RopeSlice`.
+//!
+//! Patterns are compiled into a [`Regex`](struct.Regex.html), which is
+//! then used with the regex methods of
+//! [`RopeSlice`](../struct.RopeSlice.html) (e.g.
+//! [`find_regex()`](../struct.RopeSlice.html#method.find_regex) and
+//! [`captures_regex()`](../struct.RopeSlice.html#method.captures_regex)),
+//! or with [`Rope::replace_all_regex()`](../struct.Rope.html#method.replace_all_regex).
+//!
+//! The pattern syntax is the same as that of the
+//! [`regex`](https://docs.rs/regex) crate.  Compiled patterns are run as a
+//! Thompson NFA, simulated with a Pike VM that steps through the chars of
+//! one chunk after another.  So the text is never copied, matches that
+//! straddle chunk boundaries need no special handling, and a search takes
+//! O(N * M) time at worst, where M is the size of the compiled pattern.
+
+use std;
+use std::fmt;
+use std::mem;
+use std::ops::Range;
+use std::sync::Arc;
+
+use regex_syntax;
+use regex_syntax::hir::{Class, Hir, HirKind, Look, Repetition};
+
+use slice::RopeSlice;
+
+/// The most instructions a compiled pattern may have.  Counted repetitions
+/// are compiled by repeating their sub-pattern, so e.g. `(a{1000}){1000}`
+/// compiles to a million instructions.
+const MAX_INSTS: usize = 1 << 20;
+
+/// A compiled regular expression.
+///
+/// # Example
+///
+/// ```
+/// # use ropey::Rope;
+/// # use ropey::regex::Regex;
+/// let re = Regex::new(r"(\w+)@(\w+)\.com").unwrap();
+/// let rope = Rope::from_str("Mail zoë@example.com or bob@example.com.");
+/// let caps = rope.slice(..).captures_regex(&re).unwrap();
+///
+/// assert_eq!(caps.get(0), Some(5..20));
+/// assert_eq!(caps.get(1), Some(5..8));
+/// assert_eq!(caps.get(2), Some(9..16));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Regex {
+    pattern: String,
+    forward: Program,
+    // Matches the reverse of what the pattern matches, for searching
+    // backwards.
+    reverse: Program,
+    // The name of each capture group, by index.
+    names: Arc<Vec<Option<String>>>,
+}
+
+impl Regex {
+    /// Compiles a regular expression.
+    ///
+    /// Returns an error if the pattern is invalid, or if it compiles to an
+    /// unreasonably large program.
+    pub fn new(pattern: &str) -> Result<Regex, RegexError> {
+        let hir = regex_syntax::Parser::new()
+            .parse(pattern)
+            .map_err(|err| RegexError(err.to_string()))?;
+
+        let mut names = vec![None; hir.properties().explicit_captures_len() + 1];
+        collect_names(&hir, &mut names);
+
+        Ok(Regex {
+            pattern: pattern.into(),
+            forward: Program::compile(&hir, false, names.len())?,
+            reverse: Program::compile(&hir, true, 0)?,
+            names: Arc::new(names),
+        })
+    }
+
+    /// Returns the pattern the `Regex` was compiled from.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns the number of capture groups, including the implicit group
+    /// 0 for the whole match.
+    #[inline]
+    pub fn captures_len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Finds the first match that starts at or after `char_idx` (or exactly
+    /// at it, if `anchored`), and returns its capture slots.
+    fn search_at(
+        &self,
+        cache: &mut Cache,
+        slice: &RopeSlice,
+        char_idx: usize,
+        anchored: bool,
+    ) -> Option<Vec<Option<usize>>> {
+        let prev = if char_idx > 0 {
+            Some(slice.char(char_idx - 1))
+        } else {
+            None
+        };
+        let (chunks, _, chunk_char, _) = slice.chunks_at_char(char_idx);
+        let chars = chunks.flat_map(str::chars).skip(char_idx - chunk_char);
+
+        self.forward
+            .run(cache, chars, char_idx, prev, anchored, false)
+            .map(|(_, slots)| slots)
+    }
+
+    /// Finds the match that starts last, and returns its capture slots.
+    pub(crate) fn rsearch(&self, slice: &RopeSlice) -> Option<Vec<Option<usize>>> {
+        // Running the reverse program backwards from the end finds where
+        // the last match starts, and the forward program then finds the
+        // match the pattern prefers among those that start there.
+        let chars = slice.chunks().rev().flat_map(|chunk| chunk.chars().rev());
+        let mut cache = Cache::new(&self.reverse);
+        let (start, _) = self
+            .reverse
+            .run(&mut cache, chars, slice.len_chars(), None, false, true)?;
+
+        self.search_at(&mut Cache::new(&self.forward), slice, start, true)
+    }
+}
+
+fn collect_names(hir: &Hir, names: &mut Vec<Option<String>>) {
+    if let HirKind::Capture(ref capture) = *hir.kind() {
+        names[capture.index as usize] = capture.name.as_ref().map(|name| name.to_string());
+    }
+    for sub in hir.kind().subs() {
+        collect_names(sub, names);
+    }
+}
+
+/// The error returned when a [`Regex`](struct.Regex.html) fails to
+/// compile.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RegexError(String);
+
+impl std::error::Error for RegexError {}
+
+impl fmt::Debug for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid regex: {}", self.0)
+    }
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Just re-use the debug impl.
+        write!(f, "{:?}", self)
+    }
+}
+
+//=============================================================
+
+/// The capture groups of a match.
+///
+/// Groups are given as char ranges in the searched `RopeSlice`.  Group 0 is
+/// the whole match, and the other groups are numbered by the order of their
+/// opening parentheses in the pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captures {
+    slots: Vec<Option<usize>>,
+    names: Arc<Vec<Option<String>>>,
+}
+
+impl Captures {
+    /// Returns the char range of capture group `i`, or `None` if the group
+    /// didn't take part in the match (or doesn't exist).
+    #[inline]
+    pub fn get(&self, i: usize) -> Option<Range<usize>> {
+        match (self.slots.get(i * 2), self.slots.get(i * 2 + 1)) {
+            (Some(&Some(start)), Some(&Some(end))) => Some(start..end),
+            _ => None,
+        }
+    }
+
+    /// Returns the char range of the capture group named `name`, or `None`
+    /// if the group didn't take part in the match (or doesn't exist).
+    pub fn name(&self, name: &str) -> Option<Range<usize>> {
+        let i = self.names.iter().position(|n| match *n {
+            Some(ref n) => n == name,
+            None => false,
+        })?;
+        self.get(i)
+    }
+
+    /// Appends `template` to `dst`, with references to capture groups
+    /// replaced by the text they captured from `slice`.
+    ///
+    /// `slice` should be the `RopeSlice` the captures were found in.  The
+    /// template syntax is the same as that of the `regex` crate: `$1` or
+    /// `${1}` refers to group 1, `$name` or `${name}` to the group named
+    /// `name`, and `$$` is a literal `$`.  A name extends as far as the
+    /// following chars are letters, digits or `_`, so `$1a` refers to a
+    /// group named `1a`; use `${1}a` instead.  References to groups that
+    /// don't exist or didn't take part in the match are replaced with
+    /// nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// # use ropey::regex::Regex;
+    /// let re = Regex::new(r"(?P<key>\w+)=(\w+)").unwrap();
+    /// let rope = Rope::from_str("size=12");
+    /// let slice = rope.slice(..);
+    /// let caps = slice.captures_regex(&re).unwrap();
+    ///
+    /// let mut text = String::new();
+    /// caps.expand(&slice, "$2 ${key}s ($$)", &mut text);
+    /// assert_eq!(text, "12 sizes ($)");
+    /// ```
+    pub fn expand(&self, slice: &RopeSlice, template: &str, dst: &mut String) {
+        let mut rest = template;
+        while let Some(i) = rest.find('$') {
+            dst.push_str(&rest[..i]);
+            rest = &rest[(i + 1)..];
+
+            if rest.starts_with('$') {
+                dst.push('$');
+                rest = &rest[1..];
+                continue;
+            }
+
+            let (name, after) = if rest.starts_with('{') {
+                match rest.find('}') {
+                    Some(end) => (&rest[1..end], &rest[(end + 1)..]),
+                    None => ("", rest),
+                }
+            } else {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            };
+
+            // Not a group reference, so the `$` is kept as is.
+            if name.is_empty() {
+                dst.push('$');
+                continue;
+            }
+
+            let group = match name.parse::<usize>() {
+                Ok(i) => self.get(i),
+                Err(_) => self.name(name),
+            };
+            if let Some(char_range) = group {
+                for chunk in slice.slice(char_range).chunks() {
+                    dst.push_str(chunk);
+                }
+            }
+            rest = after;
+        }
+        dst.push_str(rest);
+    }
+}
+
+/// An iterator over the non-overlapping matches of a
+/// [`Regex`](struct.Regex.html) in a `RopeSlice`.
+///
+/// Yields the char range of each match, in order.
+///
+/// Created by [`RopeSlice::matches_regex()`](../struct.RopeSlice.html#method.matches_regex).
+pub struct RegexMatches<'a, 'b>(Searches<'a, 'b>);
+
+impl<'a, 'b> RegexMatches<'a, 'b> {
+    pub(crate) fn new(slice: &RopeSlice<'a>, regex: &'b Regex) -> RegexMatches<'a, 'b> {
+        RegexMatches(Searches::new(slice, regex))
+    }
+}
+
+impl<'a, 'b> Iterator for RegexMatches<'a, 'b> {
+    type Item = Range<usize>;
+
+    /// Advances the iterator forward and returns the char range of the
+    /// next match.
+    #[inline]
+    fn next(&mut self) -> Option<Range<usize>> {
+        self.0.next().map(|slots| slots[0].unwrap()..slots[1].unwrap())
+    }
+}
+
+/// An iterator over the capture groups of the non-overlapping matches of a
+/// [`Regex`](struct.Regex.html) in a `RopeSlice`.
+///
+/// Yields the [`Captures`](struct.Captures.html) of each match, in order.
+///
+/// Created by [`RopeSlice::captures_iter_regex()`](../struct.RopeSlice.html#method.captures_iter_regex).
+pub struct CapturesIter<'a, 'b>(Searches<'a, 'b>);
+
+impl<'a, 'b> CapturesIter<'a, 'b> {
+    pub(crate) fn new(slice: &RopeSlice<'a>, regex: &'b Regex) -> CapturesIter<'a, 'b> {
+        CapturesIter(Searches::new(slice, regex))
+    }
+}
+
+impl<'a, 'b> Iterator for CapturesIter<'a, 'b> {
+    type Item = Captures;
+
+    /// Advances the iterator forward and returns the capture groups of the
+    /// next match.
+    #[inline]
+    fn next(&mut self) -> Option<Captures> {
+        let names = &self.0.regex.names;
+        self.0.next().map(|slots| Captures {
+            slots,
+            names: Arc::clone(names),
+        })
+    }
+}
+
+/// Finds successive non-overlapping matches.
+///
+/// As with the `regex` crate, an empty match is never found right where
+/// the previous match ended, so e.g. `a*` finds three matches in "xaay":
+/// at 0..0, 1..3 and 4..4.
+struct Searches<'a, 'b> {
+    regex: &'b Regex,
+    slice: RopeSlice<'a>,
+    cache: Cache,
+    // Where the next search starts, and where the last match ended.
+    next_char: usize,
+    last_end: Option<usize>,
+}
+
+impl<'a, 'b> Searches<'a, 'b> {
+    fn new(slice: &RopeSlice<'a>, regex: &'b Regex) -> Searches<'a, 'b> {
+        Searches {
+            regex,
+            slice: *slice,
+            cache: Cache::new(&regex.forward),
+            next_char: 0,
+            last_end: None,
+        }
+    }
+
+    fn next(&mut self) -> Option<Vec<Option<usize>>> {
+        loop {
+            if self.next_char > self.slice.len_chars() {
+                return None;
+            }
+            let found = self
+                .regex
+                .search_at(&mut self.cache, &self.slice, self.next_char, false);
+            let slots = match found {
+                Some(slots) => slots,
+                None => {
+                    self.next_char = self.slice.len_chars() + 1;
+                    return None;
+                }
+            };
+
+            let (start, end) = (slots[0].unwrap(), slots[1].unwrap());
+            if start == end {
+                self.next_char = end + 1;
+                if self.last_end == Some(end) {
+                    continue;
+                }
+            } else {
+                self.next_char = end;
+            }
+            self.last_end = Some(end);
+            return Some(slots);
+        }
+    }
+}
+
+//=============================================================
+// The compiled program and the Pike VM that runs it.
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    // Sorted, non-overlapping inclusive ranges.
+    Class(Vec<(char, char)>),
+    Look(Look),
+    // Continue at both, preferring the first.
+    Split(usize, usize),
+    Jump(usize),
+    Save(usize),
+    Match,
+}
+
+#[derive(Debug, Clone)]
+struct Program {
+    insts: Vec<Inst>,
+    reverse: bool,
+    slots: usize,
+}
+
+impl Program {
+    /// Compiles `hir`, or its reverse.  Reversed programs don't save any
+    /// capture slots.
+    fn compile(hir: &Hir, reverse: bool, captures_len: usize) -> Result<Program, RegexError> {
+        let mut compiler = Compiler {
+            insts: Vec::new(),
+            reverse,
+        };
+        if !reverse {
+            compiler.push(Inst::Save(0))?;
+        }
+        compiler.compile(hir)?;
+        if !reverse {
+            compiler.push(Inst::Save(1))?;
+        }
+        compiler.push(Inst::Match)?;
+
+        Ok(Program {
+            insts: compiler.insts,
+            reverse,
+            slots: if reverse { 0 } else { captures_len * 2 },
+        })
+    }
+
+    /// Runs the program over `chars`, the first of which is at char index
+    /// `at`, with `prev` the char just before them.  For a reversed program
+    /// the chars go backwards, so `at` is the index just _after_ the first
+    /// of them and `prev` the char at `at`.
+    ///
+    /// Returns the position at which the preferred match was completed and
+    /// its capture slots, or, if `earliest`, those of the first match to be
+    /// completed.
+    fn run<I: Iterator<Item = char>>(
+        &self,
+        cache: &mut Cache,
+        mut chars: I,
+        mut at: usize,
+        prev: Option<char>,
+        anchored: bool,
+        earliest: bool,
+    ) -> Option<(usize, Vec<Option<usize>>)> {
+        cache.clist.clear();
+        cache.nlist.clear();
+
+        let mut matched = None;
+        let mut cur = chars.next();
+        self.add_start_thread(cache, false, Pos {
+            at,
+            prev,
+            cur,
+        });
+
+        loop {
+            // Nothing left that could match, or match any better.
+            if cache.clist.dense.is_empty() && (matched.is_some() || anchored) {
+                break;
+            }
+
+            let next = if cur.is_some() { chars.next() } else { None };
+            let next_pos = Pos {
+                at: if self.reverse {
+                    at.wrapping_sub(1)
+                } else {
+                    at + 1
+                },
+                prev: cur,
+                cur: next,
+            };
+
+            for &pc in &cache.clist.dense {
+                let step = match self.insts[pc] {
+                    Inst::Match => {
+                        matched = Some((at, cache.clist.slots(pc).to_vec()));
+                        if earliest {
+                            return matched;
+                        }
+                        // Lower priority threads can't produce a preferred
+                        // match.
+                        break;
+                    }
+                    Inst::Char(c) => cur == Some(c),
+                    Inst::Class(ref ranges) => match cur {
+                        Some(c) => class_contains(ranges, c),
+                        None => false,
+                    },
+                    _ => false,
+                };
+                if step {
+                    cache.scratch.clear();
+                    cache.scratch.extend_from_slice(cache.clist.slots(pc));
+                    self.add_thread(
+                        &mut cache.nlist,
+                        &mut cache.stack,
+                        &mut cache.scratch,
+                        pc + 1,
+                        next_pos,
+                    );
+                }
+            }
+
+            if cur.is_none() {
+                break;
+            }
+            if !anchored && matched.is_none() {
+                self.add_start_thread(cache, true, next_pos);
+            }
+
+            mem::swap(&mut cache.clist, &mut cache.nlist);
+            cache.nlist.clear();
+            at = next_pos.at;
+            cur = next;
+        }
+
+        matched
+    }
+
+    fn add_start_thread(&self, cache: &mut Cache, to_next: bool, pos: Pos) {
+        cache.scratch.clear();
+        cache.scratch.resize(self.slots, None);
+        let list = if to_next {
+            &mut cache.nlist
+        } else {
+            &mut cache.clist
+        };
+        self.add_thread(list, &mut cache.stack, &mut cache.scratch, 0, pos);
+    }
+
+    /// Adds the thread at `pc` to `list`, following any instructions that
+    /// don't consume a char.
+    fn add_thread(
+        &self,
+        list: &mut Threads,
+        stack: &mut Vec<Frame>,
+        slots: &mut [Option<usize>],
+        pc: usize,
+        pos: Pos,
+    ) {
+        let (before, after) = if self.reverse {
+            (pos.cur, pos.prev)
+        } else {
+            (pos.prev, pos.cur)
+        };
+
+        stack.push(Frame::Explore(pc));
+        while let Some(frame) = stack.pop() {
+            let pc = match frame {
+                Frame::Explore(pc) => pc,
+                Frame::Restore(slot, value) => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+            if !list.insert(pc) {
+                continue;
+            }
+
+            match self.insts[pc] {
+                Inst::Char(_) | Inst::Class(_) | Inst::Match => {
+                    list.slots_mut(pc).copy_from_slice(slots);
+                }
+                Inst::Look(look) => {
+                    if look_matches(look, before, after) {
+                        stack.push(Frame::Explore(pc + 1));
+                    }
+                }
+                Inst::Split(first, second) => {
+                    // Pushed in reverse, so that everything reachable from
+                    // `first` is added (with higher priority) before
+                    // anything from `second`.
+                    stack.push(Frame::Explore(second));
+                    stack.push(Frame::Explore(first));
+                }
+                Inst::Jump(target) => stack.push(Frame::Explore(target)),
+                Inst::Save(slot) => {
+                    stack.push(Frame::Restore(slot, slots[slot]));
+                    slots[slot] = Some(pos.at);
+                    stack.push(Frame::Explore(pc + 1));
+                }
+            }
+        }
+    }
+}
+
+struct Compiler {
+    insts: Vec<Inst>,
+    reverse: bool,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Result<usize, RegexError> {
+        if self.insts.len() >= MAX_INSTS {
+            return Err(RegexError("compiled regex exceeds size limit".into()));
+        }
+        self.insts.push(inst);
+        Ok(self.insts.len() - 1)
+    }
+
+    fn compile(&mut self, hir: &Hir) -> Result<(), RegexError> {
+        match *hir.kind() {
+            HirKind::Empty => {}
+            HirKind::Literal(ref literal) => {
+                // The parser only produces valid utf8 literals.
+                let text = std::str::from_utf8(&literal.0).unwrap();
+                if self.reverse {
+                    for c in text.chars().rev() {
+                        self.push(Inst::Char(c))?;
+                    }
+                } else {
+                    for c in text.chars() {
+                        self.push(Inst::Char(c))?;
+                    }
+                }
+            }
+            HirKind::Class(Class::Unicode(ref class)) => {
+                let ranges = class.ranges().iter().map(|r| (r.start(), r.end()));
+                self.push(Inst::Class(ranges.collect()))?;
+            }
+            HirKind::Class(Class::Bytes(ref class)) => {
+                // The parser only produces byte classes that are all ascii.
+                let ranges = class
+                    .ranges()
+                    .iter()
+                    .map(|r| (r.start() as char, r.end() as char));
+                self.push(Inst::Class(ranges.collect()))?;
+            }
+            HirKind::Look(look) => {
+                self.push(Inst::Look(look))?;
+            }
+            HirKind::Repetition(ref repetition) => self.compile_repetition(repetition)?,
+            HirKind::Capture(ref capture) => {
+                if self.reverse {
+                    self.compile(&capture.sub)?;
+                } else {
+                    let slot = capture.index as usize * 2;
+                    self.push(Inst::Save(slot))?;
+                    self.compile(&capture.sub)?;
+                    self.push(Inst::Save(slot + 1))?;
+                }
+            }
+            HirKind::Concat(ref subs) => {
+                if self.reverse {
+                    for sub in subs.iter().rev() {
+                        self.compile(sub)?;
+                    }
+                } else {
+                    for sub in subs {
+                        self.compile(sub)?;
+                    }
+                }
+            }
+            HirKind::Alternation(ref subs) => {
+                let mut jumps = Vec::new();
+                for (i, sub) in subs.iter().enumerate() {
+                    if i + 1 == subs.len() {
+                        self.compile(sub)?;
+                    } else {
+                        let split = self.push(Inst::Split(0, 0))?;
+                        self.compile(sub)?;
+                        jumps.push(self.push(Inst::Jump(0))?);
+                        self.insts[split] = Inst::Split(split + 1, self.insts.len());
+                    }
+                }
+                let end = self.insts.len();
+                for jump in jumps {
+                    self.insts[jump] = Inst::Jump(end);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_repetition(&mut self, repetition: &Repetition) -> Result<(), RegexError> {
+        for _ in 0..repetition.min {
+            self.compile(&repetition.sub)?;
+        }
+
+        let mut splits = Vec::new();
+        match repetition.max {
+            None => {
+                let split = self.push(Inst::Split(0, 0))?;
+                self.compile(&repetition.sub)?;
+                self.push(Inst::Jump(split))?;
+                splits.push(split);
+            }
+            Some(max) => {
+                for _ in repetition.min..max {
+                    splits.push(self.push(Inst::Split(0, 0))?);
+                    self.compile(&repetition.sub)?;
+                }
+            }
+        }
+
+        let exit = self.insts.len();
+        for split in splits {
+            self.insts[split] = if repetition.greedy {
+                Inst::Split(split + 1, exit)
+            } else {
+                Inst::Split(exit, split + 1)
+            };
+        }
+        Ok(())
+    }
+}
+
+/// The threads of the Pike VM at one position: a sparse set of
+/// instruction pointers in priority order, along with the capture slots of
+/// the thread at each.
+struct Threads {
+    dense: Vec<usize>,
+    sparse: Vec<usize>,
+    slots: Vec<Option<usize>>,
+    slots_per_thread: usize,
+}
+
+impl Threads {
+    fn new(program: &Program) -> Threads {
+        let len = program.insts.len();
+        Threads {
+            dense: Vec::with_capacity(len),
+            sparse: vec![0; len],
+            slots: vec![None; len * program.slots],
+            slots_per_thread: program.slots,
+        }
+    }
+
+    /// Returns whether `pc` was newly inserted.
+    #[inline]
+    fn insert(&mut self, pc: usize) -> bool {
+        let i = self.sparse[pc];
+        if i < self.dense.len() && self.dense[i] == pc {
+            return false;
+        }
+        self.sparse[pc] = self.dense.len();
+        self.dense.push(pc);
+        true
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.dense.clear();
+    }
+
+    #[inline]
+    fn slots(&self, pc: usize) -> &[Option<usize>] {
+        let start = pc * self.slots_per_thread;
+        &self.slots[start..(start + self.slots_per_thread)]
+    }
+
+    #[inline]
+    fn slots_mut(&mut self, pc: usize) -> &mut [Option<usize>] {
+        let start = pc * self.slots_per_thread;
+        &mut self.slots[start..(start + self.slots_per_thread)]
+    }
+}
+
+/// A position in the text, along with the chars just before and at it, in
+/// the order the program runs over them.
+#[derive(Copy, Clone)]
+struct Pos {
+    at: usize,
+    prev: Option<char>,
+    cur: Option<char>,
+}
+
+enum Frame {
+    Explore(usize),
+    Restore(usize, Option<usize>),
+}
+
+/// Scratch space for running a `Program`, reused between searches.
+struct Cache {
+    clist: Threads,
+    nlist: Threads,
+    stack: Vec<Frame>,
+    scratch: Vec<Option<usize>>,
+}
+
+impl Cache {
+    fn new(program: &Program) -> Cache {
+        Cache {
+            clist: Threads::new(program),
+            nlist: Threads::new(program),
+            stack: Vec::new(),
+            scratch: Vec::with_capacity(program.slots),
+        }
+    }
+}
+
+#[inline]
+fn class_contains(ranges: &[(char, char)], c: char) -> bool {
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if start > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Returns whether `look` holds between the chars `before` and `after`,
+/// where `None` is the start or end of the text.
+fn look_matches(look: Look, before: Option<char>, after: Option<char>) -> bool {
+    let ascii = |c: Option<char>| match c {
+        Some(c) => c.is_ascii_alphanumeric() || c == '_',
+        None => false,
+    };
+    let unicode = |c: Option<char>| match c {
+        Some(c) => regex_syntax::is_word_character(c),
+        None => false,
+    };
+
+    match look {
+        Look::Start => before.is_none(),
+        Look::End => after.is_none(),
+        Look::StartLF => before.is_none() || before == Some('\n'),
+        Look::EndLF => after.is_none() || after == Some('\n'),
+        Look::StartCRLF => match before {
+            None | Some('\n') => true,
+            Some('\r') => after != Some('\n'),
+            _ => false,
+        },
+        Look::EndCRLF => match after {
+            None | Some('\r') => true,
+            Some('\n') => before != Some('\r'),
+            _ => false,
+        },
+        Look::WordAscii => ascii(before) != ascii(after),
+        Look::WordAsciiNegate => ascii(before) == ascii(after),
+        Look::WordUnicode => unicode(before) != unicode(after),
+        Look::WordUnicodeNegate => unicode(before) == unicode(after),
+        Look::WordStartAscii => !ascii(before) && ascii(after),
+        Look::WordEndAscii => ascii(before) && !ascii(after),
+        Look::WordStartUnicode => !unicode(before) && unicode(after),
+        Look::WordEndUnicode => unicode(before) && !unicode(after),
+        Look::WordStartHalfAscii => !ascii(before),
+        Look::WordEndHalfAscii => !ascii(after),
+        Look::WordStartHalfUnicode => !unicode(before),
+        Look::WordEndHalfUnicode => !unicode(after),
+    }
+}
+
+//=============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> Vec<Range<usize>> {
+        let regex = Regex::new(pattern).unwrap();
+        RegexMatches::new(&RopeSlice::from_str(text), &regex).collect()
+    }
+
+    #[test]
+    fn matches_01() {
+        assert_eq!(matches("a+", "baaac aa"), vec![1..4, 6..8]);
+        assert_eq!(matches("a+?", "baa"), vec![1..2, 2..3]);
+        assert_eq!(matches("a{2,3}", "aaaaaaa"), vec![0..3, 3..6]);
+        assert_eq!(matches("a{2,3}?", "aaaaa"), vec![0..2, 2..4]);
+        assert_eq!(matches("ab|a", "aab"), vec![0..1, 1..3]);
+        assert_eq!(matches("a|ab", "aab"), vec![0..1, 1..2]);
+        assert_eq!(matches("[^a-c]", "abcdé"), vec![3..4, 4..5]);
+        assert_eq!(matches("(?i)straSSe", "STRASSE"), vec![0..7]);
+        assert!(matches("x", "").is_empty());
+    }
+
+    #[test]
+    fn matches_02() {
+        // Empty matches.
+        assert_eq!(matches("a*", "xaay"), vec![0..0, 1..3, 4..4]);
+        assert_eq!(matches("", "añ"), vec![0..0, 1..1, 2..2]);
+        assert_eq!(matches("", ""), vec![0..0]);
+        assert_eq!(matches("(a*)*", "b"), vec![0..0, 1..1]);
+    }
+
+    #[test]
+    fn matches_03() {
+        // Look-around assertions.
+        assert_eq!(matches(r"^\w+", "ab cd\nef"), vec![0..2]);
+        assert_eq!(matches(r"(?m)^\w+", "ab cd\nef"), vec![0..2, 6..8]);
+        assert_eq!(matches(r"(?m)\w+$", "ab cd\nef"), vec![3..5, 6..8]);
+        assert_eq!(matches(r"(?Rm)^$", "a\r\n\r\nb"), vec![3..3]);
+        assert_eq!(matches(r"\bé\w*", "café éte"), vec![5..8]);
+        assert_eq!(matches(r"(?-u:\b)\w+", "café éte"), vec![0..4, 6..8]);
+        assert_eq!(matches(r"\Bf\B", "off f"), vec![1..2]);
+        assert_eq!(matches(r"\b{start}\w", "ab cd"), vec![0..1, 3..4]);
+        assert_eq!(matches(r"\w\b{end}", "ab cd"), vec![1..2, 4..5]);
+    }
+
+    #[test]
+    fn expand_01() {
+        let regex = Regex::new(r"(?P<a>\w)(\w)?").unwrap();
+        let slice = RopeSlice::from_str("x");
+        let caps = CapturesIter::new(&slice, &regex).next().unwrap();
+
+        let mut text = String::new();
+        caps.expand(&slice, "[$a|$2|$1x|${1}x|$$|$|${|$-]", &mut text);
+        assert_eq!(text, "[x|||xx|$|$|${|$-]");
+    }
+
+    #[test]
+    fn new_01() {
+        assert!(Regex::new("(").is_err());
+        assert!(Regex::new(r"\p{Foo}").is_err());
+        assert!(Regex::new("(a{1000}){2000}").is_err());
+        assert_eq!(Regex::new(r"(a)(?P<b>b)?").unwrap().captures_len(), 3);
+    }
+}
@@ -1,17 +1,29 @@
 use std;
 use std::io;
+use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
 
+use change_set::Assoc;
 use crlf;
+use edit::{Edit, Replacement};
+use encoding::{Decoder, Encoding, EncodingInfo, InvalidUtf8, ReadOptions, WriteOptions};
+use escaped_bytes::EscapedBytes;
+#[cfg(feature = "graphemes")]
+use iter::Graphemes;
 use iter::{Bytes, Chars, Chunks, Lines};
+use mark::{MarkId, MarkSet, Marks};
+#[cfg(feature = "regex")]
+use regex::Regex;
 use rope_builder::RopeBuilder;
 use slice::{CharIdxRange, RopeSlice};
 use str_utils::{
-    byte_to_char_idx, byte_to_line_idx, char_to_byte_idx, char_to_line_idx, line_to_byte_idx,
-    line_to_char_idx,
+    byte_to_char_idx, byte_to_line_idx_with_policy, char_to_byte_idx, char_to_line_idx_with_policy,
+    convert_line_endings, count_chars, line_to_byte_idx_with_policy,
+    line_to_char_idx_with_policy,
 };
 use tree::{Count, Node, NodeChildren, TextInfo, MAX_BYTES};
+use {Error, LineBreakPolicy, LineEnding, LineEndingStats, Result};
 
 /// A utf8 text rope.
 ///
@@ -65,6 +77,10 @@ use tree::{Count, Node, NodeChildren, TextInfo, MAX_BYTES};
 #[derive(Clone)]
 pub struct Rope {
     pub(crate) root: Arc<Node>,
+    pub(crate) line_break_policy: LineBreakPolicy,
+    pub(crate) insert_line_ending: Option<LineEnding>,
+    pub(crate) marks: MarkSet,
+    pub(crate) escaped_bytes: EscapedBytes,
 }
 
 impl Rope {
@@ -76,6 +92,10 @@ impl Rope {
     pub fn new() -> Self {
         Rope {
             root: Arc::new(Node::new()),
+            line_break_policy: LineBreakPolicy::default(),
+            insert_line_ending: None,
+            marks: MarkSet::new(),
+            escaped_bytes: EscapedBytes::new(),
         }
     }
 
@@ -87,6 +107,25 @@ impl Rope {
         RopeBuilder::new().build_at_once(text)
     }
 
+    /// Creates a `Rope` from a string slice, using multiple threads.
+    ///
+    /// The text is split into pieces without splitting any chars or CRLF
+    /// pairs, the pieces are built on separate threads, and the results
+    /// are joined into a single balanced tree.  The result is the same
+    /// text as with [`from_str()`](#method.from_str), but for very large
+    /// texts it's built several times faster.  Small texts are built on
+    /// the current thread, as spawning threads would cost more than it
+    /// saves.
+    ///
+    /// Only available with the `parallel` cargo feature enabled.
+    ///
+    /// Runs in O(N) time.
+    #[cfg(feature = "parallel")]
+    #[inline]
+    pub fn from_str_parallel(text: &str) -> Self {
+        RopeBuilder::build_at_once_parallel(text)
+    }
+
     /// Creates a `Rope` from the output of a reader.
     ///
     /// This is a convenience function.  To do more sophisticated text loading,
@@ -99,7 +138,9 @@ impl Rope {
     /// - If the reader returns an error, `from_reader` stops and returns
     ///   that error.
     /// - If non-utf8 data is encountered, an IO error with kind
-    ///   `InvalidData` is returned.
+    ///   `InvalidData` is returned.  To load such text anyway, see
+    ///   [`from_reader_lossy()`](#method.from_reader_lossy) and
+    ///   [`from_reader_with()`](#method.from_reader_with).
     ///
     /// Note: some data from the reader is likely consumed even if there is
     /// an error.
@@ -171,6 +212,158 @@ impl Rope {
         }
     }
 
+    /// Creates a `Rope` from the output of a reader, replacing invalid utf8
+    /// with U+FFFD (the replacement character "\u{FFFD}").
+    ///
+    /// Unlike [`from_reader()`](#method.from_reader), this doesn't fail on
+    /// invalid text.  Each invalid sequence is replaced the same way as by
+    /// `String::from_utf8_lossy()`, and everything else is kept as-is.  The
+    /// byte offsets in the reader's output of the replaced sequences are
+    /// returned along with the `Rope`.
+    ///
+    /// Runs in O(N) time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// let bytes: &[u8] = b"Hello\xFF world\xE3\x81";
+    /// let (rope, replaced) = Rope::from_reader_lossy(bytes).unwrap();
+    ///
+    /// assert_eq!(rope, "Hello\u{FFFD} world\u{FFFD}");
+    /// assert_eq!(replaced, vec![5, 12]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If the reader returns an error, `from_reader_lossy` stops and
+    ///   returns that error.
+    ///
+    /// Note: some data from the reader is likely consumed even if there is
+    /// an error.
+    pub fn from_reader_lossy<T: io::Read>(reader: T) -> io::Result<(Self, Vec<usize>)> {
+        let mut builder = RopeBuilder::new();
+        let mut replaced = Vec::new();
+        read_pieces(reader, |bytes, _| {
+            replaced.extend(builder.append_bytes_lossy(bytes));
+            Ok(())
+        })?;
+
+        let (rope, rest) = builder.finish_lossy();
+        replaced.extend(rest);
+        Ok((rope, replaced))
+    }
+
+    /// Creates a `Rope` from the output of a reader, decoding the text from
+    /// `encoding`.
+    ///
+    /// The text is decoded as it is read, so the undecoded text is never
+    /// held in memory all at once.  A byte order mark at the start of the
+    /// text is removed, and for UTF-16 it also determines the byte order,
+    /// overriding the one given by `encoding`.  The returned
+    /// [`EncodingInfo`](struct.EncodingInfo.html) gives the encoding that
+    /// was actually used, and whether there was a byte order mark.
+    ///
+    /// Runs in O(N) time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::{Encoding, Rope};
+    /// let bytes: &[u8] = b"\xFE\xFF\x00h\x00i\x20\xAC";
+    /// let (rope, info) = Rope::from_reader_with_encoding(bytes, Encoding::Utf16Le).unwrap();
+    ///
+    /// assert_eq!(rope, "hi€");
+    /// assert_eq!(info.encoding, Encoding::Utf16Be);
+    /// assert!(info.bom);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If the reader returns an error, `from_reader_with_encoding` stops
+    ///   and returns that error.
+    /// - If data that isn't valid in the encoding is encountered, an IO
+    ///   error with kind `InvalidData` is returned.  Only UTF-8 and UTF-16
+    ///   text can be invalid.
+    ///
+    /// Note: some data from the reader is likely consumed even if there is
+    /// an error.
+    pub fn from_reader_with_encoding<T: io::Read>(
+        reader: T,
+        encoding: Encoding,
+    ) -> io::Result<(Self, EncodingInfo)> {
+        let options = ReadOptions {
+            encoding,
+            ..ReadOptions::default()
+        };
+        Rope::from_reader_with(reader, options)
+    }
+
+    /// Creates a `Rope` from the output of a reader, decoding the text as
+    /// given by `options`.
+    ///
+    /// This is like
+    /// [`from_reader_with_encoding()`](#method.from_reader_with_encoding),
+    /// but can also load UTF-8 text that isn't valid, by escaping the
+    /// invalid bytes (see [`InvalidUtf8`](enum.InvalidUtf8.html)).  Writing
+    /// the `Rope` with
+    /// [`WriteOptions::escaped_bytes`](struct.WriteOptions.html) set writes
+    /// the original bytes back out, so as long as the escaped bytes aren't
+    /// edited, the text round-trips byte for byte.
+    ///
+    /// Runs in O(N) time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::{InvalidUtf8, ReadOptions, Rope, WriteOptions};
+    /// let bytes: &[u8] = b"key = \xFF\xFE\n";
+    /// let options = ReadOptions {
+    ///     invalid_utf8: InvalidUtf8::Escape,
+    ///     ..ReadOptions::default()
+    /// };
+    /// let (mut rope, info) = Rope::from_reader_with(bytes, options).unwrap();
+    /// assert_eq!(rope, "key = \u{10FFFF}\u{10FFFE}\n");
+    ///
+    /// rope.insert(0, "# Config\n");
+    /// let mut out = Vec::new();
+    /// rope.write_to_with(&mut out, WriteOptions::from(info)).unwrap();
+    /// assert_eq!(out, b"# Config\nkey = \xFF\xFE\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If the reader returns an error, `from_reader_with` stops and
+    ///   returns that error.
+    /// - If data that isn't valid in the encoding is encountered, and isn't
+    ///   escaped, an IO error with kind `InvalidData` is returned.  Only
+    ///   UTF-8 and UTF-16 text can be invalid.
+    ///
+    /// Note: some data from the reader is likely consumed even if there is
+    /// an error.
+    pub fn from_reader_with<T: io::Read>(
+        reader: T,
+        options: ReadOptions,
+    ) -> io::Result<(Self, EncodingInfo)> {
+        let mut builder = RopeBuilder::new();
+        let mut decoder = Decoder::new(options.encoding);
+        if options.encoding == Encoding::Utf8 && options.invalid_utf8 == InvalidUtf8::Escape {
+            read_pieces(reader, |bytes, last| {
+                builder.append_bytes_escaped(&decoder.strip_bom(bytes, last));
+                Ok(())
+            })?;
+        } else {
+            let mut text = String::new();
+            read_pieces(reader, |bytes, last| {
+                text.clear();
+                decoder.decode(bytes, &mut text, last)?;
+                builder.append(&text);
+                Ok(())
+            })?;
+        }
+        Ok((builder.finish(), decoder.info()))
+    }
+
     //-----------------------------------------------------------------------
     // Convenience output methods
 
@@ -190,12 +383,106 @@ impl Rope {
     #[allow(unused_mut)]
     pub fn write_to<T: io::Write>(&self, mut writer: T) -> io::Result<()> {
         for chunk in self.chunks() {
-            writer.write(chunk.as_bytes())?;
+            writer.write_all(chunk.as_bytes())?;
         }
 
         Ok(())
     }
 
+    /// Writes the contents of the `Rope` to a writer, with the encoding,
+    /// byte order mark, line endings and escaped bytes given by `options`.
+    ///
+    /// Line endings are converted as the text is written, without changing
+    /// the `Rope`.
+    ///
+    /// Runs in O(N) time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::{Encoding, LineEnding, Rope, WriteOptions};
+    /// let rope = Rope::from_str("h\u{E9}\nyo\r\n");
+    /// let options = WriteOptions {
+    ///     encoding: Encoding::Utf16Le,
+    ///     bom: true,
+    ///     line_ending: Some(LineEnding::Crlf),
+    ///     ..WriteOptions::default()
+    /// };
+    ///
+    /// let mut out = Vec::new();
+    /// rope.write_to_with(&mut out, options).unwrap();
+    /// assert_eq!(out, b"\xFF\xFEh\x00\xE9\x00\r\x00\n\x00y\x00o\x00\r\x00\n\x00");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If the writer returns an error, `write_to_with` stops and returns
+    ///   that error.
+    /// - If a char can't be represented in the encoding, an IO error with
+    ///   kind `InvalidData` is returned.  It wraps an
+    ///   [`Error::UnencodableChar`](enum.Error.html#variant.UnencodableChar)
+    ///   with the char index of the char.
+    ///
+    /// Note: some data may have been written even if an error is returned.
+    pub fn write_to_with<T: io::Write>(
+        &self,
+        mut writer: T,
+        options: WriteOptions,
+    ) -> io::Result<()> {
+        if options.bom {
+            if let Some(bom) = options.encoding.bom() {
+                writer.write_all(bom)?;
+            }
+        }
+        let write_escaped_bytes = options.escaped_bytes && !self.escaped_bytes.is_empty();
+        if options.encoding == Encoding::Utf8
+            && options.line_ending.is_none()
+            && !write_escaped_bytes
+        {
+            return self.write_to(writer);
+        }
+
+        const BUFFER_SIZE: usize = MAX_BYTES * 2;
+        let mut buffer = Vec::with_capacity(BUFFER_SIZE + 4);
+        let mut escaped_bytes = self.escaped_bytes.entries().iter().peekable();
+        let mut char_idx = 0;
+        let mut after_cr = false;
+        for chunk in self.chunks() {
+            for c in chunk.chars() {
+                match options.line_ending {
+                    Some(_) if c == '\n' && after_cr => {}
+                    Some(ending) if c == '\n' || c == '\r' => {
+                        for c in ending.as_str().chars() {
+                            options.encoding.encode_char(c, &mut buffer);
+                        }
+                    }
+                    _ if write_escaped_bytes
+                        && escaped_bytes.peek().map(|&&(idx, _)| idx) == Some(char_idx) =>
+                    {
+                        buffer.push(escaped_bytes.next().unwrap().1);
+                    }
+                    _ => {
+                        if !options.encoding.encode_char(c, &mut buffer) {
+                            writer.write_all(&buffer)?;
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                Error::UnencodableChar(char_idx, c),
+                            ));
+                        }
+                    }
+                }
+                after_cr = c == '\r';
+                char_idx += 1;
+
+                if buffer.len() >= BUFFER_SIZE {
+                    writer.write_all(&buffer)?;
+                    buffer.clear();
+                }
+            }
+        }
+        writer.write_all(&buffer)
+    }
+
     //-----------------------------------------------------------------------
     // Informational methods
 
@@ -220,7 +507,130 @@ impl Rope {
     /// Runs in O(1) time.
     #[inline]
     pub fn len_lines(&self) -> usize {
-        self.root.line_break_count() + 1
+        self.root.line_break_count(self.line_break_policy) + 1
+    }
+
+    /// The [`LineBreakPolicy`](enum.LineBreakPolicy.html) that the `Rope`'s
+    /// line-related methods and iterators use.
+    ///
+    /// Defaults to `LineBreakPolicy::Unicode`.
+    #[inline]
+    pub fn line_break_policy(&self) -> LineBreakPolicy {
+        self.line_break_policy
+    }
+
+    /// Sets the [`LineBreakPolicy`](enum.LineBreakPolicy.html) used by the
+    /// `Rope`'s line-related methods and iterators.
+    ///
+    /// The `Rope` only keeps line break counts for its current policy, so
+    /// changing the policy counts the line breaks again.  This also copies
+    /// any text that's shared with clones of the `Rope`.
+    ///
+    /// Runs in O(N) time if the policy changes, and O(1) time otherwise.
+    pub fn set_line_break_policy(&mut self, policy: LineBreakPolicy) {
+        if policy != self.line_break_policy {
+            self.line_break_policy = policy;
+            Arc::make_mut(&mut self.root).recount_line_breaks(policy);
+        }
+    }
+
+    /// The line ending that [`insert()`](#method.insert) converts line
+    /// endings in inserted text to, if any.
+    ///
+    /// Defaults to `None`.
+    #[inline]
+    pub fn insert_line_ending(&self) -> Option<LineEnding> {
+        self.insert_line_ending
+    }
+
+    /// Sets the line ending that [`insert()`](#method.insert) converts the
+    /// LF, CR and CRLF line endings in inserted text to, or `None` to insert
+    /// text as-is.
+    ///
+    /// This is useful for keeping pasted text consistent with the rest of
+    /// the document.  Other editing methods, like
+    /// [`insert_char()`](#method.insert_char) and
+    /// [`apply_edits()`](#method.apply_edits), always use the text they're
+    /// given as-is.
+    ///
+    /// Runs in O(1) time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// let mut rope = Rope::from_str("one\r\ntwo\r\nthree\n");
+    /// let dominant = rope.line_ending_stats().dominant();
+    /// rope.set_insert_line_ending(dominant);
+    /// rope.insert(5, "a\nb\n");
+    ///
+    /// assert_eq!(rope, "one\r\na\r\nb\r\ntwo\r\nthree\n");
+    /// ```
+    #[inline]
+    pub fn set_insert_line_ending(&mut self, line_ending: Option<LineEnding>) {
+        self.insert_line_ending = line_ending;
+    }
+
+    /// Total number of utf16 code units that would be in the `Rope` if
+    /// it were encoded as utf16.
+    ///
+    /// Ropey stores text internally as utf8, but sometimes it is necessary
+    /// to interact with external APIs that still use utf16.  This function
+    /// is primarily intended for such situations, and is otherwise not very
+    /// useful.
+    ///
+    /// Runs in O(1) time.
+    #[inline]
+    pub fn len_utf16_cu(&self) -> usize {
+        let info = self.root.text_info(self.line_break_policy);
+        (info.chars + info.utf16_surrogates) as usize
+    }
+
+    /// Counts each kind of line break in the `Rope`.
+    ///
+    /// All of the line breaks listed in the
+    /// [crate docs](index.html#a-note-about-line-endings) are counted,
+    /// regardless of the `Rope`'s line break policy.
+    ///
+    /// Runs in O(N) time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::{LineEnding, Rope};
+    /// let rope = Rope::from_str("a\r\nb\r\nc\nd\u{2028}");
+    /// let stats = rope.line_ending_stats();
+    ///
+    /// assert_eq!((stats.crlf, stats.lf, stats.ls), (2, 1, 1));
+    /// assert_eq!(stats.dominant(), Some(LineEnding::Crlf));
+    /// ```
+    pub fn line_ending_stats(&self) -> LineEndingStats {
+        let mut stats = LineEndingStats::default();
+        let mut after_cr = false;
+        for chunk in self.chunks() {
+            let bytes = chunk.as_bytes();
+            for (i, &byte) in bytes.iter().enumerate() {
+                // The multi-byte breaks are recognized by their last byte.
+                // Lead bytes are never continuation bytes, so the bytes
+                // before it are enough to tell.
+                match byte {
+                    0x0A if after_cr => {
+                        stats.cr -= 1;
+                        stats.crlf += 1;
+                    }
+                    0x0A => stats.lf += 1,
+                    0x0B => stats.vt += 1,
+                    0x0C => stats.ff += 1,
+                    0x0D => stats.cr += 1,
+                    0x85 if i >= 1 && bytes[i - 1] == 0xC2 => stats.nel += 1,
+                    0xA8 if i >= 2 && bytes[(i - 2)..i] == [0xE2, 0x80] => stats.ls += 1,
+                    0xA9 if i >= 2 && bytes[(i - 2)..i] == [0xE2, 0x80] => stats.ps += 1,
+                    _ => {}
+                }
+                after_cr = byte == 0x0D;
+            }
+        }
+        stats
     }
 
     //-----------------------------------------------------------------------
@@ -264,6 +674,10 @@ impl Rope {
         let mut node_stack = Vec::new();
         let mut builder = RopeBuilder::new();
 
+        let policy = self.line_break_policy;
+        let insert_line_ending = self.insert_line_ending;
+        let marks = std::mem::replace(&mut self.marks, MarkSet::new());
+        let escaped_bytes = std::mem::replace(&mut self.escaped_bytes, EscapedBytes::new());
         node_stack.push(self.root.clone());
         *self = Rope::new();
 
@@ -286,6 +700,10 @@ impl Rope {
         }
 
         *self = builder.finish();
+        self.set_line_break_policy(policy);
+        self.insert_line_ending = insert_line_ending;
+        self.marks = marks;
+        self.escaped_bytes = escaped_bytes;
     }
 
     //-----------------------------------------------------------------------
@@ -293,6 +711,14 @@ impl Rope {
 
     /// Inserts `text` at char index `char_idx`.
     ///
+    /// If an [insert line ending](#method.set_insert_line_ending) is set,
+    /// the line endings in `text` are converted to it first.  Like in
+    /// [`normalize_line_endings_in()`](#method.normalize_line_endings_in),
+    /// a line ending at either end of `text` is left as it is if converting
+    /// it would join it with a line ending next to `char_idx` into a CRLF
+    /// pair.  If it would join even as it is, e.g. an LF inserted right
+    /// after a CR, it's inserted as a CRLF instead.
+    ///
     /// Runs in O(M + log N) time, where N is the length of the `Rope` and M
     /// is the length of `text`.
     ///
@@ -300,6 +726,46 @@ impl Rope {
     ///
     /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
     pub fn insert(&mut self, char_idx: usize, text: &str) {
+        match self.insert_line_ending {
+            Some(line_ending) if text.contains(['\n', '\r']) && char_idx <= self.len_chars() => {
+                let text = self.convert_for_insert(char_idx, text, line_ending);
+                self.insert_as_is(char_idx, &text);
+            }
+            _ => self.insert_as_is(char_idx, text),
+        }
+    }
+
+    /// Converts the line endings in `text` to `line_ending` for inserting
+    /// at `char_idx`, without joining either end of it with a line ending
+    /// next to `char_idx`.
+    fn convert_for_insert(&self, char_idx: usize, text: &str, line_ending: LineEnding) -> String {
+        let mut converted = convert_line_endings(text, line_ending);
+
+        // Only a converted LF can join with a CR before it, and only a
+        // converted CR with an LF after it.
+        if char_idx > 0 && converted.starts_with('\n') && self.char(char_idx - 1) == '\r' {
+            let head = if text.starts_with('\r') && !text.starts_with("\r\n") {
+                "\r"
+            } else {
+                "\r\n"
+            };
+            converted.replace_range(..1, head);
+        }
+        if char_idx < self.len_chars() && converted.ends_with('\r') && self.char(char_idx) == '\n' {
+            let tail = if text.ends_with('\n') && !text.ends_with("\r\n") {
+                "\n"
+            } else {
+                "\r\n"
+            };
+            let tail_start = converted.len() - 1;
+            converted.replace_range(tail_start.., tail);
+        }
+        converted
+    }
+
+    /// Inserts `text` at char index `char_idx`, without converting its line
+    /// endings.
+    fn insert_as_is(&mut self, char_idx: usize, text: &str) {
         // TODO: handle large insertions more efficiently, instead of doing a split
         // and appends.
 
@@ -311,13 +777,21 @@ impl Rope {
             self.len_chars()
         );
 
+        self.map_edits(&[(char_idx, char_idx, count_chars(text))]);
+        let policy = self.line_break_policy;
+
         if text.len() > MAX_BYTES * 6 {
             // For huge insertion texts, build a tree out of it and then
-            // split and join.
+            // split and join.  The marks and escaped bytes are set aside so
+            // that the split and join don't move them a second time.
+            let marks = std::mem::replace(&mut self.marks, MarkSet::new());
+            let escaped_bytes = std::mem::replace(&mut self.escaped_bytes, EscapedBytes::new());
             let text_rope = Rope::from_str(text);
             let right = self.split_off(char_idx);
             self.append(text_rope);
             self.append(right);
+            self.marks = marks;
+            self.escaped_bytes = escaped_bytes;
         } else {
             // Otherwise, for small-to-medium sized inserts, iteratively insert in
             // chunks.
@@ -333,10 +807,11 @@ impl Rope {
                 let mut left_seam = false;
 
                 // Do the insertion
-                let root_info = self.root.text_info();
+                let root_info = self.root.text_info(policy);
                 let (l_info, residual) = Arc::make_mut(&mut self.root).edit_chunk_at_char(
                     char_idx,
                     root_info,
+                    policy,
                     |idx, cur_info, leaf_text| {
                         // First check if we have a left seam.
                         if idx == 0 && char_idx > 0 && ins_text.as_bytes()[0] == 0x0A {
@@ -356,27 +831,27 @@ impl Rope {
                             // Calculate new info without doing a full re-scan of cur_text
                             let new_info = {
                                 // Get summed info of current text and to-be-inserted text
-                                let mut info = cur_info + TextInfo::from_str(ins_text);
+                                let mut info = cur_info + TextInfo::from_str(ins_text, policy);
                                 // Check for CRLF pairs on the insertion seams, and
                                 // adjust line break counts accordingly
                                 if byte_idx > 0 {
                                     if leaf_text.as_bytes()[byte_idx - 1] == 0x0D
                                         && ins_text.as_bytes()[0] == 0x0A
                                     {
-                                        info.line_breaks -= 1;
+                                        info.join_crlf(policy);
                                     }
                                     if byte_idx < leaf_text.len()
                                         && leaf_text.as_bytes()[byte_idx - 1] == 0x0D
                                         && leaf_text.as_bytes()[byte_idx] == 0x0A
                                     {
-                                        info.line_breaks += 1;
+                                        info.split_crlf(policy);
                                     }
                                 }
                                 if byte_idx < leaf_text.len()
                                     && *ins_text.as_bytes().last().unwrap() == 0x0D
                                     && leaf_text.as_bytes()[byte_idx] == 0x0A
                                 {
-                                    info.line_breaks -= 1;
+                                    info.join_crlf(policy);
                                 }
                                 info
                             };
@@ -387,9 +862,9 @@ impl Rope {
                         // We're splitting the node
                         else {
                             let r_text = leaf_text.insert_str_split(byte_idx, ins_text);
-                            let l_text_info = TextInfo::from_str(&leaf_text);
+                            let l_text_info = TextInfo::from_str(&leaf_text, policy);
                             if r_text.len() > 0 {
-                                let r_text_info = TextInfo::from_str(&r_text);
+                                let r_text_info = TextInfo::from_str(&r_text, policy);
                                 (
                                     l_text_info,
                                     Some((r_text_info, Arc::new(Node::Leaf(r_text)))),
@@ -419,10 +894,11 @@ impl Rope {
                 // better?
                 if left_seam {
                     // Do the insertion
-                    let root_info = self.root.text_info();
+                    let root_info = self.root.text_info(policy);
                     let (l_info, residual) = Arc::make_mut(&mut self.root).edit_chunk_at_char(
                         char_idx - 1,
                         root_info,
+                        policy,
                         |_, cur_info, leaf_text| {
                             let byte_idx = leaf_text.len();
 
@@ -432,8 +908,9 @@ impl Rope {
                                 let mut new_info = cur_info;
                                 new_info.bytes += 1;
                                 new_info.chars += 1;
-                                if *leaf_text.as_bytes().last().unwrap() != 0x0D {
-                                    new_info.line_breaks += 1;
+                                new_info.line_breaks += 1;
+                                if *leaf_text.as_bytes().last().unwrap() == 0x0D {
+                                    new_info.join_crlf(policy);
                                 }
                                 // Insert the text and return the new info
                                 leaf_text.insert_str(byte_idx, "\n");
@@ -442,9 +919,9 @@ impl Rope {
                             // We're splitting the node
                             else {
                                 let r_text = leaf_text.insert_str_split(byte_idx, "\n");
-                                let l_text_info = TextInfo::from_str(&leaf_text);
+                                let l_text_info = TextInfo::from_str(&leaf_text, policy);
                                 if r_text.len() > 0 {
-                                    let r_text_info = TextInfo::from_str(&r_text);
+                                    let r_text_info = TextInfo::from_str(&r_text, policy);
                                     (
                                         l_text_info,
                                         Some((r_text_info, Arc::new(Node::Leaf(r_text)))),
@@ -475,6 +952,10 @@ impl Rope {
 
     /// Inserts a single char `ch` at char index `char_idx`.
     ///
+    /// Unlike [`insert()`](#method.insert), `ch` is always inserted as it
+    /// is, even if an [insert line ending](#method.set_insert_line_ending)
+    /// is set, so that typing a CR and then an LF gives a CRLF pair.
+    ///
     /// Runs in O(log N) time, where N is the length of the `Rope`.
     ///
     /// # Panics
@@ -483,7 +964,7 @@ impl Rope {
     #[inline]
     pub fn insert_char(&mut self, char_idx: usize, ch: char) {
         let mut buf = [0u8; 4];
-        self.insert(char_idx, ch.encode_utf8(&mut buf));
+        self.insert_as_is(char_idx, ch.encode_utf8(&mut buf));
     }
 
     /// Removes the text in the given char index range.
@@ -521,6 +1002,8 @@ impl Rope {
             self.len_chars()
         );
 
+        self.map_edits(&[(start, end, 0)]);
+
         // A special case that the rest of the logic doesn't handle
         // correctly.
         if start == 0 && end == self.len_chars() {
@@ -530,22 +1013,23 @@ impl Rope {
 
         // Scope to contain borrow of root
         {
+            let policy = self.line_break_policy;
             let root = Arc::make_mut(&mut self.root);
 
-            let root_info = root.text_info();
-            let (_, crlf_seam, needs_fix) = root.remove_char_range(start, end, root_info);
+            let root_info = root.text_info(policy);
+            let (_, crlf_seam, needs_fix) = root.remove_char_range(start, end, root_info, policy);
 
             if crlf_seam {
                 // TODO: make fix_crlf_seam() work in terms of char indices,
                 // to avoid this conversion.  In practice this is unlikely
                 // to have any real performance impact, because it runs very
                 // rarely.  But still!
-                let seam_idx = root.char_to_byte_and_line(start).0;
-                root.fix_crlf_seam(seam_idx as Count, false);
+                let seam_idx = root.char_to_byte_and_line(start, policy).0;
+                root.fix_crlf_seam(seam_idx as Count, false, policy);
             }
 
             if needs_fix {
-                root.fix_after_remove(start);
+                root.fix_after_remove(start, policy);
             }
         }
 
@@ -555,6 +1039,10 @@ impl Rope {
     /// Splits the `Rope` at `char_idx`, returning the right part of
     /// the split.
     ///
+    /// Marks after `char_idx` move to the returned `Rope`.  Marks exactly
+    /// at `char_idx` stay with this `Rope` if they have `Assoc::Before`
+    /// gravity, and move if they have `Assoc::After` gravity.
+    ///
     /// # Panics
     ///
     /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
@@ -567,21 +1055,30 @@ impl Rope {
             self.len_chars()
         );
 
-        if char_idx == 0 {
+        let right_marks = self.marks.split_off(char_idx);
+        let right_escaped_bytes = self.escaped_bytes.split_off(char_idx);
+
+        let mut right = if char_idx == 0 {
             // Special case 1
             let mut new_rope = Rope::new();
-            std::mem::swap(self, &mut new_rope);
+            new_rope.line_break_policy = self.line_break_policy;
+            new_rope.insert_line_ending = self.insert_line_ending;
+            std::mem::swap(&mut self.root, &mut new_rope.root);
             new_rope
         } else if char_idx == self.len_chars() {
             // Special case 2
-            Rope::new()
+            let mut new_rope = Rope::new();
+            new_rope.line_break_policy = self.line_break_policy;
+            new_rope.insert_line_ending = self.insert_line_ending;
+            new_rope
         } else {
             // Do the split
-            let mut new_rope_root = Arc::new(Arc::make_mut(&mut self.root).split(char_idx));
+            let policy = self.line_break_policy;
+            let mut new_rope_root = Arc::new(Arc::make_mut(&mut self.root).split(char_idx, policy));
 
             // Fix up the edges
-            Arc::make_mut(&mut self.root).zip_fix_right();
-            Arc::make_mut(&mut new_rope_root).zip_fix_left();
+            Arc::make_mut(&mut self.root).zip_fix_right(policy);
+            Arc::make_mut(&mut new_rope_root).zip_fix_left(policy);
             self.pull_up_singular_nodes();
 
             while (!new_rope_root.is_leaf()) && new_rope_root.child_count() == 1 {
@@ -594,139 +1091,587 @@ impl Rope {
                 new_rope_root = child;
             }
 
-            // Return right rope
             Rope {
                 root: new_rope_root,
+                line_break_policy: self.line_break_policy,
+                insert_line_ending: self.insert_line_ending,
+                marks: MarkSet::new(),
+                escaped_bytes: EscapedBytes::new(),
             }
-        }
+        };
+
+        // Return right rope
+        right.marks = right_marks;
+        right.escaped_bytes = right_escaped_bytes;
+        right
     }
 
     /// Appends a `Rope` to the end of this one, consuming the other `Rope`.
-    pub fn append(&mut self, other: Self) {
+    ///
+    /// The result keeps this `Rope`'s line break policy, and the other
+    /// `Rope`'s line breaks are counted again if it has a different one.
+    /// The other `Rope`'s marks are moved over, except for any that have the same id
+    /// as one of this `Rope`'s marks (e.g. when appending a clone).
+    pub fn append(&mut self, mut other: Self) {
+        let policy = self.line_break_policy;
+        other.set_line_break_policy(policy);
+
+        let len_chars = self.len_chars();
+        self.marks.append(&other.marks, len_chars);
+        self.escaped_bytes.append(&other.escaped_bytes, len_chars);
+
         if self.len_chars() == 0 {
-            let mut other = other;
-            std::mem::swap(self, &mut other);
+            self.root = other.root;
         } else if other.len_chars() > 0 {
-            let seam_byte_i = self.root.text_info().bytes;
+            let seam_byte_i = self.root.text_info(policy).bytes;
 
             let l_depth = self.root.depth();
             let r_depth = other.root.depth();
 
             if l_depth > r_depth {
-                let extra =
-                    Arc::make_mut(&mut self.root).append_at_depth(other.root, l_depth - r_depth);
+                let extra = Arc::make_mut(&mut self.root).append_at_depth(
+                    other.root,
+                    l_depth - r_depth,
+                    policy,
+                );
                 if let Some(node) = extra {
                     let mut children = NodeChildren::new();
-                    children.push((self.root.text_info(), Arc::clone(&self.root)));
-                    children.push((node.text_info(), node));
+                    children.push((self.root.text_info(policy), Arc::clone(&self.root)));
+                    children.push((node.text_info(policy), node));
                     self.root = Arc::new(Node::Internal(children));
                 }
             } else {
-                let mut other = other;
-                let extra = Arc::make_mut(&mut other.root)
-                    .prepend_at_depth(Arc::clone(&self.root), r_depth - l_depth);
+                let extra = Arc::make_mut(&mut other.root).prepend_at_depth(
+                    Arc::clone(&self.root),
+                    r_depth - l_depth,
+                    policy,
+                );
                 if let Some(node) = extra {
                     let mut children = NodeChildren::new();
-                    children.push((node.text_info(), node));
-                    children.push((other.root.text_info(), Arc::clone(&other.root)));
+                    children.push((node.text_info(policy), node));
+                    children.push((other.root.text_info(policy), Arc::clone(&other.root)));
                     other.root = Arc::new(Node::Internal(children));
                 }
-                *self = other;
+                self.root = other.root;
             };
 
-            Arc::make_mut(&mut self.root).fix_crlf_seam(seam_byte_i, true);
+            Arc::make_mut(&mut self.root).fix_crlf_seam(seam_byte_i, true, policy);
         }
     }
 
-    //-----------------------------------------------------------------------
-    // Index conversion methods
-
-    /// Returns the char index of the given byte.
+    /// Applies a batch of [`Edit`](struct.Edit.html)s as a single operation.
     ///
-    /// Notes:
+    /// The char ranges of all the edits refer to the text as it is _before_
+    /// any of them are applied, and the edits can be given in any order.
+    /// Edits may touch but must not overlap.  Multiple insertions at the
+    /// same char index are inserted in the order they were given.
     ///
-    /// - If the byte is in the middle of a multi-byte char, returns the
-    ///   index of the char that the byte belongs to.
-    /// - `byte_idx` can be one-past-the-end, which will return
-    ///   one-past-the-end char index.
+    /// All of the edits are validated before any of them are applied, so
+    /// the `Rope` is left untouched if any of them are invalid.
+    ///
+    /// The edits are applied in a single pass down the tree, which only
+    /// rebuilds the nodes that they touch.  Everything else stays shared
+    /// with any clones of the `Rope`.
+    ///
+    /// Runs in O(M log N + K) time, where M is the number of edits and K is
+    /// the total length of their text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::{Edit, Rope};
+    /// let mut rope = Rope::from_str("one two three");
+    /// rope.apply_edits(&[
+    ///     Edit::replace(8..13, "3"),
+    ///     Edit::insert(0, "<"),
+    ///     Edit::replace(4..7, "2"),
+    ///     Edit::insert(13, ">"),
+    /// ]);
+    ///
+    /// assert_eq!(rope, "<one 2 3>");
+    /// ```
     ///
     /// # Panics
     ///
-    /// Panics if `byte_idx` is out of bounds (i.e. `byte_idx > len_bytes()`).
-    #[inline]
-    pub fn byte_to_char(&self, byte_idx: usize) -> usize {
-        // Bounds check
-        assert!(
-            byte_idx <= self.len_bytes(),
-            "Attempt to index past end of Rope: byte index {}, Rope byte length {}",
-            byte_idx,
-            self.len_bytes()
-        );
-
-        let (chunk, b, c, _) = self.chunk_at_byte(byte_idx);
-        c + byte_to_char_idx(chunk, byte_idx - b)
+    /// Panics if any edit's char range is reversed or out of bounds, or if
+    /// any two edits overlap.
+    pub fn apply_edits(&mut self, edits: &[Edit]) {
+        let order = match self.check_edits(edits) {
+            Ok(order) => order,
+            Err(err) => panic!("Attempt to apply invalid edits: {:?}", err),
+        };
+        self.apply_sorted_edits(edits, &order);
     }
 
-    /// Returns the line index of the given byte.
+    /// Replaces every non-overlapping match of `pattern` with
+    /// `replacement`, and returns where the replacements were made.
     ///
-    /// Notes:
+    /// Matches are found the same way as by
+    /// [`RopeSlice::matches()`](struct.RopeSlice.html#method.matches), and
+    /// all of them are replaced as a single batch, the same way as by
+    /// [`apply_edits()`](#method.apply_edits).  The returned
+    /// [`Replacement`](struct.Replacement.html)s are in order, and give the
+    /// char range of each replacement in both the old and the new text.
     ///
-    /// - Lines are zero-indexed.  This is functionally equivalent to
-    ///   counting the line endings before the specified byte.
-    /// - `byte_idx` can be one-past-the-end, which will return the
-    ///   last line index.
+    /// Runs in O(N + K log N) time, where K is the number of matches.
     ///
-    /// # Panics
+    /// # Example
     ///
-    /// Panics if `byte_idx` is out of bounds (i.e. `byte_idx > len_bytes()`).
+    /// ```
+    /// # use ropey::Rope;
+    /// let mut rope = Rope::from_str("one two one two");
+    /// let report = rope.replace_all("two", "2");
+    ///
+    /// assert_eq!(rope, "one 2 one 2");
+    /// assert_eq!(report[1].old_range, 12..15);
+    /// assert_eq!(report[1].new_range, 10..11);
+    /// ```
     #[inline]
-    pub fn byte_to_line(&self, byte_idx: usize) -> usize {
-        // Bounds check
-        assert!(
-            byte_idx <= self.len_bytes(),
-            "Attempt to index past end of Rope: byte index {}, Rope byte length {}",
-            byte_idx,
-            self.len_bytes()
-        );
-
-        let (chunk, b, _, l) = self.chunk_at_byte(byte_idx);
-        l + byte_to_line_idx(chunk, byte_idx - b)
+    pub fn replace_all(&mut self, pattern: &str, replacement: &str) -> Vec<Replacement> {
+        self.replace_in(.., pattern, replacement)
     }
 
-    /// Returns the byte index of the given char.
+    /// Like [`replace_all()`](#method.replace_all), but only replaces the
+    /// matches that lie entirely within `char_range`.
     ///
-    /// Notes:
+    /// Uses range syntax, e.g. `2..7`, `2..`, etc.
     ///
-    /// - `char_idx` can be one-past-the-end, which will return
-    ///   one-past-the-end byte index.
+    /// Runs in O(M + K log N) time, where M is the length of the range and
+    /// K is the number of matches.
     ///
     /// # Panics
     ///
-    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
-    #[inline]
-    pub fn char_to_byte(&self, char_idx: usize) -> usize {
+    /// Panics if the start of the range is greater than the end, or if the
+    /// end is out of bounds (i.e. `end > len_chars()`).
+    pub fn replace_in<R: CharIdxRange>(
+        &mut self,
+        char_range: R,
+        pattern: &str,
+        replacement: &str,
+    ) -> Vec<Replacement> {
+        let start = char_range.start().unwrap_or(0);
+        let end = char_range.end().unwrap_or_else(|| self.len_chars());
+
         // Bounds check
+        assert!(start <= end);
         assert!(
-            char_idx <= self.len_chars(),
-            "Attempt to index past end of Rope: char index {}, Rope char length {}",
-            char_idx,
+            end <= self.len_chars(),
+            "Attempt to replace past end of Rope: range end {}, Rope length {}",
+            end,
             self.len_chars()
         );
 
-        let (chunk, b, c, _) = self.chunk_at_char(char_idx);
-        b + char_to_byte_idx(chunk, char_idx - c)
+        let pattern_chars = count_chars(pattern);
+        let replacements: Vec<_> = self
+            .slice(start..end)
+            .matches(pattern)
+            .map(|i| ((start + i)..(start + i + pattern_chars), replacement))
+            .collect();
+        self.replace_sorted(&replacements);
+
+        let replacement_chars = count_chars(replacement);
+        replacements
+            .into_iter()
+            .enumerate()
+            .map(|(i, (old_range, _))| {
+                let new_start = old_range.start - (i * pattern_chars) + (i * replacement_chars);
+                Replacement {
+                    old_range,
+                    new_range: new_start..(new_start + replacement_chars),
+                }
+            })
+            .collect()
     }
 
-    /// Returns the line index of the given char.
+    /// Replaces every non-overlapping match of `regex` with `template`
+    /// expanded for that match, and returns where the replacements were
+    /// made.
     ///
-    /// Notes:
+    /// Matches are found the same way as by
+    /// [`RopeSlice::matches_regex()`](struct.RopeSlice.html#method.matches_regex),
+    /// and `template` can refer to their capture groups as described in
+    /// [`Captures::expand()`](regex/struct.Captures.html#method.expand).
+    /// Otherwise this works like [`replace_all()`](#method.replace_all).
     ///
-    /// - Lines are zero-indexed.  This is functionally equivalent to
-    ///   counting the line endings before the specified char.
-    /// - `char_idx` can be one-past-the-end, which will return the
-    ///   last line index.
+    /// Only available with the `regex` cargo feature enabled.
     ///
-    /// # Panics
+    /// Runs in O(N + K log N) time, where K is the number of matches.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// # use ropey::regex::Regex;
+    /// let re = Regex::new(r"(\w+)=(\w+)").unwrap();
+    /// let mut rope = Rope::from_str("a=1, bb=22");
+    /// let report = rope.replace_all_regex(&re, "$2=$1");
+    ///
+    /// assert_eq!(rope, "1=a, 22=bb");
+    /// assert_eq!(report[1].new_range, 5..10);
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn replace_all_regex(&mut self, regex: &Regex, template: &str) -> Vec<Replacement> {
+        let texts: Vec<(Range<usize>, String)> = {
+            let slice = self.slice(..);
+            slice
+                .captures_iter_regex(regex)
+                .map(|captures| {
+                    let mut text = String::new();
+                    captures.expand(&slice, template, &mut text);
+                    (captures.get(0).unwrap(), text)
+                })
+                .collect()
+        };
+        let replacements: Vec<_> = texts
+            .iter()
+            .map(|(range, text)| (range.clone(), text.as_str()))
+            .collect();
+        self.replace_sorted(&replacements);
+
+        let mut delta = 0isize;
+        texts
+            .iter()
+            .map(|(old_range, text)| {
+                let new_start = (old_range.start as isize + delta) as usize;
+                let new_end = new_start + count_chars(text);
+                delta += (new_end - new_start) as isize;
+                delta -= (old_range.end - old_range.start) as isize;
+                Replacement {
+                    old_range: old_range.clone(),
+                    new_range: new_start..new_end,
+                }
+            })
+            .collect()
+    }
+
+    /// Converts every LF, CR and CRLF line ending in the `Rope` to
+    /// `line_ending`, and returns where the conversions were made.
+    ///
+    /// Other line breaks are left as they are.  The conversions are made
+    /// as a single batch, the same way as by
+    /// [`apply_edits()`](#method.apply_edits), and are reported the same
+    /// way as by [`replace_all()`](#method.replace_all).
+    ///
+    /// Runs in O(N + K log N) time, where K is the number of conversions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::{LineEnding, Rope};
+    /// let mut rope = Rope::from_str("one\ntwo\r\nthree\rfour");
+    /// let report = rope.normalize_line_endings(LineEnding::Crlf);
+    ///
+    /// assert_eq!(rope, "one\r\ntwo\r\nthree\r\nfour");
+    /// assert_eq!(report.len(), 2);
+    /// ```
+    #[inline]
+    pub fn normalize_line_endings(&mut self, line_ending: LineEnding) -> Vec<Replacement> {
+        self.normalize_line_endings_in(.., line_ending)
+    }
+
+    /// Like [`normalize_line_endings()`](#method.normalize_line_endings),
+    /// but only converts the line endings that lie entirely within
+    /// `char_range`.
+    ///
+    /// A line ending at either end of the range is also left alone if
+    /// converting it would join it with a line ending just outside the
+    /// range into a CRLF pair, e.g. converting a CR to an LF right after
+    /// another CR.
+    ///
+    /// Uses range syntax, e.g. `2..7`, `2..`, etc.
+    ///
+    /// Runs in O(M + K log N) time, where M is the length of the range and
+    /// K is the number of conversions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than the end, or if the
+    /// end is out of bounds (i.e. `end > len_chars()`).
+    pub fn normalize_line_endings_in<R: CharIdxRange>(
+        &mut self,
+        char_range: R,
+        line_ending: LineEnding,
+    ) -> Vec<Replacement> {
+        let start = char_range.start().unwrap_or(0);
+        let end = char_range.end().unwrap_or_else(|| self.len_chars());
+
+        // Bounds check
+        assert!(start <= end);
+        assert!(
+            end <= self.len_chars(),
+            "Attempt to normalize past end of Rope: range end {}, Rope length {}",
+            end,
+            self.len_chars()
+        );
+
+        let new_text = line_ending.as_str();
+        let mut replacements = Vec::new();
+        {
+            let mut chars = self.chars_at(start).peekable();
+            let mut char_idx = start;
+
+            // Skip the LF of a CRLF pair that starts before the range.
+            if start > 0 && start < end && self.char(start - 1) == '\r' && self.char(start) == '\n'
+            {
+                chars.next();
+                char_idx += 1;
+            }
+
+            while char_idx < end {
+                let old_text = match chars.next().unwrap() {
+                    '\r' if chars.peek() == Some(&'\n') => {
+                        chars.next();
+                        "\r\n"
+                    }
+                    '\r' => "\r",
+                    '\n' => "\n",
+                    _ => {
+                        char_idx += 1;
+                        continue;
+                    }
+                };
+                let old_end = char_idx + old_text.len();
+                if old_end > end {
+                    break;
+                }
+                if old_text != new_text {
+                    replacements.push((char_idx..old_end, new_text));
+                }
+                char_idx = old_end;
+            }
+        }
+
+        // Skip conversions that would join with a line ending just outside
+        // the range into a CRLF pair.
+        if end < self.len_chars() && new_text.ends_with('\r') && self.char(end) == '\n' {
+            if let Some((range, _)) = replacements.last() {
+                if range.end == end {
+                    replacements.pop();
+                }
+            }
+        }
+        if start > 0 && new_text.starts_with('\n') && self.char(start - 1) == '\r' {
+            if let Some((range, _)) = replacements.first() {
+                if range.start == start {
+                    replacements.remove(0);
+                }
+            }
+        }
+
+        self.replace_sorted(&replacements);
+
+        let new_chars = new_text.len();
+        let mut delta = 0isize;
+        replacements
+            .into_iter()
+            .map(|(old_range, _)| {
+                let new_start = (old_range.start as isize + delta) as usize;
+                delta += new_chars as isize - (old_range.end - old_range.start) as isize;
+                Replacement {
+                    old_range,
+                    new_range: new_start..(new_start + new_chars),
+                }
+            })
+            .collect()
+    }
+
+    //-----------------------------------------------------------------------
+    // Mark methods
+
+    /// Adds a mark at char index `char_idx`, and returns its id.
+    ///
+    /// A mark is a position that stays attached to the surrounding text as
+    /// the `Rope` is edited: every editing method moves the marks along
+    /// with the text around them.  `gravity` decides which way a mark goes
+    /// when text is inserted exactly at it: with `Assoc::Before` (left
+    /// gravity) it stays before the inserted text, and with `Assoc::After`
+    /// (right gravity) it ends up after it.  Marks in removed text collapse
+    /// to the start of the removed range.
+    ///
+    /// Marks are carried along by `clone()`, and moved by
+    /// [`split_off()`](#method.split_off) and [`append()`](#method.append).
+    /// Clones share their marks until either one is edited, so cloning
+    /// stays O(1).
+    ///
+    /// Every edit takes an additional O(K) time when there are K marks, and
+    /// a batch of E edits (e.g. with
+    /// [`apply_edits()`](#method.apply_edits)) takes O(K log E).  The first
+    /// edit after cloning also copies the marks.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::{Assoc, Rope};
+    /// let mut rope = Rope::from_str("Hello world!");
+    /// let mark = rope.add_mark(6, Assoc::Before);
+    ///
+    /// rope.insert(0, "Oh, ");
+    /// rope.insert(10, "big ");
+    /// assert_eq!(rope.mark_char(mark), Some(10));
+    ///
+    /// rope.remove(8..14);
+    /// assert_eq!(rope.mark_char(mark), Some(8));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    pub fn add_mark(&mut self, char_idx: usize, gravity: Assoc) -> MarkId {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to add mark past end of Rope: char index {}, Rope length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        self.marks.add(char_idx, gravity)
+    }
+
+    /// Removes a mark, returning its char index.
+    ///
+    /// Returns `None` if this `Rope` has no mark with that id.
+    #[inline]
+    pub fn remove_mark(&mut self, mark: MarkId) -> Option<usize> {
+        self.marks.remove(mark).map(|mark| mark.char_idx)
+    }
+
+    /// Removes all marks.
+    #[inline]
+    pub fn clear_marks(&mut self) {
+        self.marks.clear();
+    }
+
+    /// Returns the char index of a mark, or `None` if this `Rope` has no
+    /// mark with that id.
+    #[inline]
+    pub fn mark_char(&self, mark: MarkId) -> Option<usize> {
+        self.marks.get(mark).map(|mark| mark.char_idx)
+    }
+
+    /// Returns the byte index of a mark, or `None` if this `Rope` has no
+    /// mark with that id.
+    ///
+    /// Runs in O(log N) time.
+    #[inline]
+    pub fn mark_byte(&self, mark: MarkId) -> Option<usize> {
+        self.mark_char(mark).map(|char_idx| self.char_to_byte(char_idx))
+    }
+
+    /// Returns the index of the line a mark is on, or `None` if this `Rope`
+    /// has no mark with that id.
+    ///
+    /// Runs in O(log N) time.
+    #[inline]
+    pub fn mark_line(&self, mark: MarkId) -> Option<usize> {
+        self.mark_char(mark).map(|char_idx| self.char_to_line(char_idx))
+    }
+
+    /// Returns the gravity of a mark, or `None` if this `Rope` has no mark
+    /// with that id.
+    #[inline]
+    pub fn mark_gravity(&self, mark: MarkId) -> Option<Assoc> {
+        self.marks.get(mark).map(|mark| mark.gravity)
+    }
+
+    /// Creates an iterator over the `Rope`'s marks.
+    ///
+    /// Yields the id and char index of each mark, in the order the marks
+    /// were added.
+    #[inline]
+    pub fn marks<'a>(&'a self) -> Marks<'a> {
+        Marks::new(&self.marks)
+    }
+
+    //-----------------------------------------------------------------------
+    // Index conversion methods
+
+    /// Returns the char index of the given byte.
+    ///
+    /// Notes:
+    ///
+    /// - If the byte is in the middle of a multi-byte char, returns the
+    ///   index of the char that the byte belongs to.
+    /// - `byte_idx` can be one-past-the-end, which will return
+    ///   one-past-the-end char index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_idx` is out of bounds (i.e. `byte_idx > len_bytes()`).
+    #[inline]
+    pub fn byte_to_char(&self, byte_idx: usize) -> usize {
+        // Bounds check
+        assert!(
+            byte_idx <= self.len_bytes(),
+            "Attempt to index past end of Rope: byte index {}, Rope byte length {}",
+            byte_idx,
+            self.len_bytes()
+        );
+
+        let (chunk, b, c, _) = self.chunk_at_byte(byte_idx);
+        c + byte_to_char_idx(chunk, byte_idx - b)
+    }
+
+    /// Returns the line index of the given byte.
+    ///
+    /// Notes:
+    ///
+    /// - Lines are zero-indexed.  This is functionally equivalent to
+    ///   counting the line endings before the specified byte.
+    /// - `byte_idx` can be one-past-the-end, which will return the
+    ///   last line index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_idx` is out of bounds (i.e. `byte_idx > len_bytes()`).
+    #[inline]
+    pub fn byte_to_line(&self, byte_idx: usize) -> usize {
+        // Bounds check
+        assert!(
+            byte_idx <= self.len_bytes(),
+            "Attempt to index past end of Rope: byte index {}, Rope byte length {}",
+            byte_idx,
+            self.len_bytes()
+        );
+
+        let (chunk, b, _, l) = self.chunk_at_byte(byte_idx);
+        l + byte_to_line_idx_with_policy(chunk, byte_idx - b, self.line_break_policy)
+    }
+
+    /// Returns the byte index of the given char.
+    ///
+    /// Notes:
+    ///
+    /// - `char_idx` can be one-past-the-end, which will return
+    ///   one-past-the-end byte index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    #[inline]
+    pub fn char_to_byte(&self, char_idx: usize) -> usize {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to index past end of Rope: char index {}, Rope char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        let (chunk, b, c, _) = self.chunk_at_char(char_idx);
+        b + char_to_byte_idx(chunk, char_idx - c)
+    }
+
+    /// Returns the line index of the given char.
+    ///
+    /// Notes:
+    ///
+    /// - Lines are zero-indexed.  This is functionally equivalent to
+    ///   counting the line endings before the specified char.
+    /// - `char_idx` can be one-past-the-end, which will return the
+    ///   last line index.
+    ///
+    /// # Panics
     ///
     /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
     #[inline]
@@ -740,7 +1685,7 @@ impl Rope {
         );
 
         let (chunk, _, c, l) = self.chunk_at_char(char_idx);
-        l + char_to_line_idx(chunk, char_idx - c)
+        l + char_to_line_idx_with_policy(chunk, char_idx - c, self.line_break_policy)
     }
 
     /// Returns the byte index of the start of the given line.
@@ -768,7 +1713,7 @@ impl Rope {
             self.len_bytes()
         } else {
             let (chunk, b, _, l) = self.chunk_at_line_break(line_idx);
-            b + line_to_byte_idx(chunk, line_idx - l)
+            b + line_to_byte_idx_with_policy(chunk, line_idx - l, self.line_break_policy)
         }
     }
 
@@ -797,10 +1742,69 @@ impl Rope {
             self.len_chars()
         } else {
             let (chunk, _, c, l) = self.chunk_at_line_break(line_idx);
-            c + line_to_char_idx(chunk, line_idx - l)
+            c + line_to_char_idx_with_policy(chunk, line_idx - l, self.line_break_policy)
         }
     }
 
+    /// Returns the utf16 code unit index of the given char.
+    ///
+    /// Ropey stores text internally as utf8, but sometimes it is necessary
+    /// to interact with external APIs that still use utf16.  This function
+    /// is primarily intended for such situations, and is otherwise not very
+    /// useful.
+    ///
+    /// Notes:
+    ///
+    /// - `char_idx` can be one-past-the-end, which will return
+    ///   one-past-the-end utf16 code unit index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    #[inline]
+    pub fn char_to_utf16_cu(&self, char_idx: usize) -> usize {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to index past end of Rope: char index {}, Rope char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        char_idx + self.root.char_to_utf16_surrogate_idx(char_idx)
+    }
+
+    /// Returns the char index of the given utf16 code unit.
+    ///
+    /// Ropey stores text internally as utf8, but sometimes it is necessary
+    /// to interact with external APIs that still use utf16.  This function
+    /// is primarily intended for such situations, and is otherwise not very
+    /// useful.
+    ///
+    /// Notes:
+    ///
+    /// - If the utf16 code unit is in the middle of a surrogate pair,
+    ///   returns the index of the char that the surrogate pair belongs to.
+    /// - `utf16_cu_idx` can be one-past-the-end, which will return
+    ///   one-past-the-end char index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `utf16_cu_idx` is out of bounds
+    /// (i.e. `utf16_cu_idx > len_utf16_cu()`).
+    #[inline]
+    pub fn utf16_cu_to_char(&self, utf16_cu_idx: usize) -> usize {
+        // Bounds check
+        assert!(
+            utf16_cu_idx <= self.len_utf16_cu(),
+            "Attempt to index past end of Rope: utf16 code unit index {}, Rope utf16 length {}",
+            utf16_cu_idx,
+            self.len_utf16_cu()
+        );
+
+        self.root.utf16_cu_to_char(utf16_cu_idx)
+    }
+
     //-----------------------------------------------------------------------
     // Fetch methods
 
@@ -841,15 +1845,16 @@ impl Rope {
             self.len_lines()
         );
 
+        let policy = self.line_break_policy;
         let (chunk_1, _, c1, l1) = self.chunk_at_line_break(line_idx);
         let (chunk_2, _, c2, l2) = self.chunk_at_line_break(line_idx + 1);
         if c1 == c2 {
-            let text1 = &chunk_1[line_to_byte_idx(chunk_1, line_idx - l1)..];
-            let text2 = &text1[..line_to_byte_idx(text1, 1)];
-            RopeSlice::from_str(text2)
+            let text1 = &chunk_1[line_to_byte_idx_with_policy(chunk_1, line_idx - l1, policy)..];
+            let text2 = &text1[..line_to_byte_idx_with_policy(text1, 1, policy)];
+            RopeSlice::from_str_with_policy(text2, policy)
         } else {
-            let start = c1 + line_to_char_idx(chunk_1, line_idx - l1);
-            let end = c2 + line_to_char_idx(chunk_2, line_idx + 1 - l2);
+            let start = c1 + line_to_char_idx_with_policy(chunk_1, line_idx - l1, policy);
+            let end = c2 + line_to_char_idx_with_policy(chunk_2, line_idx + 1 - l2, policy);
             self.slice(start..end)
         }
     }
@@ -964,7 +1969,34 @@ impl Rope {
             self.len_chars()
         );
 
-        RopeSlice::new_with_range(&self.root, start, end)
+        RopeSlice::new_with_range(&self.root, start, end, self.line_break_policy)
+    }
+
+    /// Gets an immutable slice of the `Rope`, using byte indices.
+    ///
+    /// Uses range syntax, e.g. `2..7`, `2..`, etc.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// let rope = Rope::from_str("Hello みんな!");
+    /// let slice = rope.byte_slice(6..12);
+    ///
+    /// assert_eq!("みん", slice);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than the end, if the
+    /// end is out of bounds (i.e. `end > len_bytes()`), or if either end of
+    /// the range is not a char boundary.
+    #[inline]
+    pub fn byte_slice<R: CharIdxRange>(&self, byte_range: R) -> RopeSlice<'_> {
+        match self.try_byte_slice(byte_range) {
+            Ok(slice) => slice,
+            Err(err) => panic!("Attempt to slice Rope with an invalid byte range: {:?}", err),
+        }
     }
 
     //-----------------------------------------------------------------------
@@ -985,7 +2017,7 @@ impl Rope {
     /// Creates an iterator over the lines of the `Rope`.
     #[inline]
     pub fn lines(&self) -> Lines {
-        Lines::new(&self.root)
+        Lines::new(&self.root, self.line_break_policy)
     }
 
     /// Creates an iterator over the chunks of the `Rope`.
@@ -994,10 +2026,192 @@ impl Rope {
         Chunks::new(&self.root)
     }
 
-    //-----------------------------------------------------------------------
-    // Conversion methods
-
-    /// Returns the entire text of the `Rope` as a newly allocated String.
+    /// Creates an iterator over the bytes of the `Rope`, starting at byte
+    /// `byte_idx`.
+    ///
+    /// The first call to `next()` returns the byte at `byte_idx`, and the
+    /// first call to `prev()` returns the byte before it.  If
+    /// `byte_idx == len_bytes()` then an iterator at the end of the `Rope`
+    /// is created (i.e. `next()` will return `None`).
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_idx` is out of bounds (i.e. `byte_idx > len_bytes()`).
+    #[inline]
+    pub fn bytes_at(&self, byte_idx: usize) -> Bytes<'_> {
+        // Bounds check
+        assert!(
+            byte_idx <= self.len_bytes(),
+            "Attempt to index past end of Rope: byte index {}, Rope byte length {}",
+            byte_idx,
+            self.len_bytes()
+        );
+
+        Bytes::new_with_range_at(&self.root, byte_idx, 0, self.len_chars())
+    }
+
+    /// Creates an iterator over the chars of the `Rope`, starting at char
+    /// `char_idx`.
+    ///
+    /// The first call to `next()` returns the char at `char_idx`, and the
+    /// first call to `prev()` returns the char before it.  If
+    /// `char_idx == len_chars()` then an iterator at the end of the `Rope`
+    /// is created (i.e. `next()` will return `None`).
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    #[inline]
+    pub fn chars_at(&self, char_idx: usize) -> Chars<'_> {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to index past end of Rope: char index {}, Rope char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        Chars::new_with_range_at(&self.root, self.char_to_byte(char_idx), 0, self.len_chars())
+    }
+
+    /// Creates an iterator over the lines of the `Rope`, starting at line
+    /// `line_idx`.
+    ///
+    /// The first call to `next()` returns the line at `line_idx`, and the
+    /// first call to `prev()` returns the line before it.  If
+    /// `line_idx == len_lines()` then an iterator at the end of the `Rope`
+    /// is created (i.e. `next()` will return `None`).
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line_idx` is out of bounds (i.e. `line_idx > len_lines()`).
+    #[inline]
+    pub fn lines_at(&self, line_idx: usize) -> Lines<'_> {
+        // Bounds check
+        assert!(
+            line_idx <= self.len_lines(),
+            "Attempt to index past end of Rope: line index {}, Rope line length {}",
+            line_idx,
+            self.len_lines()
+        );
+
+        Lines::new_with_range_at(
+            &self.root,
+            line_idx,
+            0,
+            self.len_chars(),
+            self.line_break_policy,
+        )
+    }
+
+    /// Creates an iterator over the chunks of the `Rope`, with the
+    /// iterator starting at the chunk containing `byte_idx`.
+    ///
+    /// Also returns the byte and char indices of the beginning of that
+    /// chunk and the index of the line that the chunk starts on.
+    ///
+    /// The first call to `next()` returns the chunk containing `byte_idx`,
+    /// and the first call to `prev()` returns the chunk before it.  If
+    /// `byte_idx == len_bytes()` then an iterator at the end of the `Rope`
+    /// is created (i.e. `next()` will return `None`), and the returned
+    /// indices are those of the last chunk.
+    ///
+    /// The return value is organized as
+    /// `(iterator, chunk_byte_idx, chunk_char_idx, chunk_line_idx)`.
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_idx` is out of bounds (i.e. `byte_idx > len_bytes()`).
+    #[inline]
+    pub fn chunks_at_byte(&self, byte_idx: usize) -> (Chunks<'_>, usize, usize, usize) {
+        let (_, b, c, l) = self.chunk_at_byte(byte_idx);
+        let at_byte = if byte_idx == self.len_bytes() {
+            byte_idx
+        } else {
+            b
+        };
+        (self.chunks_at(at_byte), b, c, l)
+    }
+
+    /// Creates an iterator over the chunks of the `Rope`, with the
+    /// iterator starting at the chunk containing `char_idx`.
+    ///
+    /// Also returns the byte and char indices of the beginning of that
+    /// chunk and the index of the line that the chunk starts on.
+    ///
+    /// The first call to `next()` returns the chunk containing `char_idx`,
+    /// and the first call to `prev()` returns the chunk before it.  If
+    /// `char_idx == len_chars()` then an iterator at the end of the `Rope`
+    /// is created (i.e. `next()` will return `None`), and the returned
+    /// indices are those of the last chunk.
+    ///
+    /// The return value is organized as
+    /// `(iterator, chunk_byte_idx, chunk_char_idx, chunk_line_idx)`.
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    #[inline]
+    pub fn chunks_at_char(&self, char_idx: usize) -> (Chunks<'_>, usize, usize, usize) {
+        let (_, b, c, l) = self.chunk_at_char(char_idx);
+        let at_byte = if char_idx == self.len_chars() {
+            self.len_bytes()
+        } else {
+            b
+        };
+        (self.chunks_at(at_byte), b, c, l)
+    }
+
+    /// Creates an iterator over the chunks of the `Rope`, with the
+    /// iterator starting at the chunk containing the given line break.
+    ///
+    /// Also returns the byte and char indices of the beginning of that
+    /// chunk and the index of the line that the chunk starts on.
+    ///
+    /// As with [`chunk_at_line_break()`](#method.chunk_at_line_break), both
+    /// the beginning and end of the rope are considered line breaks.  If
+    /// `line_break_idx == len_lines()` then an iterator at the end of the
+    /// `Rope` is created (i.e. `next()` will return `None`), and the
+    /// returned indices are those of the last chunk.
+    ///
+    /// The return value is organized as
+    /// `(iterator, chunk_byte_idx, chunk_char_idx, chunk_line_idx)`.
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line_break_idx` is out of bounds (i.e. `line_break_idx > len_lines()`).
+    #[inline]
+    pub fn chunks_at_line_break(&self, line_break_idx: usize) -> (Chunks<'_>, usize, usize, usize) {
+        let (_, b, c, l) = self.chunk_at_line_break(line_break_idx);
+        let at_byte = if line_break_idx == self.len_lines() {
+            self.len_bytes()
+        } else {
+            b
+        };
+        (self.chunks_at(at_byte), b, c, l)
+    }
+
+    #[inline]
+    fn chunks_at(&self, at_byte: usize) -> Chunks<'_> {
+        Chunks::new_with_range_at(&self.root, at_byte, 0, self.len_chars())
+    }
+
+    //-----------------------------------------------------------------------
+    // Conversion methods
+
+    /// Returns the entire text of the `Rope` as a newly allocated String.
     ///
     /// Runs in O(N) time.
     #[inline]
@@ -1019,7 +2233,7 @@ impl Rope {
     /// tree is consistent with the actual data.
     #[doc(hidden)]
     pub fn assert_integrity(&self) {
-        self.root.assert_integrity();
+        self.root.assert_integrity(self.line_break_policy);
     }
 
     /// NOT PART OF THE PUBLIC API (hidden from docs for a reason!)
@@ -1070,6 +2284,530 @@ impl Rope {
             self.root = child;
         }
     }
+
+    /// Moves the marks and escaped bytes along for a batch of replacements,
+    /// each given as `(start, end, inserted_char_count)`.
+    fn map_edits(&mut self, edits: &[(usize, usize, usize)]) {
+        if !self.marks.is_empty() {
+            self.marks.map_edits(edits);
+        }
+        if !self.escaped_bytes.is_empty() {
+            self.escaped_bytes.map_edits(edits);
+        }
+    }
+}
+
+/// # Grapheme clusters
+///
+/// Only available with the `graphemes` cargo feature enabled.  Grapheme
+/// clusters here are Unicode extended grapheme clusters, and are found
+/// correctly even when they span chunk boundaries.
+#[cfg(feature = "graphemes")]
+impl Rope {
+    /// Returns whether `char_idx` is on a grapheme cluster boundary.
+    ///
+    /// The start and end of the `Rope` are always boundaries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    #[inline]
+    pub fn is_grapheme_boundary(&self, char_idx: usize) -> bool {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to index past end of Rope: char index {}, Rope char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        self.slice(..).is_grapheme_boundary(char_idx)
+    }
+
+    /// Returns the char index of the grapheme cluster boundary before
+    /// `char_idx`.
+    ///
+    /// Returns 0 if `char_idx` is 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    #[inline]
+    pub fn prev_grapheme_boundary(&self, char_idx: usize) -> usize {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to index past end of Rope: char index {}, Rope char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        self.slice(..).prev_grapheme_boundary(char_idx)
+    }
+
+    /// Returns the char index of the grapheme cluster boundary after
+    /// `char_idx`.
+    ///
+    /// Returns `len_chars()` if `char_idx` is `len_chars()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    #[inline]
+    pub fn next_grapheme_boundary(&self, char_idx: usize) -> usize {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to index past end of Rope: char index {}, Rope char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        self.slice(..).next_grapheme_boundary(char_idx)
+    }
+
+    /// Creates an iterator over the grapheme clusters of the `Rope`.
+    #[inline]
+    pub fn graphemes(&self) -> Graphemes<'_> {
+        Graphemes::new(self.slice(..))
+    }
+}
+
+/// # Non-panicking
+///
+/// The methods in this impl block provide non-panicking versions of
+/// `Rope`'s panicking methods.  They return `Err(Error)` in the situations
+/// where their panicking counterparts would have panicked.
+impl Rope {
+    /// Non-panicking version of [`insert()`](#method.insert).
+    #[inline]
+    pub fn try_insert(&mut self, char_idx: usize, text: &str) -> Result<()> {
+        if char_idx <= self.len_chars() {
+            self.insert(char_idx, text);
+            Ok(())
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`insert_char()`](#method.insert_char).
+    #[inline]
+    pub fn try_insert_char(&mut self, char_idx: usize, ch: char) -> Result<()> {
+        if char_idx <= self.len_chars() {
+            self.insert_char(char_idx, ch);
+            Ok(())
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`remove()`](#method.remove).
+    #[inline]
+    pub fn try_remove<R: CharIdxRange>(&mut self, char_range: R) -> Result<()> {
+        let (start, end) = self.check_char_range(char_range)?;
+        self.remove(start..end);
+        Ok(())
+    }
+
+    /// Non-panicking version of [`apply_edits()`](#method.apply_edits).
+    #[inline]
+    pub fn try_apply_edits(&mut self, edits: &[Edit]) -> Result<()> {
+        let order = self.check_edits(edits)?;
+        self.apply_sorted_edits(edits, &order);
+        Ok(())
+    }
+
+    /// Non-panicking version of [`replace_in()`](#method.replace_in).
+    #[inline]
+    pub fn try_replace_in<R: CharIdxRange>(
+        &mut self,
+        char_range: R,
+        pattern: &str,
+        replacement: &str,
+    ) -> Result<Vec<Replacement>> {
+        let (start, end) = self.check_char_range(char_range)?;
+        Ok(self.replace_in(start..end, pattern, replacement))
+    }
+
+    /// Non-panicking version of
+    /// [`normalize_line_endings_in()`](#method.normalize_line_endings_in).
+    #[inline]
+    pub fn try_normalize_line_endings_in<R: CharIdxRange>(
+        &mut self,
+        char_range: R,
+        line_ending: LineEnding,
+    ) -> Result<Vec<Replacement>> {
+        let (start, end) = self.check_char_range(char_range)?;
+        Ok(self.normalize_line_endings_in(start..end, line_ending))
+    }
+
+    /// Non-panicking version of [`add_mark()`](#method.add_mark).
+    #[inline]
+    pub fn try_add_mark(&mut self, char_idx: usize, gravity: Assoc) -> Result<MarkId> {
+        if char_idx <= self.len_chars() {
+            Ok(self.add_mark(char_idx, gravity))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`split_off()`](#method.split_off).
+    #[inline]
+    pub fn try_split_off(&mut self, char_idx: usize) -> Result<Self> {
+        if char_idx <= self.len_chars() {
+            Ok(self.split_off(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`byte_to_char()`](#method.byte_to_char).
+    #[inline]
+    pub fn try_byte_to_char(&self, byte_idx: usize) -> Result<usize> {
+        if byte_idx <= self.len_bytes() {
+            Ok(self.byte_to_char(byte_idx))
+        } else {
+            Err(Error::ByteIndexOutOfBounds(byte_idx, self.len_bytes()))
+        }
+    }
+
+    /// Non-panicking version of [`byte_to_line()`](#method.byte_to_line).
+    #[inline]
+    pub fn try_byte_to_line(&self, byte_idx: usize) -> Result<usize> {
+        if byte_idx <= self.len_bytes() {
+            Ok(self.byte_to_line(byte_idx))
+        } else {
+            Err(Error::ByteIndexOutOfBounds(byte_idx, self.len_bytes()))
+        }
+    }
+
+    /// Non-panicking version of [`char_to_byte()`](#method.char_to_byte).
+    #[inline]
+    pub fn try_char_to_byte(&self, char_idx: usize) -> Result<usize> {
+        if char_idx <= self.len_chars() {
+            Ok(self.char_to_byte(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`char_to_line()`](#method.char_to_line).
+    #[inline]
+    pub fn try_char_to_line(&self, char_idx: usize) -> Result<usize> {
+        if char_idx <= self.len_chars() {
+            Ok(self.char_to_line(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`line_to_byte()`](#method.line_to_byte).
+    #[inline]
+    pub fn try_line_to_byte(&self, line_idx: usize) -> Result<usize> {
+        if line_idx <= self.len_lines() {
+            Ok(self.line_to_byte(line_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_idx, self.len_lines()))
+        }
+    }
+
+    /// Non-panicking version of [`line_to_char()`](#method.line_to_char).
+    #[inline]
+    pub fn try_line_to_char(&self, line_idx: usize) -> Result<usize> {
+        if line_idx <= self.len_lines() {
+            Ok(self.line_to_char(line_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_idx, self.len_lines()))
+        }
+    }
+
+    /// Non-panicking version of [`char_to_utf16_cu()`](#method.char_to_utf16_cu).
+    #[inline]
+    pub fn try_char_to_utf16_cu(&self, char_idx: usize) -> Result<usize> {
+        if char_idx <= self.len_chars() {
+            Ok(self.char_to_utf16_cu(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`utf16_cu_to_char()`](#method.utf16_cu_to_char).
+    #[inline]
+    pub fn try_utf16_cu_to_char(&self, utf16_cu_idx: usize) -> Result<usize> {
+        if utf16_cu_idx <= self.len_utf16_cu() {
+            Ok(self.utf16_cu_to_char(utf16_cu_idx))
+        } else {
+            Err(Error::Utf16IndexOutOfBounds(utf16_cu_idx, self.len_utf16_cu()))
+        }
+    }
+
+    /// Non-panicking version of [`char()`](#method.char).
+    #[inline]
+    pub fn try_char(&self, char_idx: usize) -> Result<char> {
+        if char_idx < self.len_chars() {
+            Ok(self.char(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`line()`](#method.line).
+    #[inline]
+    pub fn try_line(&self, line_idx: usize) -> Result<RopeSlice<'_>> {
+        if line_idx < self.len_lines() {
+            Ok(self.line(line_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_idx, self.len_lines()))
+        }
+    }
+
+    /// Non-panicking version of [`chunk_at_byte()`](#method.chunk_at_byte).
+    #[inline]
+    pub fn try_chunk_at_byte(&self, byte_idx: usize) -> Result<(&str, usize, usize, usize)> {
+        if byte_idx <= self.len_bytes() {
+            Ok(self.chunk_at_byte(byte_idx))
+        } else {
+            Err(Error::ByteIndexOutOfBounds(byte_idx, self.len_bytes()))
+        }
+    }
+
+    /// Non-panicking version of [`chunk_at_char()`](#method.chunk_at_char).
+    #[inline]
+    pub fn try_chunk_at_char(&self, char_idx: usize) -> Result<(&str, usize, usize, usize)> {
+        if char_idx <= self.len_chars() {
+            Ok(self.chunk_at_char(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`chunk_at_line_break()`](#method.chunk_at_line_break).
+    #[inline]
+    pub fn try_chunk_at_line_break(
+        &self,
+        line_break_idx: usize,
+    ) -> Result<(&str, usize, usize, usize)> {
+        if line_break_idx <= self.len_lines() {
+            Ok(self.chunk_at_line_break(line_break_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_break_idx, self.len_lines()))
+        }
+    }
+
+    /// Non-panicking version of [`slice()`](#method.slice).
+    #[inline]
+    pub fn try_slice<R: CharIdxRange>(&self, char_range: R) -> Result<RopeSlice<'_>> {
+        let (start, end) = self.check_char_range(char_range)?;
+        Ok(self.slice(start..end))
+    }
+
+    /// Non-panicking version of [`byte_slice()`](#method.byte_slice).
+    pub fn try_byte_slice<R: CharIdxRange>(&self, byte_range: R) -> Result<RopeSlice<'_>> {
+        let start = byte_range.start().unwrap_or(0);
+        let end = byte_range.end().unwrap_or_else(|| self.len_bytes());
+
+        // Bounds check
+        if start > end {
+            return Err(Error::ByteRangeInvalid(start, end));
+        } else if end > self.len_bytes() {
+            return Err(Error::ByteIndexOutOfBounds(end, self.len_bytes()));
+        }
+
+        // Char boundary check
+        for &byte_idx in &[start, end] {
+            let (chunk, b, _, _) = self.chunk_at_byte(byte_idx);
+            if !chunk.is_char_boundary(byte_idx - b) {
+                return Err(Error::ByteIndexNotCharBoundary(byte_idx));
+            }
+        }
+
+        Ok(self.slice(self.byte_to_char(start)..self.byte_to_char(end)))
+    }
+
+    /// Non-panicking version of [`bytes_at()`](#method.bytes_at).
+    #[inline]
+    pub fn try_bytes_at(&self, byte_idx: usize) -> Result<Bytes<'_>> {
+        if byte_idx <= self.len_bytes() {
+            Ok(self.bytes_at(byte_idx))
+        } else {
+            Err(Error::ByteIndexOutOfBounds(byte_idx, self.len_bytes()))
+        }
+    }
+
+    /// Non-panicking version of [`chars_at()`](#method.chars_at).
+    #[inline]
+    pub fn try_chars_at(&self, char_idx: usize) -> Result<Chars<'_>> {
+        if char_idx <= self.len_chars() {
+            Ok(self.chars_at(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`lines_at()`](#method.lines_at).
+    #[inline]
+    pub fn try_lines_at(&self, line_idx: usize) -> Result<Lines<'_>> {
+        if line_idx <= self.len_lines() {
+            Ok(self.lines_at(line_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_idx, self.len_lines()))
+        }
+    }
+
+    /// Non-panicking version of [`chunks_at_byte()`](#method.chunks_at_byte).
+    #[inline]
+    pub fn try_chunks_at_byte(&self, byte_idx: usize) -> Result<(Chunks<'_>, usize, usize, usize)> {
+        if byte_idx <= self.len_bytes() {
+            Ok(self.chunks_at_byte(byte_idx))
+        } else {
+            Err(Error::ByteIndexOutOfBounds(byte_idx, self.len_bytes()))
+        }
+    }
+
+    /// Non-panicking version of [`chunks_at_char()`](#method.chunks_at_char).
+    #[inline]
+    pub fn try_chunks_at_char(&self, char_idx: usize) -> Result<(Chunks<'_>, usize, usize, usize)> {
+        if char_idx <= self.len_chars() {
+            Ok(self.chunks_at_char(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`chunks_at_line_break()`](#method.chunks_at_line_break).
+    #[inline]
+    pub fn try_chunks_at_line_break(
+        &self,
+        line_break_idx: usize,
+    ) -> Result<(Chunks<'_>, usize, usize, usize)> {
+        if line_break_idx <= self.len_lines() {
+            Ok(self.chunks_at_line_break(line_break_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_break_idx, self.len_lines()))
+        }
+    }
+
+    /// Returns the start and end of `char_range`, or an error if it's
+    /// inverted or out of bounds.
+    fn check_char_range<R: CharIdxRange>(&self, char_range: R) -> Result<(usize, usize)> {
+        let start = char_range.start().unwrap_or(0);
+        let end = char_range.end().unwrap_or_else(|| self.len_chars());
+
+        if start > end {
+            Err(Error::CharRangeInvalid(start, end))
+        } else if end > self.len_chars() {
+            Err(Error::CharIndexOutOfBounds(end, self.len_chars()))
+        } else {
+            Ok((start, end))
+        }
+    }
+
+    /// Validates a batch of edits, returning their indices sorted by
+    /// position.
+    fn check_edits(&self, edits: &[Edit]) -> Result<Vec<usize>> {
+        for edit in edits {
+            self.check_char_range(edit.range.clone())?;
+        }
+
+        // Stable, so that insertions at the same index keep their order.
+        let mut order: Vec<usize> = (0..edits.len()).collect();
+        order.sort_by_key(|&i| (edits[i].range.start, edits[i].range.end));
+
+        for pair in order.windows(2) {
+            if edits[pair[0]].range.end > edits[pair[1]].range.start {
+                return Err(Error::EditsOverlap(
+                    pair[0].min(pair[1]),
+                    pair[0].max(pair[1]),
+                ));
+            }
+        }
+
+        Ok(order)
+    }
+
+    /// Applies already-validated edits, in the order given by `order`.
+    fn apply_sorted_edits(&mut self, edits: &[Edit], order: &[usize]) {
+        let replacements: Vec<_> = order
+            .iter()
+            .map(|&i| (edits[i].range.clone(), edits[i].text.as_str()))
+            .collect();
+        self.replace_sorted(&replacements);
+    }
+
+    /// Replaces each char range with its text.  The ranges must be valid,
+    /// sorted, and non-overlapping.
+    ///
+    /// This is done in a single pass down the tree, which only rebuilds the
+    /// nodes that the edits touch, so everything else stays shared with any
+    /// clones of the `Rope`.
+    fn replace_sorted(&mut self, replacements: &[(Range<usize>, &str)]) {
+        let replacements: Vec<_> = replacements
+            .iter()
+            .filter(|&&(ref range, text)| range.start < range.end || !text.is_empty())
+            .cloned()
+            .collect();
+        if replacements.is_empty() {
+            return;
+        }
+
+        // The char index of the start and end of each edit's new text, in
+        // the edited text, which is where the edits may have split CRLF
+        // pairs.  The marks are updated for the whole batch at once, rather
+        // than by each individual edit.
+        let mut seams = Vec::with_capacity(replacements.len() * 2);
+        let mut char_edits = Vec::with_capacity(replacements.len());
+        let mut removed_chars = 0;
+        let mut inserted_chars = 0;
+        for &(ref range, text) in &replacements {
+            let text_chars = count_chars(text);
+            let new_start = range.start - removed_chars + inserted_chars;
+            seams.push(new_start);
+            seams.push(new_start + text_chars);
+            char_edits.push((range.start, range.end, text_chars));
+            removed_chars += range.end - range.start;
+            inserted_chars += text_chars;
+        }
+        self.map_edits(&char_edits);
+
+        let policy = self.line_break_policy;
+        self.root = Node::from_siblings(self.root.replace_sorted(&replacements, policy), policy);
+
+        let len_chars = self.len_chars();
+        for &char_idx in &seams {
+            if char_idx > 0
+                && char_idx < len_chars
+                && self.char(char_idx - 1) == '\r'
+                && self.char(char_idx) == '\n'
+            {
+                let byte_idx = self.char_to_byte(char_idx);
+                Arc::make_mut(&mut self.root).fix_crlf_seam(byte_idx as Count, false, policy);
+            }
+        }
+        self.pull_up_singular_nodes();
+    }
+}
+
+/// Reads all of `reader`'s output, passing each piece of it to `f` as it
+/// is read, followed by an empty last piece.  `f`'s second argument is
+/// whether the piece is the last one.
+fn read_pieces<T, F>(mut reader: T, mut f: F) -> io::Result<()>
+where
+    T: io::Read,
+    F: FnMut(&[u8], bool) -> io::Result<()>,
+{
+    let mut buffer = [0u8; MAX_BYTES * 2];
+    loop {
+        let read_count = match reader.read(&mut buffer) {
+            Ok(read_count) => read_count,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        f(&buffer[..read_count], read_count == 0)?;
+        if read_count == 0 {
+            return Ok(());
+        }
+    }
 }
 
 //==============================================================
@@ -1160,21 +2898,129 @@ impl<'a> std::cmp::PartialEq<Rope> for std::borrow::Cow<'a, str> {
     }
 }
 
+impl std::cmp::Eq for Rope {}
+
+impl std::cmp::Ord for Rope {
+    #[inline]
+    fn cmp(&self, other: &Rope) -> std::cmp::Ordering {
+        self.slice(..).cmp(&other.slice(..))
+    }
+}
+
+impl std::cmp::PartialOrd<Rope> for Rope {
+    #[inline]
+    fn partial_cmp(&self, other: &Rope) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> std::cmp::PartialOrd<&'a str> for Rope {
+    #[inline]
+    fn partial_cmp(&self, other: &&'a str) -> Option<std::cmp::Ordering> {
+        self.slice(..).partial_cmp(other)
+    }
+}
+
+impl<'a> std::cmp::PartialOrd<Rope> for &'a str {
+    #[inline]
+    fn partial_cmp(&self, other: &Rope) -> Option<std::cmp::Ordering> {
+        self.partial_cmp(&other.slice(..))
+    }
+}
+
+impl std::cmp::PartialOrd<str> for Rope {
+    #[inline]
+    fn partial_cmp(&self, other: &str) -> Option<std::cmp::Ordering> {
+        self.slice(..).partial_cmp(other)
+    }
+}
+
+impl std::cmp::PartialOrd<Rope> for str {
+    #[inline]
+    fn partial_cmp(&self, other: &Rope) -> Option<std::cmp::Ordering> {
+        self.partial_cmp(&other.slice(..))
+    }
+}
+
+impl std::cmp::PartialOrd<String> for Rope {
+    #[inline]
+    fn partial_cmp(&self, other: &String) -> Option<std::cmp::Ordering> {
+        self.slice(..).partial_cmp(other.as_str())
+    }
+}
+
+impl std::cmp::PartialOrd<Rope> for String {
+    #[inline]
+    fn partial_cmp(&self, other: &Rope) -> Option<std::cmp::Ordering> {
+        self.as_str().partial_cmp(&other.slice(..))
+    }
+}
+
+impl<'a> std::cmp::PartialOrd<std::borrow::Cow<'a, str>> for Rope {
+    #[inline]
+    fn partial_cmp(&self, other: &std::borrow::Cow<'a, str>) -> Option<std::cmp::Ordering> {
+        self.slice(..).partial_cmp(&**other)
+    }
+}
+
+impl<'a> std::cmp::PartialOrd<Rope> for std::borrow::Cow<'a, str> {
+    #[inline]
+    fn partial_cmp(&self, other: &Rope) -> Option<std::cmp::Ordering> {
+        (**self).partial_cmp(&other.slice(..))
+    }
+}
+
+impl std::hash::Hash for Rope {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.slice(..).hash(state)
+    }
+}
+
 //==============================================================
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use str_utils::byte_to_char_idx;
+    use change_set::ChangeSet;
+    use str_utils::{byte_to_char_idx, byte_to_line_idx, char_to_line_idx};
+    #[cfg(feature = "graphemes")]
+    use unicode_segmentation::UnicodeSegmentation;
 
     // 127 bytes, 103 chars, 1 line
     const TEXT: &str = "Hello there!  How're you doing?  It's \
                         a fine day, isn't it?  Aren't you glad \
                         we're alive?  こんにちは、みんなさん！";
+    // 13 bytes, 12 chars, 6 Unicode lines, 4 LF/CR lines, 3 LF lines
+    const TEXT_BREAKS: &str = "a\u{000C}b\r\nc\rd\u{0085}e\nf";
     // 124 bytes, 100 chars, 4 lines
     const TEXT_LINES: &str = "Hello there!  How're you doing?\nIt's \
                               a fine day, isn't it?\nAren't you glad \
                               we're alive?\nこんにちは、みんなさん！";
+    // 143 bytes, 107 chars, 111 utf16 code units, 1 line
+    const TEXT_EMOJI: &str = "Hello there!🐸  How're you doing?🐸  It's \
+                              a fine day, isn't it?🐸  Aren't you glad \
+                              we're alive?🐸  こんにちは、みんなさん！";
+
+    // Grapheme clusters that are much longer than a chunk (in test builds),
+    // regional indicator pairs, a CRLF pair, and a ZWJ sequence.
+    #[cfg(feature = "graphemes")]
+    const TEXT_GRAPHEMES: &str = "He\u{303}\u{303}\u{303}\u{303}\u{303}\u{303}\u{303}\u{303}llo \
+                                  \u{1F1EC}\u{1F1E7}\u{1F1EF}\u{1F1F5}\u{1F1FA}\u{1F1F8} \
+                                  a\r\nb \u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467} \
+                                  o\u{301}\u{301}\u{301}\u{301}\u{301}\u{301}k!";
+
+    /// Char indices of the grapheme boundaries of `text`, including its
+    /// start and end.
+    #[cfg(feature = "graphemes")]
+    fn grapheme_boundaries(text: &str) -> Vec<usize> {
+        let mut boundaries: Vec<usize> = text
+            .grapheme_indices(true)
+            .map(|(i, _)| byte_to_char_idx(text, i))
+            .collect();
+        boundaries.push(text.chars().count());
+        boundaries
+    }
 
     #[test]
     fn new_01() {
@@ -1649,27 +3495,726 @@ mod tests {
     }
 
     #[test]
-    fn append_05() {
+    fn append_05() {
+        let mut r = Rope::from_str(TEXT);
+        let r2 = Rope::from_str("");
+
+        r.append(r2);
+        assert_eq!(r, TEXT);
+
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn append_06() {
+        let mut r = Rope::from_str("");
+        let r2 = Rope::from_str(TEXT);
+
+        r.append(r2);
+        assert_eq!(r, TEXT);
+
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn apply_edits_01() {
+        let mut r = Rope::from_str(TEXT);
+        r.apply_edits(&[
+            Edit::replace(38..58, "a fine evening"),
+            Edit::remove(0..14),
+        ]);
+
+        assert_eq!(
+            r,
+            "How're you doing?  It's a fine evening?  Aren't you glad \
+             we're alive?  こんにちは、みんなさん！"
+        );
+
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn apply_edits_02() {
+        let mut r = Rope::from_str(TEXT);
+        r.apply_edits(&[
+            Edit::insert(103, "!"),
+            Edit::replace(38..58, "a fine evening"),
+            Edit::insert(0, "> "),
+            Edit::insert(103, "?"),
+            Edit::remove(0..14),
+            Edit::insert(0, "...")
+        ]);
+
+        assert_eq!(
+            r,
+            "> ...How're you doing?  It's a fine evening?  Aren't you glad \
+             we're alive?  こんにちは、みんなさん！!?"
+        );
+
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn apply_edits_03() {
+        // Same edits as apply_edits_02, but on a longer text.
+        let text = TEXT.repeat(4);
+        let mut r = Rope::from_str(&text);
+        r.apply_edits(&[
+            Edit::insert(103, "!"),
+            Edit::replace(38..58, "a fine evening"),
+            Edit::insert(0, "> "),
+            Edit::insert(103, "?"),
+            Edit::remove(0..14),
+            Edit::insert(0, "...")
+        ]);
+
+        assert_eq!(
+            r.slice(..90),
+            "> ...How're you doing?  It's a fine evening?  Aren't you glad \
+             we're alive?  こんにちは、みんなさん！!?"
+        );
+        assert_eq!(r.slice(90..), &text[127..]);
+
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn apply_edits_04() {
+        let mut r = Rope::from_str("\r\n\r\n");
+        r.set_line_break_policy(LineBreakPolicy::Lf);
+        r.apply_edits(&[
+            Edit::insert(1, "\r"),
+            Edit::remove(2..3),
+            Edit::insert(4, "\n"),
+            Edit::insert(0, "\n"),
+        ]);
+
+        assert_eq!(r, "\n\r\r\n\n\n");
+        assert_eq!(LineBreakPolicy::Lf, r.line_break_policy());
+        assert_eq!(5, r.len_lines());
+
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn apply_edits_05() {
+        let mut r = Rope::from_str(TEXT);
+        r.apply_edits(&[]);
+
+        assert_eq!(r, TEXT);
+    }
+
+    #[test]
+    #[should_panic]
+    fn apply_edits_06() {
+        let mut r = Rope::from_str(TEXT);
+        r.apply_edits(&[Edit::insert(5, "a"), Edit::remove(2..6)]);
+    }
+
+    #[test]
+    fn apply_edits_07() {
+        // Only the nodes the edits touch are rebuilt, and everything else
+        // stays shared with the original.
+        fn all_nodes(node: &Arc<Node>, nodes: &mut Vec<Arc<Node>>) {
+            nodes.push(Arc::clone(node));
+            if let Node::Internal(ref children) = **node {
+                for child in children.nodes() {
+                    all_nodes(child, nodes);
+                }
+            }
+        }
+        fn leaf_at(node: &Arc<Node>, char_idx: usize) -> &Arc<Node> {
+            match **node {
+                Node::Leaf(_) => node,
+                Node::Internal(ref children) => {
+                    let (child_i, acc_info) = children.search_char_idx(char_idx);
+                    leaf_at(&children.nodes()[child_i], char_idx - acc_info.chars as usize)
+                }
+            }
+        }
+
+        let text = TEXT.repeat(20);
+        let r1 = Rope::from_str(&text);
+        let mut r2 = r1.clone();
+        r2.apply_edits(&[
+            Edit::replace(50..53, "abc!"),
+            Edit::remove(900..930),
+            Edit::insert(2000, "xyz"),
+        ]);
+
+        let mut old_nodes = Vec::new();
+        let mut new_nodes = Vec::new();
+        all_nodes(&r1.root, &mut old_nodes);
+        all_nodes(&r2.root, &mut new_nodes);
+        let fresh_count = new_nodes
+            .iter()
+            .filter(|node| !old_nodes.iter().any(|old| Arc::ptr_eq(old, node)))
+            .count();
+        assert!(fresh_count <= 3 * 4 * r2.root.depth());
+        assert!(Arc::ptr_eq(leaf_at(&r1.root, 500), leaf_at(&r2.root, 501)));
+        assert!(Arc::ptr_eq(leaf_at(&r1.root, 1500), leaf_at(&r2.root, 1471)));
+
+        let mut expected = text.clone();
+        expected.insert_str(char_to_byte_idx(&text, 2000), "xyz");
+        expected.replace_range(
+            char_to_byte_idx(&text, 900)..char_to_byte_idx(&text, 930),
+            "",
+        );
+        expected.replace_range(
+            char_to_byte_idx(&text, 50)..char_to_byte_idx(&text, 53),
+            "abc!",
+        );
+        assert_eq!(r2, expected);
+        assert_eq!(r1, text);
+        r2.assert_integrity();
+        r2.assert_invariants();
+    }
+
+    #[test]
+    fn apply_edits_08() {
+        // Lots of edits that make and break CRLF pairs, and that remove
+        // and insert text spanning many leaves.
+        let texts = ["", "\n", "\r", "\r\n", "x\r", "\ny", "\u{3042}\r\n\r"];
+        let long_text = "ab\r\n\u{3042}\r".repeat(30);
+        let mut seed = 12345u32;
+        let mut rand = |n: usize| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 8) as usize % n
+        };
+
+        let mut text = "\r\n\r\na\rb\nc\u{3042}\r".repeat(40);
+        let mut r = Rope::from_str(&text);
+        for _ in 0..50 {
+            let len = r.len_chars();
+            let mut points: Vec<_> = (0..(rand(12) * 2)).map(|_| rand(len + 1)).collect();
+            points.sort();
+            let edits: Vec<_> = points
+                .chunks(2)
+                .map(|pair| {
+                    let ins_text = match rand(10) {
+                        0 => long_text.as_str(),
+                        i => texts[i % texts.len()],
+                    };
+                    Edit::replace(pair[0]..pair[1], ins_text)
+                })
+                .collect();
+
+            r.apply_edits(&edits);
+            for edit in edits.iter().rev() {
+                let start = char_to_byte_idx(&text, edit.range.start);
+                let end = char_to_byte_idx(&text, edit.range.end);
+                text.replace_range(start..end, &edit.text);
+            }
+
+            assert_eq!(r, text);
+            r.assert_integrity();
+            r.assert_invariants();
+        }
+    }
+
+    #[test]
+    fn try_apply_edits_01() {
+        let mut r = Rope::from_str(TEXT);
+
+        assert_eq!(
+            Err(Error::EditsOverlap(1, 2)),
+            r.try_apply_edits(&[
+                Edit::insert(0, "a"),
+                Edit::remove(4..10),
+                Edit::replace(2..5, "b"),
+            ])
+        );
+        assert_eq!(
+            Err(Error::CharIndexOutOfBounds(104, 103)),
+            r.try_apply_edits(&[Edit::insert(0, "a"), Edit::remove(100..104)])
+        );
+        assert_eq!(
+            Err(Error::CharRangeInvalid(5, 4)),
+            r.try_apply_edits(&[Edit::insert(0, "a"), Edit::remove(5..4)])
+        );
+        assert_eq!(r, TEXT);
+
+        assert_eq!(
+            Ok(()),
+            r.try_apply_edits(&[Edit::insert(4, "a"), Edit::remove(2..4)])
+        );
+        assert_eq!(r.slice(..10), "Heao there");
+    }
+
+    #[test]
+    fn replace_all_01() {
+        let mut r = Rope::from_str(TEXT);
+        let report = r.replace_all("you", "thou");
+
+        assert_eq!(r, TEXT.replace("you", "thou"));
+        assert_eq!(
+            report,
+            vec![
+                Replacement {
+                    old_range: 21..24,
+                    new_range: 21..25,
+                },
+                Replacement {
+                    old_range: 68..71,
+                    new_range: 69..73,
+                },
+            ]
+        );
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn replace_all_02() {
+        // Enough matches that the rope gets rebuilt.
+        let mut r = Rope::from_str(TEXT);
+        let report = r.replace_all(" ", "");
+
+        assert_eq!(r, TEXT.replace(" ", ""));
+        assert_eq!(report.len(), 20);
+        for (i, replacement) in report.iter().enumerate() {
+            let new_start = replacement.old_range.start - i;
+            assert_eq!(replacement.new_range, new_start..new_start);
+        }
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn replace_all_03() {
+        let mut r = Rope::from_str("こんにちは");
+        let report = r.replace_all("", "-");
+
+        assert_eq!(r, "-こ-ん-に-ち-は-");
+        assert_eq!(report.len(), 6);
+        assert_eq!(report[5].old_range, 5..5);
+        assert_eq!(report[5].new_range, 10..11);
+
+        assert!(r.replace_all("x", "y").is_empty());
+        assert_eq!(r, "-こ-ん-に-ち-は-");
+    }
+
+    #[test]
+    fn replace_all_04() {
+        let mut r = Rope::from_str(TEXT);
+        let a = r.add_mark(22, Assoc::Before);
+        let b = r.add_mark(75, Assoc::Before);
+        r.replace_all("you", "thou");
+
+        assert_eq!(r.mark_char(a), Some(25));
+        assert_eq!(r.mark_char(b), Some(77));
+    }
+
+    #[test]
+    fn replace_in_01() {
+        let mut r = Rope::from_str(TEXT);
+        let report = r.replace_in(21..70, "you", "thou");
+
+        assert_eq!(report.len(), 1);
+        assert_eq!(r.slice(..40), "Hello there!  How're thou doing?  It's a");
+        assert_eq!(r.slice(65..72), "n't you");
+
+        let report = r.replace_in(..5, "l", "");
+        assert_eq!(report[1].new_range, 2..2);
+        assert_eq!(r.slice(..10), "Heo there!");
+    }
+
+    #[test]
+    #[should_panic]
+    fn replace_in_02() {
+        let mut r = Rope::from_str(TEXT);
+        r.replace_in(50..104, "you", "thou");
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn replace_all_regex_01() {
+        let mut r = Rope::from_str(TEXT);
+        let m = r.add_mark(23, Assoc::Before);
+        let re = Regex::new(r"(\w+)'(\w+)").unwrap();
+        let report = r.replace_all_regex(&re, "$2'${1}");
+
+        assert_eq!(
+            r,
+            "Hello there!  re'How you doing?  s'It a fine day, t'isn it?  \
+             t'Aren you glad re'we alive?  こんにちは、みんなさん！"
+        );
+        assert_eq!(report.len(), 5);
+        assert_eq!(report[0].old_range, 14..20);
+        assert_eq!(report[0].new_range, 14..20);
+        assert_eq!(report[4].old_range, 77..82);
+        assert_eq!(report[4].new_range, 77..82);
+        assert_eq!(r.mark_char(m), Some(23));
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn replace_all_regex_02() {
+        // Empty matches, and templates of different lengths.
+        let mut r = Rope::from_str("xaay");
+        let report = r.replace_all_regex(&Regex::new("a*").unwrap(), "<$0>");
+
+        assert_eq!(r, "<>x<aa>y<>");
+        let new_ranges: Vec<_> = report.iter().map(|rep| rep.new_range.clone()).collect();
+        assert_eq!(new_ranges, vec![0..2, 3..7, 8..10]);
+    }
+
+    #[test]
+    fn try_replace_in_01() {
+        let mut r = Rope::from_str(TEXT);
+        assert_eq!(
+            Err(Error::CharIndexOutOfBounds(104, 103)),
+            r.try_replace_in(50..104, "you", "thou")
+        );
+        assert_eq!(
+            Err(Error::CharRangeInvalid(51, 50)),
+            r.try_replace_in(51..50, "you", "thou")
+        );
+        assert_eq!(r, TEXT);
+        assert_eq!(r.try_replace_in(50.., "you", "thou").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn line_ending_stats_01() {
+        let r = Rope::from_str(
+            "a\nb\r\nc\rd\u{85}e\u{2028}f\u{2029}g\u{0B}h\u{0C}\r\r\n\n\u{2027}\u{A8}",
+        );
+        let stats = r.line_ending_stats();
+        assert_eq!(
+            stats,
+            LineEndingStats {
+                lf: 2,
+                crlf: 2,
+                cr: 2,
+                nel: 1,
+                ls: 1,
+                ps: 1,
+                vt: 1,
+                ff: 1,
+            }
+        );
+        assert_eq!(stats.dominant(), Some(LineEnding::Lf));
+        assert_eq!(Rope::from_str("\u{2028}").line_ending_stats().dominant(), None);
+
+        // Lots of CRLFs, across many chunks.
+        let r = Rope::from_str(&"Hello\r\n".repeat(100));
+        assert_eq!(r.line_ending_stats().crlf, 100);
+        assert_eq!(r.line_ending_stats().dominant(), Some(LineEnding::Crlf));
+    }
+
+    #[test]
+    fn normalize_line_endings_01() {
+        let mut r = Rope::from_str("a\nb\r\nc\rd\u{2028}\r\r\n\n");
+        let report = r.normalize_line_endings(LineEnding::Crlf);
+
+        assert_eq!(r, "a\r\nb\r\nc\r\nd\u{2028}\r\n\r\n\r\n");
+        let old_ranges: Vec<_> = report.iter().map(|rep| rep.old_range.clone()).collect();
+        let new_ranges: Vec<_> = report.iter().map(|rep| rep.new_range.clone()).collect();
+        assert_eq!(old_ranges, vec![1..2, 6..7, 9..10, 12..13]);
+        assert_eq!(new_ranges, vec![1..3, 7..9, 11..13, 15..17]);
+
+        r.normalize_line_endings(LineEnding::Lf);
+        assert_eq!(r, "a\nb\nc\nd\u{2028}\n\n\n");
+        assert!(r.normalize_line_endings(LineEnding::Lf).is_empty());
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn normalize_line_endings_02() {
+        // Ranges that split CRLF pairs leave them alone.
+        let mut r = Rope::from_str("a\r\nb\nc\r\nd");
+        let report = r.normalize_line_endings_in(2..7, LineEnding::Cr);
+        assert_eq!(r, "a\r\nb\rc\r\nd");
+        assert_eq!(report.len(), 1);
+
+        let report = r.normalize_line_endings_in(2..8, LineEnding::Cr);
+        assert_eq!(r, "a\r\nb\rc\rd");
+        assert_eq!(report[0].old_range, 6..8);
+        assert_eq!(report[0].new_range, 6..7);
+
+        // Conversions that would join with a line ending outside the range
+        // are skipped.
+        let mut r = Rope::from_str("a\n\nb");
+        assert!(r.normalize_line_endings_in(1..2, LineEnding::Cr).is_empty());
+        assert_eq!(r, "a\n\nb");
+        assert_eq!(r.len_lines(), 3);
+        assert_eq!(r.normalize_line_endings_in(1..3, LineEnding::Cr).len(), 2);
+        assert_eq!(r, "a\r\rb");
+        assert_eq!(r.len_lines(), 3);
+
+        let mut r = Rope::from_str("a\r\r\nb");
+        assert!(r.normalize_line_endings_in(2.., LineEnding::Lf).is_empty());
+        assert_eq!(r, "a\r\r\nb");
+        assert_eq!(r.len_lines(), 3);
+        r.normalize_line_endings_in(1.., LineEnding::Lf);
+        assert_eq!(r, "a\n\nb");
+
+        // A big text, with conversions all through it.
+        let mut r = Rope::from_str(TEXT);
+        r.normalize_line_endings(LineEnding::Lf);
+        assert_eq!(r, TEXT.replace("\r\n", "\n"));
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn try_normalize_line_endings_in_01() {
+        let mut r = Rope::from_str(TEXT_BREAKS);
+        assert_eq!(
+            Err(Error::CharIndexOutOfBounds(13, 12)),
+            r.try_normalize_line_endings_in(5..13, LineEnding::Lf)
+        );
+        assert_eq!(r, TEXT_BREAKS);
+        assert_eq!(
+            r.try_normalize_line_endings_in(5.., LineEnding::Lf).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn insert_line_ending_01() {
+        let mut r = Rope::from_str("one\r\ntwo\r\n");
+        assert_eq!(r.insert_line_ending(), None);
+        r.insert(0, "a\n");
+        assert_eq!(r, "a\none\r\ntwo\r\n");
+
+        r.set_insert_line_ending(Some(LineEnding::Crlf));
+        r.insert(2, "b\nc\rd\r\n");
+        r.insert(0, "\n");
+        assert_eq!(r, "\r\na\nb\r\nc\r\nd\r\none\r\ntwo\r\n");
+
+        // Single chars and batch edits are inserted as-is, and clones and
+        // splits keep the setting.
+        r.insert_char(0, '\r');
+        r.insert_char(1, '\n');
+        assert_eq!(r.slice(..4), "\r\n\r\n");
+        r.apply_edits(&[Edit::insert(0, "\n")]);
+        assert_eq!(r.char(1), '\r');
+        let mut right = r.clone().split_off(5);
+        assert_eq!(right.insert_line_ending(), Some(LineEnding::Crlf));
+        right.insert(0, "\r");
+        assert_eq!(right.char(1), '\n');
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn insert_line_ending_02() {
+        // Converted line endings don't join with the ones next to them.
+        let mut r = Rope::from_str("a\rb");
+        r.set_insert_line_ending(Some(LineEnding::Lf));
+        r.insert(2, "\nx");
+        assert_eq!(r, "a\r\r\nxb");
+        r.insert(2, "\ry\r\n");
+        assert_eq!(r, "a\r\ry\n\r\nxb");
+        assert_eq!(r.len_lines(), 5);
+
+        let mut r = Rope::from_str("a\nb");
+        r.set_insert_line_ending(Some(LineEnding::Cr));
+        r.insert(1, "x\n");
+        assert_eq!(r, "ax\n\nb");
+        r.insert(2, "\r");
+        assert_eq!(r, "ax\r\n\n\nb");
+        assert_eq!(r.len_lines(), 4);
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn marks_01() {
+        let mut r = Rope::from_str(TEXT);
+        let a = r.add_mark(6, Assoc::Before);
+        let b = r.add_mark(6, Assoc::After);
+        let c = r.add_mark(20, Assoc::Before);
+
+        r.insert(6, "you ");
+        assert_eq!(r.mark_char(a), Some(6));
+        assert_eq!(r.mark_char(b), Some(10));
+        assert_eq!(r.mark_char(c), Some(24));
+
+        r.remove(0..8);
+        assert_eq!(r.mark_char(a), Some(0));
+        assert_eq!(r.mark_char(b), Some(2));
+        assert_eq!(r.mark_char(c), Some(16));
+        assert_eq!(r.slice(2..8), "there!");
+    }
+
+    #[test]
+    fn marks_02() {
+        // Insertions large enough to be done by splitting and appending.
+        let mut r = Rope::from_str(TEXT);
+        let a = r.add_mark(50, Assoc::Before);
+        let b = r.add_mark(50, Assoc::After);
+        let c = r.add_mark(51, Assoc::Before);
+        let d = r.add_mark(0, Assoc::After);
+        let e = r.add_mark(103, Assoc::Before);
+
+        r.insert(50, TEXT);
+        assert_eq!(r.mark_char(a), Some(50));
+        assert_eq!(r.mark_char(b), Some(153));
+        assert_eq!(r.mark_char(c), Some(154));
+        assert_eq!(r.mark_char(d), Some(0));
+        assert_eq!(r.mark_char(e), Some(206));
+        assert_eq!(r.marks().count(), 5);
+    }
+
+    #[test]
+    fn marks_03() {
+        let mut r = Rope::from_str(TEXT);
+        let a = r.add_mark(10, Assoc::Before);
+        let b = r.add_mark(10, Assoc::After);
+        let c = r.add_mark(50, Assoc::Before);
+
+        let mut r2 = r.split_off(10);
+        assert_eq!(r.mark_char(a), Some(10));
+        assert_eq!(r.mark_char(b), None);
+        assert_eq!(r.mark_char(c), None);
+        assert_eq!(r2.mark_char(a), None);
+        assert_eq!(r2.mark_char(b), Some(0));
+        assert_eq!(r2.mark_char(c), Some(40));
+
+        r2.insert(0, "xx");
+        r.append(r2);
+        assert_eq!(r.mark_char(a), Some(10));
+        assert_eq!(r.mark_char(b), Some(12));
+        assert_eq!(r.mark_char(c), Some(52));
+    }
+
+    #[test]
+    fn marks_04() {
+        // Splitting at the ends.
+        let mut r = Rope::from_str(TEXT);
+        let a = r.add_mark(0, Assoc::Before);
+        let b = r.add_mark(0, Assoc::After);
+        let c = r.add_mark(103, Assoc::Before);
+        let d = r.add_mark(103, Assoc::After);
+
+        let mut r2 = r.split_off(0);
+        assert_eq!(r, "");
+        assert_eq!(r.marks().collect::<Vec<_>>(), vec![(a, 0)]);
+        assert_eq!(r2.marks().collect::<Vec<_>>(), vec![(b, 0), (c, 103), (d, 103)]);
+
+        let r3 = r2.split_off(103);
+        assert_eq!(r3, "");
+        assert_eq!(r2.marks().collect::<Vec<_>>(), vec![(b, 0), (c, 103)]);
+        assert_eq!(r3.marks().collect::<Vec<_>>(), vec![(d, 0)]);
+    }
+
+    #[test]
+    fn marks_05() {
+        // Appending a clone keeps just the original marks.
+        let mut r = Rope::from_str(TEXT);
+        let a = r.add_mark(5, Assoc::Before);
+        let r2 = r.clone();
+        assert_eq!(r2.mark_char(a), Some(5));
+
+        r.append(r2);
+        assert_eq!(r.marks().collect::<Vec<_>>(), vec![(a, 5)]);
+    }
+
+    #[test]
+    fn marks_06() {
+        // Both a text that is a single leaf and one that isn't.
+        for text in &[TEXT, "Hello world!"] {
+            let mut r = Rope::from_str(text);
+            let marks = [
+                r.add_mark(3, Assoc::Before),
+                r.add_mark(3, Assoc::After),
+                r.add_mark(4, Assoc::Before),
+                r.add_mark(6, Assoc::Before),
+                r.add_mark(6, Assoc::After),
+                r.add_mark(10, Assoc::Before),
+            ];
+
+            r.apply_edits(&[Edit::insert(6, "abcd"), Edit::replace(3..6, "XY")]);
+            let positions: Vec<_> = marks.iter().map(|&m| r.mark_char(m).unwrap()).collect();
+            assert_eq!(positions, vec![3, 5, 5, 5, 9, 13]);
+        }
+    }
+
+    #[test]
+    fn marks_07() {
+        // Marks move the same way as `ChangeSet::map_pos()`.
+        let mut cs = ChangeSet::new(103);
+        cs.retain(5);
+        cs.delete(10);
+        cs.insert("abc");
+        cs.retain(20);
+        cs.insert("de");
+        cs.delete(3);
+
+        let mut r = Rope::from_str(TEXT);
+        let mut marks = Vec::new();
+        for i in 0..104 {
+            for &gravity in &[Assoc::Before, Assoc::After] {
+                marks.push((r.add_mark(i, gravity), i, gravity));
+            }
+        }
+
+        cs.apply(&mut r);
+        for &(mark, char_idx, gravity) in &marks {
+            assert_eq!(r.mark_char(mark), Some(cs.map_pos(char_idx, gravity)));
+        }
+    }
+
+    #[test]
+    fn marks_08() {
+        let mut r = Rope::from_str(TEXT_LINES);
+        let a = r.add_mark(40, Assoc::Before);
+        let b = r.add_mark(95, Assoc::Before);
+        assert_eq!(r.mark_byte(a), Some(40));
+        assert_eq!(r.mark_line(a), Some(1));
+        assert_eq!(r.mark_byte(b), Some(r.char_to_byte(95)));
+        assert_eq!(r.mark_line(b), Some(3));
+        assert_eq!(r.mark_gravity(b), Some(Assoc::Before));
+
+        r.shrink_to_fit();
+        assert_eq!(r.mark_char(a), Some(40));
+
+        assert_eq!(r.remove_mark(a), Some(40));
+        assert_eq!(r.remove_mark(a), None);
+        assert_eq!(r.mark_char(a), None);
+        assert_eq!(r.mark_byte(a), None);
+        assert_eq!(r.mark_line(a), None);
+        assert_eq!(r.marks().collect::<Vec<_>>(), vec![(b, 95)]);
+
+        r.clear_marks();
+        assert_eq!(r.marks().count(), 0);
+    }
+
+    #[test]
+    fn marks_09() {
+        // Marks don't affect equality.
         let mut r = Rope::from_str(TEXT);
-        let r2 = Rope::from_str("");
-
-        r.append(r2);
-        assert_eq!(r, TEXT);
-
-        r.assert_integrity();
-        r.assert_invariants();
+        r.add_mark(5, Assoc::Before);
+        assert_eq!(r, Rope::from_str(TEXT));
     }
 
     #[test]
-    fn append_06() {
-        let mut r = Rope::from_str("");
-        let r2 = Rope::from_str(TEXT);
-
-        r.append(r2);
-        assert_eq!(r, TEXT);
+    #[should_panic]
+    fn add_mark_01() {
+        let mut r = Rope::from_str(TEXT);
+        r.add_mark(104, Assoc::Before);
+    }
 
-        r.assert_integrity();
-        r.assert_invariants();
+    #[test]
+    fn try_add_mark_01() {
+        let mut r = Rope::from_str(TEXT);
+        assert_eq!(
+            Err(Error::CharIndexOutOfBounds(104, 103)),
+            r.try_add_mark(104, Assoc::Before)
+        );
+        let a = r.try_add_mark(103, Assoc::Before).unwrap();
+        assert_eq!(r.mark_char(a), Some(103));
     }
 
     #[test]
@@ -1874,6 +4419,72 @@ mod tests {
         r.line_to_char(5);
     }
 
+    #[test]
+    fn len_utf16_cu_01() {
+        let r = Rope::from_str(TEXT);
+        assert_eq!(r.len_utf16_cu(), 103);
+    }
+
+    #[test]
+    fn len_utf16_cu_02() {
+        let r = Rope::from_str(TEXT_EMOJI);
+        assert_eq!(r.len_utf16_cu(), 111);
+    }
+
+    #[test]
+    fn char_to_utf16_cu_01() {
+        let r = Rope::from_str(TEXT_EMOJI);
+
+        assert_eq!(0, r.char_to_utf16_cu(0));
+        assert_eq!(12, r.char_to_utf16_cu(12));
+        assert_eq!(14, r.char_to_utf16_cu(13));
+        assert_eq!(33, r.char_to_utf16_cu(32));
+        assert_eq!(35, r.char_to_utf16_cu(33));
+        assert_eq!(63, r.char_to_utf16_cu(61));
+        assert_eq!(65, r.char_to_utf16_cu(62));
+        assert_eq!(95, r.char_to_utf16_cu(92));
+        assert_eq!(97, r.char_to_utf16_cu(93));
+        assert_eq!(111, r.char_to_utf16_cu(107));
+    }
+
+    #[test]
+    #[should_panic]
+    fn char_to_utf16_cu_02() {
+        let r = Rope::from_str(TEXT_EMOJI);
+        r.char_to_utf16_cu(108);
+    }
+
+    #[test]
+    fn utf16_cu_to_char_01() {
+        let r = Rope::from_str(TEXT_EMOJI);
+
+        assert_eq!(0, r.utf16_cu_to_char(0));
+        assert_eq!(12, r.utf16_cu_to_char(12));
+        assert_eq!(12, r.utf16_cu_to_char(13));
+        assert_eq!(13, r.utf16_cu_to_char(14));
+        assert_eq!(32, r.utf16_cu_to_char(33));
+        assert_eq!(32, r.utf16_cu_to_char(34));
+        assert_eq!(33, r.utf16_cu_to_char(35));
+        assert_eq!(92, r.utf16_cu_to_char(96));
+        assert_eq!(93, r.utf16_cu_to_char(97));
+        assert_eq!(107, r.utf16_cu_to_char(111));
+    }
+
+    #[test]
+    #[should_panic]
+    fn utf16_cu_to_char_02() {
+        let r = Rope::from_str(TEXT_EMOJI);
+        r.utf16_cu_to_char(112);
+    }
+
+    #[test]
+    fn utf16_cu_char_round_trip() {
+        let r = Rope::from_str(TEXT_EMOJI);
+        for i in 0..(r.len_chars() + 1) {
+            assert_eq!(i, r.utf16_cu_to_char(r.char_to_utf16_cu(i)));
+        }
+    }
+
     #[test]
     fn char_01() {
         let r = Rope::from_str(TEXT);
@@ -1943,6 +4554,111 @@ mod tests {
         r.line(4);
     }
 
+    #[test]
+    fn line_break_policy_01() {
+        let mut r = Rope::from_str(TEXT_BREAKS);
+        assert_eq!(LineBreakPolicy::Unicode, r.line_break_policy());
+        assert_eq!(6, r.len_lines());
+
+        r.set_line_break_policy(LineBreakPolicy::LfCr);
+        assert_eq!(4, r.len_lines());
+
+        r.set_line_break_policy(LineBreakPolicy::Lf);
+        assert_eq!(3, r.len_lines());
+
+        r.set_line_break_policy(LineBreakPolicy::Unicode);
+        assert_eq!(6, r.len_lines());
+    }
+
+    #[test]
+    fn line_break_policy_02() {
+        let mut r = Rope::from_str(TEXT_BREAKS);
+        r.set_line_break_policy(LineBreakPolicy::Lf);
+
+        assert_eq!("a\u{000C}b\r\n", r.line(0));
+        assert_eq!("c\rd\u{0085}e\n", r.line(1));
+        assert_eq!("f", r.line(2));
+        assert_eq!(0, r.char_to_line(4));
+        assert_eq!(1, r.char_to_line(6));
+        assert_eq!(1, r.byte_to_line(10));
+        assert_eq!(11, r.line_to_char(2));
+        assert_eq!(12, r.line_to_byte(2));
+
+        let lines: Vec<_> = r.lines().collect();
+        assert_eq!(lines, ["a\u{000C}b\r\n", "c\rd\u{0085}e\n", "f"]);
+    }
+
+    #[test]
+    fn line_break_policy_03() {
+        let mut r = Rope::from_str(TEXT_BREAKS);
+        r.set_line_break_policy(LineBreakPolicy::LfCr);
+
+        assert_eq!("c\r", r.line(1));
+        assert_eq!(2, r.char_to_line(7));
+        assert_eq!(7, r.line_to_char(2));
+
+        let lines: Vec<_> = r.lines().collect();
+        assert_eq!(lines, ["a\u{000C}b\r\n", "c\r", "d\u{0085}e\n", "f"]);
+    }
+
+    #[test]
+    fn line_break_policy_04() {
+        // Line break counts must stay accurate through edits under a
+        // non-default policy, including ones that join or split CRLF pairs.
+        let mut r = Rope::from_str(&TEXT_BREAKS.repeat(8));
+        r.set_line_break_policy(LineBreakPolicy::Lf);
+        assert_eq!(17, r.len_lines());
+
+        r.insert(3, "\u{000C}\n");
+        r.insert(1, "\r");
+        r.remove(20..21);
+        r.assert_integrity();
+        r.assert_invariants();
+        let text = r.to_string();
+        assert_eq!(text.matches('\n').count() + 1, r.len_lines());
+
+        let r2 = r.split_off(30);
+        assert_eq!(LineBreakPolicy::Lf, r2.line_break_policy());
+        assert_eq!(text[r.len_bytes()..].matches('\n').count() + 1, r2.len_lines());
+
+        r.append(r2);
+        assert_eq!(LineBreakPolicy::Lf, r.line_break_policy());
+        assert_eq!(text.matches('\n').count() + 1, r.len_lines());
+    }
+
+    #[test]
+    fn line_break_policy_05() {
+        let mut r = Rope::from_str(TEXT_BREAKS);
+        r.set_line_break_policy(LineBreakPolicy::Lf);
+
+        let s = r.slice(2..);
+        assert_eq!(LineBreakPolicy::Lf, s.line_break_policy());
+        assert_eq!(3, s.len_lines());
+        assert_eq!("c\rd\u{0085}e\n", s.line(1));
+
+        let r2 = s.to_rope();
+        assert_eq!(LineBreakPolicy::Lf, r2.line_break_policy());
+        assert_eq!(3, r2.len_lines());
+    }
+
+    #[test]
+    fn line_break_policy_06() {
+        // Changing a clone's policy leaves the original's counts alone, and
+        // appending a `Rope` with another policy counts its lines again.
+        let mut r = Rope::from_str(&TEXT_BREAKS.repeat(8));
+        let mut r2 = r.clone();
+        r2.set_line_break_policy(LineBreakPolicy::Lf);
+        r2.assert_integrity();
+        assert_eq!(41, r.len_lines());
+        assert_eq!(17, r2.len_lines());
+
+        r.append(r2);
+        r.assert_integrity();
+        r.assert_invariants();
+        assert_eq!(LineBreakPolicy::Unicode, r.line_break_policy());
+        assert_eq!(81, r.len_lines());
+    }
+
     #[test]
     fn chunk_at_byte() {
         let r = Rope::from_str(TEXT_LINES);
@@ -2032,6 +4748,140 @@ mod tests {
         }
     }
 
+    #[test]
+    fn bytes_at_01() {
+        let r = Rope::from_str(TEXT_LINES);
+        let text = TEXT_LINES;
+
+        for i in 0..(r.len_bytes() + 1) {
+            assert!(r.bytes_at(i).eq(text.bytes().skip(i)));
+
+            let mut bytes = r.bytes_at(i);
+            let mut prev_bytes = Vec::new();
+            while let Some(b) = bytes.prev() {
+                prev_bytes.push(b);
+            }
+            prev_bytes.reverse();
+            assert_eq!(&prev_bytes[..], &text.as_bytes()[..i]);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn bytes_at_02() {
+        let r = Rope::from_str(TEXT_LINES);
+        r.bytes_at(r.len_bytes() + 1);
+    }
+
+    #[test]
+    fn chars_at_01() {
+        let r = Rope::from_str(TEXT_LINES);
+        let text = TEXT_LINES;
+
+        for i in 0..(r.len_chars() + 1) {
+            assert!(r.chars_at(i).eq(text.chars().skip(i)));
+
+            let mut chars = r.chars_at(i);
+            let mut prev_chars = Vec::new();
+            while let Some(c) = chars.prev() {
+                prev_chars.push(c);
+            }
+            prev_chars.reverse();
+            assert!(prev_chars.into_iter().eq(text.chars().take(i)));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn chars_at_02() {
+        let r = Rope::from_str(TEXT_LINES);
+        r.chars_at(r.len_chars() + 1);
+    }
+
+    #[test]
+    fn lines_at_01() {
+        let r = Rope::from_str(TEXT_LINES);
+
+        let lines: Vec<_> = r.lines().collect();
+        for i in 0..(r.len_lines() + 1) {
+            let mut itr = r.lines_at(i);
+            assert!(r.lines_at(i).eq(lines[i..].iter().cloned()));
+            for j in (0..i).rev() {
+                assert_eq!(lines[j], itr.prev().unwrap());
+            }
+            assert!(itr.prev().is_none());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn lines_at_02() {
+        let r = Rope::from_str(TEXT_LINES);
+        r.lines_at(r.len_lines() + 1);
+    }
+
+    #[test]
+    fn chunks_at_byte_01() {
+        let r = Rope::from_str(TEXT_LINES);
+        let text = TEXT_LINES;
+
+        for i in 0..(r.len_bytes() + 1) {
+            let (mut chunks, b, c, l) = r.chunks_at_byte(i);
+            let (chunk, b2, c2, l2) = r.chunk_at_byte(i);
+            assert_eq!((b, c, l), (b2, c2, l2));
+            if i < r.len_bytes() {
+                assert_eq!(Some(chunk), chunks.next());
+                assert_eq!(Some(chunk), chunks.prev());
+            } else {
+                assert!(chunks.next().is_none());
+            }
+
+            let mut prev_text = String::new();
+            while let Some(chunk) = chunks.prev() {
+                prev_text.insert_str(0, chunk);
+            }
+            if i < r.len_bytes() {
+                assert_eq!(&text[..b], prev_text);
+            } else {
+                assert_eq!(text, prev_text);
+            }
+        }
+    }
+
+    #[test]
+    fn chunks_at_char_01() {
+        let r = Rope::from_str(TEXT_LINES);
+
+        for i in 0..(r.len_chars() + 1) {
+            let (mut chunks, b, c, l) = r.chunks_at_char(i);
+            let (chunk, b2, c2, l2) = r.chunk_at_char(i);
+            assert_eq!((b, c, l), (b2, c2, l2));
+            if i < r.len_chars() {
+                assert_eq!(Some(chunk), chunks.next());
+            } else {
+                assert!(chunks.next().is_none());
+                assert_eq!(Some(chunk), chunks.prev());
+            }
+        }
+    }
+
+    #[test]
+    fn chunks_at_line_break_01() {
+        let r = Rope::from_str(TEXT_LINES);
+        let text = TEXT_LINES;
+
+        for i in 0..(r.len_lines() + 1) {
+            let (chunks, b, c, l) = r.chunks_at_line_break(i);
+            let (_, b2, c2, l2) = r.chunk_at_line_break(i);
+            assert_eq!((b, c, l), (b2, c2, l2));
+            if i < r.len_lines() {
+                assert_eq!(chunks.collect::<String>(), &text[b..]);
+            } else {
+                assert_eq!(chunks.count(), 0);
+            }
+        }
+    }
+
     #[test]
     fn slice_01() {
         let r = Rope::from_str(TEXT);
@@ -2082,6 +4932,125 @@ mod tests {
         r.slice(102..104);
     }
 
+    #[test]
+    fn byte_slice_01() {
+        let r = Rope::from_str(TEXT);
+        let s = r.byte_slice(109..121);
+
+        assert_eq!(&TEXT[109..121], s);
+        assert_eq!(TEXT, r.byte_slice(..));
+        assert_eq!("", r.byte_slice(127..));
+    }
+
+    #[test]
+    #[should_panic]
+    fn byte_slice_02() {
+        let r = Rope::from_str(TEXT);
+        r.byte_slice(110..121);
+    }
+
+    #[test]
+    fn try_insert_01() {
+        let mut r = Rope::from_str(TEXT);
+
+        assert_eq!(Ok(()), r.try_insert(103, "!"));
+        assert_eq!(
+            Err(Error::CharIndexOutOfBounds(105, 104)),
+            r.try_insert(105, "!")
+        );
+        assert_eq!(
+            Err(Error::CharIndexOutOfBounds(105, 104)),
+            r.try_insert_char(105, '!')
+        );
+        assert_eq!(r, &[TEXT, "!"].concat()[..]);
+    }
+
+    #[test]
+    fn try_remove_01() {
+        let mut r = Rope::from_str(TEXT);
+
+        assert_eq!(Err(Error::CharRangeInvalid(10, 5)), r.try_remove(10..5));
+        assert_eq!(
+            Err(Error::CharIndexOutOfBounds(104, 103)),
+            r.try_remove(5..104)
+        );
+        assert_eq!(r, TEXT);
+
+        assert_eq!(Ok(()), r.try_remove(5..));
+        assert_eq!(r, "Hello");
+    }
+
+    #[test]
+    fn try_split_off_01() {
+        let mut r = Rope::from_str(TEXT);
+
+        assert!(r.try_split_off(104).is_err());
+        assert_eq!(r, TEXT);
+        assert_eq!(&TEXT[5..], r.try_split_off(5).unwrap());
+        assert_eq!(r, "Hello");
+    }
+
+    #[test]
+    fn try_index_conversion_01() {
+        let r = Rope::from_str(TEXT_LINES);
+
+        assert_eq!(Ok(100), r.try_byte_to_char(124));
+        assert_eq!(Err(Error::ByteIndexOutOfBounds(125, 124)), r.try_byte_to_char(125));
+        assert_eq!(Err(Error::ByteIndexOutOfBounds(125, 124)), r.try_byte_to_line(125));
+        assert_eq!(Ok(124), r.try_char_to_byte(100));
+        assert_eq!(Err(Error::CharIndexOutOfBounds(101, 100)), r.try_char_to_byte(101));
+        assert_eq!(Err(Error::CharIndexOutOfBounds(101, 100)), r.try_char_to_line(101));
+        assert_eq!(Ok(100), r.try_line_to_char(4));
+        assert_eq!(Err(Error::LineIndexOutOfBounds(5, 4)), r.try_line_to_char(5));
+        assert_eq!(Err(Error::LineIndexOutOfBounds(5, 4)), r.try_line_to_byte(5));
+        assert_eq!(Err(Error::CharIndexOutOfBounds(101, 100)), r.try_char_to_utf16_cu(101));
+        assert_eq!(Err(Error::Utf16IndexOutOfBounds(101, 100)), r.try_utf16_cu_to_char(101));
+    }
+
+    #[test]
+    fn try_fetch_01() {
+        let r = Rope::from_str(TEXT_LINES);
+
+        assert_eq!(Ok('！'), r.try_char(99));
+        assert_eq!(Err(Error::CharIndexOutOfBounds(100, 100)), r.try_char(100));
+        assert_eq!("こんにちは、みんなさん！", r.try_line(3).unwrap());
+        assert_eq!(Err(Error::LineIndexOutOfBounds(4, 4)), r.try_line(4));
+        assert!(r.try_chunk_at_byte(124).is_ok());
+        assert_eq!(Err(Error::ByteIndexOutOfBounds(125, 124)), r.try_chunk_at_byte(125));
+        assert_eq!(Err(Error::CharIndexOutOfBounds(101, 100)), r.try_chunk_at_char(101));
+        assert_eq!(Err(Error::LineIndexOutOfBounds(5, 4)), r.try_chunk_at_line_break(5));
+    }
+
+    #[test]
+    fn try_slice_01() {
+        let r = Rope::from_str(TEXT);
+
+        assert_eq!(&TEXT[5..21], r.try_slice(5..21).unwrap());
+        assert_eq!(Err(Error::CharRangeInvalid(21, 5)), r.try_slice(21..5));
+        assert_eq!(Err(Error::CharIndexOutOfBounds(104, 103)), r.try_slice(5..104));
+
+        assert_eq!(&TEXT[109..121], r.try_byte_slice(109..121).unwrap());
+        assert_eq!(Err(Error::ByteRangeInvalid(121, 109)), r.try_byte_slice(121..109));
+        assert_eq!(Err(Error::ByteIndexOutOfBounds(128, 127)), r.try_byte_slice(109..128));
+        assert_eq!(Err(Error::ByteIndexNotCharBoundary(110)), r.try_byte_slice(110..121));
+        assert_eq!(Err(Error::ByteIndexNotCharBoundary(120)), r.try_byte_slice(109..120));
+    }
+
+    #[test]
+    fn try_iter_at_01() {
+        let r = Rope::from_str(TEXT_LINES);
+
+        assert!(r.try_bytes_at(124).unwrap().next().is_none());
+        assert!(r.try_bytes_at(125).is_err());
+        assert!(r.try_chars_at(100).unwrap().next().is_none());
+        assert!(r.try_chars_at(101).is_err());
+        assert!(r.try_lines_at(4).unwrap().next().is_none());
+        assert!(r.try_lines_at(5).is_err());
+        assert!(r.try_chunks_at_byte(125).is_err());
+        assert!(r.try_chunks_at_char(101).is_err());
+        assert!(r.try_chunks_at_line_break(5).is_err());
+    }
+
     #[test]
     fn eq_rope_01() {
         let r = Rope::from_str("");
@@ -2141,5 +5110,136 @@ mod tests {
         assert_eq!(s, r);
     }
 
+    #[test]
+    fn cmp_rope_01() {
+        let r1 = Rope::from_str(TEXT);
+        let mut r2 = r1.clone();
+        r2.remove(26..27);
+        r2.insert(26, "z");
+
+        assert_eq!(std::cmp::Ordering::Equal, r1.cmp(&r1.clone()));
+        assert!(r1 < r2);
+        assert!(r2 > r1);
+    }
+
+    #[test]
+    fn cmp_rope_02() {
+        let r1 = Rope::from_str(TEXT);
+        let mut r2 = r1.clone();
+        r2.remove(100..);
+
+        assert!(r2 < r1);
+        assert!(Rope::new() < r2);
+        assert_eq!(std::cmp::Ordering::Equal, Rope::new().cmp(&Rope::new()));
+    }
+
+    #[test]
+    fn cmp_rope_03() {
+        let r = Rope::from_str(TEXT);
+        let s: String = TEXT.into();
+
+        assert_eq!(Some(std::cmp::Ordering::Equal), r.partial_cmp(TEXT));
+        assert!(r < "Hello there!  How're you doing?  It's z");
+        assert!("Hello there!  How're you doing?  It's z" > r);
+        assert!(r > "Hello");
+        assert!("Hello" < r);
+        assert!(r <= s);
+        assert!(s >= r);
+        assert!(r <= std::borrow::Cow::Borrowed(TEXT));
+    }
+
+    #[test]
+    fn hash_rope_01() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        fn hash<T: Hash>(t: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            t.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        // Same text built up in different ways, so that it's split into
+        // chunks differently.
+        let r1 = Rope::from_str(TEXT);
+        let mut r2 = Rope::new();
+        for c in TEXT.chars().rev() {
+            r2.insert_char(0, c);
+        }
+        assert_ne!(r1.chunks().count(), r2.chunks().count());
+
+        assert_eq!(hash(&r1), hash(&r2));
+        assert_eq!(hash(&r1), hash(&r1.slice(..)));
+        assert_ne!(hash(&r1), hash(&r1.slice(..100)));
+    }
+
     // Iterator tests are in the iter module
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn is_grapheme_boundary_01() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let boundaries = grapheme_boundaries(TEXT_GRAPHEMES);
+
+        for i in 0..=r.len_chars() {
+            assert_eq!(boundaries.contains(&i), r.is_grapheme_boundary(i));
+        }
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    #[should_panic]
+    fn is_grapheme_boundary_02() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        r.is_grapheme_boundary(r.len_chars() + 1);
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn prev_grapheme_boundary_01() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let boundaries = grapheme_boundaries(TEXT_GRAPHEMES);
+
+        assert_eq!(0, r.prev_grapheme_boundary(0));
+        for i in 1..=r.len_chars() {
+            let expected = *boundaries.iter().filter(|&&b| b < i).last().unwrap();
+            assert_eq!(expected, r.prev_grapheme_boundary(i));
+        }
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn next_grapheme_boundary_01() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let boundaries = grapheme_boundaries(TEXT_GRAPHEMES);
+
+        for i in 0..r.len_chars() {
+            let expected = *boundaries.iter().find(|&&b| b > i).unwrap();
+            assert_eq!(expected, r.next_grapheme_boundary(i));
+        }
+        assert_eq!(r.len_chars(), r.next_grapheme_boundary(r.len_chars()));
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn graphemes_01() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+
+        let mut count = 0;
+        for (g1, g2) in r.graphemes().zip(TEXT_GRAPHEMES.graphemes(true)) {
+            assert_eq!(g1, g2);
+            count += 1;
+        }
+        assert_eq!(TEXT_GRAPHEMES.graphemes(true).count(), count);
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn graphemes_02() {
+        let r = Rope::from_str("");
+        assert!(r.graphemes().next().is_none());
+        assert!(r.is_grapheme_boundary(0));
+        assert_eq!(0, r.prev_grapheme_boundary(0));
+        assert_eq!(0, r.next_grapheme_boundary(0));
+    }
 }
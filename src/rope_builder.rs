@@ -1,11 +1,18 @@
 use std;
+use std::mem;
 use std::sync::Arc;
+#[cfg(feature = "parallel")]
+use std::thread;
 
 use smallvec::SmallVec;
 
 use crlf;
+use escaped_bytes::EscapedBytes;
+use mark::MarkSet;
 use rope::Rope;
+use str_utils::escape_byte;
 use tree::{Node, NodeChildren, NodeText, MAX_BYTES, MAX_CHILDREN};
+use LineBreakPolicy;
 
 /// An efficient incremental `Rope` builder.
 ///
@@ -43,7 +50,25 @@ use tree::{Node, NodeChildren, NodeText, MAX_BYTES, MAX_CHILDREN};
 #[derive(Debug, Clone)]
 pub struct RopeBuilder {
     stack: SmallVec<[Arc<Node>; 4]>,
+    // Whether to collect leaves in `leaves` instead of adding them to the
+    // tree right away, so that `finish_parallel()` can split up the work
+    // of adding them.
+    defer_leaves: bool,
+    leaves: Vec<Arc<Node>>,
     buffer: String,
+    // The start of a char left over from `append_bytes_lossy()` or
+    // `append_bytes_escaped()`, and how to handle it if it's never finished.
+    pending: Vec<u8>,
+    pending_invalid: InvalidBytes,
+    // The offsets of partial chars replaced by `append()`, which haven't
+    // been returned by `append_bytes_lossy()` or `finish_lossy()` yet.
+    unreported: Vec<usize>,
+    // Total bytes appended so far, including `pending`.
+    appended_len: usize,
+    // Total bytes of text in the in-progress `Rope`, and the byte index in
+    // it and the value of each escaped byte.
+    text_len: usize,
+    escaped_bytes: Vec<(usize, u8)>,
 }
 
 impl RopeBuilder {
@@ -55,10 +80,34 @@ impl RopeBuilder {
                 stack.push(Arc::new(Node::new()));
                 stack
             },
+            defer_leaves: false,
+            leaves: Vec::new(),
             buffer: String::new(),
+            pending: Vec::new(),
+            pending_invalid: InvalidBytes::Replace,
+            unreported: Vec::new(),
+            appended_len: 0,
+            text_len: 0,
+            escaped_bytes: Vec::new(),
         }
     }
 
+    /// Creates a new RopeBuilder, for finishing with
+    /// [`finish_parallel()`](#method.finish_parallel).
+    ///
+    /// Most of the work of building a `Rope` is in indexing its text (e.g.
+    /// counting chars and line breaks).  Normally that's done as the text
+    /// is appended, but this builder leaves it until it's finished, so
+    /// that `finish_parallel()` can split it between threads.
+    ///
+    /// Only available with the `parallel` cargo feature enabled.
+    #[cfg(feature = "parallel")]
+    pub fn new_parallel() -> Self {
+        let mut builder = RopeBuilder::new();
+        builder.defer_leaves = true;
+        builder
+    }
+
     /// Appends `chunk` to the end of the in-progress `Rope`.
     ///
     /// This method is called repeatedly to incrementally build up a
@@ -67,20 +116,120 @@ impl RopeBuilder {
     ///
     /// `chunk` must be valid utf8 text.
     pub fn append(&mut self, chunk: &str) {
+        self.replace_pending();
+        self.appended_len += chunk.len();
         self.append_internal(chunk, false);
     }
 
+    /// Appends `bytes` to the end of the in-progress `Rope`, replacing
+    /// invalid utf8 with U+FFFD (the replacement character "\u{FFFD}").
+    ///
+    /// Returns the byte offsets of the replaced sequences in the source
+    /// text, counting everything appended to the builder so far.  Each
+    /// invalid sequence is replaced the same way as by
+    /// `String::from_utf8_lossy()`.
+    ///
+    /// `bytes` may end partway through a char, in which case the rest of
+    /// the char is expected at the start of the next call.  If it doesn't
+    /// come, the partial char is replaced by the next call to
+    /// `append_bytes_lossy()` or `append()`, or by `finish()`.  If it's
+    /// replaced by `append()`, its offset is returned by the next call to
+    /// `append_bytes_lossy()` instead.  Use
+    /// [`finish_lossy()`](#method.finish_lossy) to also get the offsets of
+    /// partial chars replaced since then or left at the very end.
+    ///
+    /// # Example
+    /// ```
+    /// # use ropey::RopeBuilder;
+    /// #
+    /// let mut builder = RopeBuilder::new();
+    ///
+    /// assert_eq!(builder.append_bytes_lossy(b"Hello \xFF \xE3\x81"), vec![6]);
+    /// assert_eq!(builder.append_bytes_lossy(b"\x93 world"), vec![]);
+    ///
+    /// assert_eq!(builder.finish(), "Hello \u{FFFD} \u{3053} world");
+    /// ```
+    pub fn append_bytes_lossy(&mut self, bytes: &[u8]) -> Vec<usize> {
+        let mut replaced = mem::take(&mut self.unreported);
+        replaced.extend(self.append_bytes_internal(bytes, InvalidBytes::Replace));
+        replaced
+    }
+
+    /// Appends `bytes` to the end of the in-progress `Rope`, escaping
+    /// invalid utf8 so that the original bytes can be restored.
+    ///
+    /// Each byte of an invalid sequence is stood in for by a char in the
+    /// range U+10FF00 to U+10FFFF (see
+    /// [`str_utils::escape_byte()`](str_utils/fn.escape_byte.html)).  The
+    /// `Rope` keeps track of which chars are stand-ins separately from its
+    /// text, so valid chars in that range are left as they are.  Writing
+    /// with [`WriteOptions::escaped_bytes`](struct.WriteOptions.html)
+    /// writes the original bytes back out.
+    ///
+    /// `bytes` may end partway through a char, in the same way as with
+    /// [`append_bytes_lossy()`](#method.append_bytes_lossy).  A partial
+    /// char that is never finished is escaped byte by byte.
+    ///
+    /// # Example
+    /// ```
+    /// # use ropey::RopeBuilder;
+    /// #
+    /// let mut builder = RopeBuilder::new();
+    /// builder.append_bytes_escaped(b"Hello \xFF world");
+    ///
+    /// assert_eq!(builder.finish(), "Hello \u{10FFFF} world");
+    /// ```
+    pub fn append_bytes_escaped(&mut self, bytes: &[u8]) {
+        self.append_bytes_internal(bytes, InvalidBytes::Escape);
+    }
+
     /// Finishes the build, and returns the `Rope`.
     ///
     /// Note: this method consumes the builder.  If you want to continue
     /// building other ropes with the same prefix, you can clone the builder
     /// before calling `finish()`.
     pub fn finish(mut self) -> Rope {
+        self.replace_pending();
+
         // Append the last leaf
         self.append_internal("", true);
         self.finish_internal()
     }
 
+    /// Like [`finish()`](#method.finish), but builds the `Rope` using
+    /// multiple threads.
+    ///
+    /// For a builder created with [`new_parallel()`](#method.new_parallel),
+    /// this splits the work of indexing the text between the available
+    /// cores, and joins the parts together into a single balanced tree.
+    /// The result is the same text as with `finish()`.  Other builders have
+    /// already indexed their text, so for them this is the same as
+    /// `finish()`.
+    ///
+    /// Small texts are built on the current thread, as spawning threads
+    /// would cost more than it saves.
+    ///
+    /// Only available with the `parallel` cargo feature enabled.
+    #[cfg(feature = "parallel")]
+    pub fn finish_parallel(mut self) -> Rope {
+        self.replace_pending();
+        self.append_internal("", true);
+        let thread_count = parallel_thread_count(self.leaves.len());
+        self.finish_with_threads(thread_count)
+    }
+
+    /// Like [`finish()`](#method.finish), but also returns the byte offsets
+    /// in the source text of the partial chars from
+    /// [`append_bytes_lossy()`](#method.append_bytes_lossy) that haven't
+    /// been reported yet, which get replaced with U+FFFD.  These are the
+    /// ones replaced by `append()` since the last call to
+    /// `append_bytes_lossy()`, and one left at the very end.
+    pub fn finish_lossy(mut self) -> (Rope, Vec<usize>) {
+        self.replace_pending();
+        let replaced = mem::take(&mut self.unreported);
+        (self.finish(), replaced)
+    }
+
     /// Builds a rope all at once from a single string slice.
     ///
     /// This avoids the creation and use of the internal buffer.  This is
@@ -91,10 +240,192 @@ impl RopeBuilder {
         self.finish_internal()
     }
 
+    /// Builds a rope all at once from a single string slice, using multiple
+    /// threads.  This is the implementation of `Rope::from_str_parallel()`.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn build_at_once_parallel(text: &str) -> Rope {
+        let thread_count = parallel_thread_count(text.len() / MAX_BYTES);
+        RopeBuilder::build_with_threads(text, thread_count)
+    }
+
     //-----------------------------------------------------------------
 
+    // Internal workings of `build_at_once_parallel()`.
+    #[cfg(feature = "parallel")]
+    fn build_with_threads(text: &str, thread_count: usize) -> Rope {
+        if thread_count <= 1 {
+            return RopeBuilder::new().build_at_once(text);
+        }
+
+        // Split the text so that every piece starts and ends on a leaf
+        // boundary, i.e. not in the middle of a char or CRLF pair.
+        let mut pieces = Vec::with_capacity(thread_count);
+        let mut start = 0;
+        for i in 1..thread_count {
+            let split_idx = crlf::find_good_split(
+                text.len() / thread_count * i,
+                text.as_bytes(),
+                true,
+            );
+            if split_idx > start && split_idx < text.len() {
+                pieces.push(&text[start..split_idx]);
+                start = split_idx;
+            }
+        }
+        pieces.push(&text[start..]);
+
+        let ropes = thread::scope(|scope| {
+            let handles: Vec<_> = pieces
+                .iter()
+                .map(|&piece| scope.spawn(move || RopeBuilder::new().build_at_once(piece)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        join_ropes(ropes)
+    }
+
+    // Internal workings of `finish_parallel()`, after the last leaf has
+    // been made.
+    #[cfg(feature = "parallel")]
+    fn finish_with_threads(mut self, thread_count: usize) -> Rope {
+        if thread_count <= 1 {
+            return self.finish_internal();
+        }
+        let escaped_bytes = mem::take(&mut self.escaped_bytes);
+
+        // There are leaves to split up, since there's more than one thread.
+        let group_len = ((self.leaves.len() - 1) / thread_count) + 1;
+        let ropes = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .leaves
+                .chunks(group_len)
+                .map(|leaves| {
+                    scope.spawn(move || {
+                        let mut builder = RopeBuilder::new();
+                        for leaf in leaves {
+                            builder.append_leaf_node(Arc::clone(leaf));
+                        }
+                        builder.finish_internal()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        let mut rope = join_ropes(ropes);
+        rope.escaped_bytes = index_escaped_bytes(&rope, escaped_bytes);
+        rope
+    }
+
+    //-----------------------------------------------------------------
+
+    // Internal workings of `append_bytes_lossy()` and
+    // `append_bytes_escaped()`.
+    fn append_bytes_internal(&mut self, bytes: &[u8], invalid: InvalidBytes) -> Vec<usize> {
+        let mut replaced = Vec::new();
+        let mut start = 0;
+
+        // Finish the char left over from the previous call first.
+        if !self.pending.is_empty() {
+            let pending_len = self.pending.len();
+            let mut char_bytes = mem::take(&mut self.pending);
+            char_bytes.extend_from_slice(&bytes[..bytes.len().min(3)]);
+            let offset = self.appended_len - pending_len;
+            let consumed = self.append_valid_prefix(&char_bytes, invalid, offset, &mut replaced);
+            if consumed < pending_len {
+                // Still not enough bytes to finish it.
+                self.pending.extend_from_slice(&char_bytes[consumed..]);
+                self.pending_invalid = invalid;
+                self.appended_len += bytes.len();
+                return replaced;
+            }
+            start = consumed - pending_len;
+        }
+
+        let offset = self.appended_len + start;
+        let consumed =
+            start + self.append_valid_prefix(&bytes[start..], invalid, offset, &mut replaced);
+        self.pending.extend_from_slice(&bytes[consumed..]);
+        self.pending_invalid = invalid;
+        self.appended_len += bytes.len();
+        replaced
+    }
+
+    // Appends the text of `bytes`, handling invalid sequences as given by
+    // `invalid` and recording their offsets, where `offset` is the offset
+    // of `bytes` in the source text.  Stops short of a partial char at the
+    // end, and returns how many bytes were consumed.
+    fn append_valid_prefix(
+        &mut self,
+        bytes: &[u8],
+        invalid: InvalidBytes,
+        offset: usize,
+        replaced: &mut Vec<usize>,
+    ) -> usize {
+        let mut byte_idx = 0;
+        loop {
+            match std::str::from_utf8(&bytes[byte_idx..]) {
+                Ok(text) => {
+                    self.append_internal(text, false);
+                    return bytes.len();
+                }
+                Err(e) => {
+                    let valid_end = byte_idx + e.valid_up_to();
+                    self.append_internal(
+                        unsafe { std::str::from_utf8_unchecked(&bytes[byte_idx..valid_end]) },
+                        false,
+                    );
+                    byte_idx = valid_end;
+                    match e.error_len() {
+                        Some(len) => {
+                            replaced.push(offset + byte_idx);
+                            self.append_invalid(&bytes[byte_idx..(byte_idx + len)], invalid);
+                            byte_idx += len;
+                        }
+                        None => return byte_idx,
+                    }
+                }
+            }
+        }
+    }
+
+    // Appends the replacement or escapes of an invalid sequence.
+    fn append_invalid(&mut self, bytes: &[u8], invalid: InvalidBytes) {
+        match invalid {
+            InvalidBytes::Replace => self.append_internal("\u{FFFD}", false),
+            InvalidBytes::Escape => {
+                let mut buf = [0u8; 4];
+                for &byte in bytes {
+                    self.escaped_bytes.push((self.text_len, byte));
+                    self.append_internal(escape_byte(byte).encode_utf8(&mut buf), false);
+                }
+            }
+        }
+    }
+
+    // Handles a partial char left over from `append_bytes_internal()` as an
+    // invalid sequence.  If it's replaced, its offset in the source text is
+    // recorded in `unreported`.
+    fn replace_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let offset = self.appended_len - self.pending.len();
+        let pending = mem::take(&mut self.pending);
+        self.append_invalid(&pending, self.pending_invalid);
+        if self.pending_invalid == InvalidBytes::Replace {
+            self.unreported.push(offset);
+        }
+    }
+
     // Internal workings of `append()`.
     fn append_internal(&mut self, chunk: &str, is_last_chunk: bool) {
+        self.text_len += chunk.len();
         let mut chunk = chunk;
 
         // Repeatedly chop text off the end of the input, creating
@@ -109,11 +440,11 @@ impl RopeBuilder {
                 NextText::None => break,
                 NextText::UseBuffer => {
                     let leaf_text = NodeText::from_str(&self.buffer);
-                    self.append_leaf_node(Arc::new(Node::Leaf(leaf_text)));
+                    self.append_leaf(Arc::new(Node::Leaf(leaf_text)));
                     self.buffer.clear();
                 }
                 NextText::String(s) => {
-                    self.append_leaf_node(Arc::new(Node::Leaf(NodeText::from_str(s))));
+                    self.append_leaf(Arc::new(Node::Leaf(NodeText::from_str(s))));
                 }
             }
         }
@@ -121,13 +452,18 @@ impl RopeBuilder {
 
     // Internal workings of `finish()`.
     fn finish_internal(mut self) -> Rope {
+        // Add any deferred leaves to the tree
+        for leaf in mem::take(&mut self.leaves) {
+            self.append_leaf_node(leaf);
+        }
+
         // Zip up all the remaining nodes on the stack
         let mut stack_idx = self.stack.len() - 1;
         while stack_idx >= 1 {
             let node = self.stack.pop().unwrap();
             if let Node::Internal(ref mut children) = *Arc::make_mut(&mut self.stack[stack_idx - 1])
             {
-                children.push((node.text_info(), node));
+                children.push((node.text_info(LineBreakPolicy::default()), node));
             } else {
                 unreachable!();
             }
@@ -136,11 +472,18 @@ impl RopeBuilder {
 
         // Get root and fix any right-side nodes with too few children.
         let mut root = self.stack.pop().unwrap();
-        Arc::make_mut(&mut root).zip_fix_right();
+        Arc::make_mut(&mut root).zip_fix_right(LineBreakPolicy::default());
 
         // Create the rope, make sure it's well-formed, and return it.
-        let mut rope = Rope { root: root };
+        let mut rope = Rope {
+            root,
+            line_break_policy: LineBreakPolicy::default(),
+            insert_line_ending: None,
+            marks: MarkSet::new(),
+            escaped_bytes: EscapedBytes::new(),
+        };
         rope.pull_up_singular_nodes();
+        rope.escaped_bytes = index_escaped_bytes(&rope, mem::take(&mut self.escaped_bytes));
         return rope;
     }
 
@@ -202,6 +545,15 @@ impl RopeBuilder {
         }
     }
 
+    // Adds a leaf to the tree, or defers it for `finish_parallel()`.
+    fn append_leaf(&mut self, leaf: Arc<Node>) {
+        if self.defer_leaves {
+            self.leaves.push(leaf);
+        } else {
+            self.append_leaf_node(leaf);
+        }
+    }
+
     fn append_leaf_node(&mut self, leaf: Arc<Node>) {
         let last = self.stack.pop().unwrap();
         match *last {
@@ -210,8 +562,8 @@ impl RopeBuilder {
                     self.stack.push(leaf);
                 } else {
                     let mut children = NodeChildren::new();
-                    children.push((last.text_info(), last));
-                    children.push((leaf.text_info(), leaf));
+                    children.push((last.text_info(LineBreakPolicy::default()), last));
+                    children.push((leaf.text_info(LineBreakPolicy::default()), leaf));
                     self.stack.push(Arc::new(Node::Internal(children)));
                 }
             }
@@ -224,21 +576,21 @@ impl RopeBuilder {
                     if stack_idx < 0 {
                         // We're above the root, so do a root split.
                         let mut children = NodeChildren::new();
-                        children.push((left.text_info(), left));
+                        children.push((left.text_info(LineBreakPolicy::default()), left));
                         self.stack.insert(0, Arc::new(Node::Internal(children)));
                         break;
                     } else if self.stack[stack_idx as usize].child_count() < (MAX_CHILDREN - 1) {
                         // There's room to add a child, so do that.
                         Arc::make_mut(&mut self.stack[stack_idx as usize])
                             .children()
-                            .push((left.text_info(), left));
+                            .push((left.text_info(LineBreakPolicy::default()), left));
                         break;
                     } else {
                         // Not enough room to fit a child, so split.
                         left = Arc::new(Node::Internal(
                             Arc::make_mut(&mut self.stack[stack_idx as usize])
                                 .children()
-                                .push_split((left.text_info(), left)),
+                                .push_split((left.text_info(LineBreakPolicy::default()), left)),
                         ));
                         std::mem::swap(&mut left, &mut self.stack[stack_idx as usize]);
                         stack_idx -= 1;
@@ -249,6 +601,46 @@ impl RopeBuilder {
     }
 }
 
+/// The fewest leaves worth giving a thread of their own when building a
+/// `Rope` in parallel.
+#[cfg(feature = "parallel")]
+const MIN_LEAVES_PER_THREAD: usize = 256;
+
+/// Returns how many threads to build a `Rope` of `leaf_count` leaves with.
+#[cfg(feature = "parallel")]
+fn parallel_thread_count(leaf_count: usize) -> usize {
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    cores.min(leaf_count / MIN_LEAVES_PER_THREAD).max(1)
+}
+
+/// Appends `ropes` together, in order.
+#[cfg(feature = "parallel")]
+fn join_ropes(ropes: Vec<Rope>) -> Rope {
+    let mut ropes = ropes.into_iter();
+    let mut joined = ropes.next().unwrap_or_default();
+    for rope in ropes {
+        joined.append(rope);
+    }
+    joined
+}
+
+/// Converts the byte indices of escaped bytes in `rope` to char indices.
+fn index_escaped_bytes(rope: &Rope, escaped_bytes: Vec<(usize, u8)>) -> EscapedBytes {
+    EscapedBytes::from_sorted(
+        escaped_bytes
+            .into_iter()
+            .map(|(byte_idx, byte)| (rope.byte_to_char(byte_idx), byte))
+            .collect(),
+    )
+}
+
+/// How `append_bytes_internal()` handles invalid utf8.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum InvalidBytes {
+    Replace,
+    Escape,
+}
+
 enum NextText<'a> {
     None,
     UseBuffer,
@@ -292,4 +684,160 @@ mod tests {
         r.assert_integrity();
         r.assert_invariants();
     }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn build_with_threads_01() {
+        let text = TEXT.repeat(50);
+        for thread_count in 1..6 {
+            let r = RopeBuilder::build_with_threads(&text, thread_count);
+
+            assert_eq!(r, text);
+            r.assert_integrity();
+            r.assert_invariants();
+        }
+
+        // Tiny texts, and CRLF pairs right at the split points.
+        for text in &["", "a", "\r\n", "\r\n\r\n", "\r\r\n\n\r\n\r"] {
+            let r = RopeBuilder::build_with_threads(text, 4);
+            assert_eq!(r, *text);
+            r.assert_integrity();
+            r.assert_invariants();
+        }
+        let text = "\r\n".repeat(1000);
+        let r = RopeBuilder::build_with_threads(&text, 7);
+        assert_eq!(r, text);
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn finish_with_threads_01() {
+        for thread_count in 1..6 {
+            let mut b = RopeBuilder::new_parallel();
+            for _ in 0..50 {
+                b.append("Hello there!  How're you doing?\r");
+                b.append("\nIt's a fine day, isn't it?\r\nAren't you ");
+                b.append("glad we're alive?\r\nこんにちは、みんなさん！");
+            }
+            b.append_internal("", true);
+            assert!(b.leaves.len() > 1);
+            let r = b.finish_with_threads(thread_count);
+
+            assert_eq!(r, TEXT.repeat(50));
+            r.assert_integrity();
+            r.assert_invariants();
+        }
+
+        let r = RopeBuilder::new_parallel().finish_parallel();
+        assert_eq!(r, "");
+        r.assert_integrity();
+        r.assert_invariants();
+
+        // Other builders add leaves to the tree as they go.
+        let mut b = RopeBuilder::new();
+        b.append(&TEXT.repeat(10));
+        assert!(b.leaves.is_empty());
+        let r = b.finish_parallel();
+        assert_eq!(r, TEXT.repeat(10));
+        r.assert_integrity();
+        r.assert_invariants();
+    }
+
+    #[test]
+    fn append_bytes_lossy_01() {
+        let bytes = b"a\xFFb\xE3\x81\x93\xF0\x9F\x98c\xC0\x80\xED\xA0\x80d";
+        let expected = String::from_utf8_lossy(bytes);
+        let offsets = vec![1, 6, 10, 11, 12, 13, 14];
+
+        for piece_len in 1..(bytes.len() + 2) {
+            let mut b = RopeBuilder::new();
+            let mut replaced = Vec::new();
+            for piece in bytes.chunks(piece_len) {
+                replaced.extend(b.append_bytes_lossy(piece));
+            }
+            let (r, rest) = b.finish_lossy();
+
+            assert_eq!(r, expected);
+            assert!(rest.is_empty());
+            assert_eq!(replaced, offsets);
+            r.assert_integrity();
+            r.assert_invariants();
+        }
+    }
+
+    #[test]
+    fn append_bytes_lossy_02() {
+        // Partial chars at the end, or before `append()`.
+        let mut b = RopeBuilder::new();
+        assert!(b.append_bytes_lossy(b"Hello\xE3").is_empty());
+        assert!(b.append_bytes_lossy(b"\x81").is_empty());
+        b.append("\r");
+        assert_eq!(b.append_bytes_lossy(b"\nworld\xF0"), vec![5]);
+        assert!(b.append_bytes_lossy(b"\x9F").is_empty());
+        let (r, rest) = b.finish_lossy();
+
+        assert_eq!(r, "Hello\u{FFFD}\r\nworld\u{FFFD}");
+        assert_eq!(rest, vec![14]);
+        r.assert_integrity();
+        r.assert_invariants();
+
+        let mut b = RopeBuilder::new();
+        b.append_bytes_lossy(b"\xE3\x81");
+        assert_eq!(b.finish(), "\u{FFFD}");
+
+        // Partial chars replaced by `append()` right before finishing.
+        let mut b = RopeBuilder::new();
+        assert!(b.append_bytes_lossy(b"ab\xE3\x81").is_empty());
+        b.append("cd");
+        let (r, rest) = b.finish_lossy();
+        assert_eq!(r, "ab\u{FFFD}cd");
+        assert_eq!(rest, vec![2]);
+
+        let mut b = RopeBuilder::new();
+        assert!(b.append_bytes_lossy(b"ab\xE3\x81").is_empty());
+        b.append("cd");
+        b.append("e");
+        assert_eq!(b.append_bytes_lossy(b"\xF0"), vec![2]);
+        b.append("f");
+        assert_eq!(b.append_bytes_lossy(b"\xF0\x9F"), vec![7]);
+        let (r, rest) = b.finish_lossy();
+        assert_eq!(r, "ab\u{FFFD}cde\u{FFFD}f\u{FFFD}");
+        assert_eq!(rest, vec![9]);
+    }
+
+    #[test]
+    fn append_bytes_escaped_01() {
+        let bytes = b"a\xFFb\xE3\x81\x93\xF0\x9F\x98c\xF4\x8F\xBF\xBF\xED\xA0\x80\xF4\x8F\xBC";
+        let expected = "a\u{10FFFF}b\u{3053}\u{10FFF0}\u{10FF9F}\u{10FF98}c\u{10FFFF}\
+                        \u{10FFED}\u{10FFA0}\u{10FF80}\
+                        \u{10FFF4}\u{10FF8F}\u{10FFBC}";
+        // The valid U+10FFFF at char 8 isn't an escaped byte.
+        let escaped_bytes = [
+            (1, 0xFF),
+            (4, 0xF0),
+            (5, 0x9F),
+            (6, 0x98),
+            (9, 0xED),
+            (10, 0xA0),
+            (11, 0x80),
+            (12, 0xF4),
+            (13, 0x8F),
+            (14, 0xBC),
+        ];
+
+        for piece_len in 1..(bytes.len() + 2) {
+            let mut b = RopeBuilder::new();
+            for piece in bytes.chunks(piece_len) {
+                b.append_bytes_escaped(piece);
+            }
+            let r = b.finish();
+
+            assert_eq!(r, expected);
+            assert_eq!(r.escaped_bytes.entries(), &escaped_bytes[..]);
+            r.assert_integrity();
+            r.assert_invariants();
+        }
+    }
 }
@@ -0,0 +1,764 @@
+//! Searching for text in a `Rope`/`RopeSlice`.
+//!
+//! The search methods themselves live on
+//! [`RopeSlice`](../struct.RopeSlice.html) (e.g.
+//! [`find()`](../struct.RopeSlice.html#method.find) and
+//! [`matches()`](../struct.RopeSlice.html#method.matches)).  This module
+//! contains the types they return.
+//!
+//! All searches work directly on the chunks of the text, and also find
+//! matches that straddle chunk boundaries.  The chunks themselves are
+//! searched with the Two-Way algorithm, using SIMD to quickly skip to
+//! candidate positions where available.
+//!
+//! With the optional `multi-pattern` cargo feature enabled, many patterns
+//! can also be searched for at once: compile them into a
+//! [`MultiPattern`](struct.MultiPattern.html) and search with
+//! [`RopeSlice::matches_multi()`](../struct.RopeSlice.html#method.matches_multi),
+//! which makes a single pass over the text no matter how many patterns
+//! there are.
+
+use std::ops::Range;
+
+#[cfg(feature = "multi-pattern")]
+use aho_corasick::automaton::Automaton;
+#[cfg(feature = "multi-pattern")]
+use aho_corasick::nfa::contiguous::NFA;
+#[cfg(feature = "multi-pattern")]
+use aho_corasick::{Anchored, MatchKind};
+use memchr::memmem::{Finder, FinderRev};
+
+use iter::{Chars, Chunks};
+use slice::{CharIdxRange, RopeSlice};
+use str_utils::count_chars;
+
+/// Options for [`RopeSlice::matches_with()`](../struct.RopeSlice.html#method.matches_with)
+/// and [`RopeSlice::find_with()`](../struct.RopeSlice.html#method.find_with).
+///
+/// The default options search the whole text, case-sensitively, for
+/// matches anywhere.
+///
+/// # Example
+///
+/// ```
+/// # use ropey::Rope;
+/// # use ropey::search::SearchOptions;
+/// let rope = Rope::from_str("Straße, STRASSE, strasse.  Rasse, strasses.");
+/// let options = SearchOptions::new().ignore_case(true).whole_word(true);
+/// let matches: Vec<_> = rope.slice(..).matches_with("strasse", &options).collect();
+///
+/// assert_eq!(matches, vec![8..15, 17..24]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct SearchOptions {
+    ignore_case: bool,
+    whole_word: bool,
+    start: Option<usize>,
+    end: Option<usize>,
+}
+
+impl SearchOptions {
+    /// Creates the default options.
+    #[inline]
+    pub fn new() -> SearchOptions {
+        SearchOptions::default()
+    }
+
+    /// Sets whether to match regardless of case.
+    ///
+    /// Case is ignored by comparing text under Unicode simple case folding,
+    /// which folds each char to a single char.  So e.g. "ſ" matches "s",
+    /// but "ß" doesn't match "ss".
+    #[inline]
+    pub fn ignore_case(mut self, ignore_case: bool) -> SearchOptions {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Sets whether to only match whole words.
+    ///
+    /// A match is a whole word when neither the char right before it nor
+    /// the char right after it is a word char (alphanumeric or `_`).  This
+    /// looks at the text just outside the searched range as well.
+    #[inline]
+    pub fn whole_word(mut self, whole_word: bool) -> SearchOptions {
+        self.whole_word = whole_word;
+        self
+    }
+
+    /// Limits the search to matches that lie entirely within the given
+    /// char range.
+    ///
+    /// Uses range syntax, e.g. `2..7`, `2..`, etc.
+    #[inline]
+    pub fn range<R: CharIdxRange>(mut self, char_range: R) -> SearchOptions {
+        self.start = char_range.start();
+        self.end = char_range.end();
+        self
+    }
+
+    /// Returns the search range within a text of length `len_chars`.
+    pub(crate) fn char_range(&self, len_chars: usize) -> Range<usize> {
+        self.start.unwrap_or(0)..self.end.unwrap_or(len_chars)
+    }
+}
+
+/// An iterator over the non-overlapping matches of a pattern in a
+/// `RopeSlice`.
+///
+/// Yields the char index of the start of each match, in order.  As with
+/// `str::matches()`, an empty pattern matches at every char index,
+/// including the end of the text.
+///
+/// Created by [`RopeSlice::matches()`](../struct.RopeSlice.html#method.matches).
+pub struct Matches<'a>(MatchesEnum<'a>);
+
+enum MatchesEnum<'a> {
+    Empty {
+        next_char: usize,
+        end_char: usize,
+    },
+    Search(Box<Searcher<'a>>),
+}
+
+impl<'a> Matches<'a> {
+    /// Finds the matches that start at or after `char_idx`.
+    pub(crate) fn new_at(slice: &RopeSlice<'a>, pattern: &str, char_idx: usize) -> Matches<'a> {
+        Matches::new_internal(slice, pattern, char_idx, pattern.len())
+    }
+
+    /// Like `new_at()`, but also finds matches that overlap each other.
+    fn new_overlapping(slice: &RopeSlice<'a>, pattern: &str) -> Matches<'a> {
+        Matches::new_internal(slice, pattern, 0, 1)
+    }
+
+    /// `step` is how far past the start of a match the next match may
+    /// start, in bytes.
+    fn new_internal(
+        slice: &RopeSlice<'a>,
+        pattern: &str,
+        char_idx: usize,
+        step: usize,
+    ) -> Matches<'a> {
+        if pattern.is_empty() {
+            return Matches(MatchesEnum::Empty {
+                next_char: char_idx,
+                end_char: slice.len_chars(),
+            });
+        }
+
+        let (chunks, chunk_byte, chunk_char, _) = slice.chunks_at_char(char_idx);
+        Matches(MatchesEnum::Search(Box::new(Searcher {
+            chunks,
+            finder: Finder::new(pattern).into_owned(),
+            chunk: "",
+            chunk_byte,
+            chunk_char,
+            counted_byte: 0,
+            counted_char: 0,
+            carry: Vec::new(),
+            seam: Vec::new(),
+            seam_carry_len: 0,
+            next_byte: slice.char_to_byte(char_idx),
+            step,
+        })))
+    }
+}
+
+impl<'a> Iterator for Matches<'a> {
+    type Item = usize;
+
+    /// Advances the iterator forward and returns the char index of the
+    /// start of the next match.
+    fn next(&mut self) -> Option<usize> {
+        match self.0 {
+            MatchesEnum::Empty {
+                ref mut next_char,
+                end_char,
+            } => {
+                if *next_char > end_char {
+                    None
+                } else {
+                    *next_char += 1;
+                    Some(*next_char - 1)
+                }
+            }
+            MatchesEnum::Search(ref mut searcher) => searcher.next(),
+        }
+    }
+}
+
+/// Forward substring search over a sequence of chunks.
+///
+/// Matches that lie entirely within a chunk are found by searching the
+/// chunk itself.  Matches that straddle the seam before a chunk are found
+/// by searching `seam`, which holds the last `pattern.len() - 1` bytes of
+/// the text before the chunk (the "carry") followed by up to as many bytes
+/// from the start of the chunk.  Only matches that start in the carry are
+/// taken from the seam, and a match that extends past the seam's end is
+/// found at a later seam instead, since its start stays in the carry until
+/// the text it needs has been seen.
+struct Searcher<'a> {
+    chunks: Chunks<'a>,
+    finder: Finder<'static>,
+
+    // The current chunk, and the byte and char index of its start.
+    chunk: &'a str,
+    chunk_byte: usize,
+    chunk_char: usize,
+
+    // How far into the current chunk chars have been counted, so that
+    // multiple matches in the same chunk don't count the same chars again.
+    counted_byte: usize,
+    counted_char: usize,
+
+    carry: Vec<u8>,
+    seam: Vec<u8>,
+    seam_carry_len: usize, // Zero once the seam has been searched.
+
+    // The earliest byte index where the next match may start, and how far
+    // past the start of a match that is.
+    next_byte: usize,
+    step: usize,
+}
+
+impl<'a> Searcher<'a> {
+    fn next(&mut self) -> Option<usize> {
+        let pat_len = self.finder.needle().len();
+
+        loop {
+            // Matches that straddle the seam before the current chunk.
+            if self.seam_carry_len > 0 {
+                let seam_byte = self.chunk_byte - self.seam_carry_len;
+                let from = self.next_byte.saturating_sub(seam_byte);
+                if from < self.seam_carry_len {
+                    if let Some(i) = self.finder.find(&self.seam[from..]) {
+                        let i = from + i;
+                        if i < self.seam_carry_len {
+                            self.next_byte = seam_byte + i + self.step;
+                            let chars_before_chunk =
+                                count_chars_in_bytes(&self.seam[i..self.seam_carry_len]);
+                            return Some(self.chunk_char - chars_before_chunk);
+                        }
+                    }
+                }
+                self.seam_carry_len = 0;
+            }
+
+            // Matches within the current chunk.
+            let from = self.next_byte.saturating_sub(self.chunk_byte);
+            if from <= self.chunk.len() {
+                if let Some(i) = self.finder.find(&self.chunk.as_bytes()[from..]) {
+                    let i = from + i;
+                    self.next_byte = self.chunk_byte + i + self.step;
+                    self.counted_char += count_chars(&self.chunk[self.counted_byte..i]);
+                    self.counted_byte = i;
+                    return Some(self.chunk_char + self.counted_char);
+                }
+            }
+
+            // Move on to the next chunk.
+            let next_chunk = self.chunks.next()?;
+
+            update_carry(&mut self.carry, self.chunk.as_bytes(), pat_len - 1);
+            self.chunk_byte += self.chunk.len();
+            self.chunk_char += self.counted_char + count_chars(&self.chunk[self.counted_byte..]);
+            self.chunk = next_chunk;
+            self.counted_byte = 0;
+            self.counted_char = 0;
+
+            if !self.carry.is_empty() {
+                let prefix_len = (pat_len - 1).min(next_chunk.len());
+                self.seam.clear();
+                self.seam.extend_from_slice(&self.carry);
+                self.seam.extend_from_slice(&next_chunk.as_bytes()[..prefix_len]);
+                self.seam_carry_len = self.carry.len();
+            }
+        }
+    }
+}
+
+/// An iterator over the non-overlapping matches of a pattern in a
+/// `RopeSlice`, found according to a [`SearchOptions`](struct.SearchOptions.html).
+///
+/// Yields the char range of each match, in order.
+///
+/// Created by [`RopeSlice::matches_with()`](../struct.RopeSlice.html#method.matches_with).
+pub struct MatchesWith<'a> {
+    candidates: Candidates<'a>,
+    // The whole text, for checking word boundaries.
+    slice: RopeSlice<'a>,
+    range_start: usize,
+    pat_chars: usize,
+    whole_word: bool,
+    // The earliest char index (relative to `range_start`) where the next
+    // match may start.
+    next_char: usize,
+}
+
+/// Sources of possible matches, relative to the start of the searched
+/// range.
+enum Candidates<'a> {
+    Exact(Matches<'a>),
+    Folded(FoldedSearcher<'a>),
+}
+
+impl<'a> MatchesWith<'a> {
+    pub(crate) fn new(
+        slice: &RopeSlice<'a>,
+        pattern: &str,
+        options: &SearchOptions,
+    ) -> MatchesWith<'a> {
+        let range = options.char_range(slice.len_chars());
+        let range_slice = slice.slice(range.clone());
+
+        let candidates = if options.ignore_case && !pattern.is_empty() {
+            Candidates::Folded(FoldedSearcher::new(range_slice.chars(), pattern))
+        } else if options.whole_word {
+            // A match rejected for not being a whole word may overlap one
+            // that isn't.
+            Candidates::Exact(Matches::new_overlapping(&range_slice, pattern))
+        } else {
+            Candidates::Exact(Matches::new_at(&range_slice, pattern, 0))
+        };
+
+        MatchesWith {
+            candidates,
+            slice: *slice,
+            range_start: range.start,
+            pat_chars: count_chars(pattern),
+            whole_word: options.whole_word,
+            next_char: 0,
+        }
+    }
+
+    fn is_whole_word(&self, char_range: Range<usize>) -> bool {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let (start, end) = (char_range.start, char_range.end);
+        (start == 0 || !is_word(self.slice.char(start - 1)))
+            && (end == self.slice.len_chars() || !is_word(self.slice.char(end)))
+    }
+}
+
+impl<'a> Iterator for MatchesWith<'a> {
+    type Item = Range<usize>;
+
+    /// Advances the iterator forward and returns the char range of the
+    /// next match.
+    fn next(&mut self) -> Option<Range<usize>> {
+        loop {
+            let start = match self.candidates {
+                Candidates::Exact(ref mut matches) => matches.next()?,
+                Candidates::Folded(ref mut searcher) => searcher.next()?,
+            };
+            if start < self.next_char {
+                continue;
+            }
+
+            let end = start + self.pat_chars;
+            let char_range = (self.range_start + start)..(self.range_start + end);
+            if self.whole_word && !self.is_whole_word(char_range.clone()) {
+                continue;
+            }
+
+            self.next_char = end;
+            return Some(char_range);
+        }
+    }
+}
+
+/// Finds all (possibly overlapping) matches of a non-empty pattern under
+/// simple case folding, using Knuth-Morris-Pratt over the folded chars.
+///
+/// Simple case folding maps each char to exactly one char, so matches have
+/// the same length in chars as the pattern.
+struct FoldedSearcher<'a> {
+    chars: Chars<'a>,
+    pattern: Vec<char>,
+    // `fallback[i]` is the length of the longest proper prefix of
+    // `pattern[..=i]` that is also a suffix of it.
+    fallback: Vec<usize>,
+    matched: usize,
+    char_idx: usize,
+}
+
+impl<'a> FoldedSearcher<'a> {
+    fn new(chars: Chars<'a>, pattern: &str) -> FoldedSearcher<'a> {
+        let pattern: Vec<char> = pattern.chars().map(fold_case).collect();
+
+        let mut fallback = vec![0; pattern.len()];
+        let mut len = 0;
+        for i in 1..pattern.len() {
+            while len > 0 && pattern[i] != pattern[len] {
+                len = fallback[len - 1];
+            }
+            if pattern[i] == pattern[len] {
+                len += 1;
+            }
+            fallback[i] = len;
+        }
+
+        FoldedSearcher {
+            chars,
+            pattern,
+            fallback,
+            matched: 0,
+            char_idx: 0,
+        }
+    }
+
+    /// Returns the char index of the start of the next match.
+    fn next(&mut self) -> Option<usize> {
+        for c in &mut self.chars {
+            let c = fold_case(c);
+            self.char_idx += 1;
+
+            while self.matched > 0 && self.pattern[self.matched] != c {
+                self.matched = self.fallback[self.matched - 1];
+            }
+            if self.pattern[self.matched] == c {
+                self.matched += 1;
+            }
+            if self.matched == self.pattern.len() {
+                self.matched = self.fallback[self.matched - 1];
+                return Some(self.char_idx - self.pattern.len());
+            }
+        }
+        None
+    }
+}
+
+/// Maps a char to its Unicode simple case folding.
+///
+/// Computed from the standard library's case mappings rather than a table
+/// of its own: chars are folded to the lowercase of their uppercase, which
+/// also takes care of chars like "ς" and "ſ" that fold to a different
+/// lowercase char.  Mappings that don't map to a single char aren't part of
+/// simple case folding, and are skipped.
+fn fold_case(c: char) -> char {
+    if c.is_ascii() {
+        return c.to_ascii_lowercase();
+    }
+
+    // Dotless i only folds to "i" in Turkic languages.
+    if c == '\u{0131}' {
+        return c;
+    }
+
+    let upper = single_char(c.to_uppercase()).unwrap_or(c);
+    single_char(upper.to_lowercase()).unwrap_or(upper)
+}
+
+fn single_char<I: Iterator<Item = char>>(mut chars: I) -> Option<char> {
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// A set of patterns compiled for searching them all at once.
+///
+/// The patterns are compiled into an Aho-Corasick automaton, which
+/// [`RopeSlice::matches_multi()`](../struct.RopeSlice.html#method.matches_multi)
+/// and [`RopeSlice::find_multi()`](../struct.RopeSlice.html#method.find_multi)
+/// run over the text in a single pass, regardless of the number of
+/// patterns.  Compile the patterns once and reuse the `MultiPattern` for
+/// every search.
+///
+/// Patterns are identified by their index in the list the `MultiPattern`
+/// was created from.  Matches don't overlap, and where several patterns
+/// match starting at the same position, the one that comes first in the
+/// list wins (like alternation in a regex).  Empty patterns never match.
+///
+/// Only available with the `multi-pattern` cargo feature enabled.
+///
+/// # Example
+///
+/// ```
+/// # use ropey::Rope;
+/// # use ropey::search::MultiPattern;
+/// let keywords = MultiPattern::new(&["TODO", "FIXME", "XXX"]);
+/// let rope = Rope::from_str("// FIXME: handle XXX. TODO: docs.");
+/// let matches: Vec<_> = rope.slice(..).matches_multi(&keywords).collect();
+///
+/// assert_eq!(matches, vec![(1, 3..8), (2, 17..20), (0, 22..26)]);
+/// ```
+#[cfg(feature = "multi-pattern")]
+#[derive(Debug, Clone)]
+pub struct MultiPattern {
+    nfa: NFA,
+    // The index of each compiled pattern in the original list, which
+    // differs once empty patterns have been skipped.
+    ids: Vec<usize>,
+    // The length in chars of each compiled pattern.
+    pat_chars: Vec<usize>,
+}
+
+#[cfg(feature = "multi-pattern")]
+impl MultiPattern {
+    /// Compiles a list of patterns.
+    ///
+    /// Runs in O(M) time, where M is the total length of the patterns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the patterns are too large to compile, which takes
+    /// many millions of bytes of patterns.
+    pub fn new<I, P>(patterns: I) -> MultiPattern
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        let mut compiled = Vec::new();
+        let mut ids = Vec::new();
+        let mut pat_chars = Vec::new();
+        for (id, pattern) in patterns.into_iter().enumerate() {
+            let pattern = pattern.as_ref();
+            if !pattern.is_empty() {
+                ids.push(id);
+                pat_chars.push(count_chars(pattern));
+                compiled.push(pattern.to_string());
+            }
+        }
+
+        let nfa = NFA::builder()
+            .match_kind(MatchKind::LeftmostFirst)
+            .build(&compiled)
+            .expect("Patterns too large to compile");
+
+        MultiPattern {
+            nfa,
+            ids,
+            pat_chars,
+        }
+    }
+}
+
+/// An iterator over the non-overlapping matches of a
+/// [`MultiPattern`](struct.MultiPattern.html) in a `RopeSlice`.
+///
+/// Yields the pattern id and char range of each match, in order.
+///
+/// Created by [`RopeSlice::matches_multi()`](../struct.RopeSlice.html#method.matches_multi).
+#[cfg(feature = "multi-pattern")]
+pub struct MultiMatches<'a, 'b> {
+    patterns: &'b MultiPattern,
+    slice: RopeSlice<'a>,
+    chunks: Chunks<'a>,
+
+    // The current chunk, the byte index of its start, and how far into it
+    // the search has got.
+    chunk: &'a [u8],
+    chunk_byte: usize,
+    pos: usize,
+}
+
+#[cfg(feature = "multi-pattern")]
+impl<'a, 'b> MultiMatches<'a, 'b> {
+    pub(crate) fn new(slice: &RopeSlice<'a>, patterns: &'b MultiPattern) -> MultiMatches<'a, 'b> {
+        MultiMatches {
+            patterns,
+            slice: *slice,
+            chunks: slice.chunks(),
+            chunk: &[],
+            chunk_byte: 0,
+            pos: 0,
+        }
+    }
+
+    /// Moves the search back to `byte_idx`, which is at or after the start
+    /// of the last match found.
+    fn seek_back(&mut self, byte_idx: usize) {
+        if byte_idx >= self.chunk_byte {
+            self.pos = byte_idx - self.chunk_byte;
+        } else {
+            let (mut chunks, chunk_byte, _, _) = self.slice.chunks_at_byte(byte_idx);
+            match chunks.next() {
+                Some(chunk) => {
+                    self.chunk = chunk.as_bytes();
+                    self.chunk_byte = chunk_byte;
+                    self.pos = byte_idx - chunk_byte;
+                }
+                None => {
+                    self.chunk = &[];
+                    self.chunk_byte = byte_idx;
+                    self.pos = 0;
+                }
+            }
+            self.chunks = chunks;
+        }
+    }
+}
+
+#[cfg(feature = "multi-pattern")]
+impl<'a, 'b> Iterator for MultiMatches<'a, 'b> {
+    type Item = (usize, Range<usize>);
+
+    /// Advances the iterator forward and returns the pattern id and char
+    /// range of the next match.
+    fn next(&mut self) -> Option<(usize, Range<usize>)> {
+        let nfa = &self.patterns.nfa;
+
+        // The automaton is fed one byte at a time, so matches that straddle
+        // chunk boundaries need no special handling.  With leftmost-first
+        // semantics it has to keep going after a match until it can tell
+        // that no preferred match extends it, so the search may end up past
+        // the match and has to be moved back afterwards.
+        let mut sid = nfa
+            .start_state(Anchored::No)
+            .expect("Unanchored searches are always supported");
+        let mut found = None;
+        loop {
+            if self.pos == self.chunk.len() {
+                match self.chunks.next() {
+                    Some(chunk) => {
+                        self.chunk_byte += self.chunk.len();
+                        self.chunk = chunk.as_bytes();
+                        self.pos = 0;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+
+            sid = nfa.next_state(Anchored::No, sid, self.chunk[self.pos]);
+            self.pos += 1;
+            if nfa.is_special(sid) {
+                if nfa.is_dead(sid) {
+                    break;
+                } else if nfa.is_match(sid) {
+                    found = Some((nfa.match_pattern(sid, 0), self.chunk_byte + self.pos));
+                }
+            }
+        }
+
+        let (pid, end_byte) = found?;
+        self.seek_back(end_byte);
+
+        let end_char = self.slice.byte_to_char(end_byte);
+        let start_char = end_char - self.patterns.pat_chars[pid.as_usize()];
+        Some((self.patterns.ids[pid.as_usize()], start_char..end_char))
+    }
+}
+
+/// Returns the char index of the start of the last match of `pattern` in
+/// `slice`, or `None` if there is none.
+pub(crate) fn rfind(slice: &RopeSlice, pattern: &str) -> Option<usize> {
+    if pattern.is_empty() {
+        return Some(slice.len_chars());
+    }
+
+    // Mirrors `Searcher`, going back to front: here the carry holds the
+    // first `pattern.len() - 1` bytes of the text after the current chunk.
+    let finder = FinderRev::new(pattern);
+    let pat_len = pattern.len();
+    let mut carry: Vec<u8> = Vec::new();
+    let mut seam = Vec::new();
+    let mut chunk_end_char = slice.len_chars();
+
+    let mut chunks = slice.chunks();
+    while let Some(chunk) = chunks.next_back() {
+        let chunk_char = chunk_end_char - count_chars(chunk);
+
+        // Any match straddling the seam after the chunk starts after every
+        // match within the chunk, so it's checked first.  And since the
+        // seam holds fewer than `pat_len` bytes on either side of the seam,
+        // every match in it straddles the seam.
+        if !carry.is_empty() {
+            let suffix_len = (pat_len - 1).min(chunk.len());
+            seam.clear();
+            seam.extend_from_slice(&chunk.as_bytes()[(chunk.len() - suffix_len)..]);
+            seam.extend_from_slice(&carry);
+            if let Some(i) = finder.rfind(&seam) {
+                return Some(chunk_end_char - count_chars_in_bytes(&seam[i..suffix_len]));
+            }
+        }
+
+        if let Some(i) = finder.rfind(chunk.as_bytes()) {
+            return Some(chunk_char + count_chars(&chunk[..i]));
+        }
+
+        // Prepend the chunk to the carry.
+        let keep_len = (pat_len - 1).min(chunk.len());
+        let mut new_carry = Vec::with_capacity(pat_len - 1);
+        new_carry.extend_from_slice(&chunk.as_bytes()[..keep_len]);
+        new_carry.extend_from_slice(&carry);
+        new_carry.truncate(pat_len - 1);
+        carry = new_carry;
+
+        chunk_end_char = chunk_char;
+    }
+
+    None
+}
+
+/// Appends `bytes` to `carry`, keeping only the last `max_len` bytes.
+fn update_carry(carry: &mut Vec<u8>, bytes: &[u8], max_len: usize) {
+    if bytes.len() >= max_len {
+        carry.clear();
+        carry.extend_from_slice(&bytes[(bytes.len() - max_len)..]);
+    } else {
+        carry.extend_from_slice(bytes);
+        if carry.len() > max_len {
+            let excess = carry.len() - max_len;
+            carry.drain(..excess);
+        }
+    }
+}
+
+/// Counts the chars in a byte slice that starts on a char boundary and
+/// ends on one, without re-validating it as utf8.
+#[inline]
+fn count_chars_in_bytes(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&byte| (byte & 0xC0) != 0x80).count()
+}
+
+//=============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_carry_01() {
+        let mut carry = Vec::new();
+        update_carry(&mut carry, b"ab", 3);
+        assert_eq!(carry, b"ab");
+        update_carry(&mut carry, b"cd", 3);
+        assert_eq!(carry, b"bcd");
+        update_carry(&mut carry, b"efgh", 3);
+        assert_eq!(carry, b"fgh");
+        update_carry(&mut carry, b"", 3);
+        assert_eq!(carry, b"fgh");
+    }
+
+    #[test]
+    fn fold_case_01() {
+        assert_eq!(fold_case('A'), 'a');
+        assert_eq!(fold_case('a'), 'a');
+        assert_eq!(fold_case('Σ'), 'σ');
+        assert_eq!(fold_case('ς'), 'σ');
+        assert_eq!(fold_case('ſ'), 's');
+        assert_eq!(fold_case('\u{212A}'), 'k'); // Kelvin sign
+        assert_eq!(fold_case('ß'), 'ß');
+        assert_eq!(fold_case('ẞ'), 'ß');
+        assert_eq!(fold_case('ǅ'), 'ǆ');
+        assert_eq!(fold_case('\u{0130}'), '\u{0130}');
+        assert_eq!(fold_case('\u{0131}'), '\u{0131}');
+        assert_eq!(fold_case('ん'), 'ん');
+    }
+
+    #[test]
+    fn folded_searcher_01() {
+        let s = RopeSlice::from_str("aAaA bAAb");
+        let mut searcher = FoldedSearcher::new(s.chars(), "aa");
+        let starts: Vec<_> = ::std::iter::from_fn(|| searcher.next()).collect();
+        assert_eq!(starts, vec![0, 1, 2, 6]);
+    }
+
+    #[test]
+    fn count_chars_in_bytes_01() {
+        assert_eq!(count_chars_in_bytes(b""), 0);
+        assert_eq!(count_chars_in_bytes("aé日🐸".as_bytes()), 4);
+    }
+}
@@ -0,0 +1,58 @@
+//! `Serialize` and `Deserialize` implementations, behind the `serde`
+//! feature.
+//!
+//! Both `Rope` and `RopeSlice` serialize as a plain string, and `Rope`
+//! deserializes from one.
+
+use std::fmt;
+use std::result;
+
+use serde::de::{Deserialize, Deserializer, Error, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use rope::Rope;
+use rope_builder::RopeBuilder;
+use slice::RopeSlice;
+
+impl Serialize for Rope {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        self.slice(..).serialize(serializer)
+    }
+}
+
+impl<'a> Serialize for RopeSlice<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let mut chunks = self.chunks();
+        match (chunks.next(), chunks.next()) {
+            (None, _) => serializer.serialize_str(""),
+            (Some(chunk), None) => serializer.serialize_str(chunk),
+            // Serializers that write to a stream (e.g. serde_json) override
+            // `collect_str()` to write the `Display` output directly, so the
+            // chunks get streamed out without first being joined together.
+            _ => serializer.collect_str(self),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Rope {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Rope, D::Error> {
+        deserializer.deserialize_str(RopeVisitor)
+    }
+}
+
+struct RopeVisitor;
+
+impl<'de> Visitor<'de> for RopeVisitor {
+    type Value = Rope;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    #[inline]
+    fn visit_str<E: Error>(self, v: &str) -> result::Result<Rope, E> {
+        Ok(RopeBuilder::new().build_at_once(v))
+    }
+}
@@ -2,13 +2,28 @@ use std;
 use std::ops::{Range, RangeFrom, RangeFull, RangeTo};
 use std::sync::Arc;
 
+use escaped_bytes::EscapedBytes;
+#[cfg(feature = "graphemes")]
+use graphemes::{
+    is_grapheme_boundary_byte, next_grapheme_boundary_byte, prev_grapheme_boundary_byte,
+};
+#[cfg(feature = "graphemes")]
+use iter::Graphemes;
 use iter::{Bytes, Chars, Chunks, Lines};
+use mark::MarkSet;
 use rope::Rope;
+#[cfg(feature = "regex")]
+use regex::{Captures, CapturesIter, Regex, RegexMatches};
+use search::{self, Matches, MatchesWith, SearchOptions};
+#[cfg(feature = "multi-pattern")]
+use search::{MultiMatches, MultiPattern};
 use str_utils::{
-    byte_to_char_idx, byte_to_line_idx, char_to_byte_idx, char_to_line_idx, count_chars,
-    count_line_breaks, line_to_byte_idx, line_to_char_idx,
+    byte_to_char_idx, byte_to_line_idx_with_policy, char_to_byte_idx, char_to_line_idx_with_policy,
+    char_to_utf16_cu_idx, count_chars, count_line_breaks, count_utf16_surrogates,
+    line_to_byte_idx_with_policy, line_to_char_idx_with_policy, utf16_cu_to_char_idx,
 };
 use tree::{Count, Node};
+use {Error, LineBreakPolicy, Result};
 
 /// An immutable view into part of a `Rope`.
 ///
@@ -30,20 +45,30 @@ enum RSEnum<'a> {
         end_byte: Count,
         start_char: Count,
         end_char: Count,
+        start_utf16_surrogate: Count,
+        end_utf16_surrogate: Count,
         start_line_break: Count,
         end_line_break: Count,
+        policy: LineBreakPolicy,
     },
     Light {
         text: &'a str,
         char_count: Count,
+        utf16_surrogate_count: Count,
         line_break_count: Count,
+        policy: LineBreakPolicy,
     },
 }
 
 impl<'a> RopeSlice<'a> {
-    pub(crate) fn new_with_range(node: &'a Arc<Node>, start: usize, end: usize) -> Self {
+    pub(crate) fn new_with_range(
+        node: &'a Arc<Node>,
+        start: usize,
+        end: usize,
+        policy: LineBreakPolicy,
+    ) -> Self {
         assert!(start <= end);
-        assert!(end <= node.text_info().chars as usize);
+        assert!(end <= node.char_count());
 
         // Early-out shortcut for taking a slice of the full thing.
         if start == 0 && end == node.char_count() {
@@ -52,7 +77,9 @@ impl<'a> RopeSlice<'a> {
                 return RopeSlice(RSEnum::Light {
                     text: text,
                     char_count: (end - start) as Count,
-                    line_break_count: count_line_breaks(text) as Count,
+                    utf16_surrogate_count: count_utf16_surrogates(text) as Count,
+                    line_break_count: count_line_breaks(text, policy) as Count,
+                    policy,
                 });
             } else {
                 return RopeSlice(RSEnum::Full {
@@ -61,8 +88,11 @@ impl<'a> RopeSlice<'a> {
                     end_byte: node.byte_count() as Count,
                     start_char: 0,
                     end_char: node.char_count() as Count,
+                    start_utf16_surrogate: 0,
+                    end_utf16_surrogate: node.utf16_surrogate_count() as Count,
                     start_line_break: 0,
-                    end_line_break: node.line_break_count() as Count,
+                    end_line_break: node.line_break_count(policy) as Count,
+                    policy,
                 });
             }
         }
@@ -79,10 +109,13 @@ impl<'a> RopeSlice<'a> {
                     let start_byte = char_to_byte_idx(&text, n_start);
                     let end_byte =
                         start_byte + char_to_byte_idx(&text[start_byte..], n_end - n_start);
+                    let new_text = &text[start_byte..end_byte];
                     return RopeSlice(RSEnum::Light {
-                        text: &text[start_byte..end_byte],
+                        text: new_text,
                         char_count: (n_end - n_start) as Count,
-                        line_break_count: count_line_breaks(&text[start_byte..end_byte]) as Count,
+                        utf16_surrogate_count: count_utf16_surrogates(new_text) as Count,
+                        line_break_count: count_line_breaks(new_text, policy) as Count,
+                        policy,
                     });
                 }
 
@@ -103,16 +136,19 @@ impl<'a> RopeSlice<'a> {
         }
 
         // Create the slice
-        let (start_byte, start_line) = node.char_to_byte_and_line(n_start);
-        let (end_byte, end_line) = node.char_to_byte_and_line(n_end);
+        let (start_byte, start_line) = node.char_to_byte_and_line(n_start, policy);
+        let (end_byte, end_line) = node.char_to_byte_and_line(n_end, policy);
         RopeSlice(RSEnum::Full {
             node: node,
             start_byte: start_byte as Count,
             end_byte: end_byte as Count,
             start_char: n_start as Count,
             end_char: n_end as Count,
+            start_utf16_surrogate: node.char_to_utf16_surrogate_idx(n_start) as Count,
+            end_utf16_surrogate: node.char_to_utf16_surrogate_idx(n_end) as Count,
             start_line_break: start_line as Count,
             end_line_break: end_line as Count,
+            policy,
         })
     }
 
@@ -166,6 +202,48 @@ impl<'a> RopeSlice<'a> {
         }
     }
 
+    /// The [`LineBreakPolicy`](enum.LineBreakPolicy.html) that the
+    /// `RopeSlice`'s line-related methods and iterators use.
+    ///
+    /// This is inherited from the `Rope` that the slice was created from.
+    #[inline]
+    pub fn line_break_policy(&self) -> LineBreakPolicy {
+        match *self {
+            RopeSlice(RSEnum::Full { policy, .. }) | RopeSlice(RSEnum::Light { policy, .. }) => {
+                policy
+            }
+        }
+    }
+
+    /// Total number of utf16 code units that would be in the `RopeSlice` if
+    /// it were encoded as utf16.
+    ///
+    /// Ropey stores text internally as utf8, but sometimes it is necessary
+    /// to interact with external APIs that still use utf16.  This function
+    /// is primarily intended for such situations, and is otherwise not very
+    /// useful.
+    ///
+    /// Runs in O(1) time.
+    #[inline]
+    pub fn len_utf16_cu(&self) -> usize {
+        match *self {
+            RopeSlice(RSEnum::Full {
+                end_char,
+                start_char,
+                end_utf16_surrogate,
+                start_utf16_surrogate,
+                ..
+            }) => {
+                ((end_char + end_utf16_surrogate) - (start_char + start_utf16_surrogate)) as usize
+            }
+            RopeSlice(RSEnum::Light {
+                char_count,
+                utf16_surrogate_count,
+                ..
+            }) => (char_count + utf16_surrogate_count) as usize,
+        }
+    }
+
     //-----------------------------------------------------------------------
     // Index conversion methods
 
@@ -218,7 +296,7 @@ impl<'a> RopeSlice<'a> {
         );
 
         let (chunk, b, _, l) = self.chunk_at_byte(byte_idx);
-        l + byte_to_line_idx(chunk, byte_idx - b)
+        l + byte_to_line_idx_with_policy(chunk, byte_idx - b, self.line_break_policy())
     }
 
     /// Returns the byte index of the given char.
@@ -268,7 +346,7 @@ impl<'a> RopeSlice<'a> {
         );
 
         let (chunk, _, c, l) = self.chunk_at_char(char_idx);
-        l + char_to_line_idx(chunk, char_idx - c)
+        l + char_to_line_idx_with_policy(chunk, char_idx - c, self.line_break_policy())
     }
 
     /// Returns the byte index of the start of the given line.
@@ -296,7 +374,7 @@ impl<'a> RopeSlice<'a> {
             self.len_bytes()
         } else {
             let (chunk, b, _, l) = self.chunk_at_line_break(line_idx);
-            b + line_to_byte_idx(chunk, line_idx - l)
+            b + line_to_byte_idx_with_policy(chunk, line_idx - l, self.line_break_policy())
         }
     }
 
@@ -325,7 +403,89 @@ impl<'a> RopeSlice<'a> {
             self.len_chars()
         } else {
             let (chunk, _, c, l) = self.chunk_at_line_break(line_idx);
-            c + line_to_char_idx(chunk, line_idx - l)
+            c + line_to_char_idx_with_policy(chunk, line_idx - l, self.line_break_policy())
+        }
+    }
+
+    /// Returns the utf16 code unit index of the given char.
+    ///
+    /// Ropey stores text internally as utf8, but sometimes it is necessary
+    /// to interact with external APIs that still use utf16.  This function
+    /// is primarily intended for such situations, and is otherwise not very
+    /// useful.
+    ///
+    /// Notes:
+    ///
+    /// - `char_idx` can be one-past-the-end, which will return
+    ///   one-past-the-end utf16 code unit index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    #[inline]
+    pub fn char_to_utf16_cu(&self, char_idx: usize) -> usize {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to index past end of slice: char index {}, slice char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        match *self {
+            RopeSlice(RSEnum::Full {
+                node,
+                start_char,
+                start_utf16_surrogate,
+                ..
+            }) => {
+                let surrogates =
+                    node.char_to_utf16_surrogate_idx(start_char as usize + char_idx);
+                char_idx + surrogates - start_utf16_surrogate as usize
+            }
+            RopeSlice(RSEnum::Light { text, .. }) => char_to_utf16_cu_idx(text, char_idx),
+        }
+    }
+
+    /// Returns the char index of the given utf16 code unit.
+    ///
+    /// Ropey stores text internally as utf8, but sometimes it is necessary
+    /// to interact with external APIs that still use utf16.  This function
+    /// is primarily intended for such situations, and is otherwise not very
+    /// useful.
+    ///
+    /// Notes:
+    ///
+    /// - If the utf16 code unit is in the middle of a surrogate pair,
+    ///   returns the index of the char that the surrogate pair belongs to.
+    /// - `utf16_cu_idx` can be one-past-the-end, which will return
+    ///   one-past-the-end char index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `utf16_cu_idx` is out of bounds
+    /// (i.e. `utf16_cu_idx > len_utf16_cu()`).
+    #[inline]
+    pub fn utf16_cu_to_char(&self, utf16_cu_idx: usize) -> usize {
+        // Bounds check
+        assert!(
+            utf16_cu_idx <= self.len_utf16_cu(),
+            "Attempt to index past end of slice: utf16 code unit index {}, slice utf16 length {}",
+            utf16_cu_idx,
+            self.len_utf16_cu()
+        );
+
+        match *self {
+            RopeSlice(RSEnum::Full {
+                node,
+                start_char,
+                start_utf16_surrogate,
+                ..
+            }) => {
+                let start_utf16_cu = (start_char + start_utf16_surrogate) as usize;
+                node.utf16_cu_to_char(start_utf16_cu + utf16_cu_idx) - start_char as usize
+            }
+            RopeSlice(RSEnum::Light { text, .. }) => utf16_cu_to_char_idx(text, utf16_cu_idx),
         }
     }
 
@@ -369,15 +529,16 @@ impl<'a> RopeSlice<'a> {
             self.len_lines()
         );
 
+        let policy = self.line_break_policy();
         let (chunk_1, _, c1, l1) = self.chunk_at_line_break(line_idx);
         let (chunk_2, _, c2, l2) = self.chunk_at_line_break(line_idx + 1);
         if c1 == c2 {
-            let text1 = &chunk_1[line_to_byte_idx(chunk_1, line_idx - l1)..];
-            let text2 = &text1[..line_to_byte_idx(text1, 1)];
-            RopeSlice::from_str(text2)
+            let text1 = &chunk_1[line_to_byte_idx_with_policy(chunk_1, line_idx - l1, policy)..];
+            let text2 = &text1[..line_to_byte_idx_with_policy(text1, 1, policy)];
+            RopeSlice::from_str_with_policy(text2, policy)
         } else {
-            let start = c1 + line_to_char_idx(chunk_1, line_idx - l1);
-            let end = c2 + line_to_char_idx(chunk_2, line_idx + 1 - l2);
+            let start = c1 + line_to_char_idx_with_policy(chunk_1, line_idx - l1, policy);
+            let end = c2 + line_to_char_idx_with_policy(chunk_2, line_idx + 1 - l2, policy);
             self.slice(start..end)
         }
     }
@@ -578,12 +739,21 @@ impl<'a> RopeSlice<'a> {
     /// equality with strings and string slices.
     ///
     /// Runs in O(N) time, where N is the length of the string slice.
+    ///
+    /// The created `RopeSlice` uses the default
+    /// [`LineBreakPolicy`](enum.LineBreakPolicy.html).
     #[inline]
     pub fn from_str(text: &str) -> RopeSlice {
+        RopeSlice::from_str_with_policy(text, LineBreakPolicy::default())
+    }
+
+    pub(crate) fn from_str_with_policy(text: &str, policy: LineBreakPolicy) -> RopeSlice<'_> {
         RopeSlice(RSEnum::Light {
             text: text,
             char_count: count_chars(text) as Count,
-            line_break_count: count_line_breaks(text) as Count,
+            utf16_surrogate_count: count_utf16_surrogates(text) as Count,
+            line_break_count: count_line_breaks(text, policy) as Count,
+            policy,
         })
     }
 
@@ -615,25 +785,48 @@ impl<'a> RopeSlice<'a> {
 
         match *self {
             RopeSlice(RSEnum::Full {
-                node, start_char, ..
+                node,
+                start_char,
+                policy,
+                ..
             }) => RopeSlice::new_with_range(
                 node,
                 start_char as usize + start,
                 start_char as usize + end,
+                policy,
             ),
-            RopeSlice(RSEnum::Light { text, .. }) => {
+            RopeSlice(RSEnum::Light { text, policy, .. }) => {
                 let start_byte = char_to_byte_idx(text, start);
                 let end_byte = char_to_byte_idx(text, end);
                 let new_text = &text[start_byte..end_byte];
                 RopeSlice(RSEnum::Light {
                     text: new_text,
                     char_count: (end - start) as Count,
-                    line_break_count: count_line_breaks(new_text) as Count,
+                    utf16_surrogate_count: count_utf16_surrogates(new_text) as Count,
+                    line_break_count: count_line_breaks(new_text, policy) as Count,
+                    policy,
                 })
             }
         }
     }
 
+    /// Returns a sub-slice of the `RopeSlice` in the given byte index range.
+    ///
+    /// Uses range syntax, e.g. `2..7`, `2..`, etc.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than the end, if the
+    /// end is out of bounds (i.e. `end > len_bytes()`), or if either end of
+    /// the range is not a char boundary.
+    #[inline]
+    pub fn byte_slice<R: CharIdxRange>(&self, byte_range: R) -> Self {
+        match self.try_byte_slice(byte_range) {
+            Ok(slice) => slice,
+            Err(err) => panic!("Attempt to slice RopeSlice with an invalid byte range: {:?}", err),
+        }
+    }
+
     //-----------------------------------------------------------------------
     // Iterator methods
 
@@ -673,9 +866,10 @@ impl<'a> RopeSlice<'a> {
                 node,
                 start_char,
                 end_char,
+                policy,
                 ..
-            }) => Lines::new_with_range(node, start_char as usize, end_char as usize),
-            RopeSlice(RSEnum::Light { text, .. }) => Lines::from_str(text),
+            }) => Lines::new_with_range(node, start_char as usize, end_char as usize, policy),
+            RopeSlice(RSEnum::Light { text, policy, .. }) => Lines::from_str(text, policy),
         }
     }
 
@@ -693,6 +887,234 @@ impl<'a> RopeSlice<'a> {
         }
     }
 
+    /// Creates an iterator over the bytes of the `RopeSlice`, starting at
+    /// byte `byte_idx`.
+    ///
+    /// The first call to `next()` returns the byte at `byte_idx`, and the
+    /// first call to `prev()` returns the byte before it.  If
+    /// `byte_idx == len_bytes()` then an iterator at the end of the
+    /// `RopeSlice` is created (i.e. `next()` will return `None`).
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_idx` is out of bounds (i.e. `byte_idx > len_bytes()`).
+    #[inline]
+    pub fn bytes_at(&self, byte_idx: usize) -> Bytes<'a> {
+        // Bounds check
+        assert!(
+            byte_idx <= self.len_bytes(),
+            "Attempt to index past end of slice: byte index {}, slice byte length {}",
+            byte_idx,
+            self.len_bytes()
+        );
+
+        match *self {
+            RopeSlice(RSEnum::Full {
+                node,
+                start_char,
+                end_char,
+                ..
+            }) => Bytes::new_with_range_at(
+                node,
+                byte_idx,
+                start_char as usize,
+                end_char as usize,
+            ),
+            RopeSlice(RSEnum::Light { text, .. }) => Bytes::from_str_at(text, byte_idx),
+        }
+    }
+
+    /// Creates an iterator over the chars of the `RopeSlice`, starting at
+    /// char `char_idx`.
+    ///
+    /// The first call to `next()` returns the char at `char_idx`, and the
+    /// first call to `prev()` returns the char before it.  If
+    /// `char_idx == len_chars()` then an iterator at the end of the
+    /// `RopeSlice` is created (i.e. `next()` will return `None`).
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    #[inline]
+    pub fn chars_at(&self, char_idx: usize) -> Chars<'a> {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to index past end of slice: char index {}, slice char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        let byte_idx = self.char_to_byte(char_idx);
+        match *self {
+            RopeSlice(RSEnum::Full {
+                node,
+                start_char,
+                end_char,
+                ..
+            }) => Chars::new_with_range_at(
+                node,
+                byte_idx,
+                start_char as usize,
+                end_char as usize,
+            ),
+            RopeSlice(RSEnum::Light { text, .. }) => Chars::from_str_at(text, byte_idx),
+        }
+    }
+
+    /// Creates an iterator over the lines of the `RopeSlice`, starting at
+    /// line `line_idx`.
+    ///
+    /// The first call to `next()` returns the line at `line_idx`, and the
+    /// first call to `prev()` returns the line before it.  If
+    /// `line_idx == len_lines()` then an iterator at the end of the
+    /// `RopeSlice` is created (i.e. `next()` will return `None`).
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line_idx` is out of bounds (i.e. `line_idx > len_lines()`).
+    #[inline]
+    pub fn lines_at(&self, line_idx: usize) -> Lines<'a> {
+        // Bounds check
+        assert!(
+            line_idx <= self.len_lines(),
+            "Attempt to index past end of slice: line index {}, slice line length {}",
+            line_idx,
+            self.len_lines()
+        );
+
+        match *self {
+            RopeSlice(RSEnum::Full {
+                node,
+                start_char,
+                end_char,
+                policy,
+                ..
+            }) => Lines::new_with_range_at(
+                node,
+                line_idx,
+                start_char as usize,
+                end_char as usize,
+                policy,
+            ),
+            RopeSlice(RSEnum::Light { text, policy, .. }) => {
+                Lines::from_str_at(text, line_idx, policy)
+            }
+        }
+    }
+
+    /// Creates an iterator over the chunks of the `RopeSlice`, with the
+    /// iterator starting at the chunk containing `byte_idx`.
+    ///
+    /// Also returns the byte and char indices of the beginning of that
+    /// chunk and the index of the line that the chunk starts on.
+    ///
+    /// The first call to `next()` returns the chunk containing `byte_idx`,
+    /// and the first call to `prev()` returns the chunk before it.  If
+    /// `byte_idx == len_bytes()` then an iterator at the end of the
+    /// `RopeSlice` is created (i.e. `next()` will return `None`), and the
+    /// returned indices are those of the last chunk.
+    ///
+    /// The return value is organized as
+    /// `(iterator, chunk_byte_idx, chunk_char_idx, chunk_line_idx)`.
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_idx` is out of bounds (i.e. `byte_idx > len_bytes()`).
+    #[inline]
+    pub fn chunks_at_byte(&self, byte_idx: usize) -> (Chunks<'a>, usize, usize, usize) {
+        let (_, b, c, l) = self.chunk_at_byte(byte_idx);
+        let at_byte = if byte_idx == self.len_bytes() {
+            byte_idx
+        } else {
+            b
+        };
+        (self.chunks_at(at_byte), b, c, l)
+    }
+
+    /// Creates an iterator over the chunks of the `RopeSlice`, with the
+    /// iterator starting at the chunk containing `char_idx`.
+    ///
+    /// Also returns the byte and char indices of the beginning of that
+    /// chunk and the index of the line that the chunk starts on.
+    ///
+    /// The first call to `next()` returns the chunk containing `char_idx`,
+    /// and the first call to `prev()` returns the chunk before it.  If
+    /// `char_idx == len_chars()` then an iterator at the end of the
+    /// `RopeSlice` is created (i.e. `next()` will return `None`), and the
+    /// returned indices are those of the last chunk.
+    ///
+    /// The return value is organized as
+    /// `(iterator, chunk_byte_idx, chunk_char_idx, chunk_line_idx)`.
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    #[inline]
+    pub fn chunks_at_char(&self, char_idx: usize) -> (Chunks<'a>, usize, usize, usize) {
+        let (_, b, c, l) = self.chunk_at_char(char_idx);
+        let at_byte = if char_idx == self.len_chars() {
+            self.len_bytes()
+        } else {
+            b
+        };
+        (self.chunks_at(at_byte), b, c, l)
+    }
+
+    /// Creates an iterator over the chunks of the `RopeSlice`, with the
+    /// iterator starting at the chunk containing the given line break.
+    ///
+    /// Also returns the byte and char indices of the beginning of that
+    /// chunk and the index of the line that the chunk starts on.
+    ///
+    /// As with [`chunk_at_line_break()`](#method.chunk_at_line_break), both
+    /// the beginning and end of the slice are considered line breaks.  If
+    /// `line_break_idx == len_lines()` then an iterator at the end of the
+    /// `RopeSlice` is created (i.e. `next()` will return `None`), and the
+    /// returned indices are those of the last chunk.
+    ///
+    /// The return value is organized as
+    /// `(iterator, chunk_byte_idx, chunk_char_idx, chunk_line_idx)`.
+    ///
+    /// Runs in O(log N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line_break_idx` is out of bounds (i.e. `line_break_idx > len_lines()`).
+    #[inline]
+    pub fn chunks_at_line_break(&self, line_break_idx: usize) -> (Chunks<'a>, usize, usize, usize) {
+        let (_, b, c, l) = self.chunk_at_line_break(line_break_idx);
+        let at_byte = if line_break_idx == self.len_lines() {
+            self.len_bytes()
+        } else {
+            b
+        };
+        (self.chunks_at(at_byte), b, c, l)
+    }
+
+    #[inline]
+    fn chunks_at(&self, at_byte: usize) -> Chunks<'a> {
+        match *self {
+            RopeSlice(RSEnum::Full {
+                node,
+                start_char,
+                end_char,
+                ..
+            }) => Chunks::new_with_range_at(node, at_byte, start_char as usize, end_char as usize),
+            RopeSlice(RSEnum::Light { text, .. }) => Chunks::from_str_at(text, at_byte),
+        }
+    }
+
     //-----------------------------------------------------------------------
     // Conversion methods
 
@@ -719,14 +1141,19 @@ impl<'a> RopeSlice<'a> {
                 node,
                 start_char,
                 end_char,
+                policy,
                 ..
             }) => {
                 let mut rope = Rope {
                     root: Arc::clone(node),
+                    line_break_policy: policy,
+                    insert_line_ending: None,
+                    marks: MarkSet::new(),
+                    escaped_bytes: EscapedBytes::new(),
                 };
 
                 // Chop off right end if needed
-                if end_char < node.text_info().chars {
+                if (end_char as usize) < node.char_count() {
                     rope.split_off(end_char as usize);
                 }
 
@@ -738,32 +1165,694 @@ impl<'a> RopeSlice<'a> {
                 // Return the rope
                 rope
             }
-            RopeSlice(RSEnum::Light { text, .. }) => Rope::from_str(text),
+            RopeSlice(RSEnum::Light { text, policy, .. }) => {
+                let mut rope = Rope::from_str(text);
+                rope.set_line_break_policy(policy);
+                rope
+            }
         }
     }
 }
 
-//==============================================================
-
-impl<'a> std::fmt::Debug for RopeSlice<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        f.debug_list().entries(self.chunks()).finish()
+/// # Searching
+///
+/// Matches are found even when they span chunk boundaries, without copying
+/// the text.  Patterns are matched byte-for-byte, and match positions are
+/// returned as char indices.
+impl<'a> RopeSlice<'a> {
+    /// Returns the char index of the first match of `pattern`, or `None`
+    /// if there is none.
+    ///
+    /// As with `str::find()`, an empty pattern matches at char index 0.
+    ///
+    /// Runs in O(N) time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// let rope = Rope::from_str("Hello みんなさん! Hello again!");
+    /// let slice = rope.slice(..);
+    ///
+    /// assert_eq!(slice.find("Hello"), Some(0));
+    /// assert_eq!(slice.find("again"), Some(19));
+    /// assert_eq!(slice.find("goodbye"), None);
+    /// ```
+    #[inline]
+    pub fn find(&self, pattern: &str) -> Option<usize> {
+        self.find_from(0, pattern)
     }
-}
 
-impl<'a> std::fmt::Display for RopeSlice<'a> {
+    /// Returns the char index of the first match of `pattern` that starts
+    /// at or after `char_idx`, or `None` if there is none.
+    ///
+    /// Runs in O(M + log N) time, where M is the distance searched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
     #[inline]
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        for chunk in self.chunks() {
-            write!(f, "{}", chunk)?
-        }
-        Ok(())
+    pub fn find_from(&self, char_idx: usize, pattern: &str) -> Option<usize> {
+        self.matches_from(char_idx, pattern).next()
     }
-}
 
-impl<'a, 'b> std::cmp::PartialEq<RopeSlice<'b>> for RopeSlice<'a> {
+    /// Returns the char index of the last match of `pattern`, or `None` if
+    /// there is none.
+    ///
+    /// As with `str::rfind()`, an empty pattern matches at `len_chars()`.
+    ///
+    /// Runs in O(N) time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// let rope = Rope::from_str("Hello みんなさん! Hello again!");
+    ///
+    /// assert_eq!(rope.slice(..).rfind("Hello"), Some(13));
+    /// assert_eq!(rope.slice(..12).rfind("Hello"), Some(0));
+    /// ```
     #[inline]
-    fn eq(&self, other: &RopeSlice<'b>) -> bool {
+    pub fn rfind(&self, pattern: &str) -> Option<usize> {
+        search::rfind(self, pattern)
+    }
+
+    /// Creates an iterator over the non-overlapping matches of `pattern`.
+    ///
+    /// The iterator yields the char index of the start of each match, in
+    /// order.  As with `str::matches()`, an empty pattern matches at every
+    /// char index, including `len_chars()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// let rope = Rope::from_str("aaa ba aaa");
+    /// let matches: Vec<usize> = rope.slice(..).matches("aa").collect();
+    ///
+    /// assert_eq!(matches, vec![0, 7]);
+    /// ```
+    #[inline]
+    pub fn matches(&self, pattern: &str) -> Matches<'a> {
+        self.matches_from(0, pattern)
+    }
+
+    /// Like [`matches()`](#method.matches), but only yields the matches
+    /// that start at or after `char_idx`.
+    ///
+    /// Runs in O(log N) time to create the iterator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    #[inline]
+    pub fn matches_from(&self, char_idx: usize, pattern: &str) -> Matches<'a> {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to search from past end of RopeSlice: char index {}, RopeSlice char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        Matches::new_at(self, pattern, char_idx)
+    }
+
+    /// Returns the number of non-overlapping matches of `pattern`.
+    ///
+    /// Runs in O(N) time.
+    #[inline]
+    pub fn count_matches(&self, pattern: &str) -> usize {
+        self.matches(pattern).count()
+    }
+
+    /// Returns the char range of the first match of `pattern` according
+    /// to `options`, or `None` if there is none.
+    ///
+    /// See [`SearchOptions`](search/struct.SearchOptions.html) for the
+    /// available options.
+    ///
+    /// Runs in O(N) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range in `options` is reversed or out of bounds.
+    #[inline]
+    pub fn find_with(&self, pattern: &str, options: &SearchOptions) -> Option<Range<usize>> {
+        self.matches_with(pattern, options).next()
+    }
+
+    /// Creates an iterator over the non-overlapping matches of `pattern`
+    /// according to `options`.
+    ///
+    /// The iterator yields the char range of each match, in order.  See
+    /// [`SearchOptions`](search/struct.SearchOptions.html) for the
+    /// available options.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// # use ropey::search::SearchOptions;
+    /// let rope = Rope::from_str("Rope, rope, ropes and ROPE.");
+    /// let options = SearchOptions::new().ignore_case(true).range(1..);
+    /// let matches: Vec<_> = rope.slice(..).matches_with("rope", &options).collect();
+    ///
+    /// assert_eq!(matches, vec![6..10, 12..16, 22..26]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range in `options` is reversed or out of bounds.
+    #[inline]
+    pub fn matches_with(&self, pattern: &str, options: &SearchOptions) -> MatchesWith<'a> {
+        let range = options.char_range(self.len_chars());
+
+        // Bounds check
+        assert!(range.start <= range.end);
+        assert!(
+            range.end <= self.len_chars(),
+            "Attempt to search past end of RopeSlice: search range end {}, RopeSlice char length {}",
+            range.end,
+            self.len_chars()
+        );
+
+        MatchesWith::new(self, pattern, options)
+    }
+
+    /// Returns the pattern id and char range of the first match of any of
+    /// `patterns`, or `None` if there is none.
+    ///
+    /// See [`MultiPattern`](search/struct.MultiPattern.html) for which
+    /// match is found when several patterns match.  Only available with
+    /// the `multi-pattern` cargo feature enabled.
+    ///
+    /// Runs in O(N) time.
+    #[cfg(feature = "multi-pattern")]
+    #[inline]
+    pub fn find_multi(&self, patterns: &MultiPattern) -> Option<(usize, Range<usize>)> {
+        self.matches_multi(patterns).next()
+    }
+
+    /// Creates an iterator over the non-overlapping matches of any of
+    /// `patterns`.
+    ///
+    /// The iterator yields the pattern id and char range of each match, in
+    /// order.  The whole search takes a single pass over the text, however
+    /// many patterns there are.
+    ///
+    /// Only available with the `multi-pattern` cargo feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// # use ropey::search::MultiPattern;
+    /// let patterns = MultiPattern::new(&["apple", "app", "pie"]);
+    /// let rope = Rope::from_str("apple pie, apricot applet");
+    /// let matches: Vec<_> = rope.slice(..).matches_multi(&patterns).collect();
+    ///
+    /// assert_eq!(matches, vec![(0, 0..5), (2, 6..9), (0, 19..24)]);
+    /// ```
+    #[cfg(feature = "multi-pattern")]
+    #[inline]
+    pub fn matches_multi<'b>(&self, patterns: &'b MultiPattern) -> MultiMatches<'a, 'b> {
+        MultiMatches::new(self, patterns)
+    }
+}
+
+/// # Regular expressions
+///
+/// Only available with the `regex` cargo feature enabled.  See the
+/// [`regex`](regex/index.html) module for the pattern syntax.
+#[cfg(feature = "regex")]
+impl<'a> RopeSlice<'a> {
+    /// Returns the char range of the first match of `regex`, or `None` if
+    /// there is none.
+    ///
+    /// Runs in O(N) time.
+    #[inline]
+    pub fn find_regex(&self, regex: &Regex) -> Option<Range<usize>> {
+        self.matches_regex(regex).next()
+    }
+
+    /// Returns the char range of the match of `regex` that starts last, or
+    /// `None` if there is none.
+    ///
+    /// The search goes backwards from the end, so it only covers as much of
+    /// the text as it has to.  Note that the match that starts last may
+    /// overlap a match that a forward search would find, e.g. searching
+    /// backwards for `\d+` in "123" finds "3".  Of the matches that start at
+    /// the same position, the one found is the one that a forward search
+    /// would find.
+    ///
+    /// Runs in O(N) time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// # use ropey::regex::Regex;
+    /// let rope = Rope::from_str("1, 22, 333.");
+    ///
+    /// assert_eq!(rope.slice(..).rfind_regex(&Regex::new(r"\d+").unwrap()), Some(9..10));
+    /// assert_eq!(rope.slice(..).rfind_regex(&Regex::new(r"\b\d+").unwrap()), Some(7..10));
+    /// assert_eq!(rope.slice(..9).rfind_regex(&Regex::new(r"\b\d+").unwrap()), Some(7..9));
+    /// ```
+    #[inline]
+    pub fn rfind_regex(&self, regex: &Regex) -> Option<Range<usize>> {
+        regex.rsearch(self).map(|slots| slots[0].unwrap()..slots[1].unwrap())
+    }
+
+    /// Creates an iterator over the non-overlapping matches of `regex`.
+    ///
+    /// The iterator yields the char range of each match, in order.  As with
+    /// the `regex` crate, an empty match is never found right where the
+    /// previous match ended.
+    ///
+    /// Assertions like `^` and `\b` look at the `RopeSlice` only, and
+    /// don't see any text outside of it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ropey::Rope;
+    /// # use ropey::regex::Regex;
+    /// let re = Regex::new(r"\bt\w+").unwrap();
+    /// let rope = Rope::from_str("This is the time to start.");
+    /// let matches: Vec<_> = rope.slice(..).matches_regex(&re).collect();
+    ///
+    /// assert_eq!(matches, vec![8..11, 12..16, 17..19]);
+    /// ```
+    #[inline]
+    pub fn matches_regex<'b>(&self, regex: &'b Regex) -> RegexMatches<'a, 'b> {
+        RegexMatches::new(self, regex)
+    }
+
+    /// Returns the capture groups of the first match of `regex`, or `None`
+    /// if there is none.
+    ///
+    /// Runs in O(N) time.
+    #[inline]
+    pub fn captures_regex(&self, regex: &Regex) -> Option<Captures> {
+        self.captures_iter_regex(regex).next()
+    }
+
+    /// Creates an iterator over the capture groups of the non-overlapping
+    /// matches of `regex`.
+    ///
+    /// Matches are found the same way as by
+    /// [`matches_regex()`](#method.matches_regex).
+    #[inline]
+    pub fn captures_iter_regex<'b>(&self, regex: &'b Regex) -> CapturesIter<'a, 'b> {
+        CapturesIter::new(self, regex)
+    }
+}
+
+/// # Grapheme clusters
+///
+/// Only available with the `graphemes` cargo feature enabled.  Grapheme
+/// clusters here are Unicode extended grapheme clusters, and are found
+/// correctly even when they span chunk boundaries.
+#[cfg(feature = "graphemes")]
+impl<'a> RopeSlice<'a> {
+    /// Returns whether `char_idx` is on a grapheme cluster boundary.
+    ///
+    /// The start and end of the `RopeSlice` are always boundaries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    pub fn is_grapheme_boundary(&self, char_idx: usize) -> bool {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to index past end of slice: char index {}, slice char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        is_grapheme_boundary_byte(self, self.char_to_byte(char_idx))
+    }
+
+    /// Returns the char index of the grapheme cluster boundary before
+    /// `char_idx`.
+    ///
+    /// Returns 0 if `char_idx` is 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    pub fn prev_grapheme_boundary(&self, char_idx: usize) -> usize {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to index past end of slice: char index {}, slice char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        let byte_idx = prev_grapheme_boundary_byte(self, self.char_to_byte(char_idx));
+        self.byte_to_char(byte_idx)
+    }
+
+    /// Returns the char index of the grapheme cluster boundary after
+    /// `char_idx`.
+    ///
+    /// Returns `len_chars()` if `char_idx` is `len_chars()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is out of bounds (i.e. `char_idx > len_chars()`).
+    pub fn next_grapheme_boundary(&self, char_idx: usize) -> usize {
+        // Bounds check
+        assert!(
+            char_idx <= self.len_chars(),
+            "Attempt to index past end of slice: char index {}, slice char length {}",
+            char_idx,
+            self.len_chars()
+        );
+
+        let byte_idx = next_grapheme_boundary_byte(self, self.char_to_byte(char_idx));
+        self.byte_to_char(byte_idx)
+    }
+
+    /// Creates an iterator over the grapheme clusters of the `RopeSlice`.
+    #[inline]
+    pub fn graphemes(&self) -> Graphemes<'a> {
+        Graphemes::new(*self)
+    }
+}
+
+/// # Non-panicking
+///
+/// The methods in this impl block provide non-panicking versions of
+/// `RopeSlice`'s panicking methods.  They return `Err(Error)` in the
+/// situations where their panicking counterparts would have panicked.
+impl<'a> RopeSlice<'a> {
+    /// Non-panicking version of [`byte_to_char()`](#method.byte_to_char).
+    #[inline]
+    pub fn try_byte_to_char(&self, byte_idx: usize) -> Result<usize> {
+        if byte_idx <= self.len_bytes() {
+            Ok(self.byte_to_char(byte_idx))
+        } else {
+            Err(Error::ByteIndexOutOfBounds(byte_idx, self.len_bytes()))
+        }
+    }
+
+    /// Non-panicking version of [`byte_to_line()`](#method.byte_to_line).
+    #[inline]
+    pub fn try_byte_to_line(&self, byte_idx: usize) -> Result<usize> {
+        if byte_idx <= self.len_bytes() {
+            Ok(self.byte_to_line(byte_idx))
+        } else {
+            Err(Error::ByteIndexOutOfBounds(byte_idx, self.len_bytes()))
+        }
+    }
+
+    /// Non-panicking version of [`char_to_byte()`](#method.char_to_byte).
+    #[inline]
+    pub fn try_char_to_byte(&self, char_idx: usize) -> Result<usize> {
+        if char_idx <= self.len_chars() {
+            Ok(self.char_to_byte(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`char_to_line()`](#method.char_to_line).
+    #[inline]
+    pub fn try_char_to_line(&self, char_idx: usize) -> Result<usize> {
+        if char_idx <= self.len_chars() {
+            Ok(self.char_to_line(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`line_to_byte()`](#method.line_to_byte).
+    #[inline]
+    pub fn try_line_to_byte(&self, line_idx: usize) -> Result<usize> {
+        if line_idx <= self.len_lines() {
+            Ok(self.line_to_byte(line_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_idx, self.len_lines()))
+        }
+    }
+
+    /// Non-panicking version of [`line_to_char()`](#method.line_to_char).
+    #[inline]
+    pub fn try_line_to_char(&self, line_idx: usize) -> Result<usize> {
+        if line_idx <= self.len_lines() {
+            Ok(self.line_to_char(line_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_idx, self.len_lines()))
+        }
+    }
+
+    /// Non-panicking version of [`char_to_utf16_cu()`](#method.char_to_utf16_cu).
+    #[inline]
+    pub fn try_char_to_utf16_cu(&self, char_idx: usize) -> Result<usize> {
+        if char_idx <= self.len_chars() {
+            Ok(self.char_to_utf16_cu(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`utf16_cu_to_char()`](#method.utf16_cu_to_char).
+    #[inline]
+    pub fn try_utf16_cu_to_char(&self, utf16_cu_idx: usize) -> Result<usize> {
+        if utf16_cu_idx <= self.len_utf16_cu() {
+            Ok(self.utf16_cu_to_char(utf16_cu_idx))
+        } else {
+            Err(Error::Utf16IndexOutOfBounds(utf16_cu_idx, self.len_utf16_cu()))
+        }
+    }
+
+    /// Non-panicking version of [`char()`](#method.char).
+    #[inline]
+    pub fn try_char(&self, char_idx: usize) -> Result<char> {
+        if char_idx < self.len_chars() {
+            Ok(self.char(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`line()`](#method.line).
+    #[inline]
+    pub fn try_line(&self, line_idx: usize) -> Result<RopeSlice<'a>> {
+        if line_idx < self.len_lines() {
+            Ok(self.line(line_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_idx, self.len_lines()))
+        }
+    }
+
+    /// Non-panicking version of [`chunk_at_byte()`](#method.chunk_at_byte).
+    #[inline]
+    pub fn try_chunk_at_byte(&self, byte_idx: usize) -> Result<(&'a str, usize, usize, usize)> {
+        if byte_idx <= self.len_bytes() {
+            Ok(self.chunk_at_byte(byte_idx))
+        } else {
+            Err(Error::ByteIndexOutOfBounds(byte_idx, self.len_bytes()))
+        }
+    }
+
+    /// Non-panicking version of [`chunk_at_char()`](#method.chunk_at_char).
+    #[inline]
+    pub fn try_chunk_at_char(&self, char_idx: usize) -> Result<(&'a str, usize, usize, usize)> {
+        if char_idx <= self.len_chars() {
+            Ok(self.chunk_at_char(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`chunk_at_line_break()`](#method.chunk_at_line_break).
+    #[inline]
+    pub fn try_chunk_at_line_break(
+        &self,
+        line_break_idx: usize,
+    ) -> Result<(&'a str, usize, usize, usize)> {
+        if line_break_idx <= self.len_lines() {
+            Ok(self.chunk_at_line_break(line_break_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_break_idx, self.len_lines()))
+        }
+    }
+
+    /// Non-panicking version of [`slice()`](#method.slice).
+    #[inline]
+    pub fn try_slice<R: CharIdxRange>(&self, char_range: R) -> Result<Self> {
+        let start = char_range.start().unwrap_or(0);
+        let end = char_range.end().unwrap_or_else(|| self.len_chars());
+
+        if start > end {
+            Err(Error::CharRangeInvalid(start, end))
+        } else if end > self.len_chars() {
+            Err(Error::CharIndexOutOfBounds(end, self.len_chars()))
+        } else {
+            Ok(self.slice(start..end))
+        }
+    }
+
+    /// Non-panicking version of [`byte_slice()`](#method.byte_slice).
+    pub fn try_byte_slice<R: CharIdxRange>(&self, byte_range: R) -> Result<Self> {
+        let start = byte_range.start().unwrap_or(0);
+        let end = byte_range.end().unwrap_or_else(|| self.len_bytes());
+
+        // Bounds check
+        if start > end {
+            return Err(Error::ByteRangeInvalid(start, end));
+        } else if end > self.len_bytes() {
+            return Err(Error::ByteIndexOutOfBounds(end, self.len_bytes()));
+        }
+
+        // Char boundary check
+        for &byte_idx in &[start, end] {
+            let (chunk, b, _, _) = self.chunk_at_byte(byte_idx);
+            if !chunk.is_char_boundary(byte_idx - b) {
+                return Err(Error::ByteIndexNotCharBoundary(byte_idx));
+            }
+        }
+
+        Ok(self.slice(self.byte_to_char(start)..self.byte_to_char(end)))
+    }
+
+    /// Non-panicking version of [`bytes_at()`](#method.bytes_at).
+    #[inline]
+    pub fn try_bytes_at(&self, byte_idx: usize) -> Result<Bytes<'a>> {
+        if byte_idx <= self.len_bytes() {
+            Ok(self.bytes_at(byte_idx))
+        } else {
+            Err(Error::ByteIndexOutOfBounds(byte_idx, self.len_bytes()))
+        }
+    }
+
+    /// Non-panicking version of [`chars_at()`](#method.chars_at).
+    #[inline]
+    pub fn try_chars_at(&self, char_idx: usize) -> Result<Chars<'a>> {
+        if char_idx <= self.len_chars() {
+            Ok(self.chars_at(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`lines_at()`](#method.lines_at).
+    #[inline]
+    pub fn try_lines_at(&self, line_idx: usize) -> Result<Lines<'a>> {
+        if line_idx <= self.len_lines() {
+            Ok(self.lines_at(line_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_idx, self.len_lines()))
+        }
+    }
+
+    /// Non-panicking version of [`chunks_at_byte()`](#method.chunks_at_byte).
+    #[inline]
+    pub fn try_chunks_at_byte(&self, byte_idx: usize) -> Result<(Chunks<'a>, usize, usize, usize)> {
+        if byte_idx <= self.len_bytes() {
+            Ok(self.chunks_at_byte(byte_idx))
+        } else {
+            Err(Error::ByteIndexOutOfBounds(byte_idx, self.len_bytes()))
+        }
+    }
+
+    /// Non-panicking version of [`chunks_at_char()`](#method.chunks_at_char).
+    #[inline]
+    pub fn try_chunks_at_char(&self, char_idx: usize) -> Result<(Chunks<'a>, usize, usize, usize)> {
+        if char_idx <= self.len_chars() {
+            Ok(self.chunks_at_char(char_idx))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`find_from()`](#method.find_from).
+    #[inline]
+    pub fn try_find_from(&self, char_idx: usize, pattern: &str) -> Result<Option<usize>> {
+        if char_idx <= self.len_chars() {
+            Ok(self.find_from(char_idx, pattern))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`matches_from()`](#method.matches_from).
+    #[inline]
+    pub fn try_matches_from(&self, char_idx: usize, pattern: &str) -> Result<Matches<'a>> {
+        if char_idx <= self.len_chars() {
+            Ok(self.matches_from(char_idx, pattern))
+        } else {
+            Err(Error::CharIndexOutOfBounds(char_idx, self.len_chars()))
+        }
+    }
+
+    /// Non-panicking version of [`find_with()`](#method.find_with).
+    #[inline]
+    pub fn try_find_with(
+        &self,
+        pattern: &str,
+        options: &SearchOptions,
+    ) -> Result<Option<Range<usize>>> {
+        Ok(self.try_matches_with(pattern, options)?.next())
+    }
+
+    /// Non-panicking version of [`matches_with()`](#method.matches_with).
+    #[inline]
+    pub fn try_matches_with(
+        &self,
+        pattern: &str,
+        options: &SearchOptions,
+    ) -> Result<MatchesWith<'a>> {
+        let range = options.char_range(self.len_chars());
+        if range.start > range.end {
+            Err(Error::CharRangeInvalid(range.start, range.end))
+        } else if range.end > self.len_chars() {
+            Err(Error::CharIndexOutOfBounds(range.end, self.len_chars()))
+        } else {
+            Ok(MatchesWith::new(self, pattern, options))
+        }
+    }
+
+    /// Non-panicking version of [`chunks_at_line_break()`](#method.chunks_at_line_break).
+    #[inline]
+    pub fn try_chunks_at_line_break(
+        &self,
+        line_break_idx: usize,
+    ) -> Result<(Chunks<'a>, usize, usize, usize)> {
+        if line_break_idx <= self.len_lines() {
+            Ok(self.chunks_at_line_break(line_break_idx))
+        } else {
+            Err(Error::LineIndexOutOfBounds(line_break_idx, self.len_lines()))
+        }
+    }
+}
+
+//==============================================================
+
+impl<'a> std::fmt::Debug for RopeSlice<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.chunks()).finish()
+    }
+}
+
+impl<'a> std::fmt::Display for RopeSlice<'a> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for chunk in self.chunks() {
+            write!(f, "{}", chunk)?
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'b> std::cmp::PartialEq<RopeSlice<'b>> for RopeSlice<'a> {
+    #[inline]
+    fn eq(&self, other: &RopeSlice<'b>) -> bool {
         if self.len_bytes() != other.len_bytes() {
             return false;
         }
@@ -898,6 +1987,172 @@ impl<'a> std::cmp::PartialEq<RopeSlice<'a>> for Rope {
     }
 }
 
+impl<'a> std::cmp::Eq for RopeSlice<'a> {}
+
+impl<'a> std::cmp::Ord for RopeSlice<'a> {
+    #[inline]
+    fn cmp(&self, other: &RopeSlice<'a>) -> std::cmp::Ordering {
+        cmp_chunks(self.chunks(), other.chunks())
+    }
+}
+
+impl<'a, 'b> std::cmp::PartialOrd<RopeSlice<'b>> for RopeSlice<'a> {
+    #[inline]
+    fn partial_cmp(&self, other: &RopeSlice<'b>) -> Option<std::cmp::Ordering> {
+        Some(cmp_chunks(self.chunks(), other.chunks()))
+    }
+}
+
+impl<'a, 'b> std::cmp::PartialOrd<&'b str> for RopeSlice<'a> {
+    #[inline]
+    fn partial_cmp(&self, other: &&'b str) -> Option<std::cmp::Ordering> {
+        match *self {
+            RopeSlice(RSEnum::Full { .. }) => {
+                Some(cmp_chunks(self.chunks(), std::iter::once(*other)))
+            }
+            RopeSlice(RSEnum::Light { text, .. }) => Some(text.cmp(*other)),
+        }
+    }
+}
+
+impl<'a, 'b> std::cmp::PartialOrd<RopeSlice<'a>> for &'b str {
+    #[inline]
+    fn partial_cmp(&self, other: &RopeSlice<'a>) -> Option<std::cmp::Ordering> {
+        other.partial_cmp(self).map(|o| o.reverse())
+    }
+}
+
+impl<'a> std::cmp::PartialOrd<str> for RopeSlice<'a> {
+    #[inline]
+    fn partial_cmp(&self, other: &str) -> Option<std::cmp::Ordering> {
+        std::cmp::PartialOrd::<&str>::partial_cmp(self, &other)
+    }
+}
+
+impl<'a> std::cmp::PartialOrd<RopeSlice<'a>> for str {
+    #[inline]
+    fn partial_cmp(&self, other: &RopeSlice<'a>) -> Option<std::cmp::Ordering> {
+        std::cmp::PartialOrd::<&str>::partial_cmp(other, &self).map(|o| o.reverse())
+    }
+}
+
+impl<'a> std::cmp::PartialOrd<String> for RopeSlice<'a> {
+    #[inline]
+    fn partial_cmp(&self, other: &String) -> Option<std::cmp::Ordering> {
+        self.partial_cmp(other.as_str())
+    }
+}
+
+impl<'a> std::cmp::PartialOrd<RopeSlice<'a>> for String {
+    #[inline]
+    fn partial_cmp(&self, other: &RopeSlice<'a>) -> Option<std::cmp::Ordering> {
+        self.as_str().partial_cmp(other)
+    }
+}
+
+impl<'a, 'b> std::cmp::PartialOrd<std::borrow::Cow<'b, str>> for RopeSlice<'a> {
+    #[inline]
+    fn partial_cmp(&self, other: &std::borrow::Cow<'b, str>) -> Option<std::cmp::Ordering> {
+        self.partial_cmp(&**other)
+    }
+}
+
+impl<'a, 'b> std::cmp::PartialOrd<RopeSlice<'a>> for std::borrow::Cow<'b, str> {
+    #[inline]
+    fn partial_cmp(&self, other: &RopeSlice<'a>) -> Option<std::cmp::Ordering> {
+        (**self).partial_cmp(other)
+    }
+}
+
+impl<'a> std::cmp::PartialOrd<Rope> for RopeSlice<'a> {
+    #[inline]
+    fn partial_cmp(&self, other: &Rope) -> Option<std::cmp::Ordering> {
+        self.partial_cmp(&other.slice(..))
+    }
+}
+
+impl<'a> std::cmp::PartialOrd<RopeSlice<'a>> for Rope {
+    #[inline]
+    fn partial_cmp(&self, other: &RopeSlice<'a>) -> Option<std::cmp::Ordering> {
+        self.slice(..).partial_cmp(other)
+    }
+}
+
+impl<'a> std::hash::Hash for RopeSlice<'a> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // `Hasher`s aren't required to produce the same result when the same
+        // bytes are fed to them in differently sized pieces, so the text is
+        // re-blocked into fixed-size pieces first.  That way the hash doesn't
+        // depend on how the text happens to be split into chunks.
+        const BLOCK_SIZE: usize = 256;
+
+        let mut buffer = [0u8; BLOCK_SIZE];
+        let mut buffer_len = 0;
+
+        for chunk in self.chunks() {
+            let mut data = chunk.as_bytes();
+            while !data.is_empty() {
+                let n = (BLOCK_SIZE - buffer_len).min(data.len());
+                buffer[buffer_len..(buffer_len + n)].copy_from_slice(&data[..n]);
+                buffer_len += n;
+                data = &data[n..];
+
+                if buffer_len == BLOCK_SIZE {
+                    state.write(&buffer[..]);
+                    buffer_len = 0;
+                }
+            }
+        }
+        state.write(&buffer[..buffer_len]);
+
+        // Same terminator that `str` uses, so that e.g. tuples of slices
+        // don't collide.
+        state.write_u8(0xff);
+    }
+}
+
+/// Lexicographically compares the bytes of two sequences of chunks, in the
+/// same way that `str`'s `Ord` implementation does.
+fn cmp_chunks<'x, 'y, A, B>(mut chunk_itr_1: A, mut chunk_itr_2: B) -> std::cmp::Ordering
+where
+    A: Iterator<Item = &'x str>,
+    B: Iterator<Item = &'y str>,
+{
+    let mut chunk1: &[u8] = &[];
+    let mut chunk2: &[u8] = &[];
+
+    loop {
+        while chunk1.is_empty() {
+            match chunk_itr_1.next() {
+                Some(chunk) => chunk1 = chunk.as_bytes(),
+                None => break,
+            }
+        }
+        while chunk2.is_empty() {
+            match chunk_itr_2.next() {
+                Some(chunk) => chunk2 = chunk.as_bytes(),
+                None => break,
+            }
+        }
+
+        // If either side ran out, everything up to here was equal.
+        match (chunk1.is_empty(), chunk2.is_empty()) {
+            (true, true) => return std::cmp::Ordering::Equal,
+            (true, false) => return std::cmp::Ordering::Less,
+            (false, true) => return std::cmp::Ordering::Greater,
+            (false, false) => {}
+        }
+
+        let n = chunk1.len().min(chunk2.len());
+        match chunk1[..n].cmp(&chunk2[..n]) {
+            std::cmp::Ordering::Equal => {}
+            ordering => return ordering,
+        }
+        chunk1 = &chunk1[n..];
+        chunk2 = &chunk2[n..];
+    }
+}
+
 //===========================================================
 
 /// Trait to generalize over the various `Range` types for `a..b` syntax when
@@ -948,7 +2203,14 @@ impl CharIdxRange for RangeFull {
 #[cfg(test)]
 mod tests {
     use str_utils::{byte_to_char_idx, byte_to_line_idx, char_to_byte_idx, char_to_line_idx};
-    use Rope;
+    #[cfg(feature = "graphemes")]
+    use unicode_segmentation::UnicodeSegmentation;
+    #[cfg(feature = "regex")]
+    use regex::Regex;
+    #[cfg(feature = "multi-pattern")]
+    use search::MultiPattern;
+    use search::SearchOptions;
+    use {Error, Rope, RopeSlice};
 
     // 127 bytes, 103 chars, 1 line
     const TEXT: &str = "Hello there!  How're you doing?  It's \
@@ -958,6 +2220,30 @@ mod tests {
     const TEXT_LINES: &str = "Hello there!  How're you doing?\nIt's \
                               a fine day, isn't it?\nAren't you glad \
                               we're alive?\nこんにちは、みんなさん！";
+    // 143 bytes, 107 chars, 111 utf16 code units, 1 line
+    const TEXT_EMOJI: &str = "Hello there!🐸  How're you doing?🐸  It's \
+                              a fine day, isn't it?🐸  Aren't you glad \
+                              we're alive?🐸  こんにちは、みんなさん！";
+
+    // Grapheme clusters that are much longer than a chunk (in test builds),
+    // regional indicator pairs, a CRLF pair, and a ZWJ sequence.
+    #[cfg(feature = "graphemes")]
+    const TEXT_GRAPHEMES: &str = "He\u{303}\u{303}\u{303}\u{303}\u{303}\u{303}\u{303}\u{303}llo \
+                                  \u{1F1EC}\u{1F1E7}\u{1F1EF}\u{1F1F5}\u{1F1FA}\u{1F1F8} \
+                                  a\r\nb \u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467} \
+                                  o\u{301}\u{301}\u{301}\u{301}\u{301}\u{301}k!";
+
+    /// Char indices of the grapheme boundaries of `text`, including its
+    /// start and end.
+    #[cfg(feature = "graphemes")]
+    fn grapheme_boundaries(text: &str) -> Vec<usize> {
+        let mut boundaries: Vec<usize> = text
+            .grapheme_indices(true)
+            .map(|(i, _)| byte_to_char_idx(text, i))
+            .collect();
+        boundaries.push(text.chars().count());
+        boundaries
+    }
 
     #[test]
     fn len_bytes_01() {
@@ -1198,6 +2484,78 @@ mod tests {
         s.line_to_char(4);
     }
 
+    #[test]
+    fn len_utf16_cu_01() {
+        let r = Rope::from_str(TEXT_EMOJI);
+        let s = r.slice(5..98);
+        assert_eq!(s.len_utf16_cu(), 97);
+    }
+
+    #[test]
+    fn len_utf16_cu_02() {
+        let r = Rope::from_str(TEXT_EMOJI);
+        let s = r.slice(13..13);
+        assert_eq!(s.len_utf16_cu(), 0);
+    }
+
+    #[test]
+    fn char_to_utf16_cu_01() {
+        let r = Rope::from_str(TEXT_EMOJI);
+        let s = r.slice(5..98);
+
+        assert_eq!(0, s.char_to_utf16_cu(0));
+        assert_eq!(7, s.char_to_utf16_cu(7));
+        assert_eq!(9, s.char_to_utf16_cu(8));
+        assert_eq!(28, s.char_to_utf16_cu(27));
+        assert_eq!(30, s.char_to_utf16_cu(28));
+        assert_eq!(90, s.char_to_utf16_cu(87));
+        assert_eq!(92, s.char_to_utf16_cu(88));
+        assert_eq!(97, s.char_to_utf16_cu(93));
+    }
+
+    #[test]
+    #[should_panic]
+    fn char_to_utf16_cu_02() {
+        let r = Rope::from_str(TEXT_EMOJI);
+        let s = r.slice(5..98);
+
+        s.char_to_utf16_cu(94);
+    }
+
+    #[test]
+    fn utf16_cu_to_char_01() {
+        let r = Rope::from_str(TEXT_EMOJI);
+        let s = r.slice(5..98);
+
+        assert_eq!(0, s.utf16_cu_to_char(0));
+        assert_eq!(7, s.utf16_cu_to_char(7));
+        assert_eq!(7, s.utf16_cu_to_char(8));
+        assert_eq!(8, s.utf16_cu_to_char(9));
+        assert_eq!(27, s.utf16_cu_to_char(29));
+        assert_eq!(28, s.utf16_cu_to_char(30));
+        assert_eq!(87, s.utf16_cu_to_char(91));
+        assert_eq!(88, s.utf16_cu_to_char(92));
+        assert_eq!(93, s.utf16_cu_to_char(97));
+    }
+
+    #[test]
+    #[should_panic]
+    fn utf16_cu_to_char_02() {
+        let r = Rope::from_str(TEXT_EMOJI);
+        let s = r.slice(5..98);
+
+        s.utf16_cu_to_char(98);
+    }
+
+    #[test]
+    fn utf16_cu_char_round_trip() {
+        let r = Rope::from_str(TEXT_EMOJI);
+        let s = r.slice(5..98);
+        for i in 0..(s.len_chars() + 1) {
+            assert_eq!(i, s.utf16_cu_to_char(s.char_to_utf16_cu(i)));
+        }
+    }
+
     #[test]
     fn char_01() {
         let r = Rope::from_str(TEXT);
@@ -1312,73 +2670,216 @@ mod tests {
             assert_eq!(c1, c2);
         }
 
-        assert_eq!(t.len(), 0);
+        assert_eq!(t.len(), 0);
+    }
+
+    #[test]
+    fn chunk_at_char() {
+        let r = Rope::from_str(TEXT_LINES);
+        let s = r.slice(34..96);
+        let text = &TEXT_LINES[34..112];
+        // "'s a fine day, isn't it?\nAren't you glad \
+        //  we're alive?\nこんにちは、みん"
+
+        let mut t = text;
+        let mut prev_chunk = "";
+        for i in 0..s.len_chars() {
+            let (chunk, b, c, l) = s.chunk_at_char(i);
+            assert_eq!(b, char_to_byte_idx(text, c));
+            assert_eq!(l, char_to_line_idx(text, c));
+            if chunk != prev_chunk {
+                assert_eq!(chunk, &t[..chunk.len()]);
+                t = &t[chunk.len()..];
+                prev_chunk = chunk;
+            }
+
+            let c1 = text.chars().nth(i).unwrap();
+            let c2 = {
+                let i2 = i - c;
+                chunk.chars().nth(i2).unwrap()
+            };
+            assert_eq!(c1, c2);
+        }
+        assert_eq!(t.len(), 0);
+    }
+
+    #[test]
+    fn chunk_at_line_break() {
+        let r = Rope::from_str(TEXT_LINES);
+        let s = r.slice(34..96);
+        let text = &TEXT_LINES[34..112];
+        // "'s a fine day, isn't it?\nAren't you glad \
+        //  we're alive?\nこんにちは、みん"
+
+        // First chunk
+        {
+            let (chunk, b, c, l) = s.chunk_at_line_break(0);
+            assert_eq!(chunk, &text[..chunk.len()]);
+            assert_eq!(b, 0);
+            assert_eq!(c, 0);
+            assert_eq!(l, 0);
+        }
+
+        // Middle chunks
+        for i in 1..s.len_lines() {
+            let (chunk, b, c, l) = s.chunk_at_line_break(i);
+            assert_eq!(chunk, &text[b..(b + chunk.len())]);
+            assert_eq!(c, byte_to_char_idx(text, b));
+            assert_eq!(l, byte_to_line_idx(text, b));
+            assert!(l < i);
+            assert!(i <= byte_to_line_idx(text, b + chunk.len()));
+        }
+
+        // Last chunk
+        {
+            let (chunk, b, c, l) = s.chunk_at_line_break(s.len_lines());
+            assert_eq!(chunk, &text[(text.len() - chunk.len())..]);
+            assert_eq!(chunk, &text[b..]);
+            assert_eq!(c, byte_to_char_idx(text, b));
+            assert_eq!(l, byte_to_line_idx(text, b));
+        }
+    }
+
+    #[test]
+    fn bytes_at_01() {
+        let r = Rope::from_str(TEXT_LINES);
+        let s = r.slice(34..96);
+        let text = &TEXT_LINES[34..112];
+
+        for i in 0..(s.len_bytes() + 1) {
+            assert!(s.bytes_at(i).eq(text.bytes().skip(i)));
+
+            let mut bytes = s.bytes_at(i);
+            let mut prev_bytes = Vec::new();
+            while let Some(b) = bytes.prev() {
+                prev_bytes.push(b);
+            }
+            prev_bytes.reverse();
+            assert_eq!(&prev_bytes[..], &text.as_bytes()[..i]);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn bytes_at_02() {
+        let r = Rope::from_str(TEXT_LINES);
+        let s = r.slice(34..96);
+        s.bytes_at(s.len_bytes() + 1);
+    }
+
+    #[test]
+    fn chars_at_01() {
+        let r = Rope::from_str(TEXT_LINES);
+        let s = r.slice(34..96);
+        let text = &TEXT_LINES[34..112];
+
+        for i in 0..(s.len_chars() + 1) {
+            assert!(s.chars_at(i).eq(text.chars().skip(i)));
+
+            let mut chars = s.chars_at(i);
+            let mut prev_chars = Vec::new();
+            while let Some(c) = chars.prev() {
+                prev_chars.push(c);
+            }
+            prev_chars.reverse();
+            assert!(prev_chars.into_iter().eq(text.chars().take(i)));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn chars_at_02() {
+        let r = Rope::from_str(TEXT_LINES);
+        let s = r.slice(34..96);
+        s.chars_at(s.len_chars() + 1);
+    }
+
+    #[test]
+    fn lines_at_01() {
+        let r = Rope::from_str(TEXT_LINES);
+        let s = r.slice(34..96);
+
+        let lines: Vec<_> = s.lines().collect();
+        for i in 0..(s.len_lines() + 1) {
+            let mut itr = s.lines_at(i);
+            assert!(s.lines_at(i).eq(lines[i..].iter().cloned()));
+            for j in (0..i).rev() {
+                assert_eq!(lines[j], itr.prev().unwrap());
+            }
+            assert!(itr.prev().is_none());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn lines_at_02() {
+        let r = Rope::from_str(TEXT_LINES);
+        let s = r.slice(34..96);
+        s.lines_at(s.len_lines() + 1);
     }
 
     #[test]
-    fn chunk_at_char() {
+    fn chunks_at_byte_01() {
         let r = Rope::from_str(TEXT_LINES);
         let s = r.slice(34..96);
         let text = &TEXT_LINES[34..112];
-        // "'s a fine day, isn't it?\nAren't you glad \
-        //  we're alive?\nこんにちは、みん"
 
-        let mut t = text;
-        let mut prev_chunk = "";
-        for i in 0..s.len_chars() {
-            let (chunk, b, c, l) = s.chunk_at_char(i);
-            assert_eq!(b, char_to_byte_idx(text, c));
-            assert_eq!(l, char_to_line_idx(text, c));
-            if chunk != prev_chunk {
-                assert_eq!(chunk, &t[..chunk.len()]);
-                t = &t[chunk.len()..];
-                prev_chunk = chunk;
+        for i in 0..(s.len_bytes() + 1) {
+            let (mut chunks, b, c, l) = s.chunks_at_byte(i);
+            let (chunk, b2, c2, l2) = s.chunk_at_byte(i);
+            assert_eq!((b, c, l), (b2, c2, l2));
+            if i < s.len_bytes() {
+                assert_eq!(Some(chunk), chunks.next());
+                assert_eq!(Some(chunk), chunks.prev());
+            } else {
+                assert!(chunks.next().is_none());
             }
 
-            let c1 = text.chars().nth(i).unwrap();
-            let c2 = {
-                let i2 = i - c;
-                chunk.chars().nth(i2).unwrap()
-            };
-            assert_eq!(c1, c2);
+            let mut prev_text = String::new();
+            while let Some(chunk) = chunks.prev() {
+                prev_text.insert_str(0, chunk);
+            }
+            if i < s.len_bytes() {
+                assert_eq!(&text[..b], prev_text);
+            } else {
+                assert_eq!(text, prev_text);
+            }
         }
-        assert_eq!(t.len(), 0);
     }
 
     #[test]
-    fn chunk_at_line_break() {
+    fn chunks_at_char_01() {
         let r = Rope::from_str(TEXT_LINES);
         let s = r.slice(34..96);
-        let text = &TEXT_LINES[34..112];
-        // "'s a fine day, isn't it?\nAren't you glad \
-        //  we're alive?\nこんにちは、みん"
 
-        // First chunk
-        {
-            let (chunk, b, c, l) = s.chunk_at_line_break(0);
-            assert_eq!(chunk, &text[..chunk.len()]);
-            assert_eq!(b, 0);
-            assert_eq!(c, 0);
-            assert_eq!(l, 0);
+        for i in 0..(s.len_chars() + 1) {
+            let (mut chunks, b, c, l) = s.chunks_at_char(i);
+            let (chunk, b2, c2, l2) = s.chunk_at_char(i);
+            assert_eq!((b, c, l), (b2, c2, l2));
+            if i < s.len_chars() {
+                assert_eq!(Some(chunk), chunks.next());
+            } else {
+                assert!(chunks.next().is_none());
+                assert_eq!(Some(chunk), chunks.prev());
+            }
         }
+    }
 
-        // Middle chunks
-        for i in 1..s.len_lines() {
-            let (chunk, b, c, l) = s.chunk_at_line_break(i);
-            assert_eq!(chunk, &text[b..(b + chunk.len())]);
-            assert_eq!(c, byte_to_char_idx(text, b));
-            assert_eq!(l, byte_to_line_idx(text, b));
-            assert!(l < i);
-            assert!(i <= byte_to_line_idx(text, b + chunk.len()));
-        }
+    #[test]
+    fn chunks_at_line_break_01() {
+        let r = Rope::from_str(TEXT_LINES);
+        let s = r.slice(34..96);
+        let text = &TEXT_LINES[34..112];
 
-        // Last chunk
-        {
-            let (chunk, b, c, l) = s.chunk_at_line_break(s.len_lines());
-            assert_eq!(chunk, &text[(text.len() - chunk.len())..]);
-            assert_eq!(chunk, &text[b..]);
-            assert_eq!(c, byte_to_char_idx(text, b));
-            assert_eq!(l, byte_to_line_idx(text, b));
+        for i in 0..(s.len_lines() + 1) {
+            let (chunks, b, c, l) = s.chunks_at_line_break(i);
+            let (_, b2, c2, l2) = s.chunk_at_line_break(i);
+            assert_eq!((b, c, l), (b2, c2, l2));
+            if i < s.len_lines() {
+                assert_eq!(chunks.collect::<String>(), &text[b..]);
+            } else {
+                assert_eq!(chunks.count(), 0);
+            }
         }
     }
 
@@ -1440,6 +2941,66 @@ mod tests {
         s.slice(37..39);
     }
 
+    #[test]
+    fn byte_slice_01() {
+        let r = Rope::from_str(TEXT);
+        let s1 = r.slice(5..101);
+        let s2 = s1.byte_slice(86..110);
+
+        assert_eq!(&TEXT[91..115], s2);
+        assert_eq!(s1, s1.byte_slice(..));
+    }
+
+    #[test]
+    #[should_panic]
+    fn byte_slice_02() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(5..101);
+        s.byte_slice(87..110);
+    }
+
+    #[test]
+    fn try_slice_01() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(5..101);
+
+        assert_eq!(&TEXT[10..20], s.try_slice(5..15).unwrap());
+        assert_eq!(Err(Error::CharRangeInvalid(15, 5)), s.try_slice(15..5));
+        assert_eq!(Err(Error::CharIndexOutOfBounds(97, 96)), s.try_slice(5..97));
+
+        assert_eq!(Err(Error::ByteRangeInvalid(110, 86)), s.try_byte_slice(110..86));
+        assert_eq!(Err(Error::ByteIndexOutOfBounds(117, 116)), s.try_byte_slice(86..117));
+        assert_eq!(Err(Error::ByteIndexNotCharBoundary(87)), s.try_byte_slice(87..110));
+    }
+
+    #[test]
+    fn try_methods_01() {
+        let r = Rope::from_str(TEXT_LINES);
+        let s = r.slice(34..96);
+
+        assert_eq!(Ok(62), s.try_byte_to_char(78));
+        assert_eq!(Err(Error::ByteIndexOutOfBounds(79, 78)), s.try_byte_to_char(79));
+        assert_eq!(Err(Error::ByteIndexOutOfBounds(79, 78)), s.try_byte_to_line(79));
+        assert_eq!(Err(Error::CharIndexOutOfBounds(63, 62)), s.try_char_to_byte(63));
+        assert_eq!(Err(Error::CharIndexOutOfBounds(63, 62)), s.try_char_to_line(63));
+        assert_eq!(Err(Error::LineIndexOutOfBounds(4, 3)), s.try_line_to_byte(4));
+        assert_eq!(Err(Error::LineIndexOutOfBounds(4, 3)), s.try_line_to_char(4));
+        assert_eq!(Err(Error::CharIndexOutOfBounds(63, 62)), s.try_char_to_utf16_cu(63));
+        assert_eq!(Err(Error::Utf16IndexOutOfBounds(63, 62)), s.try_utf16_cu_to_char(63));
+        assert_eq!(Err(Error::CharIndexOutOfBounds(62, 62)), s.try_char(62));
+        assert_eq!(Err(Error::LineIndexOutOfBounds(3, 3)), s.try_line(3));
+        assert_eq!("'s a fine day, isn't it?\n", s.try_line(0).unwrap());
+        assert!(s.try_chunk_at_byte(79).is_err());
+        assert!(s.try_chunk_at_char(63).is_err());
+        assert!(s.try_chunk_at_line_break(4).is_err());
+        assert!(s.try_bytes_at(79).is_err());
+        assert!(s.try_chars_at(63).is_err());
+        assert!(s.try_lines_at(4).is_err());
+        assert!(s.try_chunks_at_byte(79).is_err());
+        assert!(s.try_chunks_at_char(63).is_err());
+        assert!(s.try_chunks_at_line_break(4).is_err());
+    }
+
     #[test]
     fn eq_str_01() {
         let r = Rope::from_str(TEXT);
@@ -1522,6 +3083,442 @@ mod tests {
         assert_eq!(s, s);
     }
 
+    #[test]
+    fn cmp_str_01() {
+        let r = Rope::from_str(TEXT);
+        let slice = r.slice(20..);
+
+        assert_eq!(Some(std::cmp::Ordering::Equal), slice.partial_cmp(&TEXT[20..]));
+        assert!(slice > &TEXT[20..30]);
+        assert!(&TEXT[20..30] < slice);
+        assert!(slice < "\u{FFFF}");
+        assert!("\u{FFFF}" > slice);
+
+        let s: String = TEXT[..20].into();
+        assert!(r.slice(..10) < s);
+        assert!(s > r.slice(..10));
+    }
+
+    #[test]
+    fn cmp_rope_slice_01() {
+        let r = Rope::from_str(TEXT);
+        let s1 = r.slice(43..97);
+        let s2 = r.slice(43..98);
+        let s3 = r.slice(44..97);
+
+        assert_eq!(std::cmp::Ordering::Equal, s1.cmp(&r.slice(43..97)));
+        assert!(s1 < s2);
+        assert!(s2 > s1);
+        assert_eq!(TEXT[43..].cmp(&TEXT[44..]), s2.cmp(&s3));
+        assert!(r.slice(43..43) < s1);
+    }
+
+    #[test]
+    fn cmp_rope_slice_02() {
+        // Same text, split into chunks at different places.
+        let r1 = Rope::from_str(TEXT);
+        let r2 = Rope::from_str(&TEXT[1..]);
+        let s1 = r1.slice(1..);
+        let s2 = r2.slice(..);
+
+        assert_eq!(std::cmp::Ordering::Equal, s1.cmp(&s2));
+        assert_eq!(Some(std::cmp::Ordering::Equal), s1.partial_cmp(&r2));
+    }
+
+    #[test]
+    fn cmp_light_01() {
+        let s1 = RopeSlice::from_str("abc");
+        let s2 = RopeSlice::from_str("abd");
+
+        assert!(s1 < s2);
+        assert!(s1 < "abd");
+        assert!("abd" > s1);
+        assert_eq!(Some(std::cmp::Ordering::Equal), s1.partial_cmp("abc"));
+    }
+
+    #[test]
+    fn hash_rope_slice_01() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        fn hash<T: Hash>(t: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            t.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let r1 = Rope::from_str(TEXT);
+        let r2 = Rope::from_str(&TEXT[1..]);
+
+        assert_eq!(hash(&r1.slice(1..)), hash(&r2.slice(..)));
+        assert_eq!(hash(&r1.slice(1..)), hash(&RopeSlice::from_str(&TEXT[1..])));
+        assert_ne!(hash(&r1.slice(1..)), hash(&r1.slice(2..)));
+    }
+
+    // Char indices of the non-overlapping matches of `pattern` in `text`.
+    fn str_matches(text: &str, pattern: &str) -> Vec<usize> {
+        text.match_indices(pattern)
+            .map(|(i, _)| byte_to_char_idx(text, i))
+            .collect()
+    }
+
+    const PATTERNS: &[&str] = &[
+        "Hello", "you", "e", "it?  Aren't you", "みんな", "！", "ん", "'", "  ", "zzz",
+    ];
+
+    #[test]
+    fn find_01() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(..);
+
+        for pattern in PATTERNS {
+            let expected = str_matches(TEXT, pattern);
+            assert_eq!(s.find(pattern), expected.first().cloned());
+            assert_eq!(s.matches(pattern).collect::<Vec<_>>(), expected);
+            assert_eq!(s.count_matches(pattern), expected.len());
+            assert_eq!(
+                s.rfind(pattern),
+                TEXT.rfind(pattern).map(|i| byte_to_char_idx(TEXT, i))
+            );
+        }
+    }
+
+    #[test]
+    fn find_02() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(5..95);
+        let text = &TEXT[5..char_to_byte_idx(TEXT, 95)];
+
+        for pattern in PATTERNS {
+            let expected = str_matches(text, pattern);
+            assert_eq!(s.find(pattern), expected.first().cloned());
+            assert_eq!(s.matches(pattern).collect::<Vec<_>>(), expected);
+            assert_eq!(
+                s.rfind(pattern),
+                text.rfind(pattern).map(|i| byte_to_char_idx(text, i))
+            );
+        }
+    }
+
+    #[test]
+    fn find_03() {
+        // Overlapping candidates, and a pattern spanning many chunks.
+        let r = Rope::from_str("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaab");
+        let s = r.slice(..);
+
+        assert_eq!(s.matches("aaa").count(), 13);
+        assert_eq!(s.rfind("aaa"), Some(36));
+        assert_eq!(s.find("aab"), Some(37));
+        assert_eq!(s.find(&"aaaaaaaaaaaaaaaaaaaaab"), Some(18));
+        assert_eq!(s.rfind(&"aaaaaaaaaaaaaaaaaaaaab"), Some(18));
+        assert_eq!(s.find(&"baaaaaaaaaaaaaaaaaaaaa"), None);
+    }
+
+    #[test]
+    fn find_04() {
+        // Empty patterns.
+        let r = Rope::from_str("こんにちは");
+        let s = r.slice(1..4);
+
+        assert_eq!(s.find(""), Some(0));
+        assert_eq!(s.rfind(""), Some(3));
+        assert_eq!(s.find_from(2, ""), Some(2));
+        assert_eq!(s.matches("").collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert_eq!(s.matches_from(3, "").collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn find_light_01() {
+        let s = RopeSlice::from_str("aaa ba aaa");
+
+        assert_eq!(s.find("a"), Some(0));
+        assert_eq!(s.rfind("aa"), Some(8));
+        assert_eq!(s.matches("aa").collect::<Vec<_>>(), vec![0, 7]);
+        assert_eq!(s.find_from(1, "aa"), Some(1));
+        assert_eq!(s.find("ab"), None);
+    }
+
+    #[test]
+    fn find_with_01() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(..);
+        let options = SearchOptions::new().ignore_case(true);
+
+        assert_eq!(s.find_with("HOW'RE YOU", &options), Some(14..24));
+        assert_eq!(s.find_with("how're you", &SearchOptions::new()), None);
+        assert_eq!(s.find_with("ARE", &options), Some(61..64));
+        assert_eq!(
+            s.matches_with("E", &options).collect::<Vec<_>>(),
+            str_matches(TEXT, "e")
+                .into_iter()
+                .map(|i| i..(i + 1))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(s.find_with("みんな", &options), Some(97..100));
+    }
+
+    #[test]
+    fn find_with_02() {
+        // Whole words.
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(..);
+        let options = SearchOptions::new().whole_word(true);
+
+        assert_eq!(s.find_with("it", &options), Some(56..58));
+        assert_eq!(s.find_with("It", &options), Some(33..35));
+        assert_eq!(s.find_with("Hell", &options), None);
+        assert_eq!(s.find_with("t", &options), Some(54..55));
+        assert_eq!(s.find_with("re", &options), Some(18..20));
+        assert_eq!(
+            s.matches_with("you", &options).collect::<Vec<_>>(),
+            vec![21..24, 68..71]
+        );
+
+        let options = options.ignore_case(true);
+        assert_eq!(s.find_with("IT", &options), Some(33..35));
+    }
+
+    #[test]
+    fn find_with_03() {
+        // Overlapping candidates where only the later one is a whole word.
+        let r = Rope::from_str("xa-a-a a-ax");
+        let s = r.slice(..);
+        let options = SearchOptions::new().whole_word(true);
+
+        assert_eq!(s.matches_with("a-a", &options).collect::<Vec<_>>(), vec![3..6]);
+        assert_eq!(
+            s.matches_with("A-A", &options.ignore_case(true)).collect::<Vec<_>>(),
+            vec![3..6]
+        );
+    }
+
+    #[test]
+    fn find_with_04() {
+        // Limited to a range, with word boundaries outside of it.
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(..);
+
+        let options = SearchOptions::new().range(22..);
+        assert_eq!(s.find_with("you", &options), Some(68..71));
+        let options = SearchOptions::new().range(..23);
+        assert_eq!(s.find_with("you", &options), None);
+        let options = SearchOptions::new().range(21..23);
+        assert_eq!(s.find_with("yo", &options), Some(21..23));
+        assert_eq!(s.find_with("yo", &options.whole_word(true)), None);
+        let options = SearchOptions::new().range(22..).whole_word(true);
+        assert_eq!(s.find_with("ou", &options), None);
+
+        // The ends of a slice are the ends of its text, though.
+        let s = r.slice(22..);
+        let options = SearchOptions::new().whole_word(true);
+        assert_eq!(s.find_with("ou", &options), Some(0..2));
+        assert_eq!(s.find_with("you", &options), Some(46..49));
+    }
+
+    #[test]
+    fn find_with_05() {
+        let r = Rope::from_str("ſtraSSe STRASSE Straße");
+        let s = r.slice(..);
+        let options = SearchOptions::new().ignore_case(true);
+
+        assert_eq!(
+            s.matches_with("strasse", &options).collect::<Vec<_>>(),
+            vec![0..7, 8..15]
+        );
+        assert_eq!(s.find_with("STRAẞE", &options), Some(16..22));
+        assert_eq!(s.matches_with("", &options).count(), 23);
+    }
+
+    #[test]
+    #[should_panic]
+    fn find_with_06() {
+        let r = Rope::from_str(TEXT);
+        r.slice(..).find_with("you", &SearchOptions::new().range(50..104));
+    }
+
+    #[test]
+    fn try_find_with_01() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(..);
+
+        assert_eq!(
+            s.try_find_with("you", &SearchOptions::new().range(50..104)),
+            Err(Error::CharIndexOutOfBounds(104, 103))
+        );
+        assert_eq!(
+            s.try_find_with("you", &SearchOptions::new().range(51..50)),
+            Err(Error::CharRangeInvalid(51, 50))
+        );
+        assert_eq!(
+            s.try_find_with("you", &SearchOptions::new().range(50..103)),
+            Ok(Some(68..71))
+        );
+    }
+
+    #[cfg(feature = "multi-pattern")]
+    #[test]
+    fn find_multi_01() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(..);
+        let patterns = MultiPattern::new(&["you", "it", "こんにちは", "みんな", "re"]);
+
+        assert_eq!(s.find_multi(&patterns), Some((4, 9..11)));
+        assert_eq!(
+            s.matches_multi(&patterns).collect::<Vec<_>>(),
+            vec![
+                (4, 9..11),
+                (4, 18..20),
+                (0, 21..24),
+                (1, 56..58),
+                (4, 62..64),
+                (0, 68..71),
+                (4, 80..82),
+                (2, 91..96),
+                (3, 97..100),
+            ]
+        );
+        assert_eq!(
+            r.slice(22..98).matches_multi(&patterns).collect::<Vec<_>>(),
+            vec![(1, 34..36), (4, 40..42), (0, 46..49), (4, 58..60), (2, 69..74)]
+        );
+    }
+
+    #[cfg(feature = "multi-pattern")]
+    #[test]
+    fn find_multi_02() {
+        // Empty patterns are skipped, but keep their place in the ids.
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(..);
+        let patterns = MultiPattern::new(vec!["", "re", "there", "ん"]);
+
+        assert_eq!(
+            s.matches_multi(&patterns).collect::<Vec<_>>(),
+            vec![
+                (2, 6..11),
+                (1, 18..20),
+                (1, 62..64),
+                (1, 80..82),
+                (3, 92..93),
+                (3, 98..99),
+                (3, 101..102),
+            ]
+        );
+
+        let none: [&str; 0] = [];
+        assert_eq!(s.find_multi(&MultiPattern::new(&none)), None);
+        assert_eq!(s.find_multi(&MultiPattern::new(&[""])), None);
+    }
+
+    #[cfg(feature = "multi-pattern")]
+    #[test]
+    fn find_multi_03() {
+        // A preferred pattern that fails to match well after the start of
+        // the match that's found instead.
+        let r = Rope::from_str("abcdefghijklmnoX abcdefghijklmnop abc");
+        let patterns = MultiPattern::new(&["abcdefghijklmnop", "abc", "bcd"]);
+
+        for s in &[r.slice(..), RopeSlice::from_str("abcdefghijklmnoX abcdefghijklmnop abc")] {
+            assert_eq!(
+                s.matches_multi(&patterns).collect::<Vec<_>>(),
+                vec![(1, 0..3), (0, 17..33), (1, 34..37)]
+            );
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn find_regex_01() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(..);
+        let re = Regex::new(r"(\w+)'(\w+)").unwrap();
+
+        assert_eq!(s.find_regex(&re), Some(14..20));
+        assert_eq!(s.rfind_regex(&re), Some(78..82));
+        assert_eq!(
+            s.matches_regex(&re).collect::<Vec<_>>(),
+            vec![14..20, 33..37, 50..55, 61..67, 77..82]
+        );
+        assert_eq!(
+            s.matches_regex(&Regex::new(r"\w+[!?]").unwrap()).collect::<Vec<_>>(),
+            vec![6..12, 25..31, 56..59, 83..89]
+        );
+        assert_eq!(s.find_regex(&Regex::new(r"み\w+").unwrap()), Some(97..102));
+        assert_eq!(s.find_regex(&Regex::new(r"\d").unwrap()), None);
+        assert_eq!(s.rfind_regex(&Regex::new(r"\d").unwrap()), None);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn find_regex_02() {
+        // Assertions only see the text of the slice.
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(22..);
+        let re = Regex::new(r"^\w+|\w+$").unwrap();
+
+        assert_eq!(s.matches_regex(&re).collect::<Vec<_>>(), vec![0..2]);
+        assert_eq!(r.slice(..99).rfind_regex(&re), Some(98..99));
+        let re = Regex::new(r"み\b").unwrap();
+        assert_eq!(r.slice(..).rfind_regex(&re), None);
+        assert_eq!(r.slice(..98).rfind_regex(&re), Some(97..98));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn captures_regex_01() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(22..);
+        let re = Regex::new(r"(?P<word>\w+)'(\w+)|(z)").unwrap();
+        let caps = s.captures_regex(&re).unwrap();
+
+        assert_eq!(re.captures_len(), 4);
+        assert_eq!(caps.get(0), Some(11..15));
+        assert_eq!(caps.get(1), Some(11..13));
+        assert_eq!(caps.name("word"), Some(11..13));
+        assert_eq!(caps.get(2), Some(14..15));
+        assert_eq!(caps.get(3), None);
+        assert_eq!(caps.get(4), None);
+        assert_eq!(caps.name("other"), None);
+        assert_eq!(s.captures_iter_regex(&re).count(), 4);
+
+        let mut text = String::new();
+        caps.expand(&s, "${2}-$word", &mut text);
+        assert_eq!(text, "s-It");
+    }
+
+    #[test]
+    fn find_from_01() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(..);
+
+        assert_eq!(s.find_from(0, "you"), Some(21));
+        assert_eq!(s.find_from(21, "you"), Some(21));
+        assert_eq!(s.find_from(22, "you"), Some(68));
+        assert_eq!(s.find_from(69, "you"), None);
+        assert_eq!(s.find_from(92, "ん"), Some(92));
+        assert_eq!(s.find_from(103, "ん"), None);
+        assert_eq!(s.matches_from(22, "you").collect::<Vec<_>>(), vec![68]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn find_from_02() {
+        let r = Rope::from_str(TEXT);
+        r.slice(..).find_from(104, "you");
+    }
+
+    #[test]
+    fn try_find_from_01() {
+        let r = Rope::from_str(TEXT);
+        let s = r.slice(..);
+
+        assert_eq!(s.try_find_from(22, "you"), Ok(Some(68)));
+        assert_eq!(
+            s.try_find_from(104, "you"),
+            Err(Error::CharIndexOutOfBounds(104, 103))
+        );
+        assert!(s.try_matches_from(104, "you").is_err());
+    }
+
     #[test]
     fn to_rope_01() {
         let r1 = Rope::from_str(TEXT);
@@ -1559,5 +3556,71 @@ mod tests {
         assert_eq!(s, r2);
     }
 
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn is_grapheme_boundary_01() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let s = r.slice(3..38);
+        let text = s.to_string();
+        let boundaries = grapheme_boundaries(&text);
+
+        for i in 0..=s.len_chars() {
+            assert_eq!(boundaries.contains(&i), s.is_grapheme_boundary(i));
+        }
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    #[should_panic]
+    fn is_grapheme_boundary_02() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let s = r.slice(3..38);
+        s.is_grapheme_boundary(36);
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn prev_grapheme_boundary_01() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let s = r.slice(3..38);
+        let text = s.to_string();
+        let boundaries = grapheme_boundaries(&text);
+
+        assert_eq!(0, s.prev_grapheme_boundary(0));
+        for i in 1..=s.len_chars() {
+            let expected = *boundaries.iter().filter(|&&b| b < i).last().unwrap();
+            assert_eq!(expected, s.prev_grapheme_boundary(i));
+        }
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn next_grapheme_boundary_01() {
+        let r = Rope::from_str(TEXT_GRAPHEMES);
+        let s = r.slice(3..38);
+        let text = s.to_string();
+        let boundaries = grapheme_boundaries(&text);
+
+        for i in 0..s.len_chars() {
+            let expected = *boundaries.iter().find(|&&b| b > i).unwrap();
+            assert_eq!(expected, s.next_grapheme_boundary(i));
+        }
+        assert_eq!(s.len_chars(), s.next_grapheme_boundary(s.len_chars()));
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn grapheme_boundary_light_01() {
+        let s = RopeSlice::from_str(TEXT_GRAPHEMES);
+        let boundaries = grapheme_boundaries(TEXT_GRAPHEMES);
+
+        for i in 0..=s.len_chars() {
+            assert_eq!(boundaries.contains(&i), s.is_grapheme_boundary(i));
+        }
+        assert_eq!(1, s.next_grapheme_boundary(0));
+        assert_eq!(10, s.next_grapheme_boundary(1));
+        assert_eq!(1, s.prev_grapheme_boundary(10));
+    }
+
     // Iterator tests are in the iter module
 }
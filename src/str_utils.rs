@@ -6,6 +6,8 @@
 
 use std;
 
+use {LineBreakPolicy, LineEnding};
+
 // Get the appropriate module (if any) for sse2 types and intrinsics for the
 // platform we're compiling for.
 #[cfg(target_arch = "x86")]
@@ -39,13 +41,20 @@ pub fn byte_to_char_idx(text: &str, byte_idx: usize) -> usize {
 /// Any past-the-end index will return the last line index.
 #[inline]
 pub fn byte_to_line_idx(text: &str, byte_idx: usize) -> usize {
+    byte_to_line_idx_with_policy(text, byte_idx, LineBreakPolicy::Unicode)
+}
+
+/// Same as [`byte_to_line_idx()`](fn.byte_to_line_idx.html), but only
+/// treats the line breaks of the given policy as line breaks.
+#[inline]
+pub fn byte_to_line_idx_with_policy(text: &str, byte_idx: usize, policy: LineBreakPolicy) -> usize {
     use crlf;
     let mut byte_idx = byte_idx.min(text.len());
     while !text.is_char_boundary(byte_idx) {
         byte_idx -= 1;
     }
-    let nl_count = count_line_breaks(&text[..byte_idx]);
-    if crlf::is_break(byte_idx, text.as_bytes()) {
+    let nl_count = count_line_breaks(&text[..byte_idx], policy);
+    if policy == LineBreakPolicy::Lf || crlf::is_break(byte_idx, text.as_bytes()) {
         nl_count
     } else {
         nl_count - 1
@@ -133,6 +142,13 @@ pub fn char_to_line_idx(text: &str, char_idx: usize) -> usize {
     byte_to_line_idx(text, char_to_byte_idx(text, char_idx))
 }
 
+/// Same as [`char_to_line_idx()`](fn.char_to_line_idx.html), but only
+/// treats the line breaks of the given policy as line breaks.
+#[inline]
+pub fn char_to_line_idx_with_policy(text: &str, char_idx: usize, policy: LineBreakPolicy) -> usize {
+    byte_to_line_idx_with_policy(text, char_to_byte_idx(text, char_idx), policy)
+}
+
 /// Converts from line-index to byte-index in a string slice.
 ///
 /// More specifically, this returns the index of the first byte of the given
@@ -141,19 +157,30 @@ pub fn char_to_line_idx(text: &str, char_idx: usize) -> usize {
 /// Any past-the-end index will return the one-past-the-end byte index.
 #[inline]
 pub fn line_to_byte_idx(text: &str, line_idx: usize) -> usize {
+    line_to_byte_idx_with_policy(text, line_idx, LineBreakPolicy::Unicode)
+}
+
+/// Same as [`line_to_byte_idx()`](fn.line_to_byte_idx.html), but only
+/// treats the line breaks of the given policy as line breaks.
+#[inline]
+pub fn line_to_byte_idx_with_policy(text: &str, line_idx: usize, policy: LineBreakPolicy) -> usize {
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     {
         if is_x86_feature_detected!("sse2") {
-            return line_to_byte_idx_inner::<sse2::__m128i>(text, line_idx);
+            return line_to_byte_idx_inner::<sse2::__m128i>(text, line_idx, policy);
         }
     }
 
     // Fallback for non-sse2 platforms.
-    line_to_byte_idx_inner::<usize>(text, line_idx)
+    line_to_byte_idx_inner::<usize>(text, line_idx, policy)
 }
 
 #[inline(always)]
-fn line_to_byte_idx_inner<T: ByteChunk>(text: &str, line_idx: usize) -> usize {
+fn line_to_byte_idx_inner<T: ByteChunk>(
+    text: &str,
+    line_idx: usize,
+    policy: LineBreakPolicy,
+) -> usize {
     let start_ptr = text.as_ptr();
     let end_ptr = unsafe { start_ptr.offset(text.len() as isize) };
 
@@ -163,8 +190,8 @@ fn line_to_byte_idx_inner<T: ByteChunk>(text: &str, line_idx: usize) -> usize {
         // Count line breaks in big chunks.
         if ptr == align_ptr(ptr, T::size()) {
             while unsafe { ptr.offset(T::size() as isize) } < end_ptr {
-                let tmp =
-                    unsafe { count_line_breaks_in_chunks_from_ptr::<T>(ptr, end_ptr) }.sum_bytes();
+                let tmp = unsafe { count_line_breaks_in_chunks_from_ptr::<T>(ptr, end_ptr, policy) }
+                    .sum_bytes();
                 if tmp + line_break_count >= line_idx {
                     break;
                 }
@@ -179,8 +206,9 @@ fn line_to_byte_idx_inner<T: ByteChunk>(text: &str, line_idx: usize) -> usize {
         while ptr < end_aligned_ptr && line_break_count < line_idx {
             let byte = unsafe { *ptr };
 
-            // Handle u{000A}, u{000B}, u{000C}, and u{000D}
-            if (byte <= 0x0D) && (byte >= 0x0A) {
+            // Handle u{000A}, u{000B}, u{000C}, and u{000D}, as far
+            // as the policy allows
+            if (byte <= 0x0D) && (byte >= 0x0A) && is_break_byte(byte, policy) {
                 line_break_count += 1;
 
                 // Check for CRLF and and subtract 1 if it is,
@@ -194,14 +222,14 @@ fn line_to_byte_idx_inner<T: ByteChunk>(text: &str, line_idx: usize) -> usize {
                 }
             }
             // Handle u{0085}
-            else if byte == 0xC2 {
+            else if byte == 0xC2 && policy == LineBreakPolicy::Unicode {
                 let next = unsafe { ptr.offset(1) };
                 if next < end_ptr && unsafe { *next } == 0x85 {
                     line_break_count += 1;
                 }
             }
             // Handle u{2028} and u{2029}
-            else if byte == 0xE2 {
+            else if byte == 0xE2 && policy == LineBreakPolicy::Unicode {
                 let next1 = unsafe { ptr.offset(1) };
                 let next2 = unsafe { ptr.offset(2) };
                 if next1 < end_ptr
@@ -236,10 +264,83 @@ pub fn line_to_char_idx(text: &str, line_idx: usize) -> usize {
     byte_to_char_idx(text, line_to_byte_idx(text, line_idx))
 }
 
+/// Same as [`line_to_char_idx()`](fn.line_to_char_idx.html), but only
+/// treats the line breaks of the given policy as line breaks.
+#[inline]
+pub fn line_to_char_idx_with_policy(text: &str, line_idx: usize, policy: LineBreakPolicy) -> usize {
+    byte_to_char_idx(text, line_to_byte_idx_with_policy(text, line_idx, policy))
+}
+
+/// Converts from char-index to utf16-code-unit-index in a string slice.
+///
+/// Chars outside of the Basic Multilingual Plane are encoded as surrogate
+/// pairs in utf16, and therefore count as two code units.
+///
+/// Any past-the-end index will return the one-past-the-end utf16 code unit
+/// index.
+#[inline]
+pub fn char_to_utf16_cu_idx(text: &str, char_idx: usize) -> usize {
+    let byte_idx = char_to_byte_idx(text, char_idx);
+    let text = &text[..byte_idx];
+    count_chars(text) + count_utf16_surrogates(text)
+}
+
+/// Converts from utf16-code-unit-index to char-index in a string slice.
+///
+/// If the code unit is the second half of a surrogate pair, returns the
+/// index of the char that the surrogate pair encodes.
+///
+/// Any past-the-end index will return the one-past-the-end char index.
+#[inline]
+pub fn utf16_cu_to_char_idx(text: &str, utf16_cu_idx: usize) -> usize {
+    let mut utf16_cu_count = 0;
+    for (char_idx, c) in text.chars().enumerate() {
+        utf16_cu_count += c.len_utf16();
+        if utf16_cu_count > utf16_cu_idx {
+            return char_idx;
+        }
+    }
+    count_chars(text)
+}
+
+/// Returns the char that stands in for a raw byte escaped by
+/// [`InvalidUtf8::Escape`](../enum.InvalidUtf8.html#variant.Escape).
+///
+/// Bytes are stood in for by the chars U+10FF00 to U+10FFFF, in order, at
+/// the end of the last private use plane.  Which chars actually are escaped
+/// bytes is kept track of separately, so these chars can also appear in
+/// text as themselves.
+#[inline]
+pub fn escape_byte(byte: u8) -> char {
+    std::char::from_u32(ESCAPED_BYTES_START + byte as u32).unwrap()
+}
+
 //===========================================================================
 // Internal
 //===========================================================================
 
+const ESCAPED_BYTES_START: u32 = 0x10FF00;
+
+/// Returns `text` with all of its LF, CR and CRLF line endings converted to
+/// `line_ending`.
+pub(crate) fn convert_line_endings(text: &str, line_ending: LineEnding) -> String {
+    let mut converted = String::with_capacity(text.len());
+    let mut last_end = 0;
+    let mut after_cr = false;
+    for (byte_idx, byte) in text.bytes().enumerate() {
+        if byte == 0x0A || byte == 0x0D {
+            converted.push_str(&text[last_end..byte_idx]);
+            if !(byte == 0x0A && after_cr) {
+                converted.push_str(line_ending.as_str());
+            }
+            last_end = byte_idx + 1;
+        }
+        after_cr = byte == 0x0D;
+    }
+    converted.push_str(&text[last_end..]);
+    converted
+}
+
 /// Uses bit-fiddling magic to count utf8 chars really quickly.
 /// We actually count the number of non-starting utf8 bytes, since
 /// they have a consistent starting two-bit pattern.  We then
@@ -304,9 +405,71 @@ fn count_chars_internal<T: ByteChunk>(text: &str) -> usize {
     len - inv_count
 }
 
+/// Counts the utf16 surrogate pairs that would be needed to encode the
+/// text.  This is the same as the number of chars outside of the Basic
+/// Multilingual Plane, which in utf8 are exactly the chars with a leading
+/// byte of the form `0b1111_0xxx`.
+#[inline]
+pub(crate) fn count_utf16_surrogates(text: &str) -> usize {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("sse2") {
+            return count_utf16_surrogates_internal::<sse2::__m128i>(text);
+        }
+    }
+
+    // Fallback for non-sse2 platforms.
+    count_utf16_surrogates_internal::<usize>(text)
+}
+
+#[inline(always)]
+fn count_utf16_surrogates_internal<T: ByteChunk>(text: &str) -> usize {
+    let mut ptr = text.as_ptr();
+    let end_ptr = unsafe { ptr.offset(text.len() as isize) };
+    let mut count = 0;
+
+    // Take care of any unaligned bytes at the beginning
+    let end_pre_ptr = align_ptr(ptr, T::size()).min(end_ptr);
+    while ptr < end_pre_ptr {
+        let byte = unsafe { *ptr };
+        count += ((byte & 0xF0) == 0xF0) as usize;
+        ptr = unsafe { ptr.offset(1) };
+    }
+
+    // Use chunks to count multiple bytes at once.
+    let mut ptr = ptr as *const T;
+    let end_mid_ptr = (end_ptr as usize - (end_ptr as usize & (T::size() - 1))) as *const T;
+    let mut acc = T::splat(0);
+    let mut i = 0;
+    while ptr < end_mid_ptr {
+        let n = unsafe { *ptr };
+        let tmp = n.bitand(T::splat(0xf0)).cmp_eq_byte(0xf0);
+        acc = acc.add(tmp);
+        i += 1;
+        if i == T::max_acc() {
+            i = 0;
+            count += acc.sum_bytes();
+            acc = T::splat(0);
+        }
+        ptr = unsafe { ptr.offset(1) };
+    }
+    count += acc.sum_bytes();
+
+    // Take care of any unaligned bytes at the end
+    let mut ptr = ptr as *const u8;
+    while ptr < end_ptr {
+        let byte = unsafe { *ptr };
+        count += ((byte & 0xF0) == 0xF0) as usize;
+        ptr = unsafe { ptr.offset(1) };
+    }
+
+    count
+}
+
 /// Uses bit-fiddling magic to count line breaks really quickly.
 ///
-/// The following unicode sequences are considered newlines by this function:
+/// With `LineBreakPolicy::Unicode`, the following unicode sequences are
+/// considered newlines by this function:
 /// - u{000A}        (Line Feed)
 /// - u{000B}        (Vertical Tab)
 /// - u{000C}        (Form Feed)
@@ -315,21 +478,23 @@ fn count_chars_internal<T: ByteChunk>(text: &str) -> usize {
 /// - u{0085}        (Next Line)
 /// - u{2028}        (Line Separator)
 /// - u{2029}        (Paragraph Separator)
+///
+/// The other policies consider only the subsets of those that they name.
 #[inline]
-pub(crate) fn count_line_breaks(text: &str) -> usize {
+pub(crate) fn count_line_breaks(text: &str, policy: LineBreakPolicy) -> usize {
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     {
         if is_x86_feature_detected!("sse2") {
-            return count_line_breaks_internal::<sse2::__m128i>(text);
+            return count_line_breaks_internal::<sse2::__m128i>(text, policy);
         }
     }
 
     // Fallback for non-sse2 platforms.
-    count_line_breaks_internal::<usize>(text)
+    count_line_breaks_internal::<usize>(text, policy)
 }
 
 #[inline(always)]
-fn count_line_breaks_internal<T: ByteChunk>(text: &str) -> usize {
+fn count_line_breaks_internal<T: ByteChunk>(text: &str, policy: LineBreakPolicy) -> usize {
     let len = text.len();
     let mut ptr = text.as_ptr();
     let end_ptr = unsafe { ptr.offset(len as isize) };
@@ -341,7 +506,9 @@ fn count_line_breaks_internal<T: ByteChunk>(text: &str) -> usize {
             let mut i = 0;
             let mut acc = T::splat(0);
             while unsafe { ptr.offset(T::size() as isize) } < end_ptr {
-                acc = acc.add(unsafe { count_line_breaks_in_chunks_from_ptr::<T>(ptr, end_ptr) });
+                acc = acc.add(unsafe {
+                    count_line_breaks_in_chunks_from_ptr::<T>(ptr, end_ptr, policy)
+                });
                 ptr = unsafe { ptr.offset(T::size() as isize) };
                 i += 1;
                 if i == T::max_acc() {
@@ -358,8 +525,9 @@ fn count_line_breaks_internal<T: ByteChunk>(text: &str) -> usize {
         while ptr < end_aligned_ptr {
             let byte = unsafe { *ptr };
 
-            // Handle u{000A}, u{000B}, u{000C}, and u{000D}
-            if (byte <= 0x0D) && (byte >= 0x0A) {
+            // Handle u{000A}, u{000B}, u{000C}, and u{000D}, as far
+            // as the policy allows
+            if (byte <= 0x0D) && (byte >= 0x0A) && is_break_byte(byte, policy) {
                 count += 1;
 
                 // Check for CRLF and and subtract 1 if it is,
@@ -373,14 +541,14 @@ fn count_line_breaks_internal<T: ByteChunk>(text: &str) -> usize {
                 }
             }
             // Handle u{0085}
-            else if byte == 0xC2 {
+            else if byte == 0xC2 && policy == LineBreakPolicy::Unicode {
                 let next = unsafe { ptr.offset(1) };
                 if next < end_ptr && unsafe { *next } == 0x85 {
                     count += 1;
                 }
             }
             // Handle u{2028} and u{2029}
-            else if byte == 0xE2 {
+            else if byte == 0xE2 && policy == LineBreakPolicy::Unicode {
                 let next1 = unsafe { ptr.offset(1) };
                 let next2 = unsafe { ptr.offset(2) };
                 if next1 < end_ptr
@@ -406,16 +574,29 @@ fn count_line_breaks_internal<T: ByteChunk>(text: &str) -> usize {
 unsafe fn count_line_breaks_in_chunks_from_ptr<T: ByteChunk>(
     ptr: *const u8,
     end_ptr: *const u8,
+    policy: LineBreakPolicy,
 ) -> T {
     let mut acc = T::splat(0);
     let c = *(ptr as *const T);
     let next_ptr = ptr.offset(T::size() as isize);
 
+    // Line Feed only, which needs no special handling of CRLF pairs.
+    if policy == LineBreakPolicy::Lf {
+        return c.cmp_eq_byte(0x0A);
+    }
+
     // Calculate the flags we're going to be working with.
-    let nl_1_flags = c.cmp_eq_byte(0xC2);
-    let sp_1_flags = c.cmp_eq_byte(0xE2);
-    let all_flags = c.bytes_between(0x09, 0x0E);
     let cr_flags = c.cmp_eq_byte(0x0D);
+    let all_flags = if policy == LineBreakPolicy::Unicode {
+        c.bytes_between(0x09, 0x0E)
+    } else {
+        c.cmp_eq_byte(0x0A).add(cr_flags)
+    };
+    let (nl_1_flags, sp_1_flags) = if policy == LineBreakPolicy::Unicode {
+        (c.cmp_eq_byte(0xC2), c.cmp_eq_byte(0xE2))
+    } else {
+        (T::splat(0), T::splat(0))
+    };
 
     // Next Line: u{0085}
     if !nl_1_flags.is_zero() {
@@ -477,6 +658,17 @@ unsafe fn count_line_breaks_in_chunks_from_ptr<T: ByteChunk>(
     acc
 }
 
+/// Returns whether `byte`, which must be in the range 0x0A-0x0D, is a line
+/// break (or the start of one, for CR) under the given policy.
+#[inline(always)]
+fn is_break_byte(byte: u8, policy: LineBreakPolicy) -> bool {
+    match policy {
+        LineBreakPolicy::Lf => byte == 0x0A,
+        LineBreakPolicy::LfCr => byte == 0x0A || byte == 0x0D,
+        LineBreakPolicy::Unicode => true,
+    }
+}
+
 /// Returns the next pointer after `ptr` that is aligned with `alignment`.
 ///
 /// NOTE: only works for power-of-two alignments.
@@ -958,6 +1150,30 @@ impl<'a> Iterator for LineBreakIter<'a> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn escape_byte_01() {
+        for byte in 0..=255u8 {
+            assert_eq!(escape_byte(byte).len_utf8(), 4);
+        }
+        assert_eq!(escape_byte(0x00), '\u{10FF00}');
+        assert_eq!(escape_byte(0xFF), '\u{10FFFF}');
+    }
+
+    #[test]
+    fn convert_line_endings_01() {
+        let text = "a\nb\r\nc\rd\u{2028}\r\r\n\n";
+        assert_eq!(
+            convert_line_endings(text, LineEnding::Lf),
+            "a\nb\nc\nd\u{2028}\n\n\n"
+        );
+        assert_eq!(
+            convert_line_endings(text, LineEnding::Crlf),
+            "a\r\nb\r\nc\r\nd\u{2028}\r\n\r\n\r\n"
+        );
+        assert_eq!(convert_line_endings(text, LineEnding::Cr), "a\rb\rc\rd\u{2028}\r\r\r");
+        assert_eq!(convert_line_endings("", LineEnding::Crlf), "");
+    }
+
     // 124 bytes, 100 chars, 4 lines
     const TEXT_LINES: &str = "Hello there!  How're you doing?\nIt's \
                               a fine day, isn't it?\nAren't you glad \
@@ -976,6 +1192,13 @@ mod tests {
         assert_eq!(100, count_chars(TEXT_LINES));
     }
 
+    #[test]
+    fn count_utf16_surrogates_01() {
+        let text = "Hello せかい! 😀 Hello 🐈🐈! Hello せかい! 😀 Hello 🐈🐈! Hello 😀せかい!";
+        assert_eq!(7, count_utf16_surrogates(text));
+        assert_eq!(0, count_utf16_surrogates(TEXT_LINES));
+    }
+
     #[test]
     fn line_breaks_iter_01() {
         let text = "\u{000A}Hello\u{000D}\u{000A}\u{000D}せ\u{000B}か\u{000C}い\u{0085}. \
@@ -998,13 +1221,45 @@ mod tests {
         let text = "\u{000A}Hello\u{000D}\u{000A}\u{000D}せ\u{000B}か\u{000C}い\u{0085}. \
                     There\u{2028}is something.\u{2029}";
         assert_eq!(48, text.len());
-        assert_eq!(8, count_line_breaks(text));
+        assert_eq!(8, count_line_breaks(text, LineBreakPolicy::Unicode));
     }
 
     #[test]
     fn count_line_breaks_02() {
         let text = "\u{000A}Hello world!  This is a longer text.\u{000D}\u{000A}\u{000D}To better test that skipping by usize doesn't mess things up.\u{000B}Hello せかい!\u{000C}\u{0085}Yet more text.  How boring.\u{2028}Hi.\u{2029}\u{000A}Hello world!  This is a longer text.\u{000D}\u{000A}\u{000D}To better test that skipping by usize doesn't mess things up.\u{000B}Hello せかい!\u{000C}\u{0085}Yet more text.  How boring.\u{2028}Hi.\u{2029}\u{000A}Hello world!  This is a longer text.\u{000D}\u{000A}\u{000D}To better test that skipping by usize doesn't mess things up.\u{000B}Hello せかい!\u{000C}\u{0085}Yet more text.  How boring.\u{2028}Hi.\u{2029}\u{000A}Hello world!  This is a longer text.\u{000D}\u{000A}\u{000D}To better test that skipping by usize doesn't mess things up.\u{000B}Hello せかい!\u{000C}\u{0085}Yet more text.  How boring.\u{2028}Hi.\u{2029}";
-        assert_eq!(count_line_breaks(text), LineBreakIter::new(text).count());
+        assert_eq!(
+            count_line_breaks(text, LineBreakPolicy::Unicode),
+            LineBreakIter::new(text).count()
+        );
+    }
+
+    #[test]
+    fn count_line_breaks_03() {
+        let text = "\u{000A}Hello\u{000D}\u{000A}\u{000D}せ\u{000B}か\u{000C}い\u{0085}. \
+                    There\u{2028}is something.\u{2029}\u{000D}";
+        assert_eq!(2, count_line_breaks(text, LineBreakPolicy::Lf));
+        assert_eq!(4, count_line_breaks(text, LineBreakPolicy::LfCr));
+        assert_eq!(9, count_line_breaks(text, LineBreakPolicy::Unicode));
+    }
+
+    #[test]
+    fn count_line_breaks_04() {
+        // Long enough to go through the chunked counting code, with CRLF
+        // pairs and lone CRs at various alignments.
+        let mut text = String::new();
+        for i in 0..200 {
+            text.push_str(&"Hi\u{000C}\u{2028}"[..(i % 4)]);
+            text.push_str(["\u{000A}", "\u{000D}", "\u{000D}\u{000A}", "\u{000B}"][i % 4]);
+        }
+        let lf = text.matches('\u{000A}').count();
+        let cr = text.matches('\u{000D}').count();
+        let crlf = text.matches("\u{000D}\u{000A}").count();
+        assert_eq!(lf, count_line_breaks(&text, LineBreakPolicy::Lf));
+        assert_eq!(lf + cr - crlf, count_line_breaks(&text, LineBreakPolicy::LfCr));
+        assert_eq!(
+            LineBreakIter::new(&text).count(),
+            count_line_breaks(&text, LineBreakPolicy::Unicode)
+        );
     }
 
     #[test]
@@ -1295,6 +1550,53 @@ mod tests {
         assert_eq!(100, line_to_char_idx(TEXT_LINES, 6));
     }
 
+    #[test]
+    fn byte_to_line_idx_with_policy_01() {
+        let text = "a\u{000C}b\u{000D}\u{000A}c\u{000D}d\u{000A}e";
+        let lf = LineBreakPolicy::Lf;
+        let lf_cr = LineBreakPolicy::LfCr;
+
+        assert_eq!(0, byte_to_line_idx_with_policy(text, 3, lf));
+        assert_eq!(0, byte_to_line_idx_with_policy(text, 4, lf));
+        assert_eq!(1, byte_to_line_idx_with_policy(text, 5, lf));
+        assert_eq!(1, byte_to_line_idx_with_policy(text, 8, lf));
+        assert_eq!(2, byte_to_line_idx_with_policy(text, 9, lf));
+
+        assert_eq!(0, byte_to_line_idx_with_policy(text, 3, lf_cr));
+        assert_eq!(0, byte_to_line_idx_with_policy(text, 4, lf_cr));
+        assert_eq!(1, byte_to_line_idx_with_policy(text, 5, lf_cr));
+        assert_eq!(2, byte_to_line_idx_with_policy(text, 7, lf_cr));
+        assert_eq!(3, byte_to_line_idx_with_policy(text, 9, lf_cr));
+    }
+
+    #[test]
+    fn line_to_byte_idx_with_policy_01() {
+        let text = "a\u{000C}b\u{000D}\u{000A}c\u{000D}d\u{000A}e";
+        let lf = LineBreakPolicy::Lf;
+        let lf_cr = LineBreakPolicy::LfCr;
+
+        assert_eq!(0, line_to_byte_idx_with_policy(text, 0, lf));
+        assert_eq!(5, line_to_byte_idx_with_policy(text, 1, lf));
+        assert_eq!(9, line_to_byte_idx_with_policy(text, 2, lf));
+        assert_eq!(10, line_to_byte_idx_with_policy(text, 3, lf));
+
+        assert_eq!(0, line_to_byte_idx_with_policy(text, 0, lf_cr));
+        assert_eq!(5, line_to_byte_idx_with_policy(text, 1, lf_cr));
+        assert_eq!(7, line_to_byte_idx_with_policy(text, 2, lf_cr));
+        assert_eq!(9, line_to_byte_idx_with_policy(text, 3, lf_cr));
+        assert_eq!(10, line_to_byte_idx_with_policy(text, 4, lf_cr));
+    }
+
+    #[test]
+    fn line_to_byte_idx_with_policy_02() {
+        // Long enough to go through the chunked counting code.
+        let text = "Hello\u{000C}world!\u{000D}\u{000A}".repeat(20);
+        for i in 0..=20 {
+            assert_eq!(i * 14, line_to_byte_idx_with_policy(&text, i, LineBreakPolicy::Lf));
+            assert_eq!(i * 14, line_to_byte_idx_with_policy(&text, i, LineBreakPolicy::LfCr));
+        }
+    }
+
     #[test]
     fn line_byte_round_trip() {
         let text = "\nHere\nare\nsome\nwords\n";
@@ -1321,6 +1623,42 @@ mod tests {
         assert_eq!(5, char_to_line_idx(text, line_to_char_idx(text, 5)));
     }
 
+    #[test]
+    fn char_to_utf16_cu_idx_01() {
+        let text = "Hi 😀 せかい 🐈!";
+        assert_eq!(0, char_to_utf16_cu_idx(text, 0));
+        assert_eq!(3, char_to_utf16_cu_idx(text, 3));
+        assert_eq!(5, char_to_utf16_cu_idx(text, 4));
+        assert_eq!(6, char_to_utf16_cu_idx(text, 5));
+        assert_eq!(9, char_to_utf16_cu_idx(text, 8));
+        assert_eq!(10, char_to_utf16_cu_idx(text, 9));
+        assert_eq!(12, char_to_utf16_cu_idx(text, 10));
+        assert_eq!(13, char_to_utf16_cu_idx(text, 11));
+        assert_eq!(13, char_to_utf16_cu_idx(text, 12));
+    }
+
+    #[test]
+    fn utf16_cu_to_char_idx_01() {
+        let text = "Hi 😀 せかい 🐈!";
+        assert_eq!(0, utf16_cu_to_char_idx(text, 0));
+        assert_eq!(3, utf16_cu_to_char_idx(text, 3));
+        assert_eq!(3, utf16_cu_to_char_idx(text, 4));
+        assert_eq!(4, utf16_cu_to_char_idx(text, 5));
+        assert_eq!(9, utf16_cu_to_char_idx(text, 10));
+        assert_eq!(9, utf16_cu_to_char_idx(text, 11));
+        assert_eq!(10, utf16_cu_to_char_idx(text, 12));
+        assert_eq!(11, utf16_cu_to_char_idx(text, 13));
+        assert_eq!(11, utf16_cu_to_char_idx(text, 14));
+    }
+
+    #[test]
+    fn utf16_cu_char_round_trip() {
+        let text = "🐈a🐈🐈bせ🐈";
+        for i in 0..(count_chars(text) + 1) {
+            assert_eq!(i, utf16_cu_to_char_idx(text, char_to_utf16_cu_idx(text, i)));
+        }
+    }
+
     #[test]
     fn has_bytes_less_than_01() {
         let v: usize = 0x0709080905090609;
@@ -1,11 +1,18 @@
 use std;
+use std::ops::Range;
 use std::sync::Arc;
 
-use str_utils::{byte_to_line_idx, char_to_byte_idx};
+use crlf;
+
+use str_utils::{
+    byte_to_line_idx_with_policy, char_to_byte_idx, count_chars, count_utf16_surrogates,
+    utf16_cu_to_char_idx,
+};
 use tree::node_text::fix_segment_seam;
 use tree::{
     Count, NodeChildren, NodeText, TextInfo, MAX_BYTES, MAX_CHILDREN, MIN_BYTES, MIN_CHILDREN,
 };
+use LineBreakPolicy;
 
 #[derive(Debug, Clone)]
 pub(crate) enum Node {
@@ -23,19 +30,36 @@ impl Node {
     /// Total number of bytes in the Rope.
     #[inline(always)]
     pub fn byte_count(&self) -> usize {
-        self.text_info().bytes as usize
+        match *self {
+            Node::Leaf(ref text) => text.len(),
+            Node::Internal(ref children) => children.combined_info().bytes as usize,
+        }
     }
 
     /// Total number of chars in the Rope.
     #[inline(always)]
     pub fn char_count(&self) -> usize {
-        self.text_info().chars as usize
+        match *self {
+            Node::Leaf(ref text) => count_chars(text),
+            Node::Internal(ref children) => children.combined_info().chars as usize,
+        }
     }
 
-    /// Total number of line breaks in the Rope.
+    /// Total number of utf16 surrogate pairs in the Rope.
+    #[inline(always)]
+    pub fn utf16_surrogate_count(&self) -> usize {
+        match *self {
+            Node::Leaf(ref text) => count_utf16_surrogates(text),
+            Node::Internal(ref children) => children.combined_info().utf16_surrogates as usize,
+        }
+    }
+
+    /// Total number of line breaks in the Rope, under the given policy.
+    ///
+    /// The policy must be the one the tree's line breaks are counted under.
     #[inline(always)]
-    pub fn line_break_count(&self) -> usize {
-        self.text_info().line_breaks as usize
+    pub fn line_break_count(&self, policy: LineBreakPolicy) -> usize {
+        self.text_info(policy).line_breaks as usize
     }
 
     /// Fetches a chunk mutably, and allows it to be edited via a closure.
@@ -47,6 +71,7 @@ impl Node {
     ///              need to calculate the TextInfo of the node you're
     ///              calling it on, but it's part of the recursion, to
     ///              make things efficient.
+    /// - policy: the line break policy the tree's line breaks are counted under.
     /// - edit: the closure that receives the chunk and does the edits.
     ///
     /// The closure is effectively the termination case for the recursion,
@@ -68,6 +93,7 @@ impl Node {
         &mut self,
         char_idx: usize,
         node_info: TextInfo,
+        policy: LineBreakPolicy,
         mut edit: F,
     ) -> (TextInfo, Option<(TextInfo, Arc<Node>)>)
     where
@@ -86,7 +112,7 @@ impl Node {
                     && children.nodes()[0].is_leaf()
                     && (children.combined_info().bytes as usize) < FRAG_MIN_BYTES
                 {
-                    children.compact_leaves();
+                    children.compact_leaves(policy);
                 }
 
                 // Find the child we care about.
@@ -95,7 +121,7 @@ impl Node {
 
                 // Handle residual node, if any, and return.
                 let (l_info, mut residual) = Arc::make_mut(&mut children.nodes_mut()[child_i])
-                    .edit_chunk_at_char(char_idx - acc_char_idx, info, edit);
+                    .edit_chunk_at_char(char_idx - acc_char_idx, info, policy, edit);
                 children.info_mut()[child_i] = l_info;
 
                 // Handle the CRLF and main insertion residuals
@@ -132,6 +158,7 @@ impl Node {
         start_idx: usize,
         end_idx: usize,
         node_info: TextInfo,
+        policy: LineBreakPolicy,
     ) -> (TextInfo, bool, bool) {
         match *self {
             // If it's a leaf
@@ -147,8 +174,8 @@ impl Node {
                             && leaf_text.as_bytes()[byte_start - 1] == 0x0D);
 
                     if (byte_end - byte_start) < leaf_text.len() {
-                        let mut info =
-                            node_info - TextInfo::from_str(&leaf_text[byte_start..byte_end]);
+                        let removed = &leaf_text[byte_start..byte_end];
+                        let mut info = node_info - TextInfo::from_str(removed, policy);
 
                         // Check for CRLF pairs on the removal seams, and
                         // adjust line break counts accordingly.
@@ -157,20 +184,20 @@ impl Node {
                                 && leaf_text.as_bytes()[byte_start - 1] == 0x0D
                                 && leaf_text.as_bytes()[byte_start] == 0x0A
                             {
-                                info.line_breaks += 1;
+                                info.split_crlf(policy);
                             }
                             if byte_end < leaf_text.len()
                                 && leaf_text.as_bytes()[byte_end - 1] == 0x0D
                                 && leaf_text.as_bytes()[byte_end] == 0x0A
                             {
-                                info.line_breaks += 1;
+                                info.split_crlf(policy);
                             }
                             if byte_start > 0
                                 && byte_end < leaf_text.len()
                                 && leaf_text.as_bytes()[byte_start - 1] == 0x0D
                                 && leaf_text.as_bytes()[byte_end] == 0x0A
                             {
-                                info.line_breaks -= 1;
+                                info.join_crlf(policy);
                             }
                         }
 
@@ -182,7 +209,7 @@ impl Node {
                         // Remove the text
                         leaf_text.remove_range(byte_start, byte_end);
 
-                        (TextInfo::from_str(&leaf_text), seam, false)
+                        (TextInfo::from_str(&leaf_text, policy), seam, false)
                     }
                 } else {
                     // Remove the text
@@ -211,6 +238,7 @@ impl Node {
                             start_idx - c_char_acc.min(start_idx),
                             (end_idx - c_char_acc).min(tmp_chars),
                             tmp_info,
+                            policy,
                         );
 
                     // Handle result
@@ -230,9 +258,9 @@ impl Node {
                         && children.nodes()[child_i].is_undersized()
                     {
                         if child_i == 0 {
-                            children.merge_distribute(child_i, child_i + 1);
+                            children.merge_distribute(child_i, child_i + 1, policy);
                         } else {
-                            children.merge_distribute(child_i - 1, child_i);
+                            children.merge_distribute(child_i - 1, child_i, policy);
                         }
                     }
                 };
@@ -311,7 +339,12 @@ impl Node {
         }
     }
 
-    pub fn append_at_depth(&mut self, other: Arc<Node>, depth: usize) -> Option<Arc<Node>> {
+    pub fn append_at_depth(
+        &mut self,
+        other: Arc<Node>,
+        depth: usize,
+        policy: LineBreakPolicy,
+    ) -> Option<Arc<Node>> {
         if depth == 0 {
             match *self {
                 Node::Leaf(_) => {
@@ -341,15 +374,16 @@ impl Node {
             }
         } else if let Node::Internal(ref mut children) = *self {
             let last_i = children.len() - 1;
-            let residual =
-                Arc::make_mut(&mut children.nodes_mut()[last_i]).append_at_depth(other, depth - 1);
-            children.update_child_info(last_i);
+            let residual = Arc::make_mut(&mut children.nodes_mut()[last_i])
+                .append_at_depth(other, depth - 1, policy);
+            children.update_child_info(last_i, policy);
             if let Some(extra_node) = residual {
                 if children.len() < MAX_CHILDREN {
-                    children.push((extra_node.text_info(), extra_node));
+                    children.push((extra_node.text_info(policy), extra_node));
                     return None;
                 } else {
-                    let r_children = children.push_split((extra_node.text_info(), extra_node));
+                    let r_children =
+                        children.push_split((extra_node.text_info(policy), extra_node));
                     return Some(Arc::new(Node::Internal(r_children)));
                 }
             } else {
@@ -360,7 +394,12 @@ impl Node {
         }
     }
 
-    pub fn prepend_at_depth(&mut self, other: Arc<Node>, depth: usize) -> Option<Arc<Node>> {
+    pub fn prepend_at_depth(
+        &mut self,
+        other: Arc<Node>,
+        depth: usize,
+        policy: LineBreakPolicy,
+    ) -> Option<Arc<Node>> {
         if depth == 0 {
             match *self {
                 Node::Leaf(_) => {
@@ -389,16 +428,16 @@ impl Node {
                 }
             }
         } else if let Node::Internal(ref mut children) = *self {
-            let residual =
-                Arc::make_mut(&mut children.nodes_mut()[0]).prepend_at_depth(other, depth - 1);
-            children.update_child_info(0);
+            let residual = Arc::make_mut(&mut children.nodes_mut()[0])
+                .prepend_at_depth(other, depth - 1, policy);
+            children.update_child_info(0, policy);
             if let Some(extra_node) = residual {
                 if children.len() < MAX_CHILDREN {
-                    children.insert(0, (extra_node.text_info(), extra_node));
+                    children.insert(0, (extra_node.text_info(policy), extra_node));
                     return None;
                 } else {
                     let mut r_children =
-                        children.insert_split(0, (extra_node.text_info(), extra_node));
+                        children.insert_split(0, (extra_node.text_info(policy), extra_node));
                     std::mem::swap(children, &mut r_children);
                     return Some(Arc::new(Node::Internal(r_children)));
                 }
@@ -412,9 +451,9 @@ impl Node {
 
     /// Splits the `Node` at char index `char_idx`, returning
     /// the right side of the split.
-    pub fn split(&mut self, char_idx: usize) -> Node {
+    pub fn split(&mut self, char_idx: usize, policy: LineBreakPolicy) -> Node {
         debug_assert!(char_idx != 0);
-        debug_assert!(char_idx != (self.text_info().chars as usize));
+        debug_assert!(char_idx != self.char_count());
         match *self {
             Node::Leaf(ref mut text) => {
                 let byte_idx = char_to_byte_idx(text, char_idx);
@@ -433,12 +472,12 @@ impl Node {
 
                     // Recurse
                     let r_node = Arc::make_mut(&mut children.nodes_mut()[child_i])
-                        .split(char_idx - acc_info.chars as usize);
+                        .split(char_idx - acc_info.chars as usize, policy);
 
-                    r_children.insert(0, (r_node.text_info(), Arc::new(r_node)));
+                    r_children.insert(0, (r_node.text_info(policy), Arc::new(r_node)));
 
-                    children.update_child_info(child_i);
-                    r_children.update_child_info(0);
+                    children.update_child_info(child_i, policy);
+                    r_children.update_child_info(0, policy);
 
                     Node::Internal(r_children)
                 }
@@ -537,21 +576,80 @@ impl Node {
         }
     }
 
-    /// Returns the byte and line index of the given char.
+    /// Returns the byte and line index of the given char, with lines
+    /// counted under the given policy.
     #[inline(always)]
-    pub fn char_to_byte_and_line(&self, char_idx: usize) -> (usize, usize) {
+    pub fn char_to_byte_and_line(&self, char_idx: usize, policy: LineBreakPolicy) -> (usize, usize) {
         let (chunk, b, c, l) = self.get_chunk_at_char(char_idx);
         let bi = char_to_byte_idx(chunk, char_idx - c);
-        (b + bi, l + byte_to_line_idx(chunk, bi))
+        (b + bi, l + byte_to_line_idx_with_policy(chunk, bi, policy))
     }
 
-    pub fn text_info(&self) -> TextInfo {
+    /// Returns the number of utf16 surrogate pairs before the given char.
+    #[inline(always)]
+    pub fn char_to_utf16_surrogate_idx(&self, char_idx: usize) -> usize {
+        let mut node = self;
+        let mut char_idx = char_idx;
+        let mut surrogates = 0;
+
+        loop {
+            match *node {
+                Node::Leaf(ref text) => {
+                    let byte_idx = char_to_byte_idx(text, char_idx);
+                    return surrogates + count_utf16_surrogates(&text[..byte_idx]);
+                }
+                Node::Internal(ref children) => {
+                    let (child_i, acc_info) = children.search_char_idx(char_idx);
+                    surrogates += acc_info.utf16_surrogates as usize;
+                    node = &*children.nodes()[child_i];
+                    char_idx -= acc_info.chars as usize;
+                }
+            }
+        }
+    }
+
+    /// Returns the char index of the given utf16 code unit.
+    ///
+    /// If the code unit is the second half of a surrogate pair, returns
+    /// the index of the char that the pair encodes.
+    pub fn utf16_cu_to_char(&self, utf16_idx: usize) -> usize {
+        let mut node = self;
+        let mut utf16_idx = utf16_idx;
+        let mut char_idx = 0;
+
+        loop {
+            match *node {
+                Node::Leaf(ref text) => {
+                    return char_idx + utf16_cu_to_char_idx(text, utf16_idx);
+                }
+                Node::Internal(ref children) => {
+                    let (child_i, acc_info) = children.search_utf16_code_unit_idx(utf16_idx);
+                    char_idx += acc_info.chars as usize;
+                    node = &*children.nodes()[child_i];
+                    utf16_idx -= (acc_info.chars + acc_info.utf16_surrogates) as usize;
+                }
+            }
+        }
+    }
+
+    pub fn text_info(&self, policy: LineBreakPolicy) -> TextInfo {
         match *self {
-            Node::Leaf(ref text) => TextInfo::from_str(text),
+            Node::Leaf(ref text) => TextInfo::from_str(text, policy),
             Node::Internal(ref children) => children.combined_info(),
         }
     }
 
+    /// Counts the line breaks of the whole tree again, under the given
+    /// policy.
+    pub fn recount_line_breaks(&mut self, policy: LineBreakPolicy) {
+        if let Node::Internal(ref mut children) = *self {
+            for i in 0..children.len() {
+                Arc::make_mut(&mut children.nodes_mut()[i]).recount_line_breaks(policy);
+                children.update_child_info(i, policy);
+            }
+        }
+    }
+
     //-----------------------------------------
 
     pub fn child_count(&self) -> usize {
@@ -620,14 +718,14 @@ impl Node {
 
     /// Debugging tool to make sure that all of the meta-data of the
     /// tree is consistent with the actual data.
-    pub fn assert_integrity(&self) {
+    pub fn assert_integrity(&self, policy: LineBreakPolicy) {
         match *self {
             Node::Leaf(_) => {}
             Node::Internal(ref children) => for (info, node) in children.iter() {
-                if *info != node.text_info() {
-                    assert_eq!(*info, node.text_info());
+                if *info != node.text_info(policy) {
+                    assert_eq!(*info, node.text_info(policy));
                 }
-                node.assert_integrity();
+                node.assert_integrity(policy);
             },
         }
     }
@@ -685,17 +783,22 @@ impl Node {
     /// But this should nevertheless get addressed at some point.
     /// Probably the most straight-forward way to address this is via the
     /// `fix_info_*` methods below, but I'm not totally sure.
-    pub fn fix_crlf_seam(&mut self, byte_pos: Count, must_be_boundary: bool) {
+    pub fn fix_crlf_seam(
+        &mut self,
+        byte_pos: Count,
+        must_be_boundary: bool,
+        policy: LineBreakPolicy,
+    ) {
         if let Node::Internal(ref mut children) = *self {
             if byte_pos == 0 {
                 // Special-case 1
                 Arc::make_mut(&mut children.nodes_mut()[0])
-                    .fix_crlf_seam(byte_pos, must_be_boundary);
+                    .fix_crlf_seam(byte_pos, must_be_boundary, policy);
             } else if byte_pos == children.combined_info().bytes {
                 // Special-case 2
                 let (info, nodes) = children.data_mut();
                 Arc::make_mut(nodes.last_mut().unwrap())
-                    .fix_crlf_seam(info.last().unwrap().bytes, must_be_boundary);
+                    .fix_crlf_seam(info.last().unwrap().bytes, must_be_boundary, policy);
             } else {
                 // Find the child to navigate into
                 let (child_i, start_info) = children.search_byte_idx(byte_pos as usize);
@@ -740,14 +843,14 @@ impl Node {
 
                         // Fix up the children's metadata after the change
                         // to their text.
-                        l_child.fix_info_right();
-                        r_child.fix_info_left();
+                        l_child.fix_info_right(policy);
+                        r_child.fix_info_left(policy);
                     }
 
                     // Fix up this node's metadata for those
                     // two children.
-                    children.update_child_info(l_child_i);
-                    children.update_child_info(l_child_i + 1);
+                    children.update_child_info(l_child_i, policy);
+                    children.update_child_info(l_child_i + 1, policy);
 
                     // Remove the children if empty.
                     if children.info()[l_child_i + 1].bytes == 0 {
@@ -758,9 +861,9 @@ impl Node {
                 } else {
                     // Internal to child
                     Arc::make_mut(&mut children.nodes_mut()[child_i])
-                        .fix_crlf_seam(pos_in_child, must_be_boundary);
+                        .fix_crlf_seam(pos_in_child, must_be_boundary, policy);
 
-                    children.update_child_info(child_i);
+                    children.update_child_info(child_i, policy);
 
                     if children.info()[child_i].bytes == 0 {
                         children.remove(child_i);
@@ -785,12 +888,12 @@ impl Node {
 
     /// Updates the tree meta-data down the left side of the tree, and removes empty
     /// children as it goes as well.
-    fn fix_info_left(&mut self) {
+    fn fix_info_left(&mut self, policy: LineBreakPolicy) {
         match *self {
             Node::Leaf(_) => {}
             Node::Internal(ref mut children) => {
-                Arc::make_mut(&mut children.nodes_mut()[0]).fix_info_left();
-                children.update_child_info(0);
+                Arc::make_mut(&mut children.nodes_mut()[0]).fix_info_left(policy);
+                children.update_child_info(0, policy);
                 if children.info()[0].bytes == 0 {
                     children.remove(0);
                 }
@@ -800,13 +903,13 @@ impl Node {
 
     /// Updates the tree meta-data down the right side of the tree, and removes empty
     /// children as it goes as well.
-    fn fix_info_right(&mut self) {
+    fn fix_info_right(&mut self, policy: LineBreakPolicy) {
         match *self {
             Node::Leaf(_) => {}
             Node::Internal(ref mut children) => {
                 let idx = children.len() - 1;
-                Arc::make_mut(&mut children.nodes_mut()[idx]).fix_info_right();
-                children.update_child_info(idx);
+                Arc::make_mut(&mut children.nodes_mut()[idx]).fix_info_right(policy);
+                children.update_child_info(idx, policy);
                 if children.info()[idx].bytes == 0 {
                     children.remove(idx);
                 }
@@ -818,7 +921,7 @@ impl Node {
     ///
     /// Returns whether it did anything or not that would affect the
     /// parent.
-    pub fn zip_fix_left(&mut self) -> bool {
+    pub fn zip_fix_left(&mut self, policy: LineBreakPolicy) -> bool {
         if let Node::Internal(ref mut children) = *self {
             let mut did_stuff = false;
             loop {
@@ -828,10 +931,10 @@ impl Node {
                 };
 
                 if do_merge {
-                    did_stuff |= children.merge_distribute(0, 1);
+                    did_stuff |= children.merge_distribute(0, 1, policy);
                 }
 
-                if !Arc::make_mut(&mut children.nodes_mut()[0]).zip_fix_left() {
+                if !Arc::make_mut(&mut children.nodes_mut()[0]).zip_fix_left(policy) {
                     break;
                 }
             }
@@ -845,7 +948,7 @@ impl Node {
     ///
     /// Returns whether it did anything or not that would affect the
     /// parent. True: did stuff, false: didn't do stuff
-    pub fn zip_fix_right(&mut self) -> bool {
+    pub fn zip_fix_right(&mut self, policy: LineBreakPolicy) -> bool {
         if let Node::Internal(ref mut children) = *self {
             let mut did_stuff = false;
             loop {
@@ -856,10 +959,12 @@ impl Node {
                 };
 
                 if do_merge {
-                    did_stuff |= children.merge_distribute(last_i - 1, last_i);
+                    did_stuff |= children.merge_distribute(last_i - 1, last_i, policy);
                 }
 
-                if !Arc::make_mut(&mut children.nodes_mut().last_mut().unwrap()).zip_fix_right() {
+                if !Arc::make_mut(&mut children.nodes_mut().last_mut().unwrap())
+                    .zip_fix_right(policy)
+                {
                     break;
                 }
             }
@@ -875,7 +980,7 @@ impl Node {
     ///
     /// Returns whether it did anything or not that would affect the
     /// parent. True: did stuff, false: didn't do stuff
-    pub fn fix_after_remove(&mut self, char_idx: usize) -> bool {
+    pub fn fix_after_remove(&mut self, char_idx: usize, policy: LineBreakPolicy) -> bool {
         if let Node::Internal(ref mut children) = *self {
             let mut did_stuff = false;
             loop {
@@ -895,7 +1000,7 @@ impl Node {
                             };
 
                         if do_merge {
-                            did_stuff |= children.merge_distribute(child_i, child_i + 1);
+                            did_stuff |= children.merge_distribute(child_i, child_i + 1, policy);
                         }
                     } else {
                         let do_merge = match *children.nodes()[child_i] {
@@ -905,9 +1010,10 @@ impl Node {
 
                         if do_merge {
                             if child_i == 0 {
-                                did_stuff |= children.merge_distribute(0, 1);
+                                did_stuff |= children.merge_distribute(0, 1, policy);
                             } else {
-                                did_stuff |= children.merge_distribute(child_i - 1, child_i);
+                                did_stuff |=
+                                    children.merge_distribute(child_i - 1, child_i, policy);
                             }
                         }
                     }
@@ -919,32 +1025,209 @@ impl Node {
 
                 if end_info.chars as usize == char_idx && (child_i + 1) < children.len() {
                     let tmp = children.info()[child_i].chars as usize;
-                    let effect_1 =
-                        Arc::make_mut(&mut children.nodes_mut()[child_i]).fix_after_remove(tmp);
-                    let effect_2 =
-                        Arc::make_mut(&mut children.nodes_mut()[child_i + 1]).fix_after_remove(0);
+                    let effect_1 = Arc::make_mut(&mut children.nodes_mut()[child_i])
+                        .fix_after_remove(tmp, policy);
+                    let effect_2 = Arc::make_mut(&mut children.nodes_mut()[child_i + 1])
+                        .fix_after_remove(0, policy);
                     if (!effect_1) && (!effect_2) {
                         break;
                     }
                 } else if !Arc::make_mut(&mut children.nodes_mut()[child_i])
-                    .fix_after_remove(char_idx - start_info.chars as usize)
+                    .fix_after_remove(char_idx - start_info.chars as usize, policy)
                 {
                     break;
                 }
             }
-            debug_assert!(children.is_info_accurate());
+            debug_assert!(children.is_info_accurate(policy));
             did_stuff
         } else {
             false
         }
     }
+
+    /// Replaces each char range in `edits` with its text, in a single pass
+    /// down the tree.  The ranges are relative to this node, and must be
+    /// valid, sorted, and non-overlapping.
+    ///
+    /// Only the nodes that the edits touch are rebuilt: untouched children
+    /// are shared with this node rather than copied.
+    ///
+    /// Returns the edited text as a list of sibling nodes of the same
+    /// height as this node, which is empty if all of the text was removed.
+    /// Any of the returned nodes may be undersized, as may a lone child
+    /// of an undersized node, so they should be passed through
+    /// `fix_siblings()` before being used as children.  CRLF pairs may
+    /// also be split where the edits meet the surrounding text.
+    pub fn replace_sorted(
+        &self,
+        edits: &[(Range<usize>, &str)],
+        policy: LineBreakPolicy,
+    ) -> Vec<Arc<Node>> {
+        match *self {
+            Node::Leaf(ref text) => {
+                let mut new_text = String::with_capacity(text.len());
+                let mut last_byte = 0;
+                for &(ref range, ins_text) in edits {
+                    let start_byte = char_to_byte_idx(text, range.start);
+                    let end_byte =
+                        start_byte + char_to_byte_idx(&text[start_byte..], range.end - range.start);
+                    new_text.push_str(&text[last_byte..start_byte]);
+                    new_text.push_str(ins_text);
+                    last_byte = end_byte;
+                }
+                new_text.push_str(&text[last_byte..]);
+                leaves_from_str(&new_text)
+            }
+            Node::Internal(ref children) => {
+                let mut new_children = Vec::with_capacity(children.len());
+                let mut child_edits = Vec::new();
+                let mut edit_i = 0;
+                let mut carried_end = None; // End of an edit continuing from the last child
+                let mut child_start = 0;
+                for (i, (info, child)) in children.iter().enumerate() {
+                    let child_chars = info.chars as usize;
+                    let child_end = child_start + child_chars;
+                    let is_last = (i + 1) == children.len();
+
+                    // Gather the parts of the edits that are in this child.
+                    // Each edit's text goes in the child where it starts.
+                    child_edits.clear();
+                    if let Some(end) = carried_end.take() {
+                        child_edits.push((0..(child_chars.min(end - child_start)), ""));
+                        if end > child_end {
+                            carried_end = Some(end);
+                        }
+                    }
+                    while edit_i < edits.len() && (edits[edit_i].0.start < child_end || is_last) {
+                        let (ref range, ins_text) = edits[edit_i];
+                        let end = range.end.min(child_end);
+                        let child_range = (range.start - child_start)..(end - child_start);
+                        child_edits.push((child_range, ins_text));
+                        if range.end > child_end {
+                            carried_end = Some(range.end);
+                        }
+                        edit_i += 1;
+                    }
+
+                    if child_edits.is_empty() {
+                        new_children.push(Arc::clone(child));
+                    } else if !(child_edits.len() == 1
+                        && child_edits[0].0 == (0..child_chars)
+                        && child_edits[0].1.is_empty())
+                    {
+                        new_children.extend(child.replace_sorted(&child_edits, policy));
+                    }
+                    child_start = child_end;
+                }
+
+                fix_siblings(&mut new_children, policy);
+                group_siblings(new_children, policy)
+            }
+        }
+    }
+
+    /// Builds a tree out of a list of sibling nodes like those returned by
+    /// `replace_sorted()`, returning its root.
+    pub fn from_siblings(mut nodes: Vec<Arc<Node>>, policy: LineBreakPolicy) -> Arc<Node> {
+        fix_siblings(&mut nodes, policy);
+        while nodes.len() > 1 {
+            nodes = group_siblings(nodes, policy);
+        }
+        nodes.pop().unwrap_or_else(|| Arc::new(Node::new()))
+    }
+}
+
+/// Splits `text` into as few leaf nodes as it fits in, of roughly even
+/// size and without splitting any CRLF pairs.
+fn leaves_from_str(text: &str) -> Vec<Arc<Node>> {
+    let mut leaves = Vec::with_capacity((text.len() / MAX_BYTES) + 1);
+    let mut text = text;
+    while !text.is_empty() {
+        let leaf_count = ((text.len() - 1) / MAX_BYTES) + 1;
+        let split_idx = if leaf_count > 1 {
+            crlf::find_good_split(text.len() / leaf_count, text.as_bytes(), true)
+        } else {
+            text.len()
+        };
+        leaves.push(Arc::new(Node::Leaf(NodeText::from_str(&text[..split_idx]))));
+        text = &text[split_idx..];
+    }
+    leaves
+}
+
+/// Merges undersized nodes in a list of siblings with their neighbors,
+/// until either none are undersized or only one node is left.
+///
+/// Merging two internal nodes also fixes up their combined children, so
+/// this fixes up any undersized nodes below the given ones as well.
+/// Leaves can still be left slightly undersized when a merged pair has to
+/// be split in two again, which is harmless.
+fn fix_siblings(nodes: &mut Vec<Arc<Node>>, policy: LineBreakPolicy) {
+    let mut i = 0;
+    while i < nodes.len() && nodes.len() > 1 {
+        if !nodes[i].is_undersized() {
+            i += 1;
+            continue;
+        }
+
+        let l = if (i + 1) < nodes.len() { i } else { i - 1 };
+        let merged = match (&*nodes[l], &*nodes[l + 1]) {
+            (Node::Leaf(l_text), Node::Leaf(r_text)) => {
+                let mut text = String::with_capacity(l_text.len() + r_text.len());
+                text.push_str(l_text);
+                text.push_str(r_text);
+                leaves_from_str(&text)
+            }
+            (Node::Internal(l_children), Node::Internal(r_children)) => {
+                let mut children: Vec<_> = l_children
+                    .nodes()
+                    .iter()
+                    .chain(r_children.nodes())
+                    .cloned()
+                    .collect();
+                fix_siblings(&mut children, policy);
+                group_siblings(children, policy)
+            }
+            _ => panic!("Siblings have different node types"),
+        };
+
+        // If the pair was split in two again, both halves are as big as
+        // they can be made.  Otherwise the merged node may still be
+        // undersized, and needs checking again.
+        i = if merged.len() > 1 { l + merged.len() } else { l };
+        nodes.splice(l..(l + 2), merged);
+    }
+}
+
+/// Groups a list of siblings under as few new parent nodes as they fit in,
+/// returning the parents.
+///
+/// If there's more than one parent, the children are spread evenly between
+/// them, so that none of them are undersized.
+fn group_siblings(nodes: Vec<Arc<Node>>, policy: LineBreakPolicy) -> Vec<Arc<Node>> {
+    let len = nodes.len();
+    if len == 0 {
+        return nodes;
+    }
+    let group_count = ((len - 1) / MAX_CHILDREN) + 1;
+    let mut nodes = nodes.into_iter();
+    (0..group_count)
+        .map(|i| {
+            let group_len = (len * (i + 1) / group_count) - (len * i / group_count);
+            let mut children = NodeChildren::new();
+            for node in nodes.by_ref().take(group_len) {
+                children.push((node.text_info(policy), node));
+            }
+            Arc::new(Node::Internal(children))
+        })
+        .collect()
 }
 
 //===========================================================================
 
 #[cfg(test)]
 mod tests {
-    use Rope;
+    use {LineBreakPolicy, Rope};
 
     // 133 chars, 209 bytes
     const TEXT: &str = "\r\nHello there!  How're you doing?  It's a fine day, \
@@ -956,7 +1239,7 @@ mod tests {
     fn line_to_byte_01() {
         let r = Rope::from_str(TEXT);
 
-        assert_eq!(3, r.root.line_break_count());
+        assert_eq!(3, r.root.line_break_count(LineBreakPolicy::Unicode));
         assert_eq!(0, r.line_to_byte(0));
         assert_eq!(2, r.line_to_byte(1));
         assert_eq!(93, r.line_to_byte(2));
@@ -967,7 +1250,7 @@ mod tests {
     fn line_to_char_01() {
         let r = Rope::from_str(TEXT);
 
-        assert_eq!(3, r.root.line_break_count());
+        assert_eq!(3, r.root.line_break_count(LineBreakPolicy::Unicode));
         assert_eq!(0, r.line_to_char(0));
         assert_eq!(2, r.line_to_char(1));
         assert_eq!(93, r.line_to_char(2));
@@ -977,6 +1260,8 @@ mod tests {
     #[test]
     fn crlf_corner_case_01() {
         use super::Node;
+        use escaped_bytes::EscapedBytes;
+        use mark::MarkSet;
         use std::iter;
         use std::sync::Arc;
         use tree::{NodeChildren, NodeText, MAX_BYTES};
@@ -989,11 +1274,15 @@ mod tests {
             .take(MAX_BYTES)
             .collect::<String>()));
         let mut children = NodeChildren::new();
-        children.push((nodel.text_info(), Arc::new(nodel)));
-        children.push((noder.text_info(), Arc::new(noder)));
+        children.push((nodel.text_info(LineBreakPolicy::default()), Arc::new(nodel)));
+        children.push((noder.text_info(LineBreakPolicy::default()), Arc::new(noder)));
         let root = Node::Internal(children);
         let mut rope = Rope {
             root: Arc::new(root),
+            line_break_policy: LineBreakPolicy::Unicode,
+            insert_line_ending: None,
+            marks: MarkSet::new(),
+            escaped_bytes: EscapedBytes::new(),
         };
         assert_eq!(rope.char(0), '\n');
         assert_eq!(rope.len_chars(), MAX_BYTES * 2 - 1);
@@ -1005,6 +1294,8 @@ mod tests {
     #[test]
     fn crlf_corner_case_02() {
         use super::Node;
+        use escaped_bytes::EscapedBytes;
+        use mark::MarkSet;
         use std::iter;
         use std::sync::Arc;
         use tree::{NodeChildren, NodeText, MAX_BYTES};
@@ -1017,11 +1308,15 @@ mod tests {
             .take(MAX_BYTES - 1)
             .collect::<String>()));
         let mut children = NodeChildren::new();
-        children.push((nodel.text_info(), Arc::new(nodel)));
-        children.push((noder.text_info(), Arc::new(noder)));
+        children.push((nodel.text_info(LineBreakPolicy::default()), Arc::new(nodel)));
+        children.push((noder.text_info(LineBreakPolicy::default()), Arc::new(noder)));
         let root = Node::Internal(children);
         let mut rope = Rope {
             root: Arc::new(root),
+            line_break_policy: LineBreakPolicy::Unicode,
+            insert_line_ending: None,
+            marks: MarkSet::new(),
+            escaped_bytes: EscapedBytes::new(),
         };
         assert_eq!(rope.char(0), '\r');
         assert_eq!(rope.len_chars(), MAX_BYTES * 2 - 1);
@@ -5,6 +5,7 @@ use std::sync::Arc;
 
 use crlf;
 use tree::{self, Node, TextInfo, MAX_BYTES};
+use LineBreakPolicy;
 
 const MAX_LEN: usize = tree::MAX_CHILDREN;
 
@@ -57,10 +58,11 @@ impl NodeChildren {
         self.0.data_mut()
     }
 
-    /// Updates the text info of the child at `idx`.
-    pub fn update_child_info(&mut self, idx: usize) {
+    /// Updates the text info of the child at `idx`, counting line breaks
+    /// under the given policy.
+    pub fn update_child_info(&mut self, idx: usize, policy: LineBreakPolicy) {
         let (info, nodes) = self.0.data_mut();
-        info[idx] = nodes[idx].text_info();
+        info[idx] = nodes[idx].text_info(policy);
     }
 
     /// Pushes an item into the end of the array.
@@ -90,7 +92,7 @@ impl NodeChildren {
     ///
     /// - True: merge was successful.
     /// - False: merge failed, equidistributed instead.
-    pub fn merge_distribute(&mut self, idx1: usize, idx2: usize) -> bool {
+    pub fn merge_distribute(&mut self, idx1: usize, idx2: usize, policy: LineBreakPolicy) -> bool {
         assert!(idx1 < idx2);
         assert!(idx2 < self.len());
         let remove_right = {
@@ -133,11 +135,11 @@ impl NodeChildren {
 
         if remove_right {
             self.remove(idx2);
-            self.update_child_info(idx1);
+            self.update_child_info(idx1, policy);
             return true;
         } else {
-            self.update_child_info(idx1);
-            self.update_child_info(idx2);
+            self.update_child_info(idx1, policy);
+            self.update_child_info(idx2, policy);
             return false;
         }
     }
@@ -156,7 +158,7 @@ impl NodeChildren {
 
     /// If the children are leaf nodes, compacts them to take up the fewest
     /// nodes.
-    pub fn compact_leaves(&mut self) {
+    pub fn compact_leaves(&mut self, policy: LineBreakPolicy) {
         if !self.nodes()[0].is_leaf() || self.len() < 2 {
             return;
         }
@@ -191,7 +193,7 @@ impl NodeChildren {
         }
 
         for i in 0..self.len() {
-            self.update_child_info(i);
+            self.update_child_info(i, policy);
         }
     }
 
@@ -348,6 +350,37 @@ impl NodeChildren {
         (idx, accum)
     }
 
+    /// Returns the child index and left-side-accumulated text info of the
+    /// child that contains the given utf16 code unit.
+    ///
+    /// One-past-the end is valid, and will return the last child.
+    pub fn search_utf16_code_unit_idx(&self, utf16_idx: usize) -> (usize, TextInfo) {
+        debug_assert!(self.len() > 0);
+
+        let mut accum = TextInfo::new();
+        let mut idx = 0;
+        for info in self.info()[0..(self.len() - 1)].iter() {
+            let next_accum = accum + *info;
+            if utf16_idx < (next_accum.chars + next_accum.utf16_surrogates) as usize {
+                break;
+            }
+            accum = next_accum;
+            idx += 1;
+        }
+
+        #[cfg(any(test, debug_assertions))]
+        assert!(
+            utf16_idx
+                <= (accum.chars
+                    + accum.utf16_surrogates
+                    + self.info()[idx].chars
+                    + self.info()[idx].utf16_surrogates) as usize,
+            "Index out of bounds."
+        );
+
+        (idx, accum)
+    }
+
     /// Same as `search_char_idx()` above, except that it only calulates the
     /// left-side-accumulated _char_ index rather than the full text info.
     ///
@@ -462,9 +495,9 @@ impl NodeChildren {
     }
 
     // Debug function, to help verify tree integrity
-    pub fn is_info_accurate(&self) -> bool {
+    pub fn is_info_accurate(&self, policy: LineBreakPolicy) -> bool {
         for (info, node) in self.info().iter().zip(self.nodes().iter()) {
-            if *info != node.text_info() {
+            if *info != node.text_info(policy) {
                 return false;
             }
         }
@@ -716,9 +749,9 @@ mod tests {
             Arc::new(Node::Leaf(NodeText::from_str("world!"))),
         ));
 
-        children.update_child_info(0);
-        children.update_child_info(1);
-        children.update_child_info(2);
+        children.update_child_info(0, LineBreakPolicy::default());
+        children.update_child_info(1, LineBreakPolicy::default());
+        children.update_child_info(2, LineBreakPolicy::default());
 
         assert_eq!(0, children.search_char_idx(0).0);
         assert_eq!(0, children.search_char_idx(1).0);
@@ -758,9 +791,9 @@ mod tests {
             Arc::new(Node::Leaf(NodeText::from_str("world!"))),
         ));
 
-        children.update_child_info(0);
-        children.update_child_info(1);
-        children.update_child_info(2);
+        children.update_child_info(0, LineBreakPolicy::default());
+        children.update_child_info(1, LineBreakPolicy::default());
+        children.update_child_info(2, LineBreakPolicy::default());
 
         children.search_char_idx(19);
     }
@@ -781,9 +814,9 @@ mod tests {
             Arc::new(Node::Leaf(NodeText::from_str("world!"))),
         ));
 
-        children.update_child_info(0);
-        children.update_child_info(1);
-        children.update_child_info(2);
+        children.update_child_info(0, LineBreakPolicy::default());
+        children.update_child_info(1, LineBreakPolicy::default());
+        children.update_child_info(2, LineBreakPolicy::default());
 
         let at_0_0 = children.search_char_idx_range(0, 0);
         let at_6_6 = children.search_char_idx_range(6, 6);
@@ -860,9 +893,9 @@ mod tests {
             Arc::new(Node::Leaf(NodeText::from_str("world!"))),
         ));
 
-        children.update_child_info(0);
-        children.update_child_info(1);
-        children.update_child_info(2);
+        children.update_child_info(0, LineBreakPolicy::default());
+        children.update_child_info(1, LineBreakPolicy::default());
+        children.update_child_info(2, LineBreakPolicy::default());
 
         children.search_char_idx_range(18, 19);
     }
@@ -883,9 +916,9 @@ mod tests {
             Arc::new(Node::Leaf(NodeText::from_str("world!\n"))),
         ));
 
-        children.update_child_info(0);
-        children.update_child_info(1);
-        children.update_child_info(2);
+        children.update_child_info(0, LineBreakPolicy::default());
+        children.update_child_info(1, LineBreakPolicy::default());
+        children.update_child_info(2, LineBreakPolicy::default());
 
         assert_eq!(0, children.search_line_break_idx(0).0);
         assert_eq!(0, children.search_line_break_idx(0).1.line_breaks);
@@ -922,9 +955,9 @@ mod tests {
             Arc::new(Node::Leaf(NodeText::from_str("world!"))),
         ));
 
-        children.update_child_info(0);
-        children.update_child_info(1);
-        children.update_child_info(2);
+        children.update_child_info(0, LineBreakPolicy::default());
+        children.update_child_info(1, LineBreakPolicy::default());
+        children.update_child_info(2, LineBreakPolicy::default());
 
         assert_eq!(0, children.search_line_break_idx(0).0);
         assert_eq!(0, children.search_line_break_idx(0).1.line_breaks);
@@ -944,7 +977,7 @@ mod tests {
             Arc::new(Node::Leaf(NodeText::from_str(""))),
         ));
 
-        children.update_child_info(0);
+        children.update_child_info(0, LineBreakPolicy::default());
 
         assert_eq!(0, children.search_line_break_idx(0).0);
         assert_eq!(0, children.search_line_break_idx(0).1.line_breaks);
@@ -962,7 +995,7 @@ mod tests {
             Arc::new(Node::Leaf(NodeText::from_str(""))),
         ));
 
-        children.update_child_info(0);
+        children.update_child_info(0, LineBreakPolicy::default());
 
         assert_eq!(0, children.search_line_break_idx(0).0);
         assert_eq!(0, children.search_line_break_idx(0).1.line_breaks);
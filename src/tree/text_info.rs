@@ -1,12 +1,14 @@
 use std::ops::{Add, AddAssign, Sub, SubAssign};
 
-use str_utils::{count_chars, count_line_breaks};
+use str_utils::{count_chars, count_line_breaks, count_utf16_surrogates};
 use tree::Count;
+use LineBreakPolicy;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct TextInfo {
     pub(crate) bytes: Count,
     pub(crate) chars: Count,
+    pub(crate) utf16_surrogates: Count,
     pub(crate) line_breaks: Count,
 }
 
@@ -16,16 +18,39 @@ impl TextInfo {
         TextInfo {
             bytes: 0,
             chars: 0,
+            utf16_surrogates: 0,
             line_breaks: 0,
         }
     }
 
+    /// Computes the text info of `text`, with line breaks counted under
+    /// the given policy.
     #[inline]
-    pub fn from_str(text: &str) -> TextInfo {
+    pub fn from_str(text: &str, policy: LineBreakPolicy) -> TextInfo {
         TextInfo {
             bytes: text.len() as Count,
             chars: count_chars(text) as Count,
-            line_breaks: count_line_breaks(text) as Count,
+            utf16_surrogates: count_utf16_surrogates(text) as Count,
+            line_breaks: count_line_breaks(text, policy) as Count,
+        }
+    }
+
+    /// Adjusts the line break count for a CRLF pair being joined together
+    /// from a separate CR and LF.  Only affects the policies that count CRs
+    /// on their own.
+    #[inline(always)]
+    pub fn join_crlf(&mut self, policy: LineBreakPolicy) {
+        if policy != LineBreakPolicy::Lf {
+            self.line_breaks -= 1;
+        }
+    }
+
+    /// Adjusts the line break count for a CRLF pair being split into a
+    /// separate CR and LF.  The inverse of `join_crlf()`.
+    #[inline(always)]
+    pub fn split_crlf(&mut self, policy: LineBreakPolicy) {
+        if policy != LineBreakPolicy::Lf {
+            self.line_breaks += 1;
         }
     }
 }
@@ -37,6 +62,7 @@ impl Add for TextInfo {
         TextInfo {
             bytes: self.bytes + rhs.bytes,
             chars: self.chars + rhs.chars,
+            utf16_surrogates: self.utf16_surrogates + rhs.utf16_surrogates,
             line_breaks: self.line_breaks + rhs.line_breaks,
         }
     }
@@ -56,6 +82,7 @@ impl Sub for TextInfo {
         TextInfo {
             bytes: self.bytes - rhs.bytes,
             chars: self.chars - rhs.chars,
+            utf16_surrogates: self.utf16_surrogates - rhs.utf16_surrogates,
             line_breaks: self.line_breaks - rhs.line_breaks,
         }
     }
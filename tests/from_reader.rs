@@ -1,9 +1,11 @@
 extern crate rand;
 extern crate ropey;
 
-use std::io::Cursor;
+use std::io::{self, BufReader, Cursor, Read};
 
-use ropey::Rope;
+use ropey::{
+    Encoding, EncodingInfo, InvalidUtf8, LoadStatus, ReadOptions, Rope, RopeLoader, WriteOptions,
+};
 
 const TEXT: &str = include_str!("test_text.txt");
 
@@ -53,3 +55,266 @@ fn from_reader_03() {
         panic!("Should have returned an invalid data error.")
     }
 }
+
+#[test]
+fn from_reader_with_encoding_01() {
+    // UTF-16 in both byte orders, with and without a byte order mark.
+    let le: Vec<u8> = TEXT.encode_utf16().flat_map(|u| u.to_le_bytes().to_vec()).collect();
+    let be: Vec<u8> = TEXT.encode_utf16().flat_map(|u| u.to_be_bytes().to_vec()).collect();
+
+    let (rope, info) =
+        Rope::from_reader_with_encoding(Cursor::new(&le), Encoding::Utf16Le).unwrap();
+    assert_eq!(rope, TEXT);
+    assert_eq!(info.encoding, Encoding::Utf16Le);
+    assert!(!info.bom);
+
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend_from_slice(&be);
+    let (rope, info) =
+        Rope::from_reader_with_encoding(Cursor::new(&bytes), Encoding::Utf16Le).unwrap();
+    assert_eq!(rope, TEXT);
+    assert_eq!(info.encoding, Encoding::Utf16Be);
+    assert!(info.bom);
+
+    // Make sure the tree is sound
+    rope.assert_integrity();
+    rope.assert_invariants();
+}
+
+#[test]
+fn from_reader_with_encoding_02() {
+    let mut bytes = vec![0xEF, 0xBB, 0xBF];
+    bytes.extend_from_slice(TEXT.as_bytes());
+    let (rope, info) =
+        Rope::from_reader_with_encoding(Cursor::new(&bytes), Encoding::Utf8).unwrap();
+    assert_eq!(rope, TEXT);
+    assert_eq!(info.encoding, Encoding::Utf8);
+    assert!(info.bom);
+
+    let (rope, info) = Rope::from_reader_with_encoding(Cursor::new(""), Encoding::Utf16Be).unwrap();
+    assert_eq!(rope, "");
+    assert_eq!(info.encoding, Encoding::Utf16Be);
+    assert!(!info.bom);
+}
+
+#[test]
+fn from_reader_with_encoding_03() {
+    let bytes = b"caf\xE9 \x80 \x93quoted\x94";
+    let (rope, _) = Rope::from_reader_with_encoding(Cursor::new(&bytes[..]), Encoding::Latin1)
+        .unwrap();
+    assert_eq!(rope, "caf\u{E9} \u{80} \u{93}quoted\u{94}");
+
+    let (rope, info) =
+        Rope::from_reader_with_encoding(Cursor::new(&bytes[..]), Encoding::Windows1252).unwrap();
+    assert_eq!(rope, "caf\u{E9} \u{20AC} \u{201C}quoted\u{201D}");
+    assert_eq!(info.encoding, Encoding::Windows1252);
+    assert!(!info.bom);
+}
+
+#[test]
+fn from_reader_with_encoding_04() {
+    // An unpaired surrogate.
+    let mut bytes: Vec<u8> = TEXT.encode_utf16().flat_map(|u| u.to_le_bytes().to_vec()).collect();
+    bytes[6132] = 0x00;
+    bytes[6133] = 0xD8;
+
+    match Rope::from_reader_with_encoding(Cursor::new(bytes), Encoding::Utf16Le) {
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::InvalidData),
+        Ok(_) => panic!("Should have returned an invalid data error."),
+    }
+}
+
+#[test]
+fn from_reader_detect_01() {
+    for &encoding in &[Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+        let bytes: Vec<u8> = match encoding {
+            Encoding::Utf8 => TEXT.as_bytes().to_vec(),
+            Encoding::Utf16Le => TEXT
+                .encode_utf16()
+                .flat_map(|u| u.to_le_bytes().to_vec())
+                .collect(),
+            _ => TEXT
+                .encode_utf16()
+                .flat_map(|u| u.to_be_bytes().to_vec())
+                .collect(),
+        };
+        let mut reader = BufReader::new(Cursor::new(bytes));
+
+        let detection = Encoding::detect_reader(&mut reader).unwrap();
+        assert_eq!(detection.encoding(), encoding);
+        assert!(!detection.is_binary);
+
+        let (rope, info) = Rope::from_reader_with_encoding(reader, detection.encoding()).unwrap();
+        assert_eq!(rope, TEXT);
+        assert_eq!(info.encoding, encoding);
+        rope.assert_integrity();
+        rope.assert_invariants();
+    }
+}
+
+#[test]
+fn from_reader_detect_02() {
+    // Windows-1252 text with smart quotes.
+    let ascii: String = TEXT.chars().filter(|c| c.is_ascii()).collect();
+    let text = ascii.replace("Lorem", "\u{201C}L\u{F6}rem\u{201D}");
+    let bytes: Vec<u8> = text
+        .chars()
+        .map(|c| match c {
+            '\u{201C}' => 0x93,
+            '\u{201D}' => 0x94,
+            c => c as u8,
+        })
+        .collect();
+    let mut reader = BufReader::new(Cursor::new(bytes));
+
+    let detection = Encoding::detect_reader(&mut reader).unwrap();
+    assert_eq!(detection.encoding(), Encoding::Windows1252);
+
+    let (rope, _) = Rope::from_reader_with_encoding(reader, detection.encoding()).unwrap();
+    assert_eq!(rope, text);
+}
+
+#[test]
+fn from_reader_lossy_01() {
+    // Valid text is loaded as-is.
+    let (rope, replaced) = Rope::from_reader_lossy(Cursor::new(TEXT)).unwrap();
+    assert_eq!(rope, TEXT);
+    assert!(replaced.is_empty());
+    rope.assert_integrity();
+    rope.assert_invariants();
+}
+
+#[test]
+fn from_reader_lossy_02() {
+    // Invalid bytes scattered through the text, including in the middle of
+    // multi-byte chars.
+    let mut bytes = TEXT.as_bytes().to_vec();
+    for &i in &[0, 1000, 5000, 5001, 8000, bytes.len() - 1] {
+        bytes[i] = 0xFF;
+    }
+    let mut offsets = Vec::new();
+    let lossy = String::from_utf8_lossy(&bytes);
+    let mut byte_idx = 0;
+    for chunk in bytes.utf8_chunks() {
+        byte_idx += chunk.valid().len();
+        if !chunk.invalid().is_empty() {
+            offsets.push(byte_idx);
+            byte_idx += chunk.invalid().len();
+        }
+    }
+
+    let (rope, replaced) = Rope::from_reader_lossy(Cursor::new(&bytes)).unwrap();
+    assert_eq!(rope, lossy);
+    assert_eq!(replaced, offsets);
+    assert!(replaced.len() >= 6);
+    rope.assert_integrity();
+    rope.assert_invariants();
+}
+
+/// Loads `bytes` with invalid utf8 escaped, and checks the `Rope`.
+fn read_escaped(bytes: &[u8]) -> (Rope, EncodingInfo) {
+    let options = ReadOptions {
+        invalid_utf8: InvalidUtf8::Escape,
+        ..ReadOptions::default()
+    };
+    let (rope, info) = Rope::from_reader_with(Cursor::new(bytes), options).unwrap();
+    rope.assert_integrity();
+    rope.assert_invariants();
+    (rope, info)
+}
+
+fn write_escaped(rope: &Rope, info: EncodingInfo) -> Vec<u8> {
+    let mut out = Vec::new();
+    rope.write_to_with(&mut out, WriteOptions::from(info)).unwrap();
+    out
+}
+
+#[test]
+fn from_reader_with_01() {
+    // Invalid bytes, and valid chars that look like escaped bytes.
+    let mut bytes = TEXT.as_bytes().to_vec();
+    for &i in &[0, 1000, 5000, 5001, 8000, bytes.len() - 1] {
+        bytes[i] = 0xFF;
+    }
+    bytes.splice(3000..3000, "\u{10FF41}\u{10FFFF}".bytes());
+
+    let (rope, info) = read_escaped(&bytes);
+    assert_eq!(rope.char(0), '\u{10FFFF}');
+    assert!(rope.to_string().contains("\u{10FF41}\u{10FFFF}"));
+    assert!(!info.bom);
+    assert_eq!(write_escaped(&rope, info), bytes);
+}
+
+#[test]
+fn from_reader_with_02() {
+    // Unmodified regions round-trip after an edit, byte order mark included.
+    let bytes = b"\xEF\xBB\xBFname = \"caf\xE9\"\r\nvalue = \xFF\r\n";
+    let (mut rope, info) = read_escaped(bytes);
+    assert!(info.bom);
+    let idx = rope.line_to_char(1);
+    rope.remove(idx..(idx + 5));
+    rope.insert(idx, "size");
+    assert_eq!(
+        write_escaped(&rope, info),
+        &b"\xEF\xBB\xBFname = \"caf\xE9\"\r\nsize = \xFF\r\n"[..]
+    );
+
+    // Replaced escaped bytes are gone, and inserted chars are just text.
+    let idx = rope.len_chars() - 3;
+    rope.remove(idx..(idx + 1));
+    rope.insert(idx, "\u{10FFFF}");
+    let mut expected = b"\xEF\xBB\xBFname = \"caf\xE9\"\r\nsize = ".to_vec();
+    expected.extend_from_slice("\u{10FFFF}\r\n".as_bytes());
+    assert_eq!(write_escaped(&rope, info), expected);
+
+    // Plain valid text is unchanged.
+    let (rope, _) = read_escaped(TEXT.as_bytes());
+    assert_eq!(rope, TEXT);
+}
+
+#[test]
+fn rope_loader_01() {
+    // Cancel partway through, then load the rest from where it stopped.
+    let mut reads = 0;
+    let outcome = RopeLoader::new()
+        .on_progress(|_| {
+            reads += 1;
+            reads < 3
+        })
+        .load(ShortReader(TEXT.as_bytes()))
+        .unwrap();
+    assert_eq!(outcome.status, LoadStatus::Cancelled);
+    assert!(outcome.byte_offset < TEXT.len());
+
+    let rest = RopeLoader::new()
+        .load(&TEXT.as_bytes()[outcome.byte_offset..])
+        .unwrap();
+    assert_eq!(rest.status, LoadStatus::Complete);
+
+    let mut rope = outcome.rope;
+    rope.append(rest.rope);
+    assert_eq!(rope, TEXT);
+    rope.assert_integrity();
+    rope.assert_invariants();
+}
+
+#[test]
+fn rope_loader_02() {
+    let outcome = RopeLoader::new()
+        .max_bytes(1000)
+        .load(TEXT.as_bytes())
+        .unwrap();
+    assert_eq!(outcome.status, LoadStatus::TooLarge);
+    assert!(outcome.byte_offset > 996 && outcome.byte_offset <= 1000);
+    assert_eq!(outcome.rope, &TEXT[..outcome.byte_offset]);
+}
+
+/// A reader that returns at most 1000 bytes per read.
+struct ShortReader<'a>(&'a [u8]);
+
+impl<'a> Read for ShortReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(1000);
+        self.0.read(&mut buf[..len])
+    }
+}
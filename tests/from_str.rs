@@ -1,6 +1,6 @@
 extern crate ropey;
 
-use ropey::Rope;
+use ropey::{Rope, RopeBuilder};
 
 const TEXT: &str = include_str!("test_text.txt");
 
@@ -16,3 +16,37 @@ fn from_str() {
     // Verify that they match
     assert_eq!(rope, TEXT);
 }
+
+#[cfg(feature = "parallel")]
+#[test]
+fn from_str_parallel() {
+    for &count in &[1, 100] {
+        let text = TEXT.repeat(count);
+        let rope = Rope::from_str_parallel(&text);
+
+        rope.assert_integrity();
+        rope.assert_invariants();
+        assert_eq!(rope, text);
+    }
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn finish_parallel() {
+    let text = TEXT.repeat(100);
+    for &parallel in &[false, true] {
+        let mut builder = if parallel {
+            RopeBuilder::new_parallel()
+        } else {
+            RopeBuilder::new()
+        };
+        for line in text.split_inclusive('\n') {
+            builder.append(line);
+        }
+        let rope = builder.finish_parallel();
+
+        rope.assert_integrity();
+        rope.assert_invariants();
+        assert_eq!(rope, text);
+    }
+}
@@ -1,6 +1,10 @@
 #[macro_use]
 extern crate proptest;
+#[cfg(feature = "regex")]
+extern crate regex;
 extern crate ropey;
+#[cfg(feature = "graphemes")]
+extern crate unicode_segmentation;
 
 use proptest::collection::vec;
 use proptest::test_runner::Config;
@@ -8,8 +12,15 @@ use ropey::{
     str_utils::{
         byte_to_char_idx, byte_to_line_idx, char_to_byte_idx, char_to_line_idx, line_to_byte_idx,
     },
-    Rope,
+    search::SearchOptions,
+    Assoc, ChangeSet, Edit, LineBreakPolicy, Rope,
 };
+#[cfg(feature = "regex")]
+use ropey::regex::Regex;
+#[cfg(feature = "multi-pattern")]
+use ropey::search::MultiPattern;
+#[cfg(feature = "graphemes")]
+use unicode_segmentation::UnicodeSegmentation;
 
 fn string_insert(text: &mut String, char_idx: usize, text_ins: &str) {
     let byte_idx = char_to_byte_idx(text, char_idx);
@@ -30,6 +41,24 @@ fn string_slice(text: &str, char_start: usize, char_end: usize) -> &str {
     &text[byte_start..byte_end]
 }
 
+/// Builds a `ChangeSet` for a text of `len` chars from arbitrary data.
+fn make_change_set(len: usize, ops: &[(u8, usize, String)]) -> ChangeSet {
+    let mut cs = ChangeSet::new(len);
+    let mut pos = 0;
+    for &(kind, n, ref text) in ops {
+        let n = n % (len - pos + 1);
+        match kind % 3 {
+            0 => cs.retain(n),
+            1 => cs.delete(n),
+            _ => cs.insert(text),
+        }
+        if kind % 3 != 2 {
+            pos += n;
+        }
+    }
+    cs
+}
+
 /// A slower, but easy-to-verify, byte->line index converter.
 ///
 /// We use this to verify the faster-but-more-complex functions in
@@ -149,6 +178,13 @@ fn line_to_byte_index_slow(text: &str, line_idx: usize) -> usize {
     i
 }
 
+/// Pieces of regexes, for building random ones.
+#[cfg(feature = "regex")]
+const REGEX_PIECES: &[&str] = &[
+    "a", "b", "\u{e9}", ".", " ", "a*", "b+?", "(a|b)", "(ab)?", "(b*)", "(?:|a)", "a{0,2}",
+    "[^a]", "\\w+", "\\b", "\\B", "^", "$", "(?m:^)", "(?m:$)",
+];
+
 //===========================================================================
 
 proptest! {
@@ -230,6 +266,137 @@ proptest! {
         assert_eq!(rope, TEXT);
     }
 
+    #[test]
+    fn pt_apply_edits(ref points in vec(0usize..(CHAR_LEN+1), 0..40), ref texts in vec("\\PC{0,5}", 20)) {
+        let mut rope = Rope::from_str(TEXT);
+        let mut text = String::from(TEXT);
+
+        // Pair up sorted points into non-overlapping (but possibly
+        // touching or empty) ranges.
+        let len = rope.len_chars();
+        let mut points: Vec<usize> = points.iter().map(|&p| p % (len + 1)).collect();
+        points.sort();
+        let mut edits: Vec<Edit> = points
+            .chunks(2)
+            .filter(|pair| pair.len() == 2)
+            .zip(texts.iter())
+            .map(|(pair, t)| Edit::replace(pair[0]..pair[1], t))
+            .collect();
+        edits.reverse();
+
+        rope.apply_edits(&edits);
+        for edit in &edits {
+            string_remove(&mut text, edit.range.start, edit.range.end);
+            string_insert(&mut text, edit.range.start, &edit.text);
+        }
+
+        rope.assert_integrity();
+        rope.assert_invariants();
+
+        assert_eq!(rope, text);
+    }
+
+    #[test]
+    fn pt_replace_all(ref text in "[ab\u{3042}]{0,300}", ref pattern in "[ab\u{3042}]{1,4}", ref replacement in "[cd\u{3044}]{0,4}") {
+        let mut rope = Rope::from_str(&text);
+        let report = rope.replace_all(pattern, replacement);
+
+        rope.assert_integrity();
+        rope.assert_invariants();
+
+        assert_eq!(rope, text.replace(pattern.as_str(), replacement));
+        assert_eq!(report.len(), text.matches(pattern.as_str()).count());
+        for r in &report {
+            assert_eq!(string_slice(&text, r.old_range.start, r.old_range.end), pattern.as_str());
+            assert_eq!(rope.slice(r.new_range.clone()), replacement.as_str());
+        }
+    }
+
+    #[test]
+    fn pt_change_set_invert(ref text in "\\PC{0,100}", ref ops in vec((0u8..3, 0usize..20, "\\PC{0,5}"), 0..20)) {
+        let mut rope = Rope::from_str(&text);
+        let cs = make_change_set(rope.len_chars(), ops);
+        let inverted = cs.invert(&rope);
+
+        cs.apply(&mut rope);
+        assert_eq!(cs.len_after(), rope.len_chars());
+        inverted.apply(&mut rope);
+
+        rope.assert_integrity();
+        rope.assert_invariants();
+        assert_eq!(rope, text.as_str());
+    }
+
+    #[test]
+    fn pt_change_set_compose(ref text in "\\PC{0,100}", ref ops1 in vec((0u8..3, 0usize..20, "\\PC{0,5}"), 0..20), ref ops2 in vec((0u8..3, 0usize..20, "\\PC{0,5}"), 0..20)) {
+        let mut rope1 = Rope::from_str(&text);
+        let mut rope2 = rope1.clone();
+        let a = make_change_set(rope1.len_chars(), ops1);
+        let b = make_change_set(a.len_after(), ops2);
+
+        a.apply(&mut rope1);
+        b.apply(&mut rope1);
+        a.compose(&b).apply(&mut rope2);
+
+        assert_eq!(rope1, rope2);
+    }
+
+    #[test]
+    fn pt_change_set_transform(ref text in "\\PC{0,100}", ref ops1 in vec((0u8..3, 0usize..20, "\\PC{0,5}"), 0..20), ref ops2 in vec((0u8..3, 0usize..20, "\\PC{0,5}"), 0..20), a_first in proptest::bool::ANY) {
+        let mut rope1 = Rope::from_str(&text);
+        let mut rope2 = rope1.clone();
+        let a = make_change_set(rope1.len_chars(), ops1);
+        let b = make_change_set(rope1.len_chars(), ops2);
+
+        a.apply(&mut rope1);
+        b.transform(&a, !a_first).apply(&mut rope1);
+        b.apply(&mut rope2);
+        a.transform(&b, a_first).apply(&mut rope2);
+
+        assert_eq!(rope1, rope2);
+    }
+
+    #[test]
+    fn pt_marks(ref text in "\\PC{0,100}", ref ops in vec((0u8..3, 0usize..20, "\\PC{0,5}"), 0..20)) {
+        let mut rope = Rope::from_str(&text);
+        let cs = make_change_set(rope.len_chars(), ops);
+        let mut marks = Vec::new();
+        for i in 0..(rope.len_chars() + 1) {
+            for &gravity in &[Assoc::Before, Assoc::After] {
+                marks.push((rope.add_mark(i, gravity), i, gravity));
+            }
+        }
+
+        cs.apply(&mut rope);
+        for &(mark, char_idx, gravity) in &marks {
+            assert_eq!(rope.mark_char(mark), Some(cs.map_pos(char_idx, gravity)));
+        }
+    }
+
+    #[test]
+    fn pt_line_break_policy(ref text in "[a\u{000A}\u{000C}\u{000D}]*", ref ins_text in "[a\u{000A}\u{000C}\u{000D}]*", idx in 0usize..1000) {
+        let mut rope = Rope::from_str(&text);
+        let mut text = text.clone();
+
+        let idx = idx % (rope.len_chars() + 1);
+        rope.insert(idx, &ins_text);
+        string_insert(&mut text, idx, &ins_text);
+
+        rope.assert_integrity();
+        rope.assert_invariants();
+
+        let lf_count = text.matches('\n').count();
+        let lone_cr_count = text.matches('\r').count() - text.matches("\r\n").count();
+
+        rope.set_line_break_policy(LineBreakPolicy::Lf);
+        assert_eq!(lf_count + 1, rope.len_lines());
+        assert_eq!(rope.len_lines(), rope.lines().count());
+
+        rope.set_line_break_policy(LineBreakPolicy::LfCr);
+        assert_eq!(lf_count + lone_cr_count + 1, rope.len_lines());
+        assert_eq!(rope.len_lines(), rope.lines().count());
+    }
+
     #[test]
     fn pt_shrink_to_fit_01(ref char_idxs in vec(0usize..1000000, 0..1000)) {
         let mut rope = Rope::new();
@@ -495,6 +662,236 @@ proptest! {
         assert_eq!(slice.len_chars(), text_slice.chars().count());
     }
 
+    #[test]
+    fn pt_slice_find(ref text in "[ab\u{3042}]{0,300}", ref pattern in "[ab\u{3042}]{0,12}", range in (0usize..1000000, 0usize..1000000), from in 0usize..1000000) {
+        let rope = Rope::from_str(&text);
+
+        let mut idx1 = range.0 % (rope.len_chars() + 1);
+        let mut idx2 = range.1 % (rope.len_chars() + 1);
+        if idx1 > idx2 {
+            std::mem::swap(&mut idx1, &mut idx2)
+        };
+
+        let slice = rope.slice(idx1..idx2);
+        let text_slice = string_slice(&text, idx1, idx2);
+        let from = from % (slice.len_chars() + 1);
+        let from_byte = char_to_byte_idx(text_slice, from);
+        let to_char = |i| byte_to_char_idx(text_slice, i);
+
+        let matches: Vec<usize> = text_slice.match_indices(pattern.as_str()).map(|(i, _)| to_char(i)).collect();
+        assert_eq!(slice.matches(pattern).collect::<Vec<_>>(), matches);
+        assert_eq!(slice.find(pattern), text_slice.find(pattern.as_str()).map(&to_char));
+        assert_eq!(slice.rfind(pattern), text_slice.rfind(pattern.as_str()).map(&to_char));
+        assert_eq!(
+            slice.find_from(from, pattern),
+            text_slice[from_byte..].find(pattern.as_str()).map(|i| to_char(from_byte + i))
+        );
+    }
+
+    #[test]
+    fn pt_slice_find_with(ref text in "[aAb \u{3c3}\u{3a3}\u{3c2}]{0,200}", ref pattern in "[aAb \u{3c3}\u{3a3}]{0,5}", range in (0usize..1000000, 0usize..1000000), ignore_case in proptest::bool::ANY, whole_word in proptest::bool::ANY) {
+        let rope = Rope::from_str(&text);
+        let mut idx1 = range.0 % (rope.len_chars() + 1);
+        let mut idx2 = range.1 % (rope.len_chars() + 1);
+        if idx1 > idx2 {
+            std::mem::swap(&mut idx1, &mut idx2)
+        };
+        let options = SearchOptions::new()
+            .ignore_case(ignore_case)
+            .whole_word(whole_word)
+            .range(idx1..idx2);
+
+        // Naive reference implementation.
+        let fold = |c: char| if !ignore_case { c } else {
+            match c {
+                'A' => 'a',
+                '\u{3a3}' | '\u{3c2}' => '\u{3c3}',
+                c => c,
+            }
+        };
+        let is_word = |c: char| c.is_alphanumeric();
+        let chars: Vec<char> = text.chars().collect();
+        let pat: Vec<char> = pattern.chars().map(&fold).collect();
+        let mut expected = Vec::new();
+        let mut start = idx1;
+        while start + pat.len() <= idx2 {
+            let end = start + pat.len();
+            let is_match = chars[start..end].iter().map(|&c| fold(c)).eq(pat.iter().cloned());
+            let bounded = (start == 0 || !is_word(chars[start - 1]))
+                && (end == chars.len() || !is_word(chars[end]));
+            if is_match && (bounded || !whole_word) {
+                expected.push(start..end);
+                start = if end > start { end } else { start + 1 };
+            } else {
+                start += 1;
+            }
+        }
+
+        let matches: Vec<_> = rope.slice(..).matches_with(pattern, &options).collect();
+        assert_eq!(matches, expected);
+    }
+
+    #[cfg(feature = "multi-pattern")]
+    #[test]
+    fn pt_slice_matches_multi(ref text in "[abc\u{3c3}]{0,200}", ref patterns in vec("[abc\u{3c3}]{0,4}", 0..6), range in (0usize..1000000, 0usize..1000000)) {
+        let rope = Rope::from_str(&text);
+        let mut idx1 = range.0 % (rope.len_chars() + 1);
+        let mut idx2 = range.1 % (rope.len_chars() + 1);
+        if idx1 > idx2 {
+            std::mem::swap(&mut idx1, &mut idx2)
+        };
+        let slice = rope.slice(idx1..idx2);
+
+        // Naive reference implementation: at each position, the first
+        // non-empty pattern in the list that matches there.
+        let chars: Vec<char> = slice.chars().collect();
+        let pats: Vec<Vec<char>> = patterns.iter().map(|p| p.chars().collect()).collect();
+        let mut expected = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let found = pats.iter().enumerate().find(|&(_, pat)| {
+                !pat.is_empty() && chars[start..].starts_with(pat)
+            });
+            match found {
+                Some((id, pat)) => {
+                    expected.push((id, start..(start + pat.len())));
+                    start += pat.len();
+                }
+                None => start += 1,
+            }
+        }
+
+        let multi = MultiPattern::new(patterns);
+        let matches: Vec<_> = slice.matches_multi(&multi).collect();
+        assert_eq!(matches, expected);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn pt_slice_regex(ref text in "[ab \n\u{e9}]{0,60}", ref pieces in vec(proptest::sample::select(REGEX_PIECES), 0..5), range in (0usize..1000000, 0usize..1000000)) {
+        let pattern = pieces.concat();
+        let rope = Rope::from_str(&text);
+        let mut idx1 = range.0 % (rope.len_chars() + 1);
+        let mut idx2 = range.1 % (rope.len_chars() + 1);
+        if idx1 > idx2 {
+            std::mem::swap(&mut idx1, &mut idx2)
+        };
+        let slice = rope.slice(idx1..idx2);
+
+        // The regex crate is the reference implementation.
+        let text = slice.to_string();
+        let reference = regex::Regex::new(&pattern).unwrap();
+        let to_chars = |m: regex::Match| {
+            text[..m.start()].chars().count()..text[..m.end()].chars().count()
+        };
+        let expected: Vec<Vec<_>> = reference
+            .captures_iter(&text)
+            .map(|caps| caps.iter().map(|group| group.map(&to_chars)).collect())
+            .collect();
+        let mut expected_last = None;
+        for byte_idx in (0..=text.len()).rev().filter(|&i| text.is_char_boundary(i)) {
+            match reference.find_at(&text, byte_idx) {
+                Some(m) if m.start() == byte_idx => {
+                    expected_last = Some(to_chars(m));
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        let re = Regex::new(&pattern).unwrap();
+        let captures: Vec<Vec<_>> = slice
+            .captures_iter_regex(&re)
+            .map(|caps| (0..re.captures_len()).map(|i| caps.get(i)).collect())
+            .collect();
+        assert_eq!(captures, expected);
+        assert_eq!(slice.rfind_regex(&re), expected_last);
+    }
+
+    #[test]
+    fn pt_slice_cmp(ref text in "[ab]{0,2000}", range1 in (0usize..1000000, 0usize..1000000), range2 in (0usize..1000000, 0usize..1000000)) {
+        let rope = Rope::from_str(&text);
+
+        let mut a1 = range1.0 % (rope.len_chars() + 1);
+        let mut a2 = range1.1 % (rope.len_chars() + 1);
+        if a1 > a2 {
+            std::mem::swap(&mut a1, &mut a2)
+        };
+        let mut b1 = range2.0 % (rope.len_chars() + 1);
+        let mut b2 = range2.1 % (rope.len_chars() + 1);
+        if b1 > b2 {
+            std::mem::swap(&mut b1, &mut b2)
+        };
+
+        let slice1 = rope.slice(a1..a2);
+        let slice2 = rope.slice(b1..b2);
+        let text1 = string_slice(&text, a1, a2);
+        let text2 = string_slice(&text, b1, b2);
+
+        assert_eq!(text1.cmp(text2), slice1.cmp(&slice2));
+        assert_eq!(text1.partial_cmp(text2), slice1.partial_cmp(text2));
+        assert_eq!(text1.partial_cmp(text2), text1.partial_cmp(&slice2));
+    }
+
+    #[test]
+    fn pt_slice_iter_rev(ref text in "\\PC*\\n?\\PC*\\r\\n?\\PC*", range in (0usize..1000000, 0usize..1000000)) {
+        let rope = Rope::from_str(&text);
+
+        let mut idx1 = range.0 % (rope.len_chars() + 1);
+        let mut idx2 = range.1 % (rope.len_chars() + 1);
+        if idx1 > idx2 {
+            std::mem::swap(&mut idx1, &mut idx2)
+        };
+
+        let slice = rope.slice(idx1..idx2);
+        let text_slice = string_slice(&text, idx1, idx2);
+
+        assert!(slice.bytes().rev().eq(text_slice.bytes().rev()));
+        assert!(slice.chars().rev().eq(text_slice.chars().rev()));
+        let mut chunks: Vec<_> = slice.chunks().rev().collect();
+        chunks.reverse();
+        assert_eq!(chunks.concat(), text_slice);
+
+        let lines: Vec<_> = slice.lines().collect();
+        let mut rev_lines: Vec<_> = slice.lines().rev().collect();
+        rev_lines.reverse();
+        assert_eq!(lines, rev_lines);
+
+        let mut chars = slice.chars();
+        while chars.next().is_some() {}
+        let mut prev_chars: Vec<char> = Vec::new();
+        while let Some(c) = chars.prev() {
+            prev_chars.push(c);
+        }
+        assert!(prev_chars.into_iter().eq(text_slice.chars().rev()));
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn pt_slice_graphemes(ref text in "\\PC*\\r\\n?\\PC*", range in (0usize..1000000, 0usize..1000000)) {
+        let rope = Rope::from_str(&text);
+
+        let mut idx1 = range.0 % (rope.len_chars() + 1);
+        let mut idx2 = range.1 % (rope.len_chars() + 1);
+        if idx1 > idx2 {
+            std::mem::swap(&mut idx1, &mut idx2)
+        };
+
+        let slice = rope.slice(idx1..idx2);
+        let text_slice = string_slice(&text, idx1, idx2);
+
+        assert!(slice.graphemes().eq(text_slice.graphemes(true)));
+        assert!(slice.graphemes().rev().eq(text_slice.graphemes(true).rev()));
+
+        for (i, _) in text_slice.grapheme_indices(true) {
+            let char_idx = byte_to_char_idx(text_slice, i);
+            assert!(slice.is_grapheme_boundary(char_idx));
+            if char_idx > 0 {
+                assert!(slice.next_grapheme_boundary(slice.prev_grapheme_boundary(char_idx)) == char_idx);
+            }
+        }
+    }
+
     #[test]
     fn pt_byte_to_line_idx(ref text in "[\\u{000A}\\u{000B}\\u{000C}\\u{000D}\\u{0085}\\u{2028}\\u{2029}]*[\\u{000A}\\u{000B}\\u{000C}\\u{000D}\\u{0085}\\u{2028}\\u{2029}]*", idx in 0usize..200) {
         let idx = idx % (text.len() + 1);
@@ -543,6 +940,17 @@ proptest! {
             );
         }
     }
+
+    #[test]
+    fn pt_char_to_utf16_cu(ref text in "\\PC*\\PC*\\PC*", idx in 0usize..200) {
+        let r = Rope::from_str(text);
+        let idx = idx % (r.len_chars() + 1);
+        let utf16_idx: usize = text.chars().take(idx).map(|c| c.len_utf16()).sum();
+
+        assert_eq!(r.char_to_utf16_cu(idx), utf16_idx);
+        assert_eq!(r.utf16_cu_to_char(utf16_idx), idx);
+        assert_eq!(r.len_utf16_cu(), text.encode_utf16().count());
+    }
 }
 
 //===========================================================================
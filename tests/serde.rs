@@ -0,0 +1,71 @@
+#![cfg(feature = "serde")]
+
+extern crate ropey;
+extern crate serde_json;
+
+use ropey::{Rope, RopeSlice};
+
+const TEXT: &str = include_str!("test_text.txt");
+
+#[test]
+fn serialize_rope() {
+    let rope = Rope::from_str(TEXT);
+    let json = serde_json::to_string(&rope).unwrap();
+
+    assert_eq!(serde_json::to_string(TEXT).unwrap(), json);
+}
+
+#[test]
+fn serialize_slice() {
+    let rope = Rope::from_str(TEXT);
+    let slice = rope.slice(1000..3000);
+    let json = serde_json::to_string(&slice).unwrap();
+
+    assert_eq!(serde_json::to_string(&slice.to_string()).unwrap(), json);
+}
+
+#[test]
+fn serialize_light_slice() {
+    let slice = RopeSlice::from_str("Hello \"world\"!\n");
+    let json = serde_json::to_string(&slice).unwrap();
+
+    assert_eq!(r#""Hello \"world\"!\n""#, json);
+}
+
+#[test]
+fn serialize_empty() {
+    let rope = Rope::new();
+
+    assert_eq!(r#""""#, serde_json::to_string(&rope).unwrap());
+}
+
+#[test]
+fn round_trip() {
+    let rope = Rope::from_str(TEXT);
+    let json = serde_json::to_string(&rope).unwrap();
+    let rope2: Rope = serde_json::from_str(&json).unwrap();
+
+    rope2.assert_integrity();
+    rope2.assert_invariants();
+
+    assert_eq!(rope, rope2);
+}
+
+#[test]
+fn round_trip_from_reader() {
+    // Deserializing from a reader can't borrow from the input, which takes
+    // a different path through the visitor.
+    let rope = Rope::from_str(TEXT);
+    let json = serde_json::to_vec(&rope).unwrap();
+    let rope2: Rope = serde_json::from_reader(&json[..]).unwrap();
+
+    rope2.assert_integrity();
+    rope2.assert_invariants();
+
+    assert_eq!(rope, rope2);
+}
+
+#[test]
+fn deserialize_not_a_string() {
+    assert!(serde_json::from_str::<Rope>("[1, 2, 3]").is_err());
+}
@@ -0,0 +1,135 @@
+extern crate ropey;
+
+use std::io::{self, Cursor, Write};
+
+use ropey::{Encoding, LineEnding, Rope, WriteOptions};
+
+const TEXT: &str = include_str!("test_text.txt");
+
+/// A writer that only accepts a few bytes at a time.
+struct TrickleWriter {
+    bytes: Vec<u8>,
+}
+
+impl Write for TrickleWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(3);
+        self.bytes.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn write_with(rope: &Rope, options: WriteOptions) -> io::Result<Vec<u8>> {
+    let mut writer = TrickleWriter { bytes: Vec::new() };
+    rope.write_to_with(&mut writer, options)?;
+    Ok(writer.bytes)
+}
+
+#[test]
+fn write_to_01() {
+    // Short writes.
+    let rope = Rope::from_str(TEXT);
+    let mut writer = TrickleWriter { bytes: Vec::new() };
+    rope.write_to(&mut writer).unwrap();
+    assert_eq!(writer.bytes, TEXT.as_bytes());
+
+    assert_eq!(
+        write_with(&rope, WriteOptions::default()).unwrap(),
+        TEXT.as_bytes()
+    );
+}
+
+#[test]
+fn write_to_with_01() {
+    // Round-trips through each encoding that can represent the text.
+    let rope = Rope::from_str(TEXT);
+    for &encoding in &[Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+        for &bom in &[false, true] {
+            let options = WriteOptions {
+                encoding: encoding,
+                bom: bom,
+                line_ending: None,
+                escaped_bytes: true,
+            };
+            let bytes = write_with(&rope, options).unwrap();
+            let (loaded, info) =
+                Rope::from_reader_with_encoding(Cursor::new(bytes), encoding).unwrap();
+            assert_eq!(loaded, TEXT);
+            assert_eq!(WriteOptions::from(info), options);
+        }
+    }
+}
+
+#[test]
+fn write_to_with_02() {
+    // Line endings.
+    let rope = Rope::from_str("a\nb\r\nc\rd\u{2028}e\r\n\r\n");
+    let with_ending = |line_ending| {
+        let options = WriteOptions {
+            line_ending: Some(line_ending),
+            ..WriteOptions::default()
+        };
+        String::from_utf8(write_with(&rope, options).unwrap()).unwrap()
+    };
+
+    assert_eq!(with_ending(LineEnding::Lf), "a\nb\nc\nd\u{2028}e\n\n");
+    assert_eq!(
+        with_ending(LineEnding::Crlf),
+        "a\r\nb\r\nc\r\nd\u{2028}e\r\n\r\n"
+    );
+    assert_eq!(with_ending(LineEnding::Cr), "a\rb\rc\rd\u{2028}e\r\r");
+
+    // A big text, across many chunks.
+    let rope = Rope::from_str(TEXT);
+    let options = WriteOptions {
+        line_ending: Some(LineEnding::Crlf),
+        ..WriteOptions::default()
+    };
+    let bytes = write_with(&rope, options).unwrap();
+    assert_eq!(
+        bytes,
+        TEXT.replace("\r\n", "\n").replace('\n', "\r\n").as_bytes()
+    );
+}
+
+#[test]
+fn write_to_with_03() {
+    // Unrepresentable chars.
+    let rope = Rope::from_str("caf\u{E9} \u{201C}ok\u{201D}");
+    let latin_1 = WriteOptions {
+        encoding: Encoding::Latin1,
+        ..WriteOptions::default()
+    };
+    let windows_1252 = WriteOptions {
+        encoding: Encoding::Windows1252,
+        ..WriteOptions::default()
+    };
+
+    let err = write_with(&rope, latin_1).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let inner = err.get_ref().unwrap().downcast_ref::<ropey::Error>();
+    assert_eq!(inner, Some(&ropey::Error::UnencodableChar(5, '\u{201C}')));
+
+    assert_eq!(
+        write_with(&rope, windows_1252).unwrap(),
+        b"caf\xE9 \x93ok\x94"
+    );
+}
+
+#[test]
+fn write_to_with_04() {
+    // Chars that look like escaped bytes, but weren't loaded as any.
+    let rope = Rope::from_str("x\u{10FF41}y");
+    let options = WriteOptions {
+        escaped_bytes: true,
+        ..WriteOptions::default()
+    };
+    assert_eq!(
+        write_with(&rope, options).unwrap(),
+        "x\u{10FF41}y".as_bytes()
+    );
+}